@@ -1,17 +1,40 @@
-use clap::{Arg, Command};
+use clap::{
+	Arg,
+	ArgAction,
+	Command,
+	builder::{PossibleValuesParser, TypedValueParser},
+};
+use nf_base::RuntimeType;
 
 use crate::nf_type::{DATAWARP_STR, INFINISYNC_STR, OMNIPATH_STR};
 
 fn get_nf_subcommand(nf_name: String) -> Command {
 	let nf_about = format!("Runs {nf_name} network function");
-	Command::new(nf_name).about(nf_about).arg(
-		Arg::new("config")
-			.help("Configuration file to use")
-			.required(true)
-			.value_name("CONFIG_FILE")
-			.long("config")
-			.short('c'),
-	)
+	Command::new(nf_name)
+		.about(nf_about)
+		.arg(
+			Arg::new("config")
+				.help("Configuration file to use, repeat to layer overrides on top of it")
+				.required(true)
+				.action(ArgAction::Append)
+				.value_name("CONFIG_FILE")
+				.long("config")
+				.short('c'),
+		)
+		.arg(
+			Arg::new("runtime")
+				.help("Tokio runtime to run on, overriding the configured runtime type")
+				.value_name("RUNTIME")
+				.long("runtime")
+				.value_parser(
+					PossibleValuesParser::new(["single", "multi"]).map(|runtime| {
+						match runtime.as_str() {
+							"single" => RuntimeType::Single,
+							_ => RuntimeType::Multi,
+						}
+					}),
+				),
+		)
 }
 
 pub fn get_clap_app(
@@ -2,6 +2,7 @@
 
 use clap::{crate_authors, crate_description, crate_name, crate_version};
 use lightning_cli::{app::get_clap_app, nf_type::App};
+use nf_base::RuntimeType;
 
 fn main() -> color_eyre::Result<()> {
 	color_eyre::install()?;
@@ -13,10 +14,13 @@ fn main() -> color_eyre::Result<()> {
 	);
 	let matches = command.get_matches();
 	let (nf_type, matches) = matches.subcommand().expect("Subcommand Not present");
-	let config_path = matches
-		.get_one::<String>("config")
-		.expect("Config not present");
-	App::start_app(nf_type, config_path)?;
+	let config_paths = matches
+		.get_many::<String>("config")
+		.expect("Config not present")
+		.map(String::as_str)
+		.collect::<Vec<_>>();
+	let runtime = matches.get_one::<RuntimeType>("runtime").copied();
+	App::start_app(nf_type, &config_paths, runtime)?;
 	Ok(())
 }
 
@@ -1,13 +1,21 @@
-use std::{fs::File, io, io::Read};
+use std::{fs, io};
 
-use nf_base::{LoggingConfig, NfConfig, NfInstance, RuntimeConfig, RuntimeType};
+use nf_base::{LogFormat, LoggingConfig, NfConfig, NfInstance, RuntimeConfig, RuntimeType};
 use omnipath::OmniPathApp;
+use serde_yaml::Value;
 use thiserror::Error;
 use tokio::runtime::{Builder, Runtime};
 use tokio_util::sync::CancellationToken;
-use tracing::{info, trace};
+use tracing::{Subscriber, info, trace};
 use tracing_error::ErrorLayer;
-use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{
+	EnvFilter,
+	Layer,
+	fmt::MakeWriter,
+	layer::SubscriberExt,
+	registry::LookupSpan,
+	util::SubscriberInitExt,
+};
 
 pub const DATAWARP_STR: &'static str = "datawarp";
 pub const INFINISYNC_STR: &'static str = "infinisync";
@@ -16,23 +24,28 @@ pub const OMNIPATH_STR: &'static str = "omnipath";
 pub struct App;
 
 impl App {
+	/// Runs the NF `app_name` on the `runtime` given, or the configured one.
 	pub fn start_app(
 		app_name: &str,
-		config_path: &str,
+		config_paths: &[&str],
+		runtime: Option<RuntimeType>,
 	) -> color_eyre::Result<()> {
 		match app_name {
-			OMNIPATH_STR => Self::run::<OmniPathApp>(config_path),
+			OMNIPATH_STR => Self::run::<OmniPathApp>(config_paths, runtime),
 			_ => unreachable!(),
 		}
 	}
 
-	fn run<T: NfInstance>(config_path: &str) -> color_eyre::Result<()> {
-		let nf_app: NfApp<T> = NfApp::new(config_path)?;
+	fn run<T: NfInstance>(
+		config_paths: &[&str],
+		runtime: Option<RuntimeType>,
+	) -> color_eyre::Result<()> {
+		let nf_app: NfApp<T> = NfApp::new_merged(config_paths)?;
 		let runtime_config = nf_app.config.get_runtime_config();
 		let logging_config = nf_app.config.get_log_config();
 		setup_logging(logging_config)?;
 		trace!("config: {:#?}", nf_app.config);
-		let rt = setup_runtime(runtime_config)?;
+		let rt = setup_runtime(runtime_config, runtime)?;
 		rt.block_on(nf_app.run())?;
 		Ok(())
 	}
@@ -94,11 +107,22 @@ pub enum NfError<T> {
 
 impl<T: NfInstance> NfApp<T> {
 	pub fn new(config_path: &str) -> Result<Self, AppSetupError> {
-		let mut file = File::open(config_path).expect("Failed to open config file");
-		let mut contents = String::new();
-		trace!("Going to parse config");
-		file.read_to_string(&mut contents)
-			.map_err(AppConfigError::from)?;
+		Self::new_merged(&[config_path])
+	}
+
+	/// Parses the config files in order and deep-merges them, so that later
+	/// files override the keys they set in earlier ones.
+	pub fn new_merged(config_paths: &[&str]) -> Result<Self, AppSetupError> {
+		let mut merged = Value::Null;
+		for config_path in config_paths {
+			trace!("Going to parse config {}", config_path);
+			let contents = fs::read_to_string(config_path).map_err(AppConfigError::from)?;
+			let overlay = serde_yaml::from_str(&contents).map_err(AppConfigError::from)?;
+			merge_yaml(&mut merged, overlay);
+		}
+		// Deserialized from the rendered document rather than the `Value`, only
+		// a document lets plain scalars like `mcc: 208` deserialize into strings.
+		let contents = serde_yaml::to_string(&merged).map_err(AppConfigError::from)?;
 		let config = serde_yaml::from_str(&contents).map_err(AppConfigError::from)?;
 
 		let cancellation_token = CancellationToken::new();
@@ -108,9 +132,12 @@ impl<T: NfInstance> NfApp<T> {
 		})
 	}
 
+	/// Registers and starts the NF until a termination signal. The NF winds
+	/// down on the signal in its own order, with the NRF deregistration last
+	/// here for NFs that leave it to the runner.
 	pub async fn run(self) -> Result<(), NfError<T::Error>> {
 		let shutdown_token = self.cancellation_token.clone();
-		let handle = tokio::spawn(async move {
+		tokio::spawn(async move {
 			use tokio::signal::unix::{SignalKind, signal};
 
 			// Infos here:
@@ -124,52 +151,83 @@ impl<T: NfInstance> NfApp<T> {
 			};
 			shutdown_token.cancel();
 		});
+		let shutdown = self.cancellation_token.clone();
 		let nf_app = T::initialize(self.config, self.cancellation_token)
 			.map_err(NfError::InitializationFailedError)?;
 		info!("App Initialized Successfully");
-		tokio::select! {
-			 _ = handle => {
-				nf_app.deregister_nf().await.map_err(NfError::ShutdownDeregistrationFailedError)
-			 },
-			 res = async {
-				nf_app.register_nf().await?;
+		// Only the registration is cut short by the signal, `start` observes
+		// the cancellation itself so its shutdown runs to completion.
+		let registered = tokio::select! {
+			_ = shutdown.cancelled() => Ok(false),
+			res = nf_app.register_nf() => res.map(|()| true),
+		};
+		let res = match registered {
+			Ok(true) => {
 				info!("Nf Registered Successfully");
-				nf_app.start().await?;
-				info!("Nf Started Successfully");
-				Ok(())
-			 } => {
-				let dreg_res = nf_app.deregister_nf().await;
-				if res.is_ok() && dreg_res.is_err() {
-					dreg_res.map_err(NfError::ShutdownDeregistrationFailedError)
-				} else if dreg_res.is_ok() && res.is_err() {
-					res.map_err(NfError::RuntimeError)
-				} else if let Err(dreg_err) = dreg_res {
-					res.map_err(|e| NfError::RuntimeWithDeregistrationError(e, dreg_err))
-				} else {
-					info!("Nf Deregistered Successfully");
-					Ok(())
+				nf_app
+					.start()
+					.await
+					.inspect(|()| info!("Nf Started Successfully"))
+			}
+			Ok(false) => Ok(()),
+			Err(e) => Err(e),
+		};
+		let dreg_res = nf_app.deregister_nf().await;
+		if res.is_ok() && dreg_res.is_err() {
+			dreg_res.map_err(NfError::ShutdownDeregistrationFailedError)
+		} else if dreg_res.is_ok() && res.is_err() {
+			res.map_err(NfError::RuntimeError)
+		} else if let Err(dreg_err) = dreg_res {
+			res.map_err(|e| NfError::RuntimeWithDeregistrationError(e, dreg_err))
+		} else {
+			info!("Nf Deregistered Successfully");
+			Ok(())
+		}
+	}
+}
+
+/// Mappings are merged key by key, any other overlay value replaces the base
+/// one. A null overlay, such as an empty file, leaves the base untouched.
+fn merge_yaml(
+	base: &mut Value,
+	overlay: Value,
+) {
+	match (base, overlay) {
+		(_, Value::Null) => (),
+		(Value::Mapping(base), Value::Mapping(overlay)) => {
+			for (key, value) in overlay {
+				match base.get_mut(&key) {
+					Some(base_value) => merge_yaml(base_value, value),
+					None => {
+						base.insert(key, value);
+					}
 				}
-			},
+			}
 		}
+		(base, overlay) => *base = overlay,
 	}
 }
 
 fn setup_logging(config: &LoggingConfig) -> Result<(), AppSetupError> {
-	install_tracing();
+	install_tracing(config.format);
 	Ok(())
 }
 
-fn setup_runtime(config: &RuntimeConfig) -> Result<Runtime, AppSetupError> {
-	trace!("Starting Tokio Runtime: {:?}", config.rt_type);
-	let rt = match config.rt_type {
+fn setup_runtime(
+	config: &RuntimeConfig,
+	rt_override: Option<RuntimeType>,
+) -> Result<Runtime, AppSetupError> {
+	let rt_type = rt_override.unwrap_or(config.rt_type);
+	trace!("Starting Tokio Runtime: {:?}", rt_type);
+	let rt = match rt_type {
 		RuntimeType::Multi => Builder::new_multi_thread().enable_all().build()?,
 		RuntimeType::Single => Builder::new_current_thread().enable_all().build()?,
 	};
 	Ok(rt)
 }
 
-fn install_tracing() {
-	let fmt_layer = tracing_subscriber::fmt::layer().with_target(false);
+fn install_tracing(format: LogFormat) {
+	let fmt_layer = fmt_layer(format, io::stdout);
 	let filter_layer = EnvFilter::try_from_default_env()
 		.or_else(|_| EnvFilter::try_new("info"))
 		.unwrap();
@@ -180,3 +238,112 @@ fn install_tracing() {
 		.with(ErrorLayer::default())
 		.init();
 }
+
+fn fmt_layer<S, W>(
+	format: LogFormat,
+	writer: W,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+	W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+	let layer = tracing_subscriber::fmt::layer()
+		.with_target(false)
+		.with_writer(writer);
+	match format {
+		LogFormat::Full => layer.boxed(),
+		LogFormat::Pretty => layer.pretty().boxed(),
+		LogFormat::Compact => layer.compact().boxed(),
+		LogFormat::Json => layer.json().boxed(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		net::Ipv4Addr,
+		sync::{Arc, Mutex},
+	};
+
+	use tokio::runtime::RuntimeFlavor;
+
+	use super::*;
+	use crate::app::get_clap_app;
+
+	#[test]
+	fn test_override_config_replaces_sbi_port() {
+		let base = concat!(env!("CARGO_MANIFEST_DIR"), "/../config/amfcfg.yaml");
+		let overlay =
+			std::env::temp_dir().join(format!("amfcfg-override-{}.yaml", std::process::id()));
+		fs::write(&overlay, "sbi:\n  port: 8080\n").unwrap();
+
+		let nf_app = NfApp::<OmniPathApp>::new_merged(&[base, overlay.to_str().unwrap()]);
+		fs::remove_file(&overlay).unwrap();
+
+		let config = nf_app.unwrap().config;
+		assert_eq!(config.sbi.port, 8080);
+		assert_eq!(config.sbi.binding_ipv4, Ipv4Addr::LOCALHOST);
+		assert_eq!(config.configuration.ngap_port, 38412);
+	}
+
+	#[test]
+	fn test_runtime_flag_overrides_config() {
+		let matches = get_clap_app("lightning-cli", "", "", "")
+			.try_get_matches_from([
+				"lightning-cli",
+				OMNIPATH_STR,
+				"-c",
+				"amfcfg.yaml",
+				"--runtime",
+				"single",
+			])
+			.unwrap();
+		let (_, matches) = matches.subcommand().unwrap();
+		let runtime = matches.get_one::<RuntimeType>("runtime").copied();
+		assert_eq!(runtime, Some(RuntimeType::Single));
+
+		let config = RuntimeConfig {
+			rt_type: RuntimeType::Multi,
+		};
+		let rt = setup_runtime(&config, runtime).unwrap();
+		assert_eq!(rt.handle().runtime_flavor(), RuntimeFlavor::CurrentThread);
+		let rt = setup_runtime(&config, None).unwrap();
+		assert_eq!(rt.handle().runtime_flavor(), RuntimeFlavor::MultiThread);
+	}
+
+	#[derive(Clone, Default)]
+	struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+	impl io::Write for SharedBuffer {
+		fn write(
+			&mut self,
+			buf: &[u8],
+		) -> io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_json_format_emits_json_lines() {
+		let buffer = SharedBuffer::default();
+		let writer = buffer.clone();
+		let subscriber =
+			tracing_subscriber::registry().with(fmt_layer(LogFormat::Json, move || writer.clone()));
+		tracing::subscriber::with_default(subscriber, || {
+			info!(gnb = "gnb-1", "Ng Setup completed");
+			info!("Nf Registered Successfully");
+		});
+
+		let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 2);
+		let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+		assert_eq!(line["level"], "INFO");
+		assert_eq!(line["fields"]["message"], "Ng Setup completed");
+		assert_eq!(line["fields"]["gnb"], "gnb-1");
+	}
+}
@@ -0,0 +1,84 @@
+use nonempty::NonEmpty;
+use oasbi::common::Guami;
+
+use super::{OmniPathConfig, SerdeValidated};
+
+/// The NRF of [`ConfigFixture`] unless set otherwise.
+const FIXTURE_NRF_URI: &str = "http://127.0.0.1:8000";
+
+/// A minimal config passing validation, for the tests to tweak the fields
+/// they are about.
+#[derive(Debug)]
+pub struct ConfigFixture(OmniPathConfig);
+
+impl OmniPathConfig {
+	pub fn test_fixture() -> ConfigFixture {
+		ConfigFixture(OmniPathConfig::default()).with_nrf_uri(FIXTURE_NRF_URI)
+	}
+}
+
+impl ConfigFixture {
+	pub fn with_nrf_uri(
+		mut self,
+		nrf_uri: &str,
+	) -> Self {
+		self.0.configuration.nrf_uri = serde_json::from_value(nrf_uri.into()).unwrap();
+		self
+	}
+
+	pub fn with_ngap_port(
+		mut self,
+		ngap_port: u16,
+	) -> Self {
+		self.0.configuration.ngap_port = ngap_port;
+		self
+	}
+
+	pub fn with_served_guami_list(
+		mut self,
+		served_guami_list: NonEmpty<Guami>,
+	) -> Self {
+		self.0.configuration.served_guami_list = served_guami_list;
+		self
+	}
+
+	/// The config as is, for tests after its violations.
+	pub fn into_inner(self) -> OmniPathConfig {
+		self.0
+	}
+
+	/// Panics on a config tweaked into failing validation.
+	pub fn build(self) -> SerdeValidated<OmniPathConfig> {
+		match SerdeValidated::new_with_report(self.0) {
+			Ok(config) => config,
+			Err(violations) => panic!("Invalid test config:\n{violations}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use nonempty::nonempty;
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn test_fixture_is_valid_and_tweakable() {
+		let guami: Guami = serde_json::from_value(json!({
+			"plmnId": { "mcc": "208", "mnc": "93" },
+			"amfId": "cafe00",
+		}))
+		.unwrap();
+		let config = OmniPathConfig::test_fixture()
+			.with_nrf_uri("http://nrf.example:8000")
+			.with_ngap_port(38413)
+			.with_served_guami_list(nonempty![guami.clone()])
+			.build();
+
+		let configuration = &config.inner().configuration;
+		assert_eq!(configuration.nrf_uri.to_string(), "http://nrf.example:8000");
+		assert_eq!(configuration.ngap_port, 38413);
+		assert_eq!(configuration.served_guami_list, nonempty![guami]);
+	}
+}
@@ -1,14 +1,47 @@
-use std::net::{IpAddr, Ipv4Addr};
+use std::{fmt::Display, net::{IpAddr, Ipv4Addr}, path::PathBuf};
 use nonempty::NonEmpty;
-use nf_base::{LoggingConfig, NfConfig, RuntimeConfig};
+use chrono::{DateTime, Utc};
+use client::HttpClientConfig;
+use nf_base::{LoggingConfig, NfConfig, RuntimeConfig, Tls};
 use oasbi::{
-	common::{Guami, PlmnId, Snssai, Tai, Uri, UriScheme},
+	common::{
+		AmfId,
+		ExtSnssai,
+		Guami,
+		NfType,
+		PlmnId,
+		Snssai,
+		Tai,
+		Uri,
+		UriScheme,
+		error::ConversionError,
+	},
 	nrf::types::ServiceName,
 };
+use sctp_config::SctpConfig;
 use serde::{Deserialize, Serialize};
-use serde_valid::Validate;
+use serde_valid::{Validate, validation::Errors};
 use serde_with::{DisplayFromStr, serde_as};
-use tokio_sctp::InitMsg;
+
+use crate::{
+	ngap::{
+		constants::app::{
+			DEFAULT_MAX_CONCURRENT_NG_SETUPS,
+			DEFAULT_MAX_UE_COUNT,
+			DEFAULT_NGAP_PORT,
+			DEFAULT_UE_INACTIVITY_TIMEOUT_SECS,
+		},
+		procedure_code_enum::ProcedureAllowList,
+	},
+	sbi::{InvalidParam, invalid_params},
+	utils::{
+		guami::{AMF_POINTER_BITS, AMF_SET_ID_BITS, amf_id_from_parts},
+		models::{TaiSet, same_slice},
+	},
+};
+
+#[cfg(test)]
+mod fixture;
 
 #[derive(Serialize, Deserialize, Debug, Validate, Default)]
 #[serde(rename_all = "camelCase")]
@@ -29,24 +62,104 @@ pub struct Info {
 	#[serde_as(as = "DisplayFromStr")]
 	pub version: versions::SemVer,
 	pub description: String,
+	/// API versions advertised besides `version`, e.g. the previous major
+	/// while peers migrate.
+	#[serde(default)]
+	pub additional_versions: Vec<ServiceVersion>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceVersion {
+	#[serde_as(as = "DisplayFromStr")]
+	pub version: versions::SemVer,
+	#[serde(default)]
+	pub expiry: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, smart_default::SmartDefault)]
 #[serde(rename_all = "camelCase")]
+#[validate(custom = tai_nssai_availability)]
 pub struct Configuration {
+	#[default("AMF")]
 	pub amf_name: String,
+	#[default(_code = "vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]")]
 	pub ngap_ip_list: Vec<IpAddr>,
+	#[default(DEFAULT_NGAP_PORT)]
 	pub ngap_port: u16,
 	#[default(_code = "NonEmpty::new(Guami::default())")]
+	#[validate(custom = amf_id_bit_widths)]
+	#[validate(custom = guami_plmn_ids)]
 	pub served_guami_list: NonEmpty<Guami>,
+	/// The AMF's position in its AMF Set. When given it is the AMF ID of
+	/// every served GUAMI, overriding the `amfId` listed with the PLMN.
+	#[serde(default)]
+	#[validate]
+	pub amf_identity: Option<AmfIdentity>,
+	#[default(_code = "vec![Tai::default()]")]
 	#[validate(min_items = 1)]
+	#[validate(custom = unique_tais)]
+	#[validate(custom = tai_plmn_ids)]
 	pub support_tai_list: Vec<Tai>,
 	#[default(_code = "NonEmpty::new(PlmnSupportItem::default())")]
+	#[validate(custom = plmn_support_list)]
 	pub plmn_support_list: NonEmpty<PlmnSupportItem>,
+	/// Rejects the NG Setup of a gNB broadcasting any PLMN missing from
+	/// `plmn_support_list`, even if some of its TAIs are served.
+	#[serde(default)]
+	pub reject_unsupported_plmns: bool,
+	/// The slices available in the TAIs restricting them, the TAIs left out
+	/// have every slice of `plmn_support_list`. Each TAI is one of
+	/// `support_tai_list`, restricted to slices of its PLMN.
+	#[serde(default)]
+	pub tai_nssai_availability: Vec<TaiNssaiAvailability>,
+	#[default(_code = "vec![\"internet\".to_string()]")]
 	#[validate(min_items = 1)]
+	#[validate(custom = dnn_list)]
 	pub support_dnn_list: Vec<String>,
 	pub nrf_uri: Uri,
-	pub sctp: SCTP,
+	/// The SCP the SBI requests are sent through, to the NRF directly when
+	/// left out.
+	#[serde(default)]
+	pub scp_uri: Option<Uri>,
+	#[validate]
+	pub sctp: SctpConfig,
+	/// Associations past this many wait for a free slot before their NG
+	/// Setup is handled, smoothing reconnection storms.
+	#[serde(default = "default_max_concurrent_ng_setups")]
+	#[default(DEFAULT_MAX_CONCURRENT_NG_SETUPS)]
+	#[validate(minimum = 1)]
+	pub max_concurrent_ng_setups: usize,
+	/// UEs the AMF is sized for, its load reported to the NRF is the share of
+	/// them being served.
+	#[serde(default = "default_max_ue_count")]
+	#[default(DEFAULT_MAX_UE_COUNT)]
+	#[validate(minimum = 1)]
+	pub max_ue_count: usize,
+	/// Seconds a UE may go without NAS or NGAP activity before its context
+	/// is released.
+	#[serde(default = "default_ue_inactivity_timeout")]
+	#[default(DEFAULT_UE_INACTIVITY_TIMEOUT_SECS)]
+	#[validate(minimum = 1)]
+	pub ue_inactivity_timeout: u32,
+	/// UE context operations and NGAP message tasks pending past which the
+	/// AMF goes into overload, sending the gNBs an Overload Start. Without it
+	/// the AMF never does.
+	#[serde(default)]
+	#[validate(minimum = 1)]
+	pub overload_threshold: Option<usize>,
+	/// Weight of the TNL associations of this AMF, the gNB sharing UEs across
+	/// AMF associations by it, TNL Association Weight Factor of TS 38.413.
+	#[serde(default)]
+	pub tnl_weight_factor: u8,
+	#[serde(default)]
+	pub ngap_tap: NgapTap,
+	/// NGAP procedures handled for connected gNBs, messages of any other
+	/// procedure are answered with an Error Indication. All are handled when
+	/// left out.
+	#[serde(default)]
+	pub enabled_ngap_procedures: ProcedureAllowList,
 	// 	pub security: NasSecurity,
 	// 	pub network_name: NetworkName,
 	// 	pub t3502_value: u16,
@@ -63,6 +176,18 @@ pub struct Configuration {
 	// 	pub default_ue_ctx_req: bool,
 }
 
+fn default_max_concurrent_ng_setups() -> usize {
+	DEFAULT_MAX_CONCURRENT_NG_SETUPS
+}
+
+fn default_max_ue_count() -> usize {
+	DEFAULT_MAX_UE_COUNT
+}
+
+fn default_ue_inactivity_timeout() -> u32 {
+	DEFAULT_UE_INACTIVITY_TIMEOUT_SECS
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, smart_default::SmartDefault, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct Sbi {
@@ -74,9 +199,60 @@ pub struct Sbi {
 	pub binding_ipv4: Ipv4Addr,
 	pub port: u16,
 	pub tls: Tls,
+	#[default(_code = "vec![ServiceName::NamfComm]")]
 	#[validate(min_items = 1)]
 	#[validate(custom = enum_list([ServiceName::NamfComm, ServiceName::NamfEvts, ServiceName::NamfMt, ServiceName::NamfLoc]))]
 	pub service_name_list: Vec<ServiceName>,
+	/// Settings of the clients towards the NRF and the peer NFs.
+	#[serde(default)]
+	pub client: HttpClientConfig,
+	/// Who the NRF lets discover the services, applied to every one of them.
+	#[serde(default)]
+	#[validate]
+	pub access_restrictions: ServiceAccessRestrictions,
+	/// Requests to the services need an NRF issued access token when given.
+	#[serde(default)]
+	#[validate]
+	pub oauth2: Option<AccessTokenConfig>,
+	/// Requests to the operator routes under `/admin` need the admin token,
+	/// without it they are refused.
+	#[serde(default)]
+	pub admin: Option<AdminConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminConfig {
+	/// Local path of the file holding the bearer token of the admin API.
+	pub token_file: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessTokenConfig {
+	#[validate(min_items = 1)]
+	pub nrf_public_keys: Vec<NrfPublicKey>,
+}
+
+/// Local path of a PEM encoded public key the NRF signs access tokens with,
+/// picked by the `kid` of the token header.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NrfPublicKey {
+	#[serde(default)]
+	pub kid: Option<String>,
+	pub pem: String,
+}
+
+/// Access restrictions of an NF service, TS 29.510 6.1.6.2.3. An empty list
+/// leaves its kind of consumer unrestricted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServiceAccessRestrictions {
+	#[validate(custom = allowed_plmn_ids)]
+	pub allowed_plmns: Vec<PlmnId>,
+	pub allowed_nf_types: Vec<NfType>,
+	pub allowed_nssais: Vec<ExtSnssai>,
 }
 
 #[allow(dead_code)]
@@ -96,6 +272,207 @@ pub fn enum_list<const N: usize>(
 	}
 }
 
+/// Checks that every GUAMI's AMF Identifier fits the 10-bit AMF Set ID and
+/// 6-bit AMF Pointer it is encoded into for NGAP, TS 23.003 2.10.1.
+pub fn amf_id_bit_widths(val: &NonEmpty<Guami>) -> Result<(), serde_valid::validation::Error> {
+	let invalid = val
+		.iter()
+		.filter(|guami| {
+			guami.amf_id.set_id.inner() >= 1 << AMF_SET_ID_BITS
+				|| guami.amf_id.pointer_id >= 1 << AMF_POINTER_BITS
+		})
+		.map(|guami| {
+			format!(
+				"(set {}, pointer {})",
+				guami.amf_id.set_id.inner(),
+				guami.amf_id.pointer_id
+			)
+		})
+		.collect::<Vec<_>>();
+	if invalid.is_empty() {
+		Ok(())
+	} else {
+		Err(serde_valid::validation::Error::Custom(format!(
+			"AMF Identifiers {} exceed the AMF Set ID or AMF Pointer width.",
+			invalid.join(", ")
+		)))
+	}
+}
+
+/// AMF Region ID, AMF Set ID and AMF Pointer making up the AMF ID, TS 23.003
+/// 2.10.1.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AmfIdentity {
+	pub amf_region_id: u8,
+	#[validate(maximum = 1023)]
+	pub amf_set_id: u16,
+	#[validate(maximum = 63)]
+	pub amf_pointer: u8,
+}
+
+impl AmfIdentity {
+	pub fn amf_id(&self) -> Result<AmfId, ConversionError> {
+		amf_id_from_parts(self.amf_region_id, self.amf_set_id, self.amf_pointer)
+	}
+}
+
+impl Configuration {
+	/// The served GUAMIs with `amf_identity` applied, each once. They keep
+	/// their configured order, the order of preference a gNB gets them in.
+	pub fn served_guamis(&self) -> Result<NonEmpty<Guami>, ConversionError> {
+		let mut served_guamis = self.served_guami_list.clone();
+		if let Some(identity) = &self.amf_identity {
+			let amf_id = identity.amf_id()?;
+			served_guamis = served_guamis.map(|mut guami| {
+				guami.amf_id = amf_id;
+				guami
+			});
+		}
+		let NonEmpty { head, tail } = served_guamis;
+		let mut unique = NonEmpty::new(head);
+		for guami in tail {
+			if !unique.contains(&guami) {
+				unique.push(guami);
+			}
+		}
+		Ok(unique)
+	}
+}
+
+/// Checks that every MCC is 3 digits and every MNC 2 or 3, TS 23.003 2.2, as
+/// the NGAP PLMN Identity encoding expects.
+fn plmn_ids<'a>(
+	plmn_ids: impl IntoIterator<Item = (&'a [u8], &'a [u8])>
+) -> Result<(), serde_valid::validation::Error> {
+	let is_digits = |code: &[u8], lengths: &[usize]| {
+		lengths.contains(&code.len()) && code.iter().all(u8::is_ascii_digit)
+	};
+	for (mcc, mnc) in plmn_ids {
+		if !is_digits(mcc, &[3]) || !is_digits(mnc, &[2, 3]) {
+			return Err(serde_valid::validation::Error::Custom(format!(
+				"PLMN ID with MCC {:?} and MNC {:?} is malformed.",
+				String::from_utf8_lossy(mcc),
+				String::from_utf8_lossy(mnc)
+			)));
+		}
+	}
+	Ok(())
+}
+
+pub fn plmn_support_list(
+	val: &NonEmpty<PlmnSupportItem>
+) -> Result<(), serde_valid::validation::Error> {
+	plmn_ids(
+		val.iter()
+			.map(|item| (item.plmn_id.mcc.as_bytes(), item.plmn_id.mnc.as_bytes())),
+	)
+}
+
+pub fn guami_plmn_ids(val: &NonEmpty<Guami>) -> Result<(), serde_valid::validation::Error> {
+	plmn_ids(
+		val.iter()
+			.map(|guami| (guami.plmn_id.mcc.as_bytes(), guami.plmn_id.mnc.as_bytes())),
+	)
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn tai_plmn_ids(val: &Vec<Tai>) -> Result<(), serde_valid::validation::Error> {
+	plmn_ids(
+		val.iter()
+			.map(|tai| (tai.plmn_id.mcc.as_bytes(), tai.plmn_id.mnc.as_bytes())),
+	)
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn allowed_plmn_ids(val: &Vec<PlmnId>) -> Result<(), serde_valid::validation::Error> {
+	plmn_ids(
+		val.iter()
+			.map(|plmn_id| (plmn_id.mcc.as_bytes(), plmn_id.mnc.as_bytes())),
+	)
+}
+
+/// Checks that no TAI is listed twice, TACs differing only in case included.
+#[allow(clippy::ptr_arg)]
+pub fn unique_tais(val: &Vec<Tai>) -> Result<(), serde_valid::validation::Error> {
+	let mut seen = TaiSet::default();
+	match val.iter().find(|tai| !seen.insert(tai)) {
+		Some(tai) => Err(serde_valid::validation::Error::Custom(format!(
+			"TAI {tai:?} is listed more than once."
+		))),
+		None => Ok(()),
+	}
+}
+
+/// Checks that the TAIs restricting their slices are supported, and that the
+/// slices they keep are ones their PLMN supports.
+pub fn tai_nssai_availability(
+	config: &Configuration
+) -> Result<(), serde_valid::validation::Error> {
+	let supported_tais = config.support_tai_list.iter().collect::<TaiSet>();
+	for availability in &config.tai_nssai_availability {
+		let tai = &availability.tai;
+		if !supported_tais.contains(tai) {
+			return Err(serde_valid::validation::Error::Custom(format!(
+				"TAI {tai:?} of taiNssaiAvailability is not in supportTaiList."
+			)));
+		}
+		let plmn_snssais = config
+			.plmn_support_list
+			.iter()
+			.find(|item| item.plmn_id == tai.plmn_id)
+			.map(|item| &item.snssai_list);
+		for snssai in &availability.snssai_list {
+			if !plmn_snssais.is_some_and(|snssais| {
+				snssais
+					.iter()
+					.any(|supported| same_slice(supported, snssai))
+			}) {
+				return Err(serde_valid::validation::Error::Custom(format!(
+					"S-NSSAI {snssai:?} of TAI {tai:?} is not supported by its PLMN."
+				)));
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Maximum length of a DNN, and of each of its labels, TS 23.003 9.1.
+const DNN_MAX_LEN: usize = 100;
+const DNN_LABEL_MAX_LEN: usize = 63;
+
+/// Checks that every DNN is a well formed APN Network Identifier, TS 23.003
+/// 9.1.1, and that none is listed twice.
+#[allow(clippy::ptr_arg)]
+pub fn dnn_list(val: &Vec<String>) -> Result<(), serde_valid::validation::Error> {
+	let mut seen = std::collections::HashSet::new();
+	for dnn in val {
+		if !is_valid_dnn(dnn) {
+			return Err(serde_valid::validation::Error::Custom(format!(
+				"DNN {dnn:?} is not a valid APN Network Identifier."
+			)));
+		}
+		if !seen.insert(dnn.to_ascii_lowercase()) {
+			return Err(serde_valid::validation::Error::Custom(format!(
+				"DNN {dnn:?} is listed more than once."
+			)));
+		}
+	}
+	Ok(())
+}
+
+fn is_valid_dnn(dnn: &str) -> bool {
+	dnn.len() <= DNN_MAX_LEN
+		&& dnn.split('.').all(|label| {
+			(1..=DNN_LABEL_MAX_LEN).contains(&label.len())
+				&& label
+					.bytes()
+					.all(|b| b.is_ascii_alphanumeric() || b == b'-')
+				&& !label.starts_with('-')
+				&& !label.ends_with('-')
+		})
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, smart_default::SmartDefault)]
 #[serde(rename_all = "camelCase")]
 pub struct PlmnSupportItem {
@@ -104,11 +481,20 @@ pub struct PlmnSupportItem {
 	pub snssai_list: NonEmpty<Snssai>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaiNssaiAvailability {
+	pub tai: Tai,
+	pub snssai_list: NonEmpty<Snssai>,
+}
+
+/// Raw NGAP capture for field debugging, only honoured when omnipath is built
+/// with the `ngap-tap` feature.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
-pub struct Tls {
-	pub pem: String,
-	pub key: String,
+pub struct NgapTap {
+	pub enable: bool,
+	pub path: PathBuf,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -165,26 +551,6 @@ pub struct Timer {
 	max_retry_times: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
-#[serde(rename_all = "camelCase")]
-pub struct SCTP {
-	num_ostreams: u16,
-	max_instreams: u16,
-	max_attempts: u16,
-	max_init_timeout: u16,
-}
-
-impl Into<InitMsg> for &SCTP {
-	fn into(self) -> InitMsg {
-		InitMsg {
-			num_ostreams: self.num_ostreams,
-			max_instreams: self.max_instreams,
-			max_attempts: self.max_attempts,
-			max_init_timeout: self.max_init_timeout,
-		}
-	}
-}
-
 impl NfConfig for OmniPathConfig {
 	fn get_log_config(&self) -> &LoggingConfig {
 		&self.logger
@@ -229,3 +595,329 @@ impl<T: Validate> SerdeValidated<T> {
 		self.0
 	}
 }
+
+impl<T: Validate + Serialize> SerdeValidated<T> {
+	/// Like [`SerdeValidated::new`], the violations reported along the values
+	/// they are about.
+	pub fn new_with_report(value: T) -> Result<Self, ConfigViolations> {
+		value
+			.validate()
+			.map_err(|errors| ConfigViolations::new(&errors, &value))?;
+		Ok(SerdeValidated(value))
+	}
+}
+
+/// The constraint violations of a config, listed one per line for operators.
+#[derive(Debug)]
+pub struct ConfigViolations(Vec<ConfigViolation>);
+
+#[derive(Debug)]
+pub struct ConfigViolation {
+	/// Path of the field as written in the config file, e.g.
+	/// `configuration.supportDnnList`.
+	pub field: String,
+	pub constraint: String,
+	/// The configured value, when the field could be found in the serialized
+	/// config.
+	pub value: Option<serde_json::Value>,
+}
+
+impl ConfigViolations {
+	pub fn new(
+		errors: &Errors,
+		config: &impl Serialize,
+	) -> Self {
+		let config = serde_json::to_value(config).ok();
+		let violations = invalid_params(errors)
+			.into_iter()
+			.map(|InvalidParam { param, reason }| ConfigViolation {
+				value: config
+					.as_ref()
+					.and_then(|config| config.pointer(&param))
+					.cloned(),
+				field: match param.trim_start_matches('/') {
+					"" => "config".to_owned(),
+					field => field.replace('/', "."),
+				},
+				constraint: reason.unwrap_or_default(),
+			})
+			.collect();
+		Self(violations)
+	}
+
+	pub fn violations(&self) -> &[ConfigViolation] {
+		&self.0
+	}
+}
+
+impl Display for ConfigViolations {
+	fn fmt(
+		&self,
+		f: &mut std::fmt::Formatter<'_>,
+	) -> std::fmt::Result {
+		for (i, violation) in self.0.iter().enumerate() {
+			if i > 0 {
+				writeln!(f)?;
+			}
+			write!(f, "  - {}: {}", violation.field, violation.constraint)?;
+			if let Some(value) = &violation.value {
+				write!(f, " (configured: {value})")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bitvec::field::BitField;
+	use nonempty::nonempty;
+	use oasbi::common::{Mcc, Mnc};
+	use serde_json::json;
+
+	use super::*;
+
+	fn guami(amf_id: &str) -> Guami {
+		serde_json::from_value(json!({
+			"plmnId": { "mcc": "208", "mnc": "93" },
+			"amfId": amf_id,
+		}))
+		.unwrap()
+	}
+
+	#[test]
+	fn test_valid_guami_amf_id() {
+		assert!(amf_id_bit_widths(&nonempty![guami("cafe00"), guami("ffffff")]).is_ok());
+	}
+
+	#[test]
+	fn test_out_of_range_amf_pointer() {
+		let mut out_of_range = guami("cafe00");
+		out_of_range.amf_id.pointer_id = 1 << AMF_POINTER_BITS;
+		assert!(amf_id_bit_widths(&nonempty![guami("cafe00"), out_of_range]).is_err());
+	}
+
+	#[test]
+	fn test_amf_identity_builds_served_guami() {
+		let configuration = Configuration {
+			served_guami_list: nonempty![guami("cafe00")],
+			amf_identity: Some(AmfIdentity {
+				amf_region_id: 0x12,
+				amf_set_id: 0xd1,
+				amf_pointer: 0x16,
+			}),
+			..Default::default()
+		};
+
+		let served_guamis = configuration.served_guamis().unwrap();
+		let ngap_guami: ngap_models::Guami =
+			crate::utils::try_convert(&served_guamis.head).unwrap();
+		let (region_id, set_id, pointer) = (
+			ngap_guami.amf_region_id.0.into_inner(),
+			ngap_guami.amf_set_id.0.into_inner(),
+			ngap_guami.amf_pointer.0.into_inner(),
+		);
+		assert_eq!(region_id.load_be::<u8>(), 0x12);
+		assert_eq!(set_id.load_be::<u16>(), 0xd1);
+		assert_eq!(pointer.load_be::<u8>(), 0x16);
+		assert_eq!(
+			serde_json::to_value(served_guamis.head.amf_id).unwrap(),
+			json!("123456")
+		);
+	}
+
+	#[test]
+	fn test_served_guamis_keep_their_order_once_each() {
+		let configuration = Configuration {
+			served_guami_list: nonempty![
+				guami("cafe02"),
+				guami("cafe00"),
+				guami("cafe02"),
+				guami("cafe01"),
+				guami("cafe00")
+			],
+			..Default::default()
+		};
+		assert_eq!(
+			configuration.served_guamis().unwrap(),
+			nonempty![guami("cafe02"), guami("cafe00"), guami("cafe01")]
+		);
+
+		// The AMF identity makes them all the same GUAMI.
+		let configuration = Configuration {
+			amf_identity: Some(AmfIdentity {
+				amf_region_id: 0x12,
+				amf_set_id: 0xd1,
+				amf_pointer: 0x16,
+			}),
+			..configuration
+		};
+		assert_eq!(configuration.served_guamis().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn test_amf_identity_out_of_range_set_id() {
+		let identity = AmfIdentity {
+			amf_region_id: 0xca,
+			amf_set_id: 1 << AMF_SET_ID_BITS,
+			amf_pointer: 0,
+		};
+		assert!(identity.validate().is_err());
+	}
+
+	#[test]
+	fn test_valid_dnn() {
+		let dnns = vec!["internet".to_owned(), "ims.mnc093.mcc208.gprs".to_owned()];
+		assert!(dnn_list(&dnns).is_ok());
+	}
+
+	#[test]
+	fn test_too_long_dnn_label() {
+		let dnns = vec![format!("{}.internet", "a".repeat(DNN_LABEL_MAX_LEN + 1))];
+		assert!(dnn_list(&dnns).is_err());
+		assert!(dnn_list(&vec!["-internet".to_owned()]).is_err());
+		assert!(dnn_list(&vec!["inter..net".to_owned()]).is_err());
+	}
+
+	#[test]
+	fn test_duplicate_dnn() {
+		let dnns = vec!["internet".to_owned(), "Internet".to_owned()];
+		assert!(dnn_list(&dnns).is_err());
+	}
+
+	#[test]
+	fn test_duplicate_tai() {
+		let tai = |tac: &str| -> Tai {
+			serde_json::from_value(json!({
+				"plmnId": { "mcc": "208", "mnc": "93" },
+				"tac": tac,
+			}))
+			.unwrap()
+		};
+		assert!(unique_tais(&vec![tai("000001"), tai("00000a")]).is_ok());
+		assert!(unique_tais(&vec![tai("000001"), tai("000001")]).is_err());
+		assert!(unique_tais(&vec![tai("00000a"), tai("00000A")]).is_err());
+	}
+
+	#[test]
+	fn test_tai_nssai_availability_follows_the_supported_tais_and_slices() {
+		let tai = |tac: &str| -> Tai {
+			serde_json::from_value(json!({
+				"plmnId": { "mcc": "208", "mnc": "93" },
+				"tac": tac,
+			}))
+			.unwrap()
+		};
+		let slice = |sst: u8| Snssai {
+			sst,
+			..Default::default()
+		};
+		let configuration = |availability: TaiNssaiAvailability| Configuration {
+			support_tai_list: vec![tai("00000a")],
+			plmn_support_list: nonempty![PlmnSupportItem {
+				plmn_id: tai("00000a").plmn_id,
+				snssai_list: nonempty![slice(1), slice(2)],
+			}],
+			tai_nssai_availability: vec![availability],
+			..Default::default()
+		};
+
+		let restricted = configuration(TaiNssaiAvailability {
+			tai: tai("00000A"),
+			snssai_list: nonempty![slice(2)],
+		});
+		assert!(tai_nssai_availability(&restricted).is_ok());
+		let unsupported_tai = configuration(TaiNssaiAvailability {
+			tai: tai("000001"),
+			snssai_list: nonempty![slice(2)],
+		});
+		assert!(tai_nssai_availability(&unsupported_tai).is_err());
+		let unsupported_slice = configuration(TaiNssaiAvailability {
+			tai: tai("00000a"),
+			snssai_list: nonempty![slice(1), slice(3)],
+		});
+		assert!(tai_nssai_availability(&unsupported_slice).is_err());
+	}
+
+	fn plmn_support_item(
+		mcc: &str,
+		mnc: &str,
+	) -> PlmnSupportItem {
+		// Unchecked, so malformed codes reach the validator.
+		let plmn_id = PlmnId {
+			mcc: unsafe { Mcc::new_unchecked(mcc.to_owned()) },
+			mnc: unsafe { Mnc::new_unchecked(mnc.to_owned()) },
+		};
+		PlmnSupportItem {
+			plmn_id,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_valid_plmn_ids() {
+		let items = nonempty![
+			plmn_support_item("208", "93"),
+			plmn_support_item("310", "410")
+		];
+		assert!(plmn_support_list(&items).is_ok());
+	}
+
+	#[test]
+	fn test_two_digit_mcc() {
+		assert!(plmn_support_list(&nonempty![plmn_support_item("20", "93")]).is_err());
+	}
+
+	#[test]
+	fn test_four_digit_mnc() {
+		let items = nonempty![
+			plmn_support_item("208", "93"),
+			plmn_support_item("208", "9301")
+		];
+		assert!(plmn_support_list(&items).is_err());
+	}
+
+	/// The config serialized and parsed back, compared in its JSON form.
+	fn assert_round_trip(config: &OmniPathConfig) {
+		let yaml = serde_yaml::to_string(config).unwrap();
+		let parsed: OmniPathConfig = serde_yaml::from_str(&yaml).unwrap();
+		assert_eq!(
+			serde_json::to_value(&parsed).unwrap(),
+			serde_json::to_value(config).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_default_config_is_valid() {
+		let config = SerdeValidated::new(OmniPathConfig::default()).unwrap();
+		assert_round_trip(config.inner());
+	}
+
+	#[test]
+	fn test_sample_config_round_trip() {
+		let config: OmniPathConfig =
+			serde_yaml::from_str(include_str!("../../../../../config/amfcfg.yaml")).unwrap();
+		assert!(config.validate().is_ok());
+		assert_round_trip(&config);
+	}
+
+	#[test]
+	fn test_min_items_violation_names_the_field() {
+		let mut config = OmniPathConfig::default();
+		config.configuration.support_dnn_list.clear();
+
+		let Err(violations) = SerdeValidated::new_with_report(config) else {
+			panic!("a config without DNNs must be rejected");
+		};
+		let [violation] = violations.violations() else {
+			panic!("expected a single violation, got {violations:?}");
+		};
+		assert_eq!(violation.field, "configuration.supportDnnList");
+		assert_eq!(violation.value, Some(json!([])));
+		assert!(
+			violations
+				.to_string()
+				.starts_with("  - configuration.supportDnnList: The length of the items must be")
+		);
+	}
+}
@@ -1,6 +1,7 @@
 use std::{net::IpAddr, ops::Deref, sync::Arc};
 
 use arc_swap::{ArcSwap, Guard};
+use chrono::{DateTime, Utc};
 use nonempty::NonEmpty;
 use oasbi::{
 	common::{Guami, NfInstanceId, Tai},
@@ -8,13 +9,18 @@ use oasbi::{
 };
 use tokio::sync::OnceCell;
 use uuid::Uuid;
+use versions::SemVer;
 
-use crate::config::{
-	Configuration as OmniPathInnerConfig,
-	OmniPathConfig,
-	PlmnSupportItem,
-	Sbi as SbiConfig,
-	SerdeValidated,
+use crate::{
+	config::{
+		Configuration as OmniPathInnerConfig,
+		Info,
+		OmniPathConfig,
+		PlmnSupportItem,
+		Sbi as SbiConfig,
+		SerdeValidated,
+	},
+	utils::models::{NssaiAvailability, ServiceInstanceId, TaiSet},
 };
 
 #[derive(Debug)]
@@ -32,25 +38,74 @@ pub struct Configuration {
 	pub served_guami_list: NonEmpty<Guami>,
 	pub support_dnn_list: Vec<String>,
 	pub support_tai_list: Vec<Tai>,
+	/// `support_tai_list` for the lookups of NG Setup.
+	pub support_tai_set: TaiSet,
 	pub plmn_support_list: NonEmpty<PlmnSupportItem>,
-	pub tnl_weight_factor: u64,
+	/// Whether NG Setup fails for a gNB broadcasting a PLMN outside
+	/// `plmn_support_list`.
+	pub reject_unsupported_plmns: bool,
+	/// The slices available per TAI, for the TAIs restricting them.
+	pub nssai_availability: NssaiAvailability,
+	/// Weight of the TNL associations of this AMF, TS 38.413 TNL Association
+	/// Weight Factor.
+	pub tnl_weight_factor: u8,
+	/// Capacity of the AMF relative to the others of its set, TS 38.413
+	/// 9.3.1.32.
+	pub relative_amf_capacity: u8,
 	pub nf_services: Vec<NfService1>,
 }
 
+/// Where the NF Instance ID of a [`Configuration`] comes from.
+pub trait NfIdSource {
+	fn nf_id(&self) -> Uuid;
+}
+
+/// A random, version 4, UUID for every configuration.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomNfId;
+
+impl NfIdSource for RandomNfId {
+	fn nf_id(&self) -> Uuid {
+		Uuid::new_v4()
+	}
+}
+
+/// The same UUID for every configuration, for tests asserting on the NF
+/// Instance ID.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedNfId(pub Uuid);
+
+impl NfIdSource for FixedNfId {
+	fn nf_id(&self) -> Uuid {
+		self.0
+	}
+}
+
 impl Configuration {
 	pub fn new(valid_config: &SerdeValidated<OmniPathConfig>) -> Self {
+		Self::with_nf_id_source(valid_config, &RandomNfId)
+	}
+
+	pub fn with_nf_id_source(
+		valid_config: &SerdeValidated<OmniPathConfig>,
+		nf_id_source: &impl NfIdSource,
+	) -> Self {
 		let config = valid_config.inner();
+		// Safety: The AMF identity bit widths are validated with the config.
+		let served_guami_list = config.configuration.served_guamis().unwrap();
 		let OmniPathInnerConfig {
 			amf_name: name,
 			ngap_ip_list: ngap_ips,
-			served_guami_list,
 			support_dnn_list,
 			support_tai_list,
 			plmn_support_list,
+			reject_unsupported_plmns,
+			tai_nssai_availability,
 			ngap_port,
+			tnl_weight_factor,
 			..
 		} = config.configuration.clone();
-		let nf_id = Uuid::new_v4();
+		let nf_id = nf_id_source.nf_id();
 
 		let nf_services = Self::build_nf_services(valid_config);
 		let configuration = Configuration {
@@ -59,10 +114,17 @@ impl Configuration {
 			ngap_port,
 			served_guami_list,
 			support_dnn_list,
+			support_tai_set: support_tai_list.iter().collect(),
 			support_tai_list,
 			plmn_support_list,
+			reject_unsupported_plmns,
+			nssai_availability: tai_nssai_availability
+				.iter()
+				.map(|availability| (&availability.tai, &availability.snssai_list))
+				.collect(),
 			nf_services,
-			tnl_weight_factor: 0,
+			tnl_weight_factor,
+			relative_amf_capacity: u8::MAX,
 			nf_id: NfInstanceId::from(nf_id),
 		};
 		configuration
@@ -71,22 +133,20 @@ impl Configuration {
 	pub fn build_nf_services(config: &SerdeValidated<OmniPathConfig>) -> Vec<NfService1> {
 		let config = config.inner();
 		let api_prefix = Some(config.sbi.get_ipv4_uri());
-		let version_uri = format!("v{}", config.info.version.major);
+		let versions = Self::build_service_versions(&config.info);
+		let service_instance_ids = ServiceInstanceId::assign(&config.sbi.service_name_list);
 		let service_list = config
 			.sbi
 			.service_name_list
 			.iter()
-			.enumerate()
-			.map(|(i, service_name)| -> NfService1 {
+			.zip(service_instance_ids)
+			.map(|(service_name, service_instance_id)| -> NfService1 {
+				let restrictions = &config.sbi.access_restrictions;
 				let nf_service = NfService1 {
 					api_prefix: api_prefix.clone(),
-					service_instance_id: i.to_string(),
+					service_instance_id: service_instance_id.into_inner(),
 					service_name: service_name.to_owned(),
-					versions: vec![NfServiceVersion {
-						api_full_version: config.info.version.to_string(),
-						api_version_in_uri: version_uri.to_owned(),
-						expiry: None,
-					}],
+					versions: versions.clone(),
 					scheme: config.sbi.scheme.clone(),
 					nf_service_status: NfServiceStatus::Registered,
 					ip_end_points: vec![IpEndPoint {
@@ -95,6 +155,9 @@ impl Configuration {
 						port: Some(config.sbi.port),
 						..Default::default()
 					}],
+					allowed_plmns: restrictions.allowed_plmns.clone(),
+					allowed_nf_types: restrictions.allowed_nf_types.clone(),
+					allowed_nssais: restrictions.allowed_nssais.clone(),
 					..Default::default()
 				};
 				nf_service
@@ -103,11 +166,42 @@ impl Configuration {
 
 		service_list
 	}
+
+	/// Versions advertised for every service, `info.version` first followed by
+	/// `info.additional_versions`.
+	pub fn build_service_versions(info: &Info) -> Vec<NfServiceVersion> {
+		std::iter::once(service_version(&info.version, None))
+			.chain(
+				info.additional_versions
+					.iter()
+					.map(|v| service_version(&v.version, v.expiry)),
+			)
+			.collect()
+	}
+}
+
+/// Builds the `NfServiceVersion` of an API version, TS 29.510 6.1.6.2.4.
+pub fn service_version(
+	version: &SemVer,
+	expiry: Option<DateTime<Utc>>,
+) -> NfServiceVersion {
+	NfServiceVersion {
+		api_full_version: version.to_string(),
+		api_version_in_uri: format!("v{}", version.major),
+		expiry,
+	}
 }
 
 impl AppContextInner {
 	pub fn initialize(config: &SerdeValidated<OmniPathConfig>) -> Self {
-		let amf_config = Configuration::new(config);
+		Self::with_nf_id_source(config, &RandomNfId)
+	}
+
+	pub fn with_nf_id_source(
+		config: &SerdeValidated<OmniPathConfig>,
+		nf_id_source: &impl NfIdSource,
+	) -> Self {
+		let amf_config = Configuration::with_nf_id_source(config, nf_id_source);
 		Self {
 			config: ArcSwap::new(Arc::new(amf_config)),
 			sbi: ArcSwap::new(Arc::new(config.inner().sbi.clone())),
@@ -160,6 +254,42 @@ impl AppContextInner {
 	pub fn get_nf_id(&self) -> NfInstanceId {
 		self.get_config().nf_id
 	}
+
+	/// The AMF fields of an NG Setup Response, all taken from one
+	/// configuration snapshot.
+	pub fn ng_setup_response_params(&self) -> NgSetupResponseParams {
+		NgSetupResponseParams(self.config.load_full())
+	}
+}
+
+/// See [`AppContextInner::ng_setup_response_params`].
+#[derive(Debug, Clone)]
+pub struct NgSetupResponseParams(Arc<Configuration>);
+
+impl NgSetupResponseParams {
+	pub fn amf_name(&self) -> &str {
+		&self.0.name
+	}
+
+	pub fn served_guami_list(&self) -> &NonEmpty<Guami> {
+		&self.0.served_guami_list
+	}
+
+	pub fn plmn_support_list(&self) -> &NonEmpty<PlmnSupportItem> {
+		&self.0.plmn_support_list
+	}
+
+	pub fn nssai_availability(&self) -> &NssaiAvailability {
+		&self.0.nssai_availability
+	}
+
+	pub fn relative_amf_capacity(&self) -> u8 {
+		self.0.relative_amf_capacity
+	}
+
+	pub fn tnl_weight_factor(&self) -> u8 {
+		self.0.tnl_weight_factor
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -170,6 +300,18 @@ impl AppContext {
 		let inner_context = AppContextInner::initialize(config);
 		Self(Arc::new(inner_context))
 	}
+
+	/// An app context with its NF Instance ID from `nf_id_source`, see
+	/// [`FixedNfId`].
+	pub fn with_nf_id_source(
+		config: &SerdeValidated<OmniPathConfig>,
+		nf_id_source: &impl NfIdSource,
+	) -> Self {
+		Self(Arc::new(AppContextInner::with_nf_id_source(
+			config,
+			nf_id_source,
+		)))
+	}
 }
 
 impl Deref for AppContext {
@@ -191,3 +333,85 @@ pub async fn get_global_app_context() -> &'static AppContext {
 		})
 		.await
 }
+
+#[cfg(test)]
+mod tests {
+	use oasbi::{common::NfType, nrf::types::ServiceName};
+
+	use super::*;
+
+	#[test]
+	fn test_service_versions_advertise_additional_versions() {
+		let info: Info = serde_yaml::from_str(
+			"version: 2.0.1\ndescription: AMF\nadditionalVersions:\n  - version: 1.3.0\n    \
+			 expiry: 2030-01-01T00:00:00Z\n",
+		)
+		.unwrap();
+		let versions = Configuration::build_service_versions(&info);
+
+		assert_eq!(versions.len(), 2);
+		assert_eq!(versions[0].api_full_version, "2.0.1");
+		assert_eq!(versions[0].api_version_in_uri, "v2");
+		assert!(versions[0].expiry.is_none());
+		assert_eq!(versions[1].api_full_version, "1.3.0");
+		assert_eq!(versions[1].api_version_in_uri, "v1");
+		assert!(versions[1].expiry.is_some());
+	}
+
+	#[test]
+	fn test_services_carry_the_allowed_nf_types() {
+		let mut config = OmniPathConfig::default();
+		config.sbi.service_name_list = vec![ServiceName::NamfComm, ServiceName::NamfEvts];
+		config.sbi.access_restrictions.allowed_nf_types = vec![NfType::Smf, NfType::Ausf];
+		let config = SerdeValidated::new(config).unwrap();
+
+		let nf_services = Configuration::new(&config).nf_services;
+		assert_eq!(nf_services.len(), 2);
+		for nf_service in nf_services {
+			assert_eq!(nf_service.allowed_nf_types, vec![NfType::Smf, NfType::Ausf]);
+			assert!(nf_service.allowed_plmns.is_empty());
+		}
+	}
+
+	#[test]
+	fn test_ng_setup_response_params_keep_their_snapshot() {
+		let config = OmniPathConfig::test_fixture().build();
+		let app_context = AppContext::initialize(&config);
+		let params = app_context.ng_setup_response_params();
+		let served_guamis = params.served_guami_list().len();
+
+		app_context.commit_config(|config| {
+			config.name = "swapped".to_owned();
+			config
+				.served_guami_list
+				.push(config.served_guami_list.head.clone());
+			config.relative_amf_capacity = 1;
+		});
+		assert_eq!(app_context.get_config().name, "swapped");
+		assert_eq!(params.amf_name(), "AMF");
+		assert_eq!(params.served_guami_list().len(), served_guamis);
+		assert_eq!(params.relative_amf_capacity(), u8::MAX);
+		assert_eq!(app_context.ng_setup_response_params().amf_name(), "swapped");
+	}
+
+	#[test]
+	fn test_configured_tnl_weight_factor_is_advertised() {
+		let mut config = OmniPathConfig::default();
+		config.configuration.tnl_weight_factor = 42;
+		let app_context = AppContext::initialize(&SerdeValidated::new(config).unwrap());
+		let params = app_context.ng_setup_response_params();
+		assert_eq!(params.tnl_weight_factor(), 42);
+	}
+
+	#[test]
+	fn test_fixed_nf_id_source_sets_the_nf_id() {
+		let config = OmniPathConfig::test_fixture().build();
+		let nf_id = Uuid::from_u128(0x4947_6f3a_0001_4000_8000_0000_0000_0001);
+		let app_context = AppContext::with_nf_id_source(&config, &FixedNfId(nf_id));
+		assert_eq!(app_context.get_nf_id(), NfInstanceId::from(nf_id));
+
+		let configuration = Configuration::with_nf_id_source(&config, &FixedNfId(nf_id));
+		assert_eq!(configuration.nf_id, app_context.get_nf_id());
+		assert_ne!(Configuration::new(&config).nf_id, configuration.nf_id);
+	}
+}
@@ -0,0 +1,85 @@
+use ngap_models::{AmfUeNgapId, GlobalRanNodeId};
+use tokio::sync::broadcast;
+
+use crate::utils::DiffEntry;
+
+/// Events kept for a subscriber lagging behind, it misses the older ones.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// What happened to the AMF, its gNBs and UEs, for the metrics, the admin API
+/// or external systems to follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmfEvent {
+	/// The NG Setup of the gNB succeeded.
+	GnbConnected(GlobalRanNodeId),
+	/// The association of the gNB went away, with every UE it served.
+	GnbDisconnected(GlobalRanNodeId),
+	/// A UE context was set up by an Initial UE Message.
+	UeRegistered(AmfUeNgapId),
+	UeReleased(AmfUeNgapId),
+	/// The backlog went past the overload threshold, the gNBs were sent an
+	/// Overload Start.
+	OverloadStarted,
+	/// The backlog recovered, the gNBs were sent an Overload Stop.
+	OverloadStopped,
+	NrfRegistered,
+	NrfDeregistered,
+	/// The NRF registered a profile differing from the one sent, by the
+	/// changes turning the sent profile into the registered one.
+	NfProfileAltered(Vec<DiffEntry>),
+}
+
+/// Broadcasts [`AmfEvent`]s to any number of subscribers, the emitters not
+/// knowing about them.
+#[derive(Debug, Clone)]
+pub struct EventBus(broadcast::Sender<AmfEvent>);
+
+impl Default for EventBus {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl EventBus {
+	pub fn new() -> Self {
+		Self(broadcast::channel(EVENT_BUS_CAPACITY).0)
+	}
+
+	/// Receives the events emitted from now on.
+	pub fn subscribe(&self) -> broadcast::Receiver<AmfEvent> {
+		self.0.subscribe()
+	}
+
+	pub fn emit(
+		&self,
+		event: AmfEvent,
+	) {
+		// Without subscribers the event is dropped.
+		let _ = self.0.send(event);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_subscriber_receives_gnb_connected() {
+		let events = EventBus::new();
+		// Emitted before anyone listens, not received.
+		events.emit(AmfEvent::NrfRegistered);
+		let mut subscriber = events.subscribe();
+
+		events.emit(AmfEvent::GnbConnected(GlobalRanNodeId::default()));
+		events.emit(AmfEvent::UeRegistered(AmfUeNgapId(1)));
+		assert_eq!(
+			subscriber.recv().await.unwrap(),
+			AmfEvent::GnbConnected(GlobalRanNodeId::default())
+		);
+		assert_eq!(
+			subscriber.recv().await.unwrap(),
+			AmfEvent::UeRegistered(AmfUeNgapId(1))
+		);
+		assert!(subscriber.try_recv().is_err());
+	}
+}
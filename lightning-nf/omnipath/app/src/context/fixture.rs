@@ -0,0 +1,95 @@
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use ngap_models::{AmfUeNgapId, GlobalRanNodeId, RanUeNgapId, RrcEstablishmentCause};
+use sctp_config::SctpConfig;
+use socket2::Domain;
+use statig::awaitable::IntoStateMachineExt;
+use tokio_sctp::{SctpListener, SctpSocket, SctpStream};
+use tokio_util::sync::CancellationToken;
+
+use super::{EventBus, GnbContext, NgapContext, UeContext, UeIdentityIndex};
+use crate::{
+	nas::nas_context::NasContext,
+	ngap::{
+		network::{Network, TnlaAssociation},
+		procedure_code_enum::ProcedureAllowList,
+	},
+};
+
+impl GnbContext {
+	/// A gNB context over a loopback association, along with the gNB end of
+	/// the association.
+	pub async fn test_fixture(
+		ue_identities: Arc<UeIdentityIndex>,
+		global_ran_node_id: GlobalRanNodeId,
+	) -> (Arc<GnbContext>, SctpStream) {
+		let socket = SctpSocket::new(Domain::IPV4).unwrap();
+		let listener = SctpListener::bind_from(socket, ([127, 0, 0, 1], 0).into()).unwrap();
+		let addr = listener.local_addr().unwrap();
+		let client = SctpSocket::new(Domain::IPV4).unwrap();
+		let (client, accepted) = tokio::join!(client.connect(addr), listener.accept());
+		let tnla = TnlaAssociation::new(accepted.unwrap().0).unwrap();
+		let mut gnb_context =
+			GnbContext::new(Arc::new(tnla), ue_identities, CancellationToken::new());
+		gnb_context.global_ran_node_id = global_ran_node_id;
+		(Arc::new(gnb_context), client.unwrap())
+	}
+}
+
+impl NgapContext {
+	/// An NGAP context listening on a loopback port, allowing one NG Setup at
+	/// a time and every procedure.
+	pub fn test_fixture() -> NgapContext {
+		let localhost = IpAddr::from([127, 0, 0, 1]);
+		let network = Network::new(localhost, 0, &SctpConfig::default()).unwrap();
+		NgapContext::new(
+			network,
+			1,
+			ProcedureAllowList::default(),
+			Duration::from_secs(60),
+			EventBus::new(),
+		)
+	}
+
+	/// A gNB context sharing the UE identities of this context, not yet
+	/// connected to it, see [`GnbContext::test_fixture`].
+	pub async fn gnb_test_fixture(
+		&self,
+		global_ran_node_id: GlobalRanNodeId,
+	) -> (Arc<GnbContext>, SctpStream) {
+		GnbContext::test_fixture(self.ue_identities.clone(), global_ran_node_id).await
+	}
+
+	/// Serves `ue_context` at its gNB, as an Initial UE Message would.
+	pub async fn add_test_ue(
+		&self,
+		ue_context: UeContext,
+	) {
+		let served_at = (
+			ue_context.gnb_context.global_ran_node_id.clone(),
+			ue_context.ran_ue_ngap_id,
+		);
+		let amf_ue_ngap_id = ue_context.amf_ue_ngap_id.clone();
+		let gnb_context = ue_context.gnb_context.clone();
+		gnb_context.add_ue_context(ue_context).await.unwrap();
+		self.ue_ids.write().await.insert(amf_ue_ngap_id, served_at);
+	}
+}
+
+impl UeContext {
+	/// A UE context at `gnb_context`, with a fresh NAS state machine.
+	pub fn test_fixture(
+		ran_ue_ngap_id: RanUeNgapId,
+		amf_ue_ngap_id: AmfUeNgapId,
+		gnb_context: Arc<GnbContext>,
+	) -> UeContext {
+		UeContext::new(
+			ran_ue_ngap_id,
+			amf_ue_ngap_id,
+			RrcEstablishmentCause::default(),
+			gnb_context,
+			None,
+			Arc::new(NasContext::new().state_machine()),
+		)
+	}
+}
@@ -1,29 +1,49 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use counter::CounterU64;
+use counter::{CounterU64, GaugeUsize};
 use derive_new::new;
-use ngap_models::{GlobalRanNodeId, PagingDrx};
+use ngap_models::{
+	AmfUeNgapId,
+	GlobalRanNodeId,
+	HandoverFailure,
+	HandoverRequestAcknowledge,
+	NgResetAcknowledge,
+	PagingDrx,
+	RanUeNgapId,
+};
 use nonempty::NonEmpty;
 use oasbi::common::{Snssai, Tai};
+use tokio::sync::{Mutex, oneshot};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-	context::ue_context::UeContext,
-	ngap::{manager::ContextManager, network::TnlaAssociation},
+	context::{UeIdentityIndex, ue_context::UeContext},
+	ngap::{
+		manager::{ContextError, ContextManager},
+		network::TnlaAssociation,
+	},
 };
 
+/// Where the target gNB's answer to a Handover Request goes.
+pub type HandoverPreparation = oneshot::Sender<Result<HandoverRequestAcknowledge, HandoverFailure>>;
+
 #[derive(Debug, new)]
 pub struct GnbContext {
 	pub tnla_association: Arc<TnlaAssociation>,
 
+	/// Shared with the `NgapContext`, so NAS procedures running on a UE of
+	/// this gNB can index the identities they learn.
+	pub ue_identities: Arc<UeIdentityIndex>,
+
 	#[new(default)]
 	pub global_ran_node_id: GlobalRanNodeId,
 
 	#[new(value = "ContextManager::new()")]
 	pub ue_context_manager: ContextManager<UeContext>,
 
+	/// The name the gNB gave in its NG Setup, if any.
 	#[new(default)]
-	pub name: String,
+	pub name: Option<String>,
 
 	#[new(default)]
 	pub default_paging_drx: PagingDrx,
@@ -32,6 +52,121 @@ pub struct GnbContext {
 
 	#[new(default)]
 	pub amf_ue_id_generator: CounterU64,
+
+	#[new(default)]
+	pub load: GnbLoad,
+
+	/// TAIs the gNB advertised in its NG Setup which the AMF does not serve.
+	#[new(default)]
+	pub unsupported_tais: Vec<Tai>,
+
+	/// Slices the gNB advertised in its NG Setup for TAIs they are not
+	/// available in.
+	#[new(default)]
+	pub unavailable_slices: Vec<(Tai, Snssai)>,
+
+	/// Where the NG Reset Acknowledge goes, while an NG Reset sent by the AMF
+	/// awaits it.
+	#[new(default)]
+	pub pending_reset: Mutex<Option<oneshot::Sender<NgResetAcknowledge>>>,
+
+	/// Where the Handover Request Acknowledges or Handover Failures go, by UE,
+	/// while handovers towards this gNB await them.
+	#[new(default)]
+	pub pending_handovers: Mutex<HashMap<AmfUeNgapId, HandoverPreparation>>,
+}
+
+impl GnbContext {
+	pub async fn add_ue_context(
+		&self,
+		ue_context: UeContext,
+	) -> Result<(), ContextError<UeContext>> {
+		self.ue_context_manager.add_context(ue_context).await?;
+		self.load.ue_added();
+		Ok(())
+	}
+
+	/// Returns whether a UE context was present for `ran_ue_ngap_id`.
+	pub async fn release_ue_context(
+		&self,
+		ran_ue_ngap_id: &RanUeNgapId,
+	) -> bool {
+		let released = self.ue_context_manager.remove_context(ran_ue_ngap_id).await;
+		if released {
+			self.load.ue_released();
+		}
+		released
+	}
+
+	/// Drops every UE context at once, for when the association goes away
+	/// without the UEs being released.
+	pub async fn release_all_ue_contexts(&self) {
+		self.ue_context_manager.clear().await;
+		self.load.association_lost();
+	}
+
+	pub fn summary(&self) -> GnbSummary {
+		GnbSummary {
+			global_ran_node_id: self.global_ran_node_id.clone(),
+			name: self.name.clone(),
+			ue_count: self.load.ue_count(),
+			pdu_session_count: self.load.pdu_session_count(),
+			slow_peer: self.tnla_association.stats.is_slow(),
+			unsupported_tai_count: self.unsupported_tais.len(),
+		}
+	}
+}
+
+/// Number of UEs and PDU sessions a gNB is currently serving.
+#[derive(Debug, Default)]
+pub struct GnbLoad {
+	ues: GaugeUsize,
+	pdu_sessions: GaugeUsize,
+}
+
+impl GnbLoad {
+	pub fn ue_added(&self) {
+		self.ues.increment();
+	}
+
+	pub fn ue_released(&self) {
+		self.ues.decrement();
+	}
+
+	pub fn pdu_session_established(&self) {
+		self.pdu_sessions.increment();
+	}
+
+	pub fn pdu_session_released(&self) {
+		self.pdu_sessions.decrement();
+	}
+
+	/// Nothing the gNB served survives its association.
+	pub fn association_lost(&self) {
+		self.ues.reset();
+		self.pdu_sessions.reset();
+	}
+
+	pub fn ue_count(&self) -> usize {
+		self.ues.get()
+	}
+
+	pub fn pdu_session_count(&self) -> usize {
+		self.pdu_sessions.get()
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct GnbSummary {
+	pub global_ran_node_id: GlobalRanNodeId,
+	pub name: Option<String>,
+	pub ue_count: usize,
+	pub pdu_session_count: usize,
+	/// The gNB does not keep up with the messages sent to it.
+	pub slow_peer: bool,
+	/// Advertised TAIs left uncovered by the AMF, see
+	/// [`GnbContext::unsupported_tais`].
+	pub unsupported_tai_count: usize,
 }
 
 #[derive(Debug)]
@@ -39,3 +174,68 @@ pub struct SupportedTai {
 	pub tai: Tai,
 	pub snssais: NonEmpty<Snssai>,
 }
+
+#[cfg(test)]
+mod tests {
+	use tokio::sync::OwnedRwLockWriteGuard;
+
+	use super::*;
+	use crate::{context::PduSession, ngap::manager::PinnedSendSyncFuture};
+
+	#[test]
+	fn test_gnb_load_tracks_ues_and_pdu_sessions() {
+		let load = GnbLoad::default();
+		load.ue_added();
+		load.ue_added();
+		load.pdu_session_established();
+		load.pdu_session_established();
+		load.pdu_session_released();
+		load.ue_released();
+		assert_eq!(load.ue_count(), 1);
+		assert_eq!(load.pdu_session_count(), 1);
+
+		load.ue_added();
+		load.pdu_session_established();
+		load.association_lost();
+		assert_eq!(load.ue_count(), 0);
+		assert_eq!(load.pdu_session_count(), 0);
+
+		load.ue_released();
+		assert_eq!(load.ue_count(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_pdu_sessions_of_the_ues_count_in_the_load() {
+		let (gnb_context, _gnb) = GnbContext::test_fixture(
+			Arc::new(UeIdentityIndex::default()),
+			GlobalRanNodeId::default(),
+		)
+		.await;
+		let session = |id| PduSession::new(id, Snssai::default(), "internet".to_owned());
+		let mut ue_context =
+			UeContext::test_fixture(RanUeNgapId(7), AmfUeNgapId(1), gnb_context.clone());
+		ue_context.add_pdu_session(session(1)).unwrap();
+		assert_eq!(gnb_context.load.pdu_session_count(), 1);
+		gnb_context.add_ue_context(ue_context).await.unwrap();
+		assert_eq!(gnb_context.load.pdu_session_count(), 1);
+
+		let closure = move |mut ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move {
+				ue_context.add_pdu_session(session(5)).unwrap();
+				assert!(ue_context.add_pdu_session(session(5)).is_err());
+				ue_context.release_pdu_session(1).unwrap();
+				assert!(ue_context.release_pdu_session(1).is_none());
+				ue_context.add_pdu_session(session(6)).unwrap();
+			}) as PinnedSendSyncFuture<_>
+		};
+		gnb_context
+			.ue_context_manager
+			.with_context(RanUeNgapId(7), closure)
+			.await
+			.unwrap();
+		assert_eq!(gnb_context.load.pdu_session_count(), 2);
+
+		assert!(gnb_context.release_ue_context(&RanUeNgapId(7)).await);
+		assert_eq!(gnb_context.load.pdu_session_count(), 0);
+	}
+}
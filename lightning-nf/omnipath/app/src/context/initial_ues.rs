@@ -0,0 +1,119 @@
+use ngap_models::{AmfUeNgapId, GlobalRanNodeId, RanUeNgapId};
+use rustc_hash::FxBuildHasher;
+use scc::hash_map::{Entry, HashMap as SccHashMap};
+
+/// The outcome of claiming the UE of an Initial UE Message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitialUe {
+	/// First seen, its context is for the caller to create.
+	New(AmfUeNgapId),
+	/// A retransmission, the context of the first message is reused.
+	Retransmitted(AmfUeNgapId),
+}
+
+/// The UEs of the Initial UE Messages received, by the gNB and the
+/// `RanUeNgapId` it picked for them.
+///
+/// A gNB may retransmit an Initial UE Message before learning the
+/// `AmfUeNgapId` of the UE. Claiming the pair before creating the UE context
+/// makes these retransmissions, even concurrent ones, resolve to the first
+/// context.
+#[derive(Debug)]
+pub struct InitialUes(SccHashMap<(GlobalRanNodeId, RanUeNgapId), AmfUeNgapId, FxBuildHasher>);
+
+impl Default for InitialUes {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl InitialUes {
+	pub fn new() -> Self {
+		Self(SccHashMap::with_hasher(FxBuildHasher::default()))
+	}
+
+	/// Claims the UE, calling `allocate` for its `AmfUeNgapId` only when first
+	/// seen.
+	pub async fn claim(
+		&self,
+		global_ran_node_id: GlobalRanNodeId,
+		ran_ue_ngap_id: RanUeNgapId,
+		allocate: impl FnOnce() -> AmfUeNgapId,
+	) -> InitialUe {
+		let key = (global_ran_node_id, ran_ue_ngap_id);
+		match self.0.entry_async(key).await {
+			Entry::Occupied(entry) => InitialUe::Retransmitted(entry.get().clone()),
+			Entry::Vacant(entry) => InitialUe::New(entry.insert_entry(allocate()).get().clone()),
+		}
+	}
+
+	/// Forgets a released UE, a later Initial UE Message with its
+	/// `RanUeNgapId` being for a new UE.
+	pub async fn release(
+		&self,
+		global_ran_node_id: GlobalRanNodeId,
+		ran_ue_ngap_id: RanUeNgapId,
+	) {
+		self.0
+			.remove_async(&(global_ran_node_id, ran_ue_ngap_id))
+			.await;
+	}
+
+	/// Forgets every UE of a gNB whose association went away.
+	pub async fn release_gnb(
+		&self,
+		global_ran_node_id: &GlobalRanNodeId,
+	) {
+		self.0
+			.retain_async(|(gnb, _), _| gnb != global_ran_node_id)
+			.await;
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU64, Ordering};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_retransmitted_initial_ue_message_reuses_the_ue() {
+		let initial_ues = InitialUes::new();
+		let next_id = AtomicU64::new(1);
+		let allocate = || AmfUeNgapId(next_id.fetch_add(1, Ordering::Relaxed));
+		let gnb = GlobalRanNodeId::default();
+
+		let first = initial_ues
+			.claim(gnb.clone(), RanUeNgapId(7), allocate)
+			.await;
+		let retransmitted = initial_ues
+			.claim(gnb.clone(), RanUeNgapId(7), allocate)
+			.await;
+		assert_eq!(first, InitialUe::New(AmfUeNgapId(1)));
+		assert_eq!(retransmitted, InitialUe::Retransmitted(AmfUeNgapId(1)));
+		assert_eq!(initial_ues.len(), 1);
+
+		let other = initial_ues
+			.claim(gnb.clone(), RanUeNgapId(8), allocate)
+			.await;
+		assert_eq!(other, InitialUe::New(AmfUeNgapId(2)));
+
+		// Once released, the gNB may give the `RanUeNgapId` to a new UE.
+		initial_ues.release(gnb.clone(), RanUeNgapId(7)).await;
+		let reused = initial_ues
+			.claim(gnb.clone(), RanUeNgapId(7), allocate)
+			.await;
+		assert_eq!(reused, InitialUe::New(AmfUeNgapId(3)));
+
+		initial_ues.release_gnb(&gnb).await;
+		assert!(initial_ues.is_empty());
+	}
+}
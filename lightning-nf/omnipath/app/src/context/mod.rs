@@ -1,9 +1,29 @@
 pub mod app_context;
+mod events;
+#[cfg(test)]
+mod fixture;
 mod gnb_context;
+mod initial_ues;
 mod ngap_context;
+mod ngap_stats;
+mod overload;
+mod pdu_session;
+mod readiness;
+mod setup_failures;
 mod ue_context;
+mod ue_identity_index;
+mod ue_inactivity;
 
 pub use app_context::AppContext;
-pub use gnb_context::{GnbContext, SupportedTai};
-pub use ngap_context::NgapContext;
-pub use ue_context::UeContext;
+pub use events::{AmfEvent, EventBus};
+pub use gnb_context::{GnbContext, GnbLoad, GnbSummary, SupportedTai};
+pub use initial_ues::{InitialUe, InitialUes};
+pub use ngap_context::{ForceReleaseError, NgapContext};
+pub use ngap_stats::{ActiveTask, NgapCounters, NgapStats};
+pub use overload::{OverloadControl, OverloadTransition};
+pub use pdu_session::{PduSession, PduSessionError, PduSessions, QosFlow, UpTunnel};
+pub use readiness::{Readiness, ReadinessState};
+pub use setup_failures::SetupFailures;
+pub use ue_context::{HandoverState, UeContext};
+pub use ue_identity_index::UeIdentityIndex;
+pub use ue_inactivity::UeInactivity;
@@ -1,16 +1,50 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use ngap_models::{AmfUeNgapId, GlobalRanNodeId, RanUeNgapId};
+use bitvec::field::BitField;
+use client::RequestLimiter;
+use ngap_models::{
+	AmfUeNgapId,
+	Cause,
+	CauseMisc,
+	CauseRadioNetwork,
+	GlobalGnbId,
+	GlobalRanNodeId,
+	GnbId,
+	RanUeNgapId,
+};
 use rustc_hash::FxBuildHasher;
 use scc::hash_map::HashMap as SccHashMap;
-use tokio::sync::RwLock;
+use thiserror::Error;
+use tokio::{
+	sync::{Mutex, OwnedRwLockWriteGuard, RwLock},
+	task::JoinSet,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 
 use crate::{
-	context::GnbContext,
+	context::{
+		AmfEvent,
+		EventBus,
+		GnbContext,
+		GnbSummary,
+		InitialUes,
+		NgapCounters,
+		NgapStats,
+		OverloadControl,
+		SetupFailures,
+		UeContext,
+		UeIdentityIndex,
+		UeInactivity,
+	},
+	nas::is_deregistered,
 	ngap::{
 		constants::app::INITIAL_GNB_CAPACITY,
+		manager::PinnedSendSyncFuture,
 		network::{Network, TnlaAssociation},
+		procedure_code_enum::ProcedureAllowList,
 	},
+	utils::models::{FiveGGuti, Supi, UeIdentity},
 };
 
 pub struct NgapContext {
@@ -20,12 +54,32 @@ pub struct NgapContext {
 	pub(crate) _gnb_associations:
 		Arc<RwLock<HashMap<GlobalRanNodeId, Arc<TnlaAssociation>, FxBuildHasher>>>,
 	// TODO: Ideally Read heavy, so used better data structure for ue_ids.
-	pub(crate) _ue_ids:
+	pub(crate) ue_ids:
 		Arc<RwLock<HashMap<AmfUeNgapId, (GlobalRanNodeId, RanUeNgapId), FxBuildHasher>>>,
+	pub(crate) ue_identities: Arc<UeIdentityIndex>,
+	pub(crate) initial_ues: InitialUes,
+	/// Bounds the NG Setups handled at once, accepted associations past it
+	/// wait their turn.
+	pub(crate) ng_setup_limiter: RequestLimiter,
+	/// NGAP loops of the connected gNBs, awaited on graceful shutdown.
+	pub(crate) gnb_tasks: Mutex<JoinSet<()>>,
+	pub(crate) enabled_procedures: ProcedureAllowList,
+	pub(crate) setup_failures: SetupFailures,
+	pub(crate) ue_inactivity: UeInactivity,
+	pub(crate) events: EventBus,
+	pub(crate) counters: NgapCounters,
+	/// Without it the AMF never goes into overload.
+	pub(crate) overload: Option<OverloadControl>,
 }
 
 impl NgapContext {
-	pub fn new(network: Network) -> Self {
+	pub fn new(
+		network: Network,
+		max_concurrent_ng_setups: usize,
+		enabled_procedures: ProcedureAllowList,
+		ue_inactivity_timeout: Duration,
+		events: EventBus,
+	) -> Self {
 		NgapContext {
 			gnb_contexts: SccHashMap::with_capacity_and_hasher(
 				INITIAL_GNB_CAPACITY,
@@ -36,10 +90,485 @@ impl NgapContext {
 				INITIAL_GNB_CAPACITY,
 				FxBuildHasher::default(),
 			))),
-			_ue_ids: Arc::new(RwLock::new(HashMap::with_capacity_and_hasher(
+			ue_ids: Arc::new(RwLock::new(HashMap::with_capacity_and_hasher(
 				INITIAL_GNB_CAPACITY,
 				FxBuildHasher::default(),
 			))),
+			ue_identities: Arc::new(UeIdentityIndex::new()),
+			initial_ues: InitialUes::new(),
+			ng_setup_limiter: RequestLimiter::new(max_concurrent_ng_setups),
+			gnb_tasks: Mutex::new(JoinSet::new()),
+			enabled_procedures,
+			setup_failures: SetupFailures::default(),
+			ue_inactivity: UeInactivity::new(ue_inactivity_timeout),
+			events,
+			counters: NgapCounters::default(),
+			overload: None,
+		}
+	}
+
+	/// Puts the AMF into overload whenever its backlog goes past the
+	/// threshold of `overload`, see [`NgapContext::monitor_overload`].
+	pub fn with_overload_control(
+		mut self,
+		overload: OverloadControl,
+	) -> Self {
+		self.overload = Some(overload);
+		self
+	}
+
+	/// Resolves a UE to its serving gNB and `RanUeNgapId` using the
+	/// `AmfUeNgapId`.
+	pub async fn find_ue(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+	) -> Option<(GlobalRanNodeId, RanUeNgapId)> {
+		self.ue_ids.read().await.get(amf_ue_ngap_id).cloned()
+	}
+
+	pub async fn find_ue_by_guti(
+		&self,
+		guti: &FiveGGuti,
+	) -> Option<(GlobalRanNodeId, RanUeNgapId)> {
+		let amf_ue_ngap_id = self.ue_identities.find_by_guti(guti)?;
+		self.find_ue(&amf_ue_ngap_id).await
+	}
+
+	pub async fn find_ue_by_supi(
+		&self,
+		supi: &Supi,
+	) -> Option<(GlobalRanNodeId, RanUeNgapId)> {
+		let amf_ue_ngap_id = self.ue_identities.find_by_supi(supi)?;
+		self.find_ue(&amf_ue_ngap_id).await
+	}
+
+	/// Drops the NGAP association and context of a UE released to CM-IDLE.
+	/// Its GUTI and SUPI stay indexed, so it is told apart from a UE the AMF
+	/// does not know.
+	pub async fn release_ue(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+	) {
+		let ue = self.ue_ids.write().await.remove(amf_ue_ngap_id);
+		self.ue_inactivity.released(amf_ue_ngap_id.clone());
+		if let Some((global_ran_node_id, ran_ue_ngap_id)) = ue {
+			self.initial_ues
+				.release(global_ran_node_id.clone(), ran_ue_ngap_id)
+				.await;
+			let gnb_context = self
+				.gnb_contexts
+				.read_async(&global_ran_node_id, |_, gnb| gnb.clone())
+				.await;
+			if let Some(gnb_context) = gnb_context {
+				gnb_context.release_ue_context(&ran_ue_ngap_id).await;
+			}
+			self.events
+				.emit(AmfEvent::UeReleased(amf_ue_ngap_id.clone()));
+		}
+	}
+
+	/// Forgets a gNB whose association went away, along with the contexts of
+	/// every UE it was serving, leaving them in CM-IDLE.
+	pub async fn remove_gnb(
+		&self,
+		gnb_context: &GnbContext,
+	) {
+		self.gnb_contexts
+			.remove_async(&gnb_context.global_ran_node_id)
+			.await;
+		self.initial_ues
+			.release_gnb(&gnb_context.global_ran_node_id)
+			.await;
+		let released = {
+			let mut ue_ids = self.ue_ids.write().await;
+			let released = ue_ids
+				.iter()
+				.filter(|(_, (gnb, _))| gnb == &gnb_context.global_ran_node_id)
+				.map(|(amf_ue_ngap_id, _)| amf_ue_ngap_id.clone())
+				.collect::<Vec<_>>();
+			for amf_ue_ngap_id in &released {
+				ue_ids.remove(amf_ue_ngap_id);
+			}
+			released
+		};
+		for amf_ue_ngap_id in &released {
+			self.ue_inactivity.released(amf_ue_ngap_id.clone());
+			self.events
+				.emit(AmfEvent::UeReleased(amf_ue_ngap_id.clone()));
+		}
+		gnb_context.release_all_ue_contexts().await;
+		self.events.emit(AmfEvent::GnbDisconnected(
+			gnb_context.global_ran_node_id.clone(),
+		));
+	}
+
+	/// Releases the UEs going without activity for the configured timeout,
+	/// until `shutdown`.
+	pub async fn release_inactive_ues(
+		&self,
+		shutdown: CancellationToken,
+	) {
+		self.ue_inactivity
+			.run(shutdown, async |amf_ue_ngap_id| {
+				self.release_inactive_ue(&amf_ue_ngap_id).await
+			})
+			.await
+	}
+
+	/// Asks the serving gNB to release an inactive UE, moving it to CM-IDLE,
+	/// and drops its context.
+	async fn release_inactive_ue(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+	) {
+		info!("Releasing inactive Ue {:?}", amf_ue_ngap_id);
+		let cause = Cause::RadioNetwork(CauseRadioNetwork::UserInactivity);
+		self.release_ue_with_command(amf_ue_ngap_id, cause).await;
+	}
+
+	/// Evicts the UE of a SUPI or 5G-GUTI on operator request, releasing it at
+	/// its serving gNB and dropping its context and identities.
+	///
+	/// A registered UE ought to be sent a network initiated Deregistration
+	/// Request first, which the NAS layer cannot build yet, so it is evicted
+	/// without one.
+	pub async fn force_release_ue(
+		&self,
+		identity: &UeIdentity,
+	) -> Result<(), ForceReleaseError> {
+		let amf_ue_ngap_id = match identity {
+			UeIdentity::Supi(supi) => self.ue_identities.find_by_supi(supi),
+			UeIdentity::Guti(guti) => self.ue_identities.find_by_guti(guti),
+		};
+		let Some(amf_ue_ngap_id) = amf_ue_ngap_id else {
+			return Err(ForceReleaseError::UeNotFound(identity.clone()));
+		};
+		info!("Force releasing Ue {} ({:?})", identity, amf_ue_ngap_id);
+		if !self.is_deregistered(&amf_ue_ngap_id).await {
+			warn!(
+				"Force releasing registered Ue {} without a Deregistration Request",
+				identity
+			);
+		}
+		let cause = Cause::Misc(CauseMisc::OmIntervention);
+		self.release_ue_with_command(&amf_ue_ngap_id, cause).await;
+		self.ue_identities.remove(&amf_ue_ngap_id);
+		Ok(())
+	}
+
+	/// Whether the GMM state of the UE is deregistered, a UE whose context is
+	/// gone has nothing left to deregister.
+	async fn is_deregistered(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+	) -> bool {
+		let Some((global_ran_node_id, ran_ue_ngap_id)) = self.find_ue(amf_ue_ngap_id).await else {
+			return true;
+		};
+		let gnb_context = self
+			.gnb_contexts
+			.read_async(&global_ran_node_id, |_, gnb| gnb.clone())
+			.await;
+		let Some(gnb_context) = gnb_context else {
+			return true;
+		};
+		let future_closure = |ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move { is_deregistered(&ue_context.gmm) }) as PinnedSendSyncFuture<_>
+		};
+		gnb_context
+			.ue_context_manager
+			.with_context(ran_ue_ngap_id, future_closure)
+			.await
+			.unwrap_or(true)
+	}
+
+	/// Sends the UE Context Release Command to the serving gNB, if the UE is
+	/// still connected, and drops the context.
+	async fn release_ue_with_command(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+		cause: Cause,
+	) {
+		if let Some((global_ran_node_id, ran_ue_ngap_id)) = self.find_ue(amf_ue_ngap_id).await {
+			let gnb_context = self
+				.gnb_contexts
+				.read_async(&global_ran_node_id, |_, gnb| gnb.clone())
+				.await;
+			if let Some(gnb_context) = gnb_context {
+				let future_closure = |ue_context: OwnedRwLockWriteGuard<UeContext>| {
+					Box::pin(async move { ue_context.send_ue_context_release_command(cause).await })
+						as PinnedSendSyncFuture<_>
+				};
+				let sent = gnb_context
+					.ue_context_manager
+					.with_context(ran_ue_ngap_id, future_closure)
+					.await;
+				if let Ok(Err(e)) = sent {
+					warn!(
+						"Unable to send the Ue Context Release Command of {:?}: {:?}",
+						amf_ue_ngap_id, e
+					);
+				}
+			}
+		}
+		// The UE Context Release Complete is not awaited, the context goes away
+		// whether the gNB answers or not.
+		self.release_ue(amf_ue_ngap_id).await;
+	}
+
+	/// UEs served across every connected gNB.
+	pub async fn ue_count(&self) -> usize {
+		self.ue_ids.read().await.len()
+	}
+
+	pub fn setup_failures(&self) -> &SetupFailures {
+		&self.setup_failures
+	}
+
+	/// UE context operations queued or running across every gNB, along with
+	/// the active message tasks.
+	pub async fn backlog(&self) -> usize {
+		let mut gnb_contexts = Vec::with_capacity(self.gnb_contexts.len());
+		self.gnb_contexts
+			.scan_async(|_, gnb_context| gnb_contexts.push(gnb_context.clone()))
+			.await;
+		let mut backlog = self.counters.active_tasks();
+		for gnb_context in gnb_contexts {
+			backlog += gnb_context.ue_context_manager.queue_depth().await;
 		}
+		backlog
+	}
+
+	pub fn is_overloaded(&self) -> bool {
+		self.overload
+			.as_ref()
+			.is_some_and(OverloadControl::is_overloaded)
+	}
+
+	/// The gNB and UE counts, message counters, active message tasks and
+	/// backlog in one snapshot. The `ue_ids` read lock is held throughout, so
+	/// no UE is added or released while it is taken.
+	pub async fn stats(&self) -> NgapStats {
+		let ue_ids = self.ue_ids.read().await;
+		NgapStats {
+			gnbs: self.gnb_contexts.len(),
+			ues: ue_ids.len(),
+			messages: self.counters.messages(),
+			active_tasks: self.counters.active_tasks(),
+			backlog: self.backlog().await,
+			overloaded: self.is_overloaded(),
+			partial_tai_matches: self.counters.partial_tai_matches(),
+		}
+	}
+
+	pub async fn list_gnbs(&self) -> Vec<GnbSummary> {
+		let mut gnbs = Vec::with_capacity(self.gnb_contexts.len());
+		self.gnb_contexts
+			.scan_async(|_, gnb_context| gnbs.push(gnb_context.summary()))
+			.await;
+		gnbs
+	}
+
+	/// The connected gNB with the gNB ID `gnb_id`, TS 38.413 9.3.1.6.
+	pub async fn find_gnb_by_gnb_id(
+		&self,
+		gnb_id: u32,
+	) -> Option<GlobalRanNodeId> {
+		let mut found = None;
+		self.gnb_contexts
+			.scan_async(|global_ran_node_id, _| {
+				if matches!(
+					global_ran_node_id,
+					GlobalRanNodeId::GlobalGnbId(GlobalGnbId {
+						gnb_id: GnbId::GnbId(id),
+						..
+					}) if id.clone().into_inner().load_be::<u32>() == gnb_id
+				) {
+					found = Some(global_ran_node_id.clone());
+				}
+			})
+			.await;
+		found
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum ForceReleaseError {
+	#[error("UeNotFound: No Ue is known by {0}")]
+	UeNotFound(UeIdentity),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		nas::registration_initiated_test_fixture,
+		ngap::procedure_code_enum::ProcedureCodeEnum,
+	};
+
+	#[tokio::test]
+	async fn test_force_released_ue_is_purged() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let global_ran_node_id = gnb_context.global_ran_node_id.clone();
+		ngap_context
+			.gnb_contexts
+			.insert_async(global_ran_node_id.clone(), gnb_context.clone())
+			.await
+			.unwrap();
+
+		let amf_ue_ngap_id = AmfUeNgapId(1);
+		let mut ue_context =
+			UeContext::test_fixture(RanUeNgapId(7), amf_ue_ngap_id.clone(), gnb_context.clone());
+		let supi: Supi = "imsi-208930000000001".parse().unwrap();
+		let guti: FiveGGuti = "20893cafe0000000001".parse().unwrap();
+		ue_context.set_supi(supi.clone());
+		ue_context.set_guti(guti.clone());
+		ngap_context.add_test_ue(ue_context).await;
+
+		let identity = UeIdentity::Supi(supi.clone());
+		ngap_context.force_release_ue(&identity).await.unwrap();
+		assert!(ngap_context.find_ue_by_supi(&supi).await.is_none());
+		assert!(ngap_context.ue_identities.find_by_guti(&guti).is_none());
+		assert_eq!(ngap_context.ue_count().await, 0);
+		assert!(gnb_context.ue_context_manager.is_empty());
+		assert_eq!(gnb_context.load.ue_count(), 0);
+		assert!(matches!(
+			ngap_context.force_release_ue(&UeIdentity::Guti(guti)).await,
+			Err(ForceReleaseError::UeNotFound(_))
+		));
+	}
+
+	#[tokio::test]
+	async fn test_registered_ue_is_force_released() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		ngap_context
+			.gnb_contexts
+			.insert_async(gnb_context.global_ran_node_id.clone(), gnb_context.clone())
+			.await
+			.unwrap();
+
+		let mut ue_context =
+			UeContext::test_fixture(RanUeNgapId(7), AmfUeNgapId(1), gnb_context.clone());
+		ue_context.gmm = Arc::new(registration_initiated_test_fixture());
+		let supi: Supi = "imsi-208930000000001".parse().unwrap();
+		let guti: FiveGGuti = "20893cafe0000000001".parse().unwrap();
+		ue_context.set_supi(supi.clone());
+		ue_context.set_guti(guti.clone());
+		ngap_context.add_test_ue(ue_context).await;
+		assert!(!ngap_context.is_deregistered(&AmfUeNgapId(1)).await);
+
+		ngap_context
+			.force_release_ue(&UeIdentity::Guti(guti.clone()))
+			.await
+			.unwrap();
+		assert_eq!(ngap_context.ue_identities.find_by_supi(&supi), None);
+		assert_eq!(ngap_context.ue_identities.find_by_guti(&guti), None);
+		assert!(gnb_context.ue_context_manager.is_empty());
+		assert_eq!(ngap_context.ue_count().await, 0);
+	}
+
+	#[tokio::test]
+	async fn test_released_ue_stays_known_in_cm_idle() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let amf_ue_ngap_id = AmfUeNgapId(1);
+		let mut ue_context =
+			UeContext::test_fixture(RanUeNgapId(7), amf_ue_ngap_id.clone(), gnb_context.clone());
+		let supi: Supi = "imsi-208930000000001".parse().unwrap();
+		ue_context.set_supi(supi.clone());
+		ngap_context.add_test_ue(ue_context).await;
+
+		ngap_context.release_ue(&amf_ue_ngap_id).await;
+		assert!(ngap_context.find_ue_by_supi(&supi).await.is_none());
+		assert_eq!(
+			ngap_context.ue_identities.find_by_supi(&supi),
+			Some(amf_ue_ngap_id)
+		);
+		assert!(gnb_context.ue_context_manager.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_idle_ue_is_purged_without_the_identities_of_others() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let global_ran_node_id = gnb_context.global_ran_node_id.clone();
+		let amf_ue_ngap_id = AmfUeNgapId(1);
+		let mut ue_context =
+			UeContext::test_fixture(RanUeNgapId(7), amf_ue_ngap_id.clone(), gnb_context.clone());
+		let supi: Supi = "imsi-208930000000001".parse().unwrap();
+		ue_context.set_supi(supi.clone());
+		ngap_context.add_test_ue(ue_context).await;
+		let other_supi: Supi = "imsi-208930000000002".parse().unwrap();
+		ngap_context
+			.ue_identities
+			.index_supi(other_supi.clone(), AmfUeNgapId(2));
+
+		assert_eq!(
+			ngap_context.find_ue_by_supi(&supi).await,
+			Some((global_ran_node_id, RanUeNgapId(7)))
+		);
+		ngap_context.release_ue(&amf_ue_ngap_id).await;
+		assert_eq!(
+			ngap_context.ue_identities.find_by_supi(&supi),
+			Some(amf_ue_ngap_id)
+		);
+
+		ngap_context
+			.force_release_ue(&UeIdentity::Supi(supi.clone()))
+			.await
+			.unwrap();
+		assert_eq!(ngap_context.ue_identities.find_by_supi(&supi), None);
+		assert_eq!(
+			ngap_context.ue_identities.find_by_supi(&other_supi),
+			Some(AmfUeNgapId(2))
+		);
+	}
+
+	#[tokio::test]
+	async fn test_stats_reflect_the_connected_gnb_and_ue() {
+		let ngap_context = NgapContext::test_fixture();
+		assert_eq!(ngap_context.stats().await, NgapStats::default());
+
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let global_ran_node_id = gnb_context.global_ran_node_id.clone();
+		ngap_context
+			.gnb_contexts
+			.insert_async(global_ran_node_id.clone(), gnb_context)
+			.await
+			.unwrap();
+		ngap_context
+			.ue_ids
+			.write()
+			.await
+			.insert(AmfUeNgapId(1), (global_ran_node_id, RanUeNgapId(7)));
+		// The header of an Initial UE Message with an empty IE container.
+		ngap_context
+			.counters
+			.record_message(&[0x00, 0x0f, 0x40, 0x03, 0x00, 0x00, 0x00]);
+		let task = ngap_context.counters.track_task();
+
+		let stats = ngap_context.stats().await;
+		assert_eq!(
+			(stats.gnbs, stats.ues, stats.active_tasks, stats.backlog),
+			(1, 1, 1, 1)
+		);
+		assert_eq!(
+			stats.messages.into_iter().collect::<Vec<_>>(),
+			[(ProcedureCodeEnum::InitialUEMessage, 1)]
+		);
+		drop(task);
+		assert_eq!(ngap_context.stats().await.active_tasks, 0);
 	}
 }
@@ -0,0 +1,105 @@
+use std::{
+	collections::BTreeMap,
+	sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use ngap_models::NgapPdu;
+
+use crate::ngap::procedure_code_enum::ProcedureCodeEnum;
+
+const PROCEDURE_CODES: usize = ProcedureCodeEnum::SecondaryRATDataUsageReport as usize + 1;
+
+/// Messages received from the gNBs per NGAP procedure, along with the message
+/// tasks in flight and the warnings raised on NG Setup.
+#[derive(Debug)]
+pub struct NgapCounters {
+	messages: [AtomicU64; PROCEDURE_CODES],
+	active_tasks: AtomicUsize,
+	partial_tai_matches: AtomicU64,
+}
+
+impl Default for NgapCounters {
+	fn default() -> Self {
+		Self {
+			messages: std::array::from_fn(|_| AtomicU64::new(0)),
+			active_tasks: AtomicUsize::new(0),
+			partial_tai_matches: AtomicU64::new(0),
+		}
+	}
+}
+
+impl NgapCounters {
+	/// Counts a received message under its procedure, messages of no known
+	/// procedure are not counted.
+	pub fn record_message(
+		&self,
+		message: &[u8],
+	) {
+		let (_, procedure_code, _) = NgapPdu::get_message_info(message);
+		if let Some(procedure) =
+			procedure_code.and_then(|code| ProcedureCodeEnum::try_from(code).ok())
+		{
+			self.messages[procedure as usize].fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	/// Counts a message task as active until the returned guard is dropped.
+	pub fn track_task(&self) -> ActiveTask<'_> {
+		self.active_tasks.fetch_add(1, Ordering::Relaxed);
+		ActiveTask(&self.active_tasks)
+	}
+
+	pub fn active_tasks(&self) -> usize {
+		self.active_tasks.load(Ordering::Relaxed)
+	}
+
+	/// Counts an NG Setup accepted although the gNB advertised TAIs the AMF
+	/// does not serve.
+	pub fn record_partial_tai_match(&self) {
+		self.partial_tai_matches.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn partial_tai_matches(&self) -> u64 {
+		self.partial_tai_matches.load(Ordering::Relaxed)
+	}
+
+	/// The message counts of the procedures with any message received.
+	pub fn messages(&self) -> BTreeMap<ProcedureCodeEnum, u64> {
+		self.messages
+			.iter()
+			.enumerate()
+			.filter_map(|(code, count)| {
+				let count = count.load(Ordering::Relaxed);
+				let procedure = ProcedureCodeEnum::try_from(code as u8).ok()?;
+				(count > 0).then_some((procedure, count))
+			})
+			.collect()
+	}
+}
+
+/// See [`NgapCounters::track_task`].
+#[derive(Debug)]
+pub struct ActiveTask<'a>(&'a AtomicUsize);
+
+impl Drop for ActiveTask<'_> {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+/// A snapshot of the NGAP side of the AMF, see [`NgapContext::stats`].
+///
+/// [`NgapContext::stats`]: super::NgapContext::stats
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NgapStats {
+	pub gnbs: usize,
+	pub ues: usize,
+	pub messages: BTreeMap<ProcedureCodeEnum, u64>,
+	pub active_tasks: usize,
+	/// UE context operations and message tasks pending, the load overload is
+	/// judged by.
+	pub backlog: usize,
+	pub overloaded: bool,
+	/// See [`NgapCounters::record_partial_tai_match`].
+	pub partial_tai_matches: u64,
+}
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the AMF went into or out of overload, see
+/// [`OverloadControl::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverloadTransition {
+	Start,
+	Stop,
+}
+
+/// Tracks the overload of the AMF from its backlog, the UE context operations
+/// and NGAP message tasks pending. Overload starts past the threshold and
+/// stops once the backlog is back down to the recovery level, so a backlog
+/// hovering around the threshold does not flap it.
+#[derive(Debug)]
+pub struct OverloadControl {
+	threshold: usize,
+	recovery: usize,
+	overloaded: AtomicBool,
+}
+
+impl OverloadControl {
+	/// Overload stops with the backlog at `recovery_percent` of the threshold.
+	pub fn new(
+		threshold: usize,
+		recovery_percent: usize,
+	) -> Self {
+		Self {
+			threshold,
+			recovery: threshold * recovery_percent.min(100) / 100,
+			overloaded: AtomicBool::new(false),
+		}
+	}
+
+	pub fn is_overloaded(&self) -> bool {
+		self.overloaded.load(Ordering::Relaxed)
+	}
+
+	/// Takes in the current backlog, returning the transition it causes, if
+	/// any.
+	pub fn update(
+		&self,
+		backlog: usize,
+	) -> Option<OverloadTransition> {
+		let overloaded = self.is_overloaded();
+		if !overloaded && backlog > self.threshold {
+			self.overloaded.store(true, Ordering::Relaxed);
+			Some(OverloadTransition::Start)
+		} else if overloaded && backlog <= self.recovery {
+			self.overloaded.store(false, Ordering::Relaxed);
+			Some(OverloadTransition::Stop)
+		} else {
+			None
+		}
+	}
+}
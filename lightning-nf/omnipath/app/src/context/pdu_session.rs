@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, net::IpAddr};
+
+use oasbi::common::Snssai;
+use thiserror::Error;
+
+/// PDU session identities a UE may use, TS 24.007 11.2.3.1b.
+const PDU_SESSION_IDS: std::ops::RangeInclusive<u8> = 1..=15;
+
+/// An N3 GTP-U tunnel endpoint, TS 38.413 9.3.2.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpTunnel {
+	pub transport_layer_address: IpAddr,
+	pub gtp_teid: u32,
+}
+
+/// A QoS flow of a PDU session, TS 23.501 5.7.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QosFlow {
+	pub qfi: u8,
+	pub five_qi: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct PduSession {
+	pub id: u8,
+	pub snssai: Snssai,
+	pub dnn: String,
+	pub qos_flows: Vec<QosFlow>,
+	/// The UPF end of the tunnel, given by the SMF.
+	pub ul_tunnel: Option<UpTunnel>,
+	/// The gNB end of the tunnel, known once the gNB set the resources up.
+	pub dl_tunnel: Option<UpTunnel>,
+}
+
+impl PduSession {
+	pub fn new(
+		id: u8,
+		snssai: Snssai,
+		dnn: String,
+	) -> Self {
+		Self {
+			id,
+			snssai,
+			dnn,
+			qos_flows: Vec::new(),
+			ul_tunnel: None,
+			dl_tunnel: None,
+		}
+	}
+
+	/// The user plane is set up on both ends.
+	pub fn is_active(&self) -> bool {
+		self.ul_tunnel.is_some() && self.dl_tunnel.is_some()
+	}
+}
+
+/// The PDU sessions of a UE by their id.
+#[derive(Debug, Clone, Default)]
+pub struct PduSessions(BTreeMap<u8, PduSession>);
+
+impl PduSessions {
+	pub fn add(
+		&mut self,
+		session: PduSession,
+	) -> Result<(), PduSessionError> {
+		if !PDU_SESSION_IDS.contains(&session.id) {
+			return Err(PduSessionError::InvalidIdError(session.id));
+		}
+		if self.0.contains_key(&session.id) {
+			return Err(PduSessionError::DuplicateSessionError(session.id));
+		}
+		self.0.insert(session.id, session);
+		Ok(())
+	}
+
+	pub fn modify(
+		&mut self,
+		id: u8,
+		modify: impl FnOnce(&mut PduSession),
+	) -> Result<(), PduSessionError> {
+		let session = self
+			.0
+			.get_mut(&id)
+			.ok_or(PduSessionError::UnknownSessionError(id))?;
+		modify(session);
+		// The id keys the session, it is not for `modify` to change.
+		session.id = id;
+		Ok(())
+	}
+
+	pub fn release(
+		&mut self,
+		id: u8,
+	) -> Option<PduSession> {
+		self.0.remove(&id)
+	}
+
+	pub fn get(
+		&self,
+		id: u8,
+	) -> Option<&PduSession> {
+		self.0.get(&id)
+	}
+
+	/// The sessions with their user plane set up, by id.
+	pub fn active(&self) -> impl Iterator<Item = &PduSession> {
+		self.0.values().filter(|session| session.is_active())
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PduSessionError {
+	#[error("InvalidIdError: PDU session id {0} is out of 1 to 15")]
+	InvalidIdError(u8),
+
+	#[error("DuplicateSessionError: PDU session {0} exists already")]
+	DuplicateSessionError(u8),
+
+	#[error("UnknownSessionError: PDU session {0} does not exist")]
+	UnknownSessionError(u8),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tunnel(gtp_teid: u32) -> Option<UpTunnel> {
+		Some(UpTunnel {
+			transport_layer_address: [10, 0, 0, 1].into(),
+			gtp_teid,
+		})
+	}
+
+	#[test]
+	fn test_sessions_are_added_modified_and_released() {
+		let mut sessions = PduSessions::default();
+		let mut internet = PduSession::new(1, Snssai::default(), "internet".to_owned());
+		internet.ul_tunnel = tunnel(0x100);
+		sessions.add(internet).unwrap();
+		sessions
+			.add(PduSession::new(5, Snssai::default(), "ims".to_owned()))
+			.unwrap();
+		assert_eq!(
+			sessions.add(PduSession::new(5, Snssai::default(), "ims".to_owned())),
+			Err(PduSessionError::DuplicateSessionError(5))
+		);
+		assert_eq!(
+			sessions.add(PduSession::new(16, Snssai::default(), "ims".to_owned())),
+			Err(PduSessionError::InvalidIdError(16))
+		);
+		assert_eq!(sessions.len(), 2);
+		assert_eq!(sessions.active().count(), 0);
+
+		// The gNB set the resources of the first session up.
+		sessions
+			.modify(1, |session| {
+				session.dl_tunnel = tunnel(0x200);
+				session.qos_flows.push(QosFlow { qfi: 1, five_qi: 9 });
+			})
+			.unwrap();
+		let active = sessions.active().collect::<Vec<_>>();
+		assert_eq!(active.len(), 1);
+		assert_eq!(active[0].id, 1);
+		assert_eq!(active[0].qos_flows, [QosFlow { qfi: 1, five_qi: 9 }]);
+
+		let released = sessions.release(5).unwrap();
+		assert_eq!(released.dnn, "ims");
+		assert!(sessions.get(5).is_none());
+		assert_eq!(
+			sessions.modify(5, |_| ()),
+			Err(PduSessionError::UnknownSessionError(5))
+		);
+		assert_eq!(sessions.len(), 1);
+	}
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use oasbi::nrf::types::NfStatus;
+use tokio::sync::watch;
+
+/// What the AMF still waits for, or has lost, before it can serve traffic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadinessState {
+	/// The NF profile is registered with the NRF.
+	pub registered: bool,
+	/// The NGAP and SBI listeners accept connections.
+	pub serving: bool,
+	/// NG Setups keep failing, see [`SetupFailures`](super::SetupFailures).
+	pub setup_failing: bool,
+	/// The AMF is winding down, still registered but no longer to be selected
+	/// by peers.
+	pub draining: bool,
+}
+
+impl ReadinessState {
+	pub fn is_ready(&self) -> bool {
+		self.registered && self.serving && !self.setup_failing && !self.draining
+	}
+
+	/// The `nfStatus` the NF profile is to carry, `UNDISCOVERABLE` while
+	/// draining so peers stop discovering the AMF without it deregistering.
+	pub fn nf_status(&self) -> NfStatus {
+		if self.draining {
+			NfStatus::Undiscoverable
+		} else {
+			NfStatus::Registered
+		}
+	}
+}
+
+/// Readiness of the AMF, shared by the parts flipping it and those awaiting
+/// it, like the `/ready` endpoint.
+#[derive(Debug, Clone)]
+pub struct Readiness(Arc<watch::Sender<ReadinessState>>);
+
+impl Default for Readiness {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Readiness {
+	pub fn new() -> Self {
+		Self(Arc::new(watch::Sender::new(ReadinessState::default())))
+	}
+
+	pub fn set_registered(
+		&self,
+		registered: bool,
+	) {
+		self.0.send_modify(|state| state.registered = registered);
+	}
+
+	pub fn set_serving(
+		&self,
+		serving: bool,
+	) {
+		self.0.send_modify(|state| state.serving = serving);
+	}
+
+	pub fn set_setup_failing(
+		&self,
+		setup_failing: bool,
+	) {
+		self.0
+			.send_modify(|state| state.setup_failing = setup_failing);
+	}
+
+	pub fn set_draining(
+		&self,
+		draining: bool,
+	) {
+		self.0.send_modify(|state| state.draining = draining);
+	}
+
+	pub fn state(&self) -> ReadinessState {
+		*self.0.borrow()
+	}
+
+	pub fn is_ready(&self) -> bool {
+		self.state().is_ready()
+	}
+
+	pub fn subscribe(&self) -> watch::Receiver<ReadinessState> {
+		self.0.subscribe()
+	}
+
+	/// Returns once the AMF is ready, right away if it already is.
+	pub async fn ready(&self) {
+		let mut state = self.subscribe();
+		// Safety: the sender is owned by `self`, so the channel is not closed.
+		state.wait_for(ReadinessState::is_ready).await.unwrap();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_ready_waits_for_every_condition() {
+		let readiness = Readiness::new();
+		let waiter = tokio::spawn({
+			let readiness = readiness.clone();
+			async move { readiness.ready().await }
+		});
+
+		readiness.set_serving(true);
+		tokio::time::sleep(Duration::from_millis(10)).await;
+		assert!(!waiter.is_finished());
+
+		readiness.set_registered(true);
+		tokio::time::timeout(Duration::from_secs(1), waiter)
+			.await
+			.unwrap()
+			.unwrap();
+		assert!(readiness.is_ready());
+
+		readiness.set_serving(false);
+		assert!(!readiness.is_ready());
+	}
+}
@@ -0,0 +1,106 @@
+use std::{
+	collections::HashMap,
+	net::IpAddr,
+	sync::{Arc, Mutex, PoisonError},
+};
+
+use rustc_hash::FxBuildHasher;
+use tokio::sync::watch;
+
+use crate::ngap::{
+	constants::app::PERSISTENT_NG_SETUP_FAILURES,
+	engine::controller::NgapSetupError,
+};
+
+/// Outcomes of the NG Setups of accepted associations. Failures are otherwise
+/// only logged by the task of their association, this lets the app notice
+/// when no gNB manages to set up.
+///
+/// Failures are counted by the address of the gNB, a gNB reconnecting over
+/// and over does not pass for several failing.
+#[derive(Debug)]
+pub struct SetupFailures {
+	consecutive: Mutex<HashMap<IpAddr, usize, FxBuildHasher>>,
+	persistent: watch::Sender<Option<Arc<NgapSetupError>>>,
+}
+
+impl Default for SetupFailures {
+	fn default() -> Self {
+		Self {
+			consecutive: Mutex::default(),
+			persistent: watch::Sender::new(None),
+		}
+	}
+}
+
+impl SetupFailures {
+	/// Any gNB setting up clears the failures of every gNB.
+	pub fn record_success(&self) {
+		self.consecutive
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.clear();
+		self.persistent
+			.send_if_modified(|failure| failure.take().is_some());
+	}
+
+	/// Marks the failures persistent once `PERSISTENT_NG_SETUP_FAILURES` NG
+	/// Setups of the gNB at `peer` failed in a row.
+	pub fn record_failure(
+		&self,
+		peer: IpAddr,
+		error: NgapSetupError,
+	) {
+		let consecutive = {
+			let mut consecutive = self
+				.consecutive
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner);
+			let failures = consecutive.entry(peer).or_default();
+			*failures += 1;
+			*failures
+		};
+		if consecutive >= PERSISTENT_NG_SETUP_FAILURES {
+			self.persistent.send_replace(Some(Arc::new(error)));
+		}
+	}
+
+	/// The last failure of a persistent run, cleared by the next success.
+	pub fn persistent(&self) -> Option<Arc<NgapSetupError>> {
+		self.persistent.borrow().clone()
+	}
+
+	pub fn subscribe(&self) -> watch::Receiver<Option<Arc<NgapSetupError>>> {
+		self.persistent.subscribe()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_repeated_failures_become_persistent() {
+		let failures = SetupFailures::default();
+		let mut persistent = failures.subscribe();
+		let (gnb, other_gnb) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+		for _ in 1..PERSISTENT_NG_SETUP_FAILURES {
+			failures.record_failure(gnb, NgapSetupError::SocketClosed);
+		}
+		// Another gNB failing is not the same gNB failing again.
+		failures.record_failure(other_gnb, NgapSetupError::SocketClosed);
+		assert!(failures.persistent().is_none());
+
+		failures.record_failure(gnb, NgapSetupError::SocketClosed);
+		assert!(persistent.has_changed().unwrap());
+		assert!(matches!(
+			persistent.borrow_and_update().as_deref(),
+			Some(NgapSetupError::SocketClosed)
+		));
+
+		failures.record_success();
+		assert!(failures.persistent().is_none());
+		failures.record_failure(gnb, NgapSetupError::SocketClosed);
+		assert!(failures.persistent().is_none());
+	}
+}
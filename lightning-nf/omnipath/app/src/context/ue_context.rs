@@ -1,12 +1,23 @@
 use std::{num::NonZeroU32, sync::Arc};
 
 use derive_new::new;
-use ngap_models::{AmfUeNgapId, RanUeNgapId, RrcEstablishmentCause};
+use ngap_models::{
+	AmfUeNgapId,
+	GlobalRanNodeId,
+	RanUeNgapId,
+	RrcEstablishmentCause,
+	UserLocationInformation,
+	UserLocationInformationNr,
+};
 use non_empty_string::NonEmptyString;
 use statig::awaitable::StateMachine;
 
-use super::GnbContext;
-use crate::{nas::nas_context::NasContext, ngap::manager::Identifiable, utils::models::FiveGSTmsi};
+use super::{GnbContext, PduSession, PduSessionError, PduSessions};
+use crate::{
+	nas::nas_context::NasContext,
+	ngap::manager::Identifiable,
+	utils::models::{FiveGGuti, FiveGSTmsi, Suci, Supi},
+};
 
 #[derive(new)]
 pub struct UeContext {
@@ -20,15 +31,45 @@ pub struct UeContext {
 	#[new(default)]
 	pub tmsi: Option<NonZeroU32>,
 	#[new(default)]
-	pub guti: Option<NonEmptyString>,
+	pub guti: Option<FiveGGuti>,
 	#[new(default)]
-	pub suci: Option<NonEmptyString>,
+	pub suci: Option<Suci>,
+	#[new(default)]
+	pub supi: Option<Supi>,
 	#[new(default)]
 	pub pei: Option<NonEmptyString>,
 	#[new(default)]
 	pub mac_addr: Option<NonEmptyString>,
 	#[new(default)]
 	pub plmn_id: Option<NonEmptyString>,
+	/// Counted in the load of `gnb_context` for as long as the context lives,
+	/// changed through [`UeContext::add_pdu_session`] and
+	/// [`UeContext::release_pdu_session`].
+	#[new(default)]
+	pdu_sessions: PduSessions,
+	/// Where the UE was last seen, reported by the gNB, recommended to the
+	/// gNBs paging it.
+	#[new(default)]
+	pub last_location: Option<UserLocationInformationNr>,
+	/// The N2 handover the UE is in, if any.
+	#[new(default)]
+	pub handover: Option<HandoverState>,
+}
+
+/// Where a UE is in an intra-AMF N2 handover, TS 23.502 4.9.1.3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandoverState {
+	/// The target gNB is asked for resources, the context is still on the
+	/// source gNB.
+	Preparing { target: GlobalRanNodeId },
+	/// The context moved to the target gNB, until it notifies the UE arrived.
+	Executing {
+		source: GlobalRanNodeId,
+		source_ran_ue_ngap_id: RanUeNgapId,
+	},
+	/// The context was copied to the target gNB, the source keeps it until
+	/// the UE arrived there.
+	HandedOver { target: GlobalRanNodeId },
 }
 
 impl std::fmt::Debug for UeContext {
@@ -45,13 +86,134 @@ impl std::fmt::Debug for UeContext {
 			.field("tmsi", &self.tmsi)
 			.field("guti", &self.guti)
 			.field("suci", &self.suci)
+			.field("supi", &self.supi)
 			.field("pei", &self.pei)
 			.field("mac_addr", &self.mac_addr)
 			.field("plmn_id", &self.plmn_id)
+			.field("pdu_sessions", &self.pdu_sessions)
+			.field("last_location", &self.last_location)
+			.field("handover", &self.handover)
 			.finish()
 	}
 }
 
+impl UeContext {
+	pub fn set_guti(
+		&mut self,
+		guti: FiveGGuti,
+	) {
+		self.gnb_context
+			.ue_identities
+			.index_guti(guti.clone(), self.amf_ue_ngap_id.clone());
+		self.guti = Some(guti);
+	}
+
+	pub fn set_supi(
+		&mut self,
+		supi: Supi,
+	) {
+		self.gnb_context
+			.ue_identities
+			.index_supi(supi.clone(), self.amf_ue_ngap_id.clone());
+		self.supi = Some(supi);
+	}
+
+	/// Refreshes where the UE was last seen from the User Location
+	/// Information of a message about it. Only NR locations are recommended
+	/// for paging, others leave the last one in place.
+	pub fn update_location(
+		&mut self,
+		user_location_information: UserLocationInformation,
+	) {
+		if let UserLocationInformation::UserLocationInformationNr(location) =
+			user_location_information
+		{
+			self.last_location = Some(location);
+		}
+	}
+}
+
+impl UeContext {
+	pub fn pdu_sessions(&self) -> &PduSessions {
+		&self.pdu_sessions
+	}
+
+	pub fn add_pdu_session(
+		&mut self,
+		session: PduSession,
+	) -> Result<(), PduSessionError> {
+		self.pdu_sessions.add(session)?;
+		self.gnb_context.load.pdu_session_established();
+		Ok(())
+	}
+
+	pub fn modify_pdu_session(
+		&mut self,
+		id: u8,
+		modify: impl FnOnce(&mut PduSession),
+	) -> Result<(), PduSessionError> {
+		self.pdu_sessions.modify(id, modify)
+	}
+
+	pub fn release_pdu_session(
+		&mut self,
+		id: u8,
+	) -> Option<PduSession> {
+		let session = self.pdu_sessions.release(id)?;
+		self.gnb_context.load.pdu_session_released();
+		Some(session)
+	}
+}
+
+/// A context going away takes its PDU sessions off the load of its gNB.
+impl Drop for UeContext {
+	fn drop(&mut self) {
+		for _ in 0..self.pdu_sessions.len() {
+			self.gnb_context.load.pdu_session_released();
+		}
+	}
+}
+
+impl UeContext {
+	/// Copies the context over to `target_gnb`, under the `RanUeNgapId` the
+	/// target gave the UE. This one stays behind, marked handed over, until
+	/// the target notifies the UE arrived.
+	pub fn hand_over(
+		&mut self,
+		target_gnb: Arc<GnbContext>,
+		ran_ue_ngap_id: RanUeNgapId,
+	) -> UeContext {
+		let source = self.gnb_context.global_ran_node_id.clone();
+		for _ in 0..self.pdu_sessions.len() {
+			target_gnb.load.pdu_session_established();
+		}
+		self.handover = Some(HandoverState::HandedOver {
+			target: target_gnb.global_ran_node_id.clone(),
+		});
+		UeContext {
+			ran_ue_ngap_id,
+			amf_ue_ngap_id: self.amf_ue_ngap_id.clone(),
+			rrc_establishment_cause: self.rrc_establishment_cause.clone(),
+			gnb_context: target_gnb,
+			five_g_s_tmsi: self.five_g_s_tmsi.clone(),
+			gmm: self.gmm.clone(),
+			tmsi: self.tmsi,
+			guti: self.guti.clone(),
+			suci: self.suci.clone(),
+			supi: self.supi.clone(),
+			pei: self.pei.clone(),
+			mac_addr: self.mac_addr.clone(),
+			plmn_id: self.plmn_id.clone(),
+			pdu_sessions: self.pdu_sessions.clone(),
+			last_location: self.last_location.clone(),
+			handover: Some(HandoverState::Executing {
+				source,
+				source_ran_ue_ngap_id: self.ran_ue_ngap_id,
+			}),
+		}
+	}
+}
+
 impl Identifiable for UeContext {
 	type ID = RanUeNgapId;
 
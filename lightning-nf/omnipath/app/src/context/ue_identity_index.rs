@@ -0,0 +1,185 @@
+use ngap_models::AmfUeNgapId;
+use rustc_hash::FxBuildHasher;
+use scc::hash_map::HashMap as SccHashMap;
+
+use crate::utils::models::{FiveGGuti, Supi};
+
+/// Secondary indexes resolving the permanent and temporary identities of a UE
+/// to its `AmfUeNgapId`.
+///
+/// NAS procedures like Service Request and paging only carry a 5G-GUTI or a
+/// SUPI, while UE contexts are stored per gNB keyed by `RanUeNgapId`. The
+/// `AmfUeNgapId` obtained here is resolved to the serving gNB through the
+/// `ue_ids` map of the `NgapContext`.
+#[derive(Debug)]
+pub struct UeIdentityIndex {
+	guti: SccHashMap<FiveGGuti, AmfUeNgapId, FxBuildHasher>,
+	supi: SccHashMap<Supi, AmfUeNgapId, FxBuildHasher>,
+	/// The identities each UE was indexed with, so that a UE only ever drops
+	/// its own.
+	ues: SccHashMap<AmfUeNgapId, UeIdentities, FxBuildHasher>,
+}
+
+#[derive(Debug, Default)]
+struct UeIdentities {
+	guti: Option<FiveGGuti>,
+	supi: Option<Supi>,
+}
+
+impl Default for UeIdentityIndex {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl UeIdentityIndex {
+	pub fn new() -> Self {
+		Self {
+			guti: SccHashMap::with_hasher(FxBuildHasher::default()),
+			supi: SccHashMap::with_hasher(FxBuildHasher::default()),
+			ues: SccHashMap::with_hasher(FxBuildHasher::default()),
+		}
+	}
+
+	/// Maps the GUTI to the UE, replacing any UE the GUTI was assigned to
+	/// before. The previous GUTI of the UE is dropped.
+	pub fn index_guti(
+		&self,
+		guti: FiveGGuti,
+		amf_ue_ngap_id: AmfUeNgapId,
+	) {
+		let previous = self
+			.ues
+			.entry(amf_ue_ngap_id.clone())
+			.or_default()
+			.get_mut()
+			.guti
+			.replace(guti.clone());
+		if let Some(previous) = previous.filter(|previous| *previous != guti) {
+			self.guti.remove_if(&previous, |id| *id == amf_ue_ngap_id);
+		}
+		self.guti.upsert(guti, amf_ue_ngap_id);
+	}
+
+	/// Maps the SUPI to the UE, replacing any previous UE of the same
+	/// subscriber.
+	pub fn index_supi(
+		&self,
+		supi: Supi,
+		amf_ue_ngap_id: AmfUeNgapId,
+	) {
+		let previous = self
+			.ues
+			.entry(amf_ue_ngap_id.clone())
+			.or_default()
+			.get_mut()
+			.supi
+			.replace(supi.clone());
+		if let Some(previous) = previous.filter(|previous| *previous != supi) {
+			self.supi.remove_if(&previous, |id| *id == amf_ue_ngap_id);
+		}
+		self.supi.upsert(supi, amf_ue_ngap_id);
+	}
+
+	pub fn find_by_guti(
+		&self,
+		guti: &FiveGGuti,
+	) -> Option<AmfUeNgapId> {
+		self.guti.read(guti, |_, id| id.clone())
+	}
+
+	pub fn find_by_supi(
+		&self,
+		supi: &Supi,
+	) -> Option<AmfUeNgapId> {
+		self.supi.read(supi, |_, id| id.clone())
+	}
+
+	/// The 5G-GUTI the UE was indexed with, kept once it is released.
+	pub fn guti_of(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+	) -> Option<FiveGGuti> {
+		self.ues
+			.read(amf_ue_ngap_id, |_, identities| identities.guti.clone())
+			.flatten()
+	}
+
+	/// Drops the identities of the released UE, leaving those since given to
+	/// another UE.
+	pub fn remove(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+	) {
+		let Some((_, identities)) = self.ues.remove(amf_ue_ngap_id) else {
+			return;
+		};
+		if let Some(guti) = identities.guti {
+			self.guti.remove_if(&guti, |id| *id == *amf_ue_ngap_id);
+		}
+		if let Some(supi) = identities.supi {
+			self.supi.remove_if(&supi, |id| *id == *amf_ue_ngap_id);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_guti(value: &str) -> FiveGGuti {
+		value.parse().unwrap()
+	}
+
+	fn parse_supi(value: &str) -> Supi {
+		value.parse().unwrap()
+	}
+
+	#[test]
+	fn test_find_ue_by_guti_and_supi() {
+		let index = UeIdentityIndex::new();
+		let guti = parse_guti("20893cafe0000000001");
+		let supi = parse_supi("imsi-208930000000001");
+
+		index.index_guti(guti.clone(), AmfUeNgapId(1));
+		index.index_supi(supi.clone(), AmfUeNgapId(1));
+		index.index_supi(parse_supi("imsi-208930000000002"), AmfUeNgapId(2));
+
+		assert_eq!(index.find_by_guti(&guti), Some(AmfUeNgapId(1)));
+		assert_eq!(index.find_by_supi(&supi), Some(AmfUeNgapId(1)));
+		assert_eq!(index.find_by_guti(&parse_guti("20893cafe0000000002")), None);
+		assert_eq!(index.guti_of(&AmfUeNgapId(1)), Some(guti));
+		assert_eq!(index.guti_of(&AmfUeNgapId(2)), None);
+	}
+
+	#[test]
+	fn test_remove_released_ue() {
+		let index = UeIdentityIndex::new();
+		let guti = parse_guti("20893cafe0000000001");
+		let supi = parse_supi("imsi-208930000000002");
+
+		index.index_guti(guti.clone(), AmfUeNgapId(1));
+		index.index_supi(supi.clone(), AmfUeNgapId(2));
+		index.remove(&AmfUeNgapId(1));
+
+		assert_eq!(index.find_by_guti(&guti), None);
+		assert_eq!(index.find_by_supi(&supi), Some(AmfUeNgapId(2)));
+	}
+
+	#[test]
+	fn test_reassigned_guti_survives_the_previous_ue() {
+		let index = UeIdentityIndex::new();
+		let (old_guti, guti) = (
+			parse_guti("20893cafe0000000001"),
+			parse_guti("20893cafe0000000002"),
+		);
+
+		index.index_guti(old_guti.clone(), AmfUeNgapId(1));
+		index.index_guti(guti.clone(), AmfUeNgapId(1));
+		assert_eq!(index.find_by_guti(&old_guti), None);
+
+		index.index_guti(guti.clone(), AmfUeNgapId(2));
+		index.remove(&AmfUeNgapId(1));
+		assert_eq!(index.find_by_guti(&guti), Some(AmfUeNgapId(2)));
+	}
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, future::poll_fn, sync::Mutex, time::Duration};
+
+use ngap_models::AmfUeNgapId;
+use rustc_hash::FxBuildHasher;
+use tokio::sync::mpsc;
+use tokio_util::{
+	sync::CancellationToken,
+	time::{DelayQueue, delay_queue::Key},
+};
+
+enum Activity {
+	Seen(AmfUeNgapId),
+	Released(AmfUeNgapId),
+}
+
+/// Ages out the UEs which have been silent for the inactivity timeout, on one
+/// timer wheel shared by every UE.
+///
+/// Activity is only recorded here, the timers live with [`run`](Self::run).
+pub struct UeInactivity {
+	timeout: Duration,
+	activity: mpsc::UnboundedSender<Activity>,
+	receiver: Mutex<Option<mpsc::UnboundedReceiver<Activity>>>,
+}
+
+impl UeInactivity {
+	pub fn new(timeout: Duration) -> Self {
+		let (activity, receiver) = mpsc::unbounded_channel();
+		Self {
+			timeout,
+			activity,
+			receiver: Mutex::new(Some(receiver)),
+		}
+	}
+
+	/// Restarts the timer of a UE, starting it for a new one.
+	pub fn seen(
+		&self,
+		amf_ue_ngap_id: AmfUeNgapId,
+	) {
+		// Only fails once `run` returned, when nothing ages out anymore.
+		let _ = self.activity.send(Activity::Seen(amf_ue_ngap_id));
+	}
+
+	/// Stops the timer of a UE released otherwise.
+	pub fn released(
+		&self,
+		amf_ue_ngap_id: AmfUeNgapId,
+	) {
+		let _ = self.activity.send(Activity::Released(amf_ue_ngap_id));
+	}
+
+	/// Hands every UE whose timer expires to `expired` until `shutdown`. Only
+	/// the first call runs the timers, later ones return right away.
+	pub async fn run(
+		&self,
+		shutdown: CancellationToken,
+		mut expired: impl AsyncFnMut(AmfUeNgapId),
+	) {
+		let Some(mut activity) = self.receiver.lock().unwrap().take() else {
+			return;
+		};
+		let mut timers = DelayQueue::new();
+		let mut keys: HashMap<AmfUeNgapId, Key, FxBuildHasher> = HashMap::default();
+		loop {
+			tokio::select! {
+				_ = shutdown.cancelled() => break,
+				Some(activity) = activity.recv() => match activity {
+					Activity::Seen(amf_ue_ngap_id) => match keys.get(&amf_ue_ngap_id) {
+						Some(key) => timers.reset(key, self.timeout),
+						None => {
+							let key = timers.insert(amf_ue_ngap_id.clone(), self.timeout);
+							keys.insert(amf_ue_ngap_id, key);
+						}
+					},
+					Activity::Released(amf_ue_ngap_id) => {
+						if let Some(key) = keys.remove(&amf_ue_ngap_id) {
+							timers.remove(&key);
+						}
+					}
+				},
+				// Yields `None` while no timer runs, leaving the branch out of
+				// this turn.
+				Some(expiry) = poll_fn(|cx| timers.poll_expired(cx)) => {
+					let amf_ue_ngap_id = expiry.into_inner();
+					keys.remove(&amf_ue_ngap_id);
+					expired(amf_ue_ngap_id).await;
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test(start_paused = true)]
+	async fn test_idle_ue_is_released_and_active_ue_is_not() {
+		let timeout = Duration::from_secs(30);
+		let inactivity = UeInactivity::new(timeout);
+		let (idle, active) = (AmfUeNgapId(1), AmfUeNgapId(2));
+		let shutdown = CancellationToken::new();
+		let (expired_tx, mut expired_rx) = mpsc::unbounded_channel();
+		let timers = inactivity.run(shutdown.clone(), async |amf_ue_ngap_id| {
+			expired_tx.send(amf_ue_ngap_id).unwrap();
+		});
+		let activity = async {
+			inactivity.seen(idle.clone());
+			inactivity.seen(active.clone());
+			for _ in 0..4 {
+				tokio::time::sleep(timeout / 2).await;
+				inactivity.seen(active.clone());
+			}
+			assert_eq!(expired_rx.recv().await, Some(idle));
+			assert!(expired_rx.try_recv().is_err());
+
+			// A released UE does not expire.
+			inactivity.released(active);
+			tokio::time::sleep(timeout * 2).await;
+			assert!(expired_rx.try_recv().is_err());
+			shutdown.cancel();
+		};
+		tokio::join!(timers, activity);
+	}
+}
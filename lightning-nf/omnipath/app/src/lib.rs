@@ -5,28 +5,63 @@ pub(crate) mod config;
 pub(crate) mod context;
 pub mod nas;
 pub mod ngap;
+pub mod sbi;
 pub mod utils;
-use std::{rc::Rc, sync::Arc};
+use std::{
+	iter,
+	net::SocketAddr,
+	rc::Rc,
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::Duration,
+};
 
-use client::nrf_client::{NrfClient, NrfManagementError};
+use backoff::Backoff;
+use client::{
+	GenericClientError,
+	HttpClientConfig,
+	ScpRouting,
+	nrf_client::{
+		NRF_REGISTRATION_BASE_DELAY,
+		NRF_REGISTRATION_MAX_DELAY,
+		NRF_REGISTRATION_RETRIES,
+		NrfClient,
+		NrfManagementError,
+		PatchItem,
+		RegistrationOutcome,
+	},
+};
 use config::OmniPathConfig;
-pub use context::app_context::get_global_app_context;
-use nf_base::NfInstance;
-use ngap::network::{Network, NetworkError};
-use oasbi::common::NfType;
+pub use context::{AmfEvent, app_context::get_global_app_context};
+use nf_base::{NfInstance, SbiServerError, SbiServerRunner};
+use ngap::{
+	constants::app::OVERLOAD_RECOVERY_PERCENT,
+	engine::controller::NgapSetupError,
+	network::{Network, NetworkError},
+};
+use oasbi::common::{NfType, UriScheme};
 use openapi_nrf::models::RegisterNfInstanceHeaderParams;
-use reqwest::{Client, Url};
+use reqwest::Url;
+use sbi::{AccessTokenError, AccessTokenVerifier, AdminToken, AdminTokenError, ApiVersions};
 use thiserror::Error;
+use tokio::time::MissedTickBehavior;
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
 	builder::sbi::ModelBuildError,
-	config::SerdeValidated,
+	config::{ConfigViolations, SerdeValidated},
 	context::{
+		EventBus,
 		NgapContext,
+		OverloadControl,
+		Readiness,
+		SetupFailures,
 		app_context::{AppContext, Configuration},
 	},
+	utils::{find_diff, json_diff},
 };
 
 const SOURCE_TYPE: NfType = NfType::Amf;
@@ -50,6 +85,18 @@ pub enum OmniPathError {
 	#[error("NgapNetworkError: Ngap Network Error")]
 	NgapNetworkError(#[from] NetworkError),
 
+	#[error("NgapSetupError: NG Setups keep failing")]
+	NgapSetupError(#[from] Arc<NgapSetupError>),
+
+	#[error("SbiServerError: Sbi Server Error")]
+	SbiServerError(#[from] SbiServerError),
+
+	#[error("AccessTokenError: Unable to load the keys verifying access tokens")]
+	AccessTokenError(#[from] AccessTokenError),
+
+	#[error("AdminTokenError: Unable to load the admin token")]
+	AdminTokenError(#[from] AdminTokenError),
+
 	#[error("GlobalAppContextSetError: Unable to set App Context Error")]
 	GlobalAppContextSetError(#[from] tokio::sync::SetError<AppContext>),
 }
@@ -59,8 +106,11 @@ pub enum OmniPathConfigError {
 	#[error("InvalidNrfUriError: The Nrf Uri is Invalid: {0} {1}")]
 	InvalidNrfUriError(#[source] url::ParseError, String),
 
-	#[error("InvalidConfig: Invalid Configuration")]
-	InvalidConfig(#[from] serde_valid::validation::Errors),
+	#[error("InvalidScpUriError: The Scp Uri is Invalid: {0} {1}")]
+	InvalidScpUriError(#[source] url::ParseError, String),
+
+	#[error("InvalidConfig: Invalid Configuration\n{0}")]
+	InvalidConfig(ConfigViolations),
 
 	#[error("ClientBuildError: Error While Building the nrf client")]
 	ClientBuildError(#[from] reqwest::Error),
@@ -85,37 +135,59 @@ pub struct OmniPathApp {
 	shutdown: CancellationToken,
 	app_context: AppContext,
 	ngap_context: Arc<NgapContext>,
+	readiness: Readiness,
+	events: EventBus,
+	/// Set once the NRF took the deregistration of the AMF, so the shutdown
+	/// of `start` and the one of the runner deregister only once.
+	deregistered: AtomicBool,
 }
 
-pub fn create_nrf_client(url: Url) -> Result<NrfClient, OmniPathConfigError> {
-	let client = Client::builder()
-		.connection_verbose(true)
-		// .https_only(true)
-		.build()?;
-	Ok(NrfClient::new(client, url, SOURCE_TYPE))
-}
+impl OmniPathApp {
+	pub fn readiness(&self) -> &Readiness {
+		&self.readiness
+	}
 
-fn find_diff<'a, T: serde::Serialize>(
-	v1: &'a T,
-	v2: &'a T,
-) -> String {
-	macro_rules! try_with_err_msg {
-		($expr:expr, $msg:expr) => {
-			match $expr {
-				Ok(val) => val,
-				Err(err) => {
-					let err_str = format!("{}: {}", $msg, err.to_string());
-					return err_str;
-				}
-			}
-		};
+	/// Returns once the AMF is registered with the NRF and its NGAP and SBI
+	/// listeners accept connections.
+	pub async fn ready(&self) {
+		self.readiness.ready().await
+	}
+
+	/// Receives the events of the AMF from now on, see [`AmfEvent`].
+	pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<AmfEvent> {
+		self.events.subscribe()
+	}
+
+	/// Makes the AMF undiscoverable from its next heartbeat on, while it stays
+	/// registered and keeps serving its UEs.
+	pub fn drain(&self) {
+		self.readiness.set_draining(true);
+	}
+
+	/// The last NG Setup failure while they keep failing, see
+	/// [`SetupFailures`].
+	pub fn setup_failure(&self) -> Option<OmniPathError> {
+		self.ngap_context
+			.setup_failures()
+			.persistent()
+			.map(OmniPathError::from)
 	}
+}
 
-	let v1 = try_with_err_msg!(serde_json::to_value(v1), "Error While Serializing");
-	let v2 = try_with_err_msg!(serde_json::to_value(v2), "Error While Serializing");
-	let mut d = treediff::tools::Recorder::default();
-	treediff::diff(&v1, &v2, &mut d);
-	format!("{:#?}", d)
+pub fn create_nrf_client(
+	url: Url,
+	scp_url: Option<Url>,
+	client_config: &HttpClientConfig,
+) -> Result<NrfClient, OmniPathConfigError> {
+	let client = client_config
+		.builder()
+		// .https_only(true)
+		.build()?;
+	let nrf_client = NrfClient::new(client, url, SOURCE_TYPE);
+	Ok(match scp_url {
+		Some(scp_url) => nrf_client.with_scp_routing(ScpRouting::new(scp_url)),
+		None => nrf_client,
+	})
 }
 
 impl NfInstance for OmniPathApp {
@@ -129,10 +201,20 @@ impl NfInstance for OmniPathApp {
 		let nrf_uri = &config.configuration.nrf_uri.to_string();
 		let nrf_url = Url::parse(nrf_uri)
 			.map_err(|e| OmniPathConfigError::InvalidNrfUriError(e, nrf_uri.to_owned()))?;
-		let nrf_client = create_nrf_client(nrf_url)?;
+		let scp_url = config
+			.configuration
+			.scp_uri
+			.as_ref()
+			.map(|scp_uri| {
+				let scp_uri = scp_uri.to_string();
+				Url::parse(&scp_uri)
+					.map_err(|e| OmniPathConfigError::InvalidScpUriError(e, scp_uri))
+			})
+			.transpose()?;
+		let nrf_client = create_nrf_client(nrf_url, scp_url, &config.sbi.client)?;
 		let nrf_client = Arc::new(nrf_client);
 		let valid_config =
-			SerdeValidated::new(config).map_err(OmniPathConfigError::InvalidConfig)?;
+			SerdeValidated::new_with_report(config).map_err(OmniPathConfigError::InvalidConfig)?;
 		let app_context = AppContext::initialize(&valid_config);
 
 		let ngap_network = Network::new(
@@ -141,7 +223,36 @@ impl NfInstance for OmniPathApp {
 			&valid_config.inner().configuration.sctp,
 		)?;
 
-		let ngap_context = NgapContext::new(ngap_network);
+		let ngap_tap = &valid_config.inner().configuration.ngap_tap;
+		#[cfg(feature = "ngap-tap")]
+		ngap::network::tap::install(ngap_tap).map_err(NetworkError::TapCreationError)?;
+		#[cfg(not(feature = "ngap-tap"))]
+		if ngap_tap.enable {
+			warn!("NGAP tap is enabled but omnipath was built without the `ngap-tap` feature");
+		}
+
+		let events = EventBus::new();
+		let mut ngap_context = NgapContext::new(
+			ngap_network,
+			valid_config.inner().configuration.max_concurrent_ng_setups,
+			valid_config
+				.inner()
+				.configuration
+				.enabled_ngap_procedures
+				.clone(),
+			Duration::from_secs(
+				valid_config
+					.inner()
+					.configuration
+					.ue_inactivity_timeout
+					.into(),
+			),
+			events.clone(),
+		);
+		if let Some(threshold) = valid_config.inner().configuration.overload_threshold {
+			ngap_context = ngap_context
+				.with_overload_control(OverloadControl::new(threshold, OVERLOAD_RECOVERY_PERCENT));
+		}
 		crate::context::app_context::APP_CONTEXT.set(app_context.clone())?;
 
 		Ok(Self {
@@ -150,52 +261,567 @@ impl NfInstance for OmniPathApp {
 			app_context,
 			config: Rc::new(valid_config),
 			ngap_context: Arc::new(ngap_context),
+			readiness: Readiness::new(),
+			events,
+			deregistered: AtomicBool::new(false),
 		})
 	}
 
 	async fn start(&self) -> Result<(), Self::Error> {
 		let ngap_context = self.ngap_context.clone();
 		let shutdown = self.shutdown.clone();
-		ngap_context.run(shutdown).await?;
+
+		let (sbi_addr, sbi_tls) = {
+			let sbi_config = self.app_context.get_sbi_config();
+			(
+				SocketAddr::from((sbi_config.binding_ipv4, sbi_config.port)),
+				matches!(sbi_config.scheme, UriScheme::Https).then(|| sbi_config.tls.clone()),
+			)
+		};
+		// Registered by now, so the NF instance id is the one the NRF knows.
+		let access_tokens = match &self.config.inner().sbi.oauth2 {
+			Some(oauth2) => Some(AccessTokenVerifier::from_config(
+				oauth2,
+				&[
+					SOURCE_TYPE.to_string(),
+					self.app_context.get_nf_id().0.to_string(),
+				],
+			)?),
+			None => None,
+		};
+		let admin_token = self
+			.config
+			.inner()
+			.sbi
+			.admin
+			.as_ref()
+			.map(AdminToken::from_config)
+			.transpose()?;
+		let info = &self.config.inner().info;
+		let api_versions = ApiVersions::from_majors(
+			iter::once(info.version.major)
+				.chain(info.additional_versions.iter().map(|v| v.version.major)),
+		);
+		let sbi_server = SbiServerRunner::bind(
+			sbi_addr,
+			sbi::router(api_versions, self.ngap_context.clone(), access_tokens),
+			sbi_tls.as_ref(),
+		)
+		.await?
+		.with_admin_routes(sbi::admin_router(
+			self.readiness.clone(),
+			self.nrf_client.clone(),
+			self.ngap_context.clone(),
+			admin_token,
+		));
+		info!("Sbi Server listening on {}", sbi_addr);
+
+		// The NGAP listener is bound by `initialize` already.
+		self.readiness.set_serving(true);
+		// The SBI outlives `shutdown`, it keeps serving until the AMF is gone
+		// from the NRF.
+		let sbi_shutdown = CancellationToken::new();
+		let served = tokio::try_join!(
+			drain_then_deregister(
+				async {
+					ngap_context
+						.clone()
+						.run(shutdown.clone())
+						.await
+						.map_err(OmniPathError::from)
+				},
+				self.deregister_nf(),
+				&sbi_shutdown,
+			),
+			async {
+				sbi_server
+					.serve(sbi_shutdown.clone())
+					.await
+					.map_err(OmniPathError::from)
+			},
+			async {
+				ngap_context.release_inactive_ues(shutdown.clone()).await;
+				Ok::<_, OmniPathError>(())
+			},
+			async {
+				ngap_context.monitor_overload(shutdown.clone()).await;
+				Ok::<_, OmniPathError>(())
+			},
+			async {
+				watch_setup_failures(
+					self.ngap_context.setup_failures(),
+					&self.readiness,
+					shutdown.clone(),
+				)
+				.await;
+				Ok::<_, OmniPathError>(())
+			},
+			async {
+				if let Some(interval) = self.nrf_client.heartbeat_interval() {
+					let max_ue_count = self.config.inner().configuration.max_ue_count;
+					report_load_with_heartbeats(
+						&self.nrf_client,
+						&self.readiness,
+						interval,
+						async || load_percent(ngap_context.ue_count().await, max_ue_count),
+						shutdown.clone(),
+					)
+					.await;
+				}
+				Ok::<_, OmniPathError>(())
+			},
+		);
+		self.readiness.set_serving(false);
+		served?;
 		Ok(())
 	}
 
 	async fn register_nf(&self) -> Result<(), Self::Error> {
-		let nf_profile = self
-			.app_context
-			.build_nf_profile()
-			.map_err(NrfError::from)?;
-		let nf_instance_id = self.app_context.get_nf_id();
-		let (nf_profile_resp, instance_id) = self
-			.nrf_client
-			.register_nf_instance(
-				nf_instance_id,
-				&RegisterNfInstanceHeaderParams::default(),
-				&nf_profile,
-			)
-			.await
-			.map_err(NrfError::from)?;
-		info!("Nrf Nf Id: {:#?}", instance_id);
-		info!(
-			"Nrf Profile Response Diff: {}",
-			&find_diff(&nf_profile, &nf_profile_resp)
-		);
-		if let Some(nf_id) = instance_id {
-			if nf_id == self.app_context.get_nf_id() {
-				let update_config_fn = move |config: &mut Configuration| {
-					config.nf_id = nf_id;
-				};
-				self.app_context.commit_config(update_config_fn);
-			}
-		}
+		register_with_nrf(
+			&self.nrf_client,
+			&self.app_context,
+			&self.readiness,
+			&self.events,
+		)
+		.await?;
+		self.events.emit(AmfEvent::NrfRegistered);
 		Ok(())
 	}
 
 	async fn deregister_nf(&self) -> Result<(), Self::Error> {
+		if self.deregistered.load(Ordering::Acquire) {
+			return Ok(());
+		}
+		self.readiness.set_registered(false);
 		self.nrf_client
 			.deregister_nf_instance()
 			.await
 			.map_err(NrfError::from)?;
+		// Only a DELETE the NRF took counts, a failed one is tried again.
+		self.deregistered.store(true, Ordering::Release);
+		self.events.emit(AmfEvent::NrfDeregistered);
 		Ok(())
 	}
 }
+
+/// The shutdown sequence of `start`: once `ngap` returns, having stopped
+/// accepting gNBs and drained those connected, the AMF deregisters from the
+/// NRF and only then `sbi_shutdown` closes the SBI server. NFs keep reaching
+/// the AMF for as long as the NRF lists it.
+async fn drain_then_deregister<E>(
+	ngap: impl Future<Output = Result<(), E>>,
+	deregister: impl Future<Output = Result<(), E>>,
+	sbi_shutdown: &CancellationToken,
+) -> Result<(), E> {
+	let drained = ngap.await;
+	let deregistered = deregister.await;
+	sbi_shutdown.cancel();
+	drained.and(deregistered)
+}
+
+/// Keeps the AMF unready while NG Setups keep failing, until `shutdown`.
+async fn watch_setup_failures(
+	setup_failures: &SetupFailures,
+	readiness: &Readiness,
+	shutdown: CancellationToken,
+) {
+	let mut persistent = setup_failures.subscribe();
+	loop {
+		let failing = persistent.borrow_and_update().clone();
+		if let Some(e) = &failing {
+			warn!("NG Setups keep failing, the last with: {:?}", e);
+		}
+		readiness.set_setup_failing(failing.is_some());
+		tokio::select! {
+			_ = shutdown.cancelled() => break,
+			// The sender lives as long as `setup_failures`.
+			_ = persistent.changed() => (),
+		}
+	}
+}
+
+/// Share of `max_ue_count` taken by `ue_count`, as the percentage the NF
+/// profile `load` is given in.
+fn load_percent(
+	ue_count: usize,
+	max_ue_count: usize,
+) -> u8 {
+	(ue_count.saturating_mul(100) / max_ue_count.max(1)).min(100) as u8
+}
+
+/// Sends a heartbeat to the NRF every `interval` until `shutdown`, carrying
+/// the `nfStatus` of `readiness` and the `load` whenever it differs from the
+/// last one the NRF accepted.
+async fn report_load_with_heartbeats(
+	nrf_client: &NrfClient,
+	readiness: &Readiness,
+	interval: Duration,
+	load: impl AsyncFn() -> u8,
+	shutdown: CancellationToken,
+) {
+	let mut reported = None;
+	let mut ticks = tokio::time::interval(interval);
+	ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+	// The first tick completes right away, the registration just went out.
+	ticks.tick().await;
+	loop {
+		tokio::select! {
+			_ = shutdown.cancelled() => break,
+			_ = ticks.tick() => (),
+		}
+		let load = load().await;
+		// Safety: `NfStatus` serializes to its name.
+		let nf_status = serde_json::to_value(readiness.state().nf_status()).unwrap();
+		let mut patch = vec![PatchItem::replace("/nfStatus", nf_status)];
+		// The registered profile carries no load, so the first heartbeat sets it.
+		if reported != Some(load) {
+			patch.push(PatchItem::add("/load", load));
+		}
+		match nrf_client.update_nf_instance(&patch).await {
+			Ok(()) => reported = Some(load),
+			Err(e) => warn!("Nrf Heartbeat Failed: {:?}", e),
+		}
+	}
+}
+
+/// Registers the NF profile of `app_context`, marking the AMF registered once
+/// the NRF accepted it.
+async fn register_with_nrf(
+	nrf_client: &NrfClient,
+	app_context: &AppContext,
+	readiness: &Readiness,
+	events: &EventBus,
+) -> Result<(), NrfError> {
+	let nf_profile = app_context.build_nf_profile()?;
+	let nf_instance_id = app_context.get_nf_id();
+	let header = RegisterNfInstanceHeaderParams::default();
+	// Timeouts and refused connections are retried, the NRF may be starting
+	// along with the AMF.
+	let delays = Backoff::new(NRF_REGISTRATION_BASE_DELAY, NRF_REGISTRATION_MAX_DELAY)
+		.jitter(0.5)
+		.take(NRF_REGISTRATION_RETRIES);
+	let (nf_profile_resp, outcome) = backoff::retry(
+		delays,
+		|e: &NrfManagementError| {
+			e.client_error()
+				.is_some_and(GenericClientError::is_transient)
+		},
+		|| nrf_client.register_nf_instance(nf_instance_id, &header, &nf_profile),
+	)
+	.await?;
+	info!("Nrf Registration Outcome: {:?}", outcome);
+	info!(
+		"Nrf Profile Response Diff: {}",
+		&find_diff(&nf_profile, &nf_profile_resp)
+	);
+	// Audited, the NRF may rewrite fields by its policy, clamping the capacity
+	// for one.
+	if let Ok(alterations) = json_diff(&nf_profile, &nf_profile_resp)
+		&& !alterations.is_empty()
+	{
+		events.emit(AmfEvent::NfProfileAltered(alterations));
+	}
+	match outcome {
+		// The NRF may assign its own id on first registration, adopt it so the
+		// profile and any later deregistration refer to the registered instance.
+		RegistrationOutcome::Created(nf_id) if nf_id != nf_instance_id => {
+			info!(
+				"Adopting Nrf assigned Nf Id {:?} in place of {:?}",
+				nf_id, nf_instance_id
+			);
+			let update_config_fn = move |config: &mut Configuration| {
+				config.nf_id = nf_id;
+			};
+			app_context.commit_config(update_config_fn);
+		}
+		RegistrationOutcome::Created(_) => (),
+		// A fresh instance id is generated at start up, finding it registered
+		// already means another instance may be using it.
+		RegistrationOutcome::Updated => {
+			warn!(
+				"Nf Id {:?} was already registered with the Nrf, its profile was replaced",
+				nf_instance_id
+			);
+		}
+	}
+	readiness.set_registered(true);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{net::IpAddr, sync::atomic::AtomicUsize};
+
+	use client::mock_nrf::{MockNrf, MockNrfOperation, MockRequest, MockResponse};
+	use reqwest::{
+		Client,
+		Method,
+		StatusCode,
+		header::{HeaderValue, LOCATION},
+	};
+
+	use super::*;
+	use crate::{ngap::constants::app::PERSISTENT_NG_SETUP_FAILURES, utils::DiffEntry};
+
+	fn app_context() -> AppContext {
+		let config = serde_yaml::from_str(include_str!("../../../../config/amfcfg.yaml")).unwrap();
+		AppContext::initialize(&SerdeValidated::new(config).unwrap())
+	}
+
+	#[tokio::test]
+	async fn test_ready_once_registered_with_mock_nrf() {
+		let nrf = MockNrf::start().await;
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), SOURCE_TYPE);
+		let app_context = app_context();
+		let readiness = Readiness::new();
+		let ready = tokio::spawn({
+			let readiness = readiness.clone();
+			async move { readiness.ready().await }
+		});
+
+		readiness.set_serving(true);
+		assert!(!readiness.is_ready());
+		register_with_nrf(&nrf_client, &app_context, &readiness, &EventBus::new())
+			.await
+			.unwrap();
+		tokio::time::timeout(Duration::from_secs(1), ready)
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(
+			nrf.requests()[0].operation,
+			MockNrfOperation::RegisterNfInstance
+		);
+	}
+
+	#[tokio::test]
+	async fn test_not_ready_when_registration_fails() {
+		let nrf = MockNrf::start().await;
+		nrf.respond(
+			MockNrfOperation::RegisterNfInstance,
+			MockResponse::problem(StatusCode::INTERNAL_SERVER_ERROR, "SYSTEM_FAILURE"),
+		);
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), SOURCE_TYPE);
+		let readiness = Readiness::new();
+		readiness.set_serving(true);
+
+		assert!(
+			register_with_nrf(&nrf_client, &app_context(), &readiness, &EventBus::new())
+				.await
+				.is_err()
+		);
+		assert!(!readiness.is_ready());
+	}
+
+	#[tokio::test]
+	async fn test_nrf_altered_priority_is_audited() {
+		let nrf = MockNrf::start().await;
+		let app_context = app_context();
+		let mut registered = serde_json::to_value(app_context.build_nf_profile().unwrap()).unwrap();
+		registered["priority"] = serde_json::json!(7);
+		let location = format!(
+			"http://mock-nrf/nnrf-nfm/v1/nf-instances/{}",
+			registered["nfInstanceId"].as_str().unwrap()
+		);
+		nrf.respond(
+			MockNrfOperation::RegisterNfInstance,
+			MockResponse::json(StatusCode::CREATED, registered)
+				.with_header(LOCATION, HeaderValue::try_from(location).unwrap()),
+		);
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), SOURCE_TYPE);
+		let events = EventBus::new();
+		let mut audit = events.subscribe();
+
+		register_with_nrf(&nrf_client, &app_context, &Readiness::new(), &events)
+			.await
+			.unwrap();
+		let Ok(AmfEvent::NfProfileAltered(alterations)) = audit.try_recv() else {
+			panic!("No audit of the altered profile");
+		};
+		// The AMF sends no priority, the NRF added it.
+		assert!(matches!(
+			&alterations[..],
+			[DiffEntry::Added { path, value } | DiffEntry::Modified { path, new: value, .. }]
+				if path == "/priority" && value == 7
+		));
+		let audited = serde_json::to_value(&alterations[0]).unwrap();
+		assert_eq!(audited["path"], "/priority");
+	}
+
+	#[tokio::test]
+	async fn test_deregistration_follows_the_ngap_drain() {
+		let ngap_context = Arc::new(NgapContext::test_fixture());
+		let shutdown = CancellationToken::new();
+		let sbi_shutdown = CancellationToken::new();
+		let ngap_stopped = AtomicBool::new(false);
+		let deregistered = AtomicBool::new(false);
+
+		let sequence = drain_then_deregister(
+			async {
+				ngap_context.clone().run(shutdown.clone()).await?;
+				ngap_stopped.store(true, Ordering::Release);
+				Ok::<_, NetworkError>(())
+			},
+			async {
+				assert!(ngap_stopped.load(Ordering::Acquire));
+				assert!(!sbi_shutdown.is_cancelled());
+				deregistered.store(true, Ordering::Release);
+				Ok(())
+			},
+			&sbi_shutdown,
+		);
+		let ((), sequence) = tokio::join!(
+			async {
+				tokio::time::sleep(Duration::from_millis(50)).await;
+				assert!(!deregistered.load(Ordering::Acquire));
+				shutdown.cancel();
+			},
+			sequence,
+		);
+		sequence.unwrap();
+		assert!(deregistered.load(Ordering::Acquire));
+		assert!(sbi_shutdown.is_cancelled());
+	}
+
+	#[tokio::test]
+	async fn test_repeated_setup_failure_fails_readiness() {
+		let setup_failures = Arc::new(SetupFailures::default());
+		let localhost = IpAddr::from([127, 0, 0, 1]);
+		let readiness = Readiness::new();
+		readiness.set_registered(true);
+		readiness.set_serving(true);
+		let shutdown = CancellationToken::new();
+		let watcher = tokio::spawn({
+			let (setup_failures, readiness) = (setup_failures.clone(), readiness.clone());
+			let shutdown = shutdown.clone();
+			async move { watch_setup_failures(&setup_failures, &readiness, shutdown).await }
+		});
+		tokio::time::timeout(Duration::from_secs(1), readiness.ready())
+			.await
+			.unwrap();
+
+		for _ in 0..PERSISTENT_NG_SETUP_FAILURES {
+			setup_failures.record_failure(localhost, NgapSetupError::SocketClosed);
+		}
+		let error = setup_failures.persistent().map(OmniPathError::from);
+		assert!(matches!(error, Some(OmniPathError::NgapSetupError(_))));
+		let mut state = readiness.subscribe();
+		tokio::time::timeout(Duration::from_secs(1), state.wait_for(|s| s.setup_failing))
+			.await
+			.unwrap()
+			.unwrap();
+		assert!(!readiness.is_ready());
+
+		setup_failures.record_success();
+		tokio::time::timeout(Duration::from_secs(1), readiness.ready())
+			.await
+			.unwrap();
+		shutdown.cancel();
+		watcher.await.unwrap();
+	}
+
+	/// Waits for a heartbeat setting the load to `load`.
+	async fn wait_for_load(
+		nrf: &MockNrf,
+		load: u8,
+	) {
+		let expected = serde_json::to_value(PatchItem::add("/load", load)).unwrap();
+		let reported = async {
+			loop {
+				let reported = nrf.requests().iter().any(|request| {
+					request.operation == MockNrfOperation::UpdateNfInstance
+						&& serde_json::from_slice::<Vec<serde_json::Value>>(&request.body)
+							.unwrap()
+							.contains(&expected)
+				});
+				if reported {
+					break;
+				}
+				tokio::time::sleep(Duration::from_millis(5)).await;
+			}
+		};
+		tokio::time::timeout(Duration::from_secs(1), reported)
+			.await
+			.unwrap();
+	}
+
+	#[test]
+	fn test_load_percent() {
+		assert_eq!(load_percent(0, 200), 0);
+		assert_eq!(load_percent(50, 200), 25);
+		assert_eq!(load_percent(300, 200), 100);
+	}
+
+	#[tokio::test]
+	async fn test_load_updates_follow_ue_count() {
+		let nrf = MockNrf::start().await;
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), SOURCE_TYPE);
+		let ue_count = AtomicUsize::new(0);
+		let shutdown = CancellationToken::new();
+		let heartbeats = report_load_with_heartbeats(
+			&nrf_client,
+			&Readiness::new(),
+			Duration::from_millis(10),
+			async || load_percent(ue_count.load(Ordering::Relaxed), 200),
+			shutdown.clone(),
+		);
+		let updates = async {
+			wait_for_load(&nrf, 0).await;
+			ue_count.store(50, Ordering::Relaxed);
+			wait_for_load(&nrf, 25).await;
+			ue_count.store(200, Ordering::Relaxed);
+			wait_for_load(&nrf, 100).await;
+			shutdown.cancel();
+		};
+		tokio::join!(heartbeats, updates);
+
+		let requests = nrf.requests();
+		assert!(requests.iter().all(|r| r.method == Method::PATCH));
+		// Heartbeats between the changes leave the load out.
+		let load_updates = requests
+			.iter()
+			.filter(|r| std::str::from_utf8(&r.body).unwrap().contains("/load"))
+			.count();
+		assert_eq!(load_updates, 3);
+	}
+
+	#[tokio::test]
+	async fn test_draining_heartbeat_is_undiscoverable() {
+		let nrf = MockNrf::start().await;
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), SOURCE_TYPE);
+		let readiness = Readiness::new();
+		let shutdown = CancellationToken::new();
+		let heartbeats = report_load_with_heartbeats(
+			&nrf_client,
+			&readiness,
+			Duration::from_millis(10),
+			async || 0,
+			shutdown.clone(),
+		);
+		let nf_status_of = |request: &MockRequest| {
+			serde_json::from_slice::<Vec<serde_json::Value>>(&request.body).unwrap()[0]["value"]
+				.clone()
+		};
+		let updates = async {
+			wait_for_load(&nrf, 0).await;
+			readiness.set_draining(true);
+			let undiscoverable = async {
+				while !nrf
+					.requests()
+					.iter()
+					.any(|request| nf_status_of(request) == "UNDISCOVERABLE")
+				{
+					tokio::time::sleep(Duration::from_millis(5)).await;
+				}
+			};
+			tokio::time::timeout(Duration::from_secs(1), undiscoverable)
+				.await
+				.unwrap();
+			shutdown.cancel();
+		};
+		tokio::join!(heartbeats, updates);
+
+		assert_eq!(nf_status_of(&nrf.requests()[0]), "REGISTERED");
+		assert!(!readiness.is_ready());
+	}
+}
@@ -0,0 +1,73 @@
+use thiserror::Error;
+
+const EXTENDED_PROTOCOL_DISCRIMINATOR_5GMM: u8 = 0x7e;
+const SECURITY_HEADER_TYPE_PLAIN: u8 = 0x00;
+const MESSAGE_TYPE_DL_NAS_TRANSPORT: u8 = 0x68;
+const PDU_SESSION_ID_IEI: u8 = 0x12;
+
+/// Payload container type, TS 24.501 9.11.3.40.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayloadContainerType {
+	N1SmInformation = 0x01,
+	Sms = 0x02,
+	Lpp = 0x03,
+	UePolicyContainer = 0x05,
+	LocationServices = 0x07,
+}
+
+/// Builds a plain DL NAS Transport, TS 24.501 8.2.11, carrying `payload` to
+/// the UE. The PDU session ID goes along N1 SM information.
+///
+/// Sent without NAS security protection, which the AMF applies to no
+/// downlink message yet.
+pub fn build_dl_nas_transport(
+	payload_container_type: PayloadContainerType,
+	payload: &[u8],
+	pdu_session_id: Option<u8>,
+) -> Result<Vec<u8>, DlNasTransportError> {
+	let length = u16::try_from(payload.len())
+		.map_err(|_| DlNasTransportError::PayloadTooLong(payload.len()))?;
+	let mut message = Vec::with_capacity(payload.len() + 8);
+	message.extend_from_slice(&[
+		EXTENDED_PROTOCOL_DISCRIMINATOR_5GMM,
+		SECURITY_HEADER_TYPE_PLAIN,
+		MESSAGE_TYPE_DL_NAS_TRANSPORT,
+		payload_container_type as u8,
+	]);
+	message.extend_from_slice(&length.to_be_bytes());
+	message.extend_from_slice(payload);
+	if let Some(pdu_session_id) = pdu_session_id {
+		message.extend_from_slice(&[PDU_SESSION_ID_IEI, pdu_session_id]);
+	}
+	Ok(message)
+}
+
+#[derive(Error, Debug)]
+pub enum DlNasTransportError {
+	#[error("PayloadTooLong: A payload container holds at most 65535 octets, not {0}")]
+	PayloadTooLong(usize),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sm_payload_is_wrapped_with_its_pdu_session_id() {
+		let message = build_dl_nas_transport(
+			PayloadContainerType::N1SmInformation,
+			&[0x2e, 0x01],
+			Some(5),
+		)
+		.unwrap();
+		assert_eq!(
+			message,
+			[0x7e, 0x00, 0x68, 0x01, 0x00, 0x02, 0x2e, 0x01, 0x12, 0x05]
+		);
+		assert!(matches!(
+			build_dl_nas_transport(PayloadContainerType::Sms, &[0; 1 << 16], None),
+			Err(DlNasTransportError::PayloadTooLong(65536))
+		));
+	}
+}
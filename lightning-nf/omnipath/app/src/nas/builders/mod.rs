@@ -1,3 +1,6 @@
 mod registration_response;
 mod authentication_request;
-mod gmm_status;
\ No newline at end of file
+mod gmm_status;
+mod dl_nas_transport;
+
+pub use dl_nas_transport::{DlNasTransportError, PayloadContainerType, build_dl_nas_transport};
@@ -12,7 +12,7 @@ pub enum NasHandlerError {
     UnknownNasMessageType,
     #[error("Unknown error occurred")]
     UnknownError,
-    #[error("FivegmmCauseError occured")]
+    #[error("FiveGmmCauseError: Rejected with 5GMM cause {0:?}")]
     FiveGmmCauseError(FiveGmmCause),
 }
 
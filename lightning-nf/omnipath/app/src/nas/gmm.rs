@@ -95,3 +95,23 @@ impl NasContext {
 		};
 	}
 }
+
+/// Whether the UE has no registration, not even one under way, that a network
+/// initiated Deregistration Request would have to end first.
+pub fn is_deregistered(gmm: &statig::awaitable::StateMachine<NasContext>) -> bool {
+	matches!(gmm.state(), State::Deregistered {})
+}
+
+/// A GMM state machine with a registration under way, as no test can drive a
+/// Registration Request through the state machine yet.
+#[cfg(test)]
+pub fn registration_initiated_test_fixture() -> statig::awaitable::StateMachine<NasContext> {
+	use statig::awaitable::IntoStateMachineExt;
+
+	let mut gmm = NasContext::new().state_machine();
+	// SAFETY: No event was handled yet, so no state is left half entered.
+	unsafe {
+		*gmm.state_mut() = State::unauthenticated();
+	}
+	gmm
+}
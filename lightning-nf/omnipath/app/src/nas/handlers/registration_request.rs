@@ -8,6 +8,7 @@ use crate::nas::error::NasHandlerError;
 use crate::nas::NasContext;
 use crate::nas::UeContext;
 use crate::nas::NasHandler;
+use crate::utils::models::{FiveGGuti, Suci};
 
 
 fn initial_registration_handler(nas_registration_request: &nas_message::NasRegistrationRequest, nas_context:&mut NasContext, ue_context: &mut UeContext) -> Result<(), NasHandlerError>{
@@ -19,10 +20,16 @@ fn initial_registration_handler(nas_registration_request: &nas_message::NasRegis
             // Todo push some logging here
         },
         nas_types::MobileIdentity::Suci(suci) => {
-            ue_context.suci = NonEmptyString::new(suci.to_string()).ok();
+            ue_context.suci = suci.to_string().parse::<Suci>().ok();
+            // A null scheme SUCI carries the SUPI in clear.
+            if let Some(supi) = ue_context.suci.as_ref().and_then(Suci::unconcealed_supi) {
+                ue_context.set_supi(supi);
+            }
         },
         nas_types::MobileIdentity::FiveGGuti(five_gguti) => {
-            ue_context.guti = NonEmptyString::new(five_gguti.get_guti_string()).ok();
+            if let Ok(guti) = five_gguti.get_guti_string().parse::<FiveGGuti>() {
+                ue_context.set_guti(guti);
+            }
         },
         nas_types::MobileIdentity::Imei(imei_or_imei_sv) => {
             ue_context.pei = NonEmptyString::new(imei_or_imei_sv.to_string()).ok();
@@ -3,6 +3,10 @@ use std::{future::Future, sync::Arc};
 use crate::context::UeContext;
 use nas_context::NasContext;
 use error::NasHandlerError;
+pub use gmm::is_deregistered;
+#[cfg(test)]
+pub use gmm::registration_initiated_test_fixture;
+pub use builders::{DlNasTransportError, PayloadContainerType, build_dl_nas_transport};
 
 
 pub mod nas_context;
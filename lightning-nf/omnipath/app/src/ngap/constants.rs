@@ -1,8 +1,44 @@
 pub mod app {
+	use std::time::Duration;
+
 	pub const INITIAL_GNB_CAPACITY: usize = 1024;
 	pub const INITIALIZATION_RETRIES: usize = 3;
 	pub const MAX_TNLA_ASSOCIATIONS: usize = 32;
 	pub const DEFAULT_NGAP_PORT: u16 = 38412;
+	pub const DEFAULT_MAX_CONCURRENT_NG_SETUPS: usize = 16;
+	pub const DEFAULT_MAX_UE_COUNT: usize = 10_000;
+	pub const DEFAULT_UE_INACTIVITY_TIMEOUT_SECS: u32 = 3600;
+	/// How often the backlog is checked against the overload threshold.
+	pub const OVERLOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+	/// Share of the overload threshold, in percent, the backlog has to come
+	/// down to for overload to stop.
+	pub const OVERLOAD_RECOVERY_PERCENT: usize = 80;
+	/// How long shutdown waits for in-flight NGAP message handling.
+	pub const NGAP_TASK_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+	/// A write to a gNB taking this long is considered blocked on a full send
+	/// buffer.
+	pub const SLOW_PEER_WRITE_THRESHOLD: Duration = Duration::from_millis(200);
+	/// Blocked writes in a row after which a gNB is flagged slow.
+	pub const SLOW_PEER_BLOCKED_WRITES: u32 = 3;
+	/// Prompt writes in a row after which a slow gNB is cleared.
+	pub const SLOW_PEER_RECOVERY_WRITES: u32 = 16;
+	/// How long a new association has to send its NG Setup Request.
+	pub const NG_SETUP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+	/// Messages of a gNB held while its NG Setup is under way, those past it
+	/// are rejected as before.
+	pub const MAX_EARLY_NGAP_MESSAGES: usize = 16;
+	/// How long an AMF initiated NG Reset waits for its acknowledgement.
+	pub const NG_RESET_ACKNOWLEDGE_TIMEOUT: Duration = Duration::from_secs(5);
+	/// How long a handover preparation waits for the target gNB to acknowledge
+	/// its Handover Request, TS 38.413 TNGRELOCprep.
+	pub const HANDOVER_PREPARATION_TIMEOUT: Duration = Duration::from_secs(5);
+	/// NG Setups of a gNB failing in a row, across its associations, after
+	/// which the AMF reports itself unready.
+	pub const PERSISTENT_NG_SETUP_FAILURES: usize = 5;
+	/// Delays between the NG Setup attempts of an association, growing from
+	/// the base to the max.
+	pub const NG_SETUP_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+	pub const NG_SETUP_RETRY_MAX_DELAY: Duration = Duration::from_secs(1);
 }
 
 pub mod asn {
@@ -0,0 +1,617 @@
+use std::sync::Arc;
+
+use ngap_models::{
+	AmfUeNgapId,
+	Cause,
+	CauseRadioNetwork,
+	GlobalRanNodeId,
+	HandoverCommand,
+	HandoverFailure,
+	HandoverNotify,
+	HandoverPreparationFailure,
+	HandoverRequest,
+	HandoverRequestAcknowledge,
+	HandoverRequired,
+	RanUeNgapId,
+	TargetId,
+	TargetRanNodeId,
+	ToNgapPdu,
+};
+use thiserror::Error;
+use tokio::{
+	sync::{OwnedRwLockWriteGuard, oneshot},
+	time::timeout,
+};
+use tracing::{info, warn};
+
+use crate::{
+	context::{GnbContext, HandoverState, NgapContext, UeContext},
+	ngap::{
+		constants::app::HANDOVER_PREPARATION_TIMEOUT,
+		engine::{
+			EmptyResponse,
+			NgapResponseError,
+			controller::{NgapWriteError, encode_and_write_ngap_pdu},
+		},
+		manager::PinnedSendSyncFuture,
+	},
+};
+
+type PreparationError = NgapResponseError<HandoverPreparationFailure, HandoverError>;
+
+impl NgapContext {
+	/// Prepares the N2 handover a source gNB asks for towards another gNB of
+	/// this AMF, TS 38.413 8.4.1. The target gNB is sent a Handover Request
+	/// and, once it acknowledges it, the UE context is copied over to the
+	/// target and the source gNB is answered with the Handover Command. A
+	/// target refusing the UE has its cause passed on to the source.
+	pub async fn handle_handover_required(
+		&self,
+		gnb_context: Arc<GnbContext>,
+		request: HandoverRequired,
+	) -> Result<HandoverCommand, PreparationError> {
+		let HandoverRequired {
+			amf_ue_ngap_id,
+			ran_ue_ngap_id,
+			handover_type,
+			cause,
+			target_id,
+			source_to_target_transparent_container,
+			..
+		} = request;
+		let failure_with = |cause: Cause, error: HandoverError| {
+			NgapResponseError::new_failure_error(
+				HandoverPreparationFailure {
+					amf_ue_ngap_id: amf_ue_ngap_id.clone(),
+					ran_ue_ngap_id,
+					cause,
+					..Default::default()
+				},
+				error,
+			)
+		};
+		let failure = |cause: CauseRadioNetwork, error: HandoverError| {
+			failure_with(Cause::RadioNetwork(cause), error)
+		};
+
+		let source = (gnb_context.global_ran_node_id.clone(), ran_ue_ngap_id);
+		if self.find_ue(&amf_ue_ngap_id).await.as_ref() != Some(&source) {
+			return Err(failure(
+				CauseRadioNetwork::UnknownLocalUeNgapId,
+				HandoverError::UnknownUe(amf_ue_ngap_id.clone()),
+			));
+		}
+		self.ue_inactivity.seen(amf_ue_ngap_id.clone());
+		// Only gNBs of this AMF are handed over to, inter-AMF and inter-system
+		// handovers are not supported.
+		let TargetId::TargetRanNodeId(TargetRanNodeId {
+			global_ran_node_id: target_id,
+			..
+		}) = target_id
+		else {
+			return Err(failure(
+				CauseRadioNetwork::UnknownTargetId,
+				HandoverError::UnsupportedTarget,
+			));
+		};
+		let Some(target_gnb) = self
+			.gnb_contexts
+			.read_async(&target_id, |_, gnb| gnb.clone())
+			.await
+		else {
+			return Err(failure(
+				CauseRadioNetwork::UnknownTargetId,
+				HandoverError::UnknownTarget(target_id),
+			));
+		};
+
+		let preparing = HandoverState::Preparing {
+			target: target_id.clone(),
+		};
+		if !set_handover(&gnb_context, ran_ue_ngap_id, None, Some(preparing)).await {
+			return Err(failure(
+				CauseRadioNetwork::Unspecified,
+				HandoverError::HandoverInProgress(amf_ue_ngap_id.clone()),
+			));
+		}
+		let acknowledged = self
+			.request_handover(
+				&target_gnb,
+				HandoverRequest {
+					amf_ue_ngap_id: amf_ue_ngap_id.clone(),
+					handover_type: handover_type.clone(),
+					cause,
+					source_to_target_transparent_container,
+					// TODO: Ask the SMFs for the Handover Request Transfers of the
+					// PDU sessions, TS 23.502 4.9.1.3.2.
+					..Default::default()
+				},
+			)
+			.await;
+		let acknowledge = match acknowledged {
+			Ok(acknowledge) => acknowledge,
+			Err(error) => {
+				let preparing = HandoverState::Preparing { target: target_id };
+				set_handover(&gnb_context, ran_ue_ngap_id, Some(preparing), None).await;
+				let cause = match &error {
+					HandoverError::Rejected(_, cause) => cause.clone(),
+					_ => Cause::RadioNetwork(CauseRadioNetwork::TngrelocprepExpiry),
+				};
+				return Err(failure_with(cause, error));
+			}
+		};
+
+		self.move_ue(
+			&amf_ue_ngap_id,
+			&gnb_context,
+			&target_gnb,
+			acknowledge.ran_ue_ngap_id,
+		)
+		.await
+		.map_err(|error| failure(CauseRadioNetwork::Unspecified, error))?;
+		info!(
+			"Handing Ue {:?} over from gNB {:?} to {:?}",
+			amf_ue_ngap_id, gnb_context.global_ran_node_id, target_gnb.global_ran_node_id
+		);
+		Ok(HandoverCommand {
+			amf_ue_ngap_id,
+			ran_ue_ngap_id,
+			handover_type,
+			target_to_source_transparent_container: acknowledge
+				.target_to_source_transparent_container,
+			..Default::default()
+		})
+	}
+
+	/// Sends the Handover Request to the target gNB and waits for its
+	/// acknowledgement or failure.
+	async fn request_handover(
+		&self,
+		target_gnb: &GnbContext,
+		request: HandoverRequest,
+	) -> Result<HandoverRequestAcknowledge, HandoverError> {
+		let amf_ue_ngap_id = request.amf_ue_ngap_id.clone();
+		let (tx, rx) = oneshot::channel();
+		target_gnb
+			.pending_handovers
+			.lock()
+			.await
+			.insert(amf_ue_ngap_id.clone(), tx);
+		let sent =
+			encode_and_write_ngap_pdu(target_gnb.tnla_association.as_ref(), request.to_pdu()).await;
+		let acknowledged = match sent {
+			Ok(()) => timeout(HANDOVER_PREPARATION_TIMEOUT, rx).await,
+			Err(e) => {
+				target_gnb
+					.pending_handovers
+					.lock()
+					.await
+					.remove(&amf_ue_ngap_id);
+				return Err(HandoverError::WriteError(e));
+			}
+		};
+		target_gnb
+			.pending_handovers
+			.lock()
+			.await
+			.remove(&amf_ue_ngap_id);
+		match acknowledged {
+			Ok(Ok(Ok(acknowledge))) => Ok(acknowledge),
+			Ok(Ok(Err(failure))) => Err(HandoverError::Rejected(amf_ue_ngap_id, failure.cause)),
+			_ => Err(HandoverError::NotAcknowledged(amf_ue_ngap_id)),
+		}
+	}
+
+	/// Copies a UE context from the source gNB to the target, where the UE is
+	/// found from now on. The source keeps its context until the Handover
+	/// Notify, TS 23.502 4.9.1.3.3.
+	async fn move_ue(
+		&self,
+		amf_ue_ngap_id: &AmfUeNgapId,
+		source_gnb: &GnbContext,
+		target_gnb: &Arc<GnbContext>,
+		target_ran_ue_ngap_id: RanUeNgapId,
+	) -> Result<(), HandoverError> {
+		let Some((_, source_ran_ue_ngap_id)) = self.find_ue(amf_ue_ngap_id).await else {
+			return Err(HandoverError::UnknownUe(amf_ue_ngap_id.clone()));
+		};
+		let target = target_gnb.clone();
+		let future_closure = move |mut ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move { ue_context.hand_over(target, target_ran_ue_ngap_id) })
+				as PinnedSendSyncFuture<_>
+		};
+		let ue_context = source_gnb
+			.ue_context_manager
+			.with_context(source_ran_ue_ngap_id, future_closure)
+			.await
+			.map_err(|_| HandoverError::UnknownUe(amf_ue_ngap_id.clone()))?;
+		target_gnb
+			.add_ue_context(ue_context)
+			.await
+			.map_err(|_| HandoverError::TargetUeExists(target_ran_ue_ngap_id))?;
+		self.ue_ids.write().await.insert(
+			amf_ue_ngap_id.clone(),
+			(target_gnb.global_ran_node_id.clone(), target_ran_ue_ngap_id),
+		);
+		Ok(())
+	}
+
+	/// Hands the Handover Request Acknowledge of a target gNB to the handover
+	/// preparation awaiting it.
+	pub async fn handle_handover_request_acknowledge(
+		&self,
+		gnb_context: Arc<GnbContext>,
+		acknowledge: HandoverRequestAcknowledge,
+	) -> Result<EmptyResponse, NgapResponseError<EmptyResponse, HandoverError>> {
+		let amf_ue_ngap_id = acknowledge.amf_ue_ngap_id.clone();
+		if !complete_preparation(&gnb_context, &amf_ue_ngap_id, Ok(acknowledge)).await {
+			return Err(NgapResponseError::new_empty_failure_error(
+				HandoverError::UnexpectedAcknowledge(amf_ue_ngap_id),
+			));
+		}
+		self.ue_inactivity.seen(amf_ue_ngap_id);
+		Ok(EmptyResponse::new())
+	}
+
+	/// Hands the Handover Failure of a target gNB unable to admit the UE to
+	/// the handover preparation awaiting it, TS 38.413 8.4.2.3.
+	pub async fn handle_handover_failure(
+		&self,
+		gnb_context: Arc<GnbContext>,
+		failure: HandoverFailure,
+	) -> Result<EmptyResponse, NgapResponseError<EmptyResponse, HandoverError>> {
+		let amf_ue_ngap_id = failure.amf_ue_ngap_id.clone();
+		if !complete_preparation(&gnb_context, &amf_ue_ngap_id, Err(failure)).await {
+			return Err(NgapResponseError::new_empty_failure_error(
+				HandoverError::UnexpectedFailure(amf_ue_ngap_id),
+			));
+		}
+		Ok(EmptyResponse::new())
+	}
+
+	/// Completes the handover of a UE the target gNB notifies has arrived, TS
+	/// 38.413 8.4.3.
+	pub async fn handle_handover_notify(
+		&self,
+		gnb_context: Arc<GnbContext>,
+		notify: HandoverNotify,
+	) -> Result<EmptyResponse, NgapResponseError<EmptyResponse, HandoverError>> {
+		let HandoverNotify {
+			amf_ue_ngap_id,
+			ran_ue_ngap_id,
+			user_location_information,
+			..
+		} = notify;
+		let future_closure = move |mut ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move {
+				let Some(HandoverState::Executing {
+					source,
+					source_ran_ue_ngap_id,
+				}) = ue_context.handover.take()
+				else {
+					return None;
+				};
+				ue_context.update_location(user_location_information);
+				info!("Ue handed over from gNB {:?}", source);
+				Some((source, source_ran_ue_ngap_id))
+			}) as PinnedSendSyncFuture<_>
+		};
+		let completed = gnb_context
+			.ue_context_manager
+			.with_context(ran_ue_ngap_id, future_closure)
+			.await;
+		match completed {
+			Ok(Some((source, source_ran_ue_ngap_id))) => {
+				self.release_handover_source(&source, source_ran_ue_ngap_id)
+					.await;
+				self.ue_inactivity.seen(amf_ue_ngap_id);
+				Ok(EmptyResponse::new())
+			}
+			_ => Err(NgapResponseError::new_empty_failure_error(
+				HandoverError::UnexpectedNotify(amf_ue_ngap_id),
+			)),
+		}
+	}
+
+	/// Releases the context the source gNB kept through the handover with the
+	/// UE Context Release Command, TS 23.502 4.9.1.3.3.
+	async fn release_handover_source(
+		&self,
+		source: &GlobalRanNodeId,
+		ran_ue_ngap_id: RanUeNgapId,
+	) {
+		let Some(source_gnb) = self
+			.gnb_contexts
+			.read_async(source, |_, gnb| gnb.clone())
+			.await
+		else {
+			return;
+		};
+		let cause = Cause::RadioNetwork(CauseRadioNetwork::SuccessfulHandover);
+		let future_closure = |ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move { ue_context.send_ue_context_release_command(cause).await })
+				as PinnedSendSyncFuture<_>
+		};
+		let sent = source_gnb
+			.ue_context_manager
+			.with_context(ran_ue_ngap_id, future_closure)
+			.await;
+		if let Ok(Err(e)) = sent {
+			warn!(
+				"Unable to release the handed over Ue {:?} at gNB {:?}: {:?}",
+				ran_ue_ngap_id, source, e
+			);
+		}
+		// The UE Context Release Complete is not awaited, as for any release.
+		source_gnb.release_ue_context(&ran_ue_ngap_id).await;
+		self.initial_ues
+			.release(source.clone(), ran_ue_ngap_id)
+			.await;
+	}
+}
+
+/// Completes the handover preparation of a UE towards `gnb_context` with the
+/// answer of the gNB, returning whether one was pending.
+async fn complete_preparation(
+	gnb_context: &GnbContext,
+	amf_ue_ngap_id: &AmfUeNgapId,
+	outcome: Result<HandoverRequestAcknowledge, HandoverFailure>,
+) -> bool {
+	let pending = gnb_context
+		.pending_handovers
+		.lock()
+		.await
+		.remove(amf_ue_ngap_id);
+	let Some(tx) = pending else {
+		return false;
+	};
+	if tx.send(outcome).is_err() {
+		warn!("Handover preparation answered after it timed out");
+	}
+	true
+}
+
+/// Moves the handover state of a UE of `gnb_context` from `from` to `to`,
+/// returning whether the UE was in `from`.
+async fn set_handover(
+	gnb_context: &GnbContext,
+	ran_ue_ngap_id: RanUeNgapId,
+	from: Option<HandoverState>,
+	to: Option<HandoverState>,
+) -> bool {
+	let future_closure = move |mut ue_context: OwnedRwLockWriteGuard<UeContext>| {
+		Box::pin(async move {
+			if ue_context.handover != from {
+				return false;
+			}
+			ue_context.handover = to;
+			true
+		}) as PinnedSendSyncFuture<_>
+	};
+	gnb_context
+		.ue_context_manager
+		.with_context(ran_ue_ngap_id, future_closure)
+		.await
+		.unwrap_or(false)
+}
+
+#[derive(Error, Debug)]
+pub enum HandoverError {
+	#[error("UnknownUe: Ue {0:?} is not served by the source gNB")]
+	UnknownUe(AmfUeNgapId),
+
+	#[error("UnsupportedTarget: Only gNBs of this AMF are handed over to")]
+	UnsupportedTarget,
+
+	#[error("UnknownTarget: No gNB {0:?} is connected")]
+	UnknownTarget(GlobalRanNodeId),
+
+	#[error("HandoverInProgress: Ue {0:?} is already being handed over")]
+	HandoverInProgress(AmfUeNgapId),
+
+	#[error("WriteError: Failed to send the Handover Request")]
+	WriteError(#[source] NgapWriteError),
+
+	#[error("NotAcknowledged: The target gNB did not acknowledge the handover of Ue {0:?} in time")]
+	NotAcknowledged(AmfUeNgapId),
+
+	#[error("Rejected: The target gNB refused the handover of Ue {0:?} with {1:?}")]
+	Rejected(AmfUeNgapId, Cause),
+
+	#[error("TargetUeExists: The target gNB already serves a Ue {0:?}")]
+	TargetUeExists(RanUeNgapId),
+
+	#[error(
+		"UnexpectedAcknowledge: Handover Request Acknowledge for Ue {0:?} with no handover pending"
+	)]
+	UnexpectedAcknowledge(AmfUeNgapId),
+
+	#[error("UnexpectedFailure: Handover Failure for Ue {0:?} with no handover pending")]
+	UnexpectedFailure(AmfUeNgapId),
+
+	#[error("UnexpectedNotify: Handover Notify for Ue {0:?} with no handover executing")]
+	UnexpectedNotify(AmfUeNgapId),
+}
+
+#[cfg(test)]
+mod tests {
+	use bitvec::prelude::*;
+	use ngap_models::{GlobalGnbId, GnbId, InitiatingMessage, NgapPdu};
+
+	use super::*;
+	use crate::ngap::{
+		engine::{NgapFailure, decode_ngap_pdu},
+		network::{SctpEvent, TnlaAssociation},
+	};
+
+	fn gnb_id(id: u32) -> GlobalRanNodeId {
+		let mut gnb_id = bitvec![u8, Msb0; 0; 22];
+		gnb_id.store_be(id);
+		GlobalRanNodeId::GlobalGnbId(GlobalGnbId {
+			gnb_id: GnbId::GnbId(gnb_id.into()),
+			..Default::default()
+		})
+	}
+
+	/// A UE served by gNB 1 with gNB 2 connected, each gNB along the other
+	/// end of its association.
+	async fn handover_fixture() -> (
+		NgapContext,
+		(Arc<GnbContext>, TnlaAssociation),
+		(Arc<GnbContext>, TnlaAssociation),
+	) {
+		let ngap_context = NgapContext::test_fixture();
+		let (source, source_gnb) = ngap_context.gnb_test_fixture(gnb_id(1)).await;
+		let (target, target_gnb) = ngap_context.gnb_test_fixture(gnb_id(2)).await;
+		for gnb_context in [&source, &target] {
+			ngap_context
+				.gnb_contexts
+				.insert_async(gnb_context.global_ran_node_id.clone(), gnb_context.clone())
+				.await
+				.unwrap();
+		}
+
+		let ue_context = UeContext::test_fixture(RanUeNgapId(7), AmfUeNgapId(1), source.clone());
+		ngap_context.add_test_ue(ue_context).await;
+		(
+			ngap_context,
+			(source, TnlaAssociation::new(source_gnb).unwrap()),
+			(target, TnlaAssociation::new(target_gnb).unwrap()),
+		)
+	}
+
+	fn handover_required() -> HandoverRequired {
+		HandoverRequired {
+			amf_ue_ngap_id: AmfUeNgapId(1),
+			ran_ue_ngap_id: RanUeNgapId(7),
+			target_id: TargetId::TargetRanNodeId(TargetRanNodeId {
+				global_ran_node_id: gnb_id(2),
+				..Default::default()
+			}),
+			..Default::default()
+		}
+	}
+
+	async fn read_pdu(gnb: &TnlaAssociation) -> NgapPdu {
+		let Ok(Some(SctpEvent::Data(message))) = gnb.read_data().await else {
+			panic!("expected an NGAP message");
+		};
+		decode_ngap_pdu(&message).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_handover_moves_the_ue_to_the_target_gnb() {
+		let (ngap_context, (source, source_gnb), (target, target_gnb)) = handover_fixture().await;
+		let amf_ue_ngap_id = AmfUeNgapId(1);
+
+		// The target gNB answers the Handover Request sent over its association.
+		let target_side = async {
+			let NgapPdu::InitiatingMessage(InitiatingMessage::HandoverRequest(request)) =
+				read_pdu(&target_gnb).await
+			else {
+				panic!("expected the Handover Request");
+			};
+			assert_eq!(request.amf_ue_ngap_id, amf_ue_ngap_id);
+			let acknowledge = HandoverRequestAcknowledge {
+				amf_ue_ngap_id: request.amf_ue_ngap_id,
+				ran_ue_ngap_id: RanUeNgapId(21),
+				..Default::default()
+			};
+			ngap_context
+				.handle_handover_request_acknowledge(target.clone(), acknowledge)
+				.await
+				.unwrap();
+		};
+		let (command, ()) = tokio::join!(
+			ngap_context.handle_handover_required(source.clone(), handover_required()),
+			target_side
+		);
+
+		let command = command.unwrap();
+		assert_eq!(command.ran_ue_ngap_id, RanUeNgapId(7));
+		assert_eq!(
+			ngap_context.find_ue(&amf_ue_ngap_id).await,
+			Some((gnb_id(2), RanUeNgapId(21)))
+		);
+		// The source keeps its context until the UE arrived at the target.
+		assert_eq!(source.load.ue_count(), 1);
+		assert_eq!(target.load.ue_count(), 1);
+		let future_closure = |ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move { ue_context.handover.clone() }) as PinnedSendSyncFuture<_>
+		};
+		let handover = target
+			.ue_context_manager
+			.with_context(RanUeNgapId(21), future_closure)
+			.await
+			.unwrap();
+		assert_eq!(
+			handover,
+			Some(HandoverState::Executing {
+				source: gnb_id(1),
+				source_ran_ue_ngap_id: RanUeNgapId(7),
+			})
+		);
+
+		let notify = HandoverNotify {
+			amf_ue_ngap_id: amf_ue_ngap_id.clone(),
+			ran_ue_ngap_id: RanUeNgapId(21),
+			..Default::default()
+		};
+		ngap_context
+			.handle_handover_notify(target.clone(), notify)
+			.await
+			.unwrap();
+		assert!(matches!(
+			read_pdu(&source_gnb).await,
+			NgapPdu::InitiatingMessage(InitiatingMessage::UeContextReleaseCommand(_))
+		));
+		assert!(source.ue_context_manager.is_empty());
+		assert_eq!(target.load.ue_count(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_target_failure_cause_reaches_the_source() {
+		let (ngap_context, (source, _source_gnb), (target, target_gnb)) = handover_fixture().await;
+
+		let target_side = async {
+			let NgapPdu::InitiatingMessage(InitiatingMessage::HandoverRequest(request)) =
+				read_pdu(&target_gnb).await
+			else {
+				panic!("expected the Handover Request");
+			};
+			let failure = HandoverFailure {
+				amf_ue_ngap_id: request.amf_ue_ngap_id,
+				cause: Cause::RadioNetwork(
+					CauseRadioNetwork::NoRadioResourcesAvailableInTargetCell,
+				),
+				..Default::default()
+			};
+			ngap_context
+				.handle_handover_failure(target.clone(), failure)
+				.await
+				.unwrap();
+		};
+		let (command, ()) = tokio::join!(
+			ngap_context.handle_handover_required(source.clone(), handover_required()),
+			target_side
+		);
+
+		let Err(NgapResponseError {
+			failure: NgapFailure::Failure(failure),
+			error,
+		}) = command
+		else {
+			panic!("expected the Handover Preparation Failure");
+		};
+		assert!(matches!(
+			failure.cause,
+			Cause::RadioNetwork(CauseRadioNetwork::NoRadioResourcesAvailableInTargetCell)
+		));
+		assert!(matches!(error, HandoverError::Rejected(_, _)));
+		assert_eq!(
+			ngap_context.find_ue(&AmfUeNgapId(1)).await,
+			Some((gnb_id(1), RanUeNgapId(7)))
+		);
+		assert!(target.ue_context_manager.is_empty());
+	}
+}
@@ -1,22 +1,33 @@
 use std::sync::Arc;
 
-use ngap_models::{AmfUeNgapId, InitialUeMessage, RanUeNgapId};
+use asn1_per::ThreeGppAsn1PerError;
+use ngap_models::{
+	AmfUeNgapId,
+	InitialUeMessage,
+	NgapPdu,
+	RanUeNgapId,
+	RerouteNasRequest,
+	ToNgapPdu,
+};
 use statig::awaitable::IntoStateMachineExt;
 use thiserror::Error;
 use tokio::sync::OwnedRwLockWriteGuard;
+use tracing::info;
 
 use crate::{
-	context::{GnbContext, NgapContext, UeContext},
+	context::{AmfEvent, GnbContext, InitialUe, NgapContext, UeContext},
+	get_global_app_context,
 	nas::nas_context::NasContext,
 	ngap::{
-		engine::{EmptyResponse, NgapRequestHandler, NgapResponseError},
+		core::nas_reroute::{build_reroute_nas_request, reroute_amf_set_id},
+		engine::{EmptyResponse, NgapRequestHandler, NgapResponseError, ToPdu},
 		manager::{ContextError, PinnedSendSyncFuture},
 	},
 	utils::models::FiveGSTmsi,
 };
 
 impl NgapRequestHandler<InitialUeMessage, Arc<GnbContext>> for NgapContext {
-	type Success = EmptyResponse;
+	type Success = InitialUeMessageResponse;
 	type Failure = EmptyResponse;
 	type Error = InitialUeMessageError;
 
@@ -25,17 +36,26 @@ impl NgapRequestHandler<InitialUeMessage, Arc<GnbContext>> for NgapContext {
 		state: Arc<GnbContext>,
 		request: InitialUeMessage,
 	) -> Result<Self::Success, NgapResponseError<Self::Failure, Self::Error>> {
-		// If the UE context already exists, return an empty response
-		// TODO: Handle the case where the UE context already exists and the UE is
-		// undergoing Registration procedure
-		if state
-			.ue_context_manager
-			.contains_context(&request.ran_ue_ngap_id)
-			.await
-		{
-			return Err(NgapResponseError::new_empty_failure_error(
-				UeContextAlreadyExistsError::InitialUeMessage(request),
-			));
+		// A UE registered with another AMF is redirected to that AMF's set.
+		let reroute_set_id = match &request.five_g_s_tmsi {
+			Some(five_g_s_tmsi) => {
+				let app_context = get_global_app_context().await;
+				reroute_amf_set_id(five_g_s_tmsi, &app_context.get_config().served_guami_list)
+			}
+			None => None,
+		};
+		if let Some(amf_set_id) = reroute_set_id {
+			info!(
+				"Rerouting Initial UE Message for Ran Ue Ngap Id {:?}",
+				request.ran_ue_ngap_id
+			);
+			return build_reroute_nas_request(&request, amf_set_id)
+				.map(InitialUeMessageResponse::Reroute)
+				.map_err(|err| {
+					NgapResponseError::new_empty_failure_error(
+						InitialUeMessageError::RerouteEncodingError(err),
+					)
+				});
 		}
 
 		let InitialUeMessage {
@@ -43,20 +63,52 @@ impl NgapRequestHandler<InitialUeMessage, Arc<GnbContext>> for NgapContext {
 			nas_pdu,
 			rrc_establishment_cause,
 			five_g_s_tmsi,
+			user_location_information,
 			..
 		} = request;
 
-		let ue_context = UeContext::new(
+		let initial_ue = self
+			.initial_ues
+			.claim(state.global_ran_node_id.clone(), ran_ue_ngap_id, || {
+				AmfUeNgapId(state.amf_ue_id_generator.increment())
+			})
+			.await;
+		let amf_ue_ngap_id = match initial_ue {
+			InitialUe::New(amf_ue_ngap_id) => amf_ue_ngap_id,
+			InitialUe::Retransmitted(amf_ue_ngap_id) => {
+				// The NAS PDU is the one already handed to the UE context, only
+				// where the UE is seen is taken.
+				info!(
+					"Initial Ue Message retransmitted for Ran Ue Ngap Id {:?}, reusing {:?}",
+					ran_ue_ngap_id, amf_ue_ngap_id
+				);
+				let future_closure = move |mut ue_context: OwnedRwLockWriteGuard<UeContext>| {
+					Box::pin(async move { ue_context.update_location(user_location_information) })
+						as PinnedSendSyncFuture<()>
+				};
+				// A context not added yet takes the location of the first message.
+				let _ = state
+					.ue_context_manager
+					.with_context(ran_ue_ngap_id, future_closure)
+					.await;
+				return Ok(InitialUeMessageResponse::Accepted);
+			}
+		};
+		let mut ue_context = UeContext::new(
 			ran_ue_ngap_id,
-			AmfUeNgapId(state.amf_ue_id_generator.increment()),
+			amf_ue_ngap_id.clone(),
 			rrc_establishment_cause,
 			state.clone(),
 			five_g_s_tmsi.map(FiveGSTmsi::from),
 			Arc::new(NasContext::new().state_machine()),
 		);
+		ue_context.update_location(user_location_information);
 
-		match state.ue_context_manager.add_context(ue_context).await {
+		match state.add_ue_context(ue_context).await {
 			Err(ContextError::ContextAlreadyExists(_, inner)) => {
+				self.initial_ues
+					.release(state.global_ran_node_id.clone(), ran_ue_ngap_id)
+					.await;
 				return Err(NgapResponseError::new_empty_failure_error(
 					UeContextAlreadyExistsError::UeContext(inner),
 				));
@@ -64,6 +116,12 @@ impl NgapRequestHandler<InitialUeMessage, Arc<GnbContext>> for NgapContext {
 			Err(_) => unreachable!(),
 			Ok(_) => (),
 		};
+		self.ue_ids.write().await.insert(
+			amf_ue_ngap_id.clone(),
+			(state.global_ran_node_id.clone(), ran_ue_ngap_id),
+		);
+		self.ue_inactivity.seen(amf_ue_ngap_id.clone());
+		self.events.emit(AmfEvent::UeRegistered(amf_ue_ngap_id));
 
 		let future_closure = move |mut ue_context: OwnedRwLockWriteGuard<UeContext>| {
 			let nas_pdu = nas_pdu.0;
@@ -80,11 +138,32 @@ impl NgapRequestHandler<InitialUeMessage, Arc<GnbContext>> for NgapContext {
 				Err(NgapResponseError::new_empty_failure_error(
 					InitialUeMessageError::UeContextNotFound(ran_ue_ngap_id),
 				)),
-				|_| Ok(EmptyResponse::new()),
+				|_| Ok(InitialUeMessageResponse::Accepted),
 			)
 	}
 }
 
+#[derive(Debug)]
+pub enum InitialUeMessageResponse {
+	/// The UE is served by this AMF, responses follow over NAS.
+	Accepted,
+	/// The UE belongs to another AMF and the gNB is asked to reroute it.
+	Reroute(RerouteNasRequest),
+}
+
+impl ToPdu for InitialUeMessageResponse {
+	fn to_pdu(self) -> Option<NgapPdu> {
+		match self {
+			InitialUeMessageResponse::Accepted => None,
+			InitialUeMessageResponse::Reroute(reroute) => Some(<_ as ToNgapPdu>::to_pdu(reroute)),
+		}
+	}
+
+	fn get_name() -> &'static str {
+		stringify!(InitialUeMessageResponse)
+	}
+}
+
 #[derive(Debug, Error)]
 pub enum InitialUeMessageError {
 	#[error("UeContextAlreadyExists")]
@@ -92,13 +171,101 @@ pub enum InitialUeMessageError {
 
 	#[error("UeContextNotFound")]
 	UeContextNotFound(RanUeNgapId),
+
+	#[error("RerouteEncodingError: Unable to encode the Initial Ue Message for rerouting")]
+	RerouteEncodingError(#[source] ThreeGppAsn1PerError),
 }
 
 #[derive(Debug, Error)]
 pub enum UeContextAlreadyExistsError {
-	#[error("InitialUeMessage: {0:?}")]
-	InitialUeMessage(InitialUeMessage),
-
 	#[error("UeContext: {0:?}")]
 	UeContext(UeContext),
 }
+
+#[cfg(test)]
+mod tests {
+	use bitvec::prelude::*;
+	use ngap_models::{
+		AmfPointer,
+		AmfRegionId,
+		AmfSetId,
+		FiveGSTmsi as NgapFiveGSTmsi,
+		FiveGTmsi,
+		GlobalRanNodeId,
+		NasPdu,
+		UserLocationInformation,
+	};
+
+	use super::*;
+	use crate::utils::convert;
+
+	#[tokio::test]
+	async fn test_retransmitted_initial_ue_message_reuses_the_ue_context() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let request = InitialUeMessage {
+			ran_ue_ngap_id: RanUeNgapId(7),
+			nas_pdu: NasPdu(vec![0x7e, 0x00, 0x41]),
+			..Default::default()
+		};
+
+		let retransmitted = InitialUeMessage {
+			user_location_information: UserLocationInformation::UserLocationInformationNr(
+				Default::default(),
+			),
+			..request.clone()
+		};
+		for request in [request, retransmitted] {
+			let response = ngap_context
+				.handle_request(gnb_context.clone(), request)
+				.await;
+			assert!(matches!(response, Ok(InitialUeMessageResponse::Accepted)));
+		}
+		assert_eq!(gnb_context.ue_context_manager.len(), 1);
+		assert_eq!(gnb_context.load.ue_count(), 1);
+		assert_eq!(ngap_context.ue_count().await, 1);
+
+		// Where the UE is seen is taken from the retransmission all the same.
+		let future_closure = |ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move { ue_context.last_location.is_some() }) as PinnedSendSyncFuture<_>
+		};
+		let located = gnb_context
+			.ue_context_manager
+			.with_context(RanUeNgapId(7), future_closure)
+			.await
+			.unwrap();
+		assert!(located);
+	}
+
+	#[tokio::test]
+	async fn test_ue_of_another_amf_of_the_own_set_is_served_here() {
+		let app_context = get_global_app_context().await;
+		let served_guami_list = &app_context.get_config().served_guami_list;
+		let (_, amf_set_id, amf_pointer): (AmfRegionId, AmfSetId, AmfPointer) =
+			convert(&served_guami_list.head.amf_id);
+		let mut other_pointer = bitvec![u8, Msb0; 0; 6];
+		other_pointer.store_be((amf_pointer.0.load_be::<u8>() + 1) % (1 << 6));
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let request = InitialUeMessage {
+			ran_ue_ngap_id: RanUeNgapId(7),
+			nas_pdu: NasPdu(vec![0x7e, 0x00, 0x41]),
+			five_g_s_tmsi: Some(NgapFiveGSTmsi {
+				amf_set_id,
+				amf_pointer: AmfPointer(other_pointer.into()),
+				five_g_tmsi: FiveGTmsi([0x12, 0x34, 0x56, 0x78]),
+			}),
+			..Default::default()
+		};
+
+		let response = ngap_context
+			.handle_request(gnb_context.clone(), request)
+			.await;
+		assert!(matches!(response, Ok(InitialUeMessageResponse::Accepted)));
+		assert_eq!(gnb_context.ue_context_manager.len(), 1);
+	}
+}
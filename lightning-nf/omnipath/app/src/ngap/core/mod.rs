@@ -1,4 +1,10 @@
+pub(crate) mod handover;
 pub(crate) mod initial_ue_message;
+pub(crate) mod nas_reroute;
+pub(crate) mod ng_reset;
 pub(crate) mod ng_setup;
 pub(crate) mod ngap_route;
+pub(crate) mod overload;
+pub(crate) mod paging;
+pub(crate) mod ue_context_release;
 mod utils;
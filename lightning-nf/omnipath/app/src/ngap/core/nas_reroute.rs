@@ -0,0 +1,125 @@
+use asn1_per::ThreeGppAsn1PerError;
+use bitvec::prelude::*;
+use ngap_models::{
+	AmfPointer as NgapAmfPointer,
+	AmfRegionId as NgapAmfRegionId,
+	AmfSetId as NgapAmfSetId,
+	FiveGSTmsi as NgapFiveGSTmsi,
+	InitialUeMessage,
+	RerouteNasRequest,
+};
+use nonempty::NonEmpty;
+use oasbi::common::Guami as SbiGuami;
+use tracing::warn;
+
+use crate::{ngap::engine::codec_to_bytes, utils::convert};
+
+/// Returns the AMF Set to reroute the UE to when the AMF that allocated its
+/// 5G-S-TMSI is not one of the served GUAMIs, TS 23.502 4.2.2.2.3. A UE of
+/// another AMF of the own AMF Set is served here, the gNB would route it right
+/// back.
+pub fn reroute_amf_set_id(
+	five_g_s_tmsi: &NgapFiveGSTmsi,
+	served_guami_list: &NonEmpty<SbiGuami>,
+) -> Option<NgapAmfSetId> {
+	let set_id = five_g_s_tmsi.amf_set_id.0.load_be::<u16>();
+	let pointer = five_g_s_tmsi.amf_pointer.0.load_be::<u8>();
+	let mut own_set = false;
+	for guami in served_guami_list.iter() {
+		let (_, amf_set_id, amf_pointer): (NgapAmfRegionId, NgapAmfSetId, NgapAmfPointer) =
+			convert(&guami.amf_id);
+		if amf_set_id.0.load_be::<u16>() == set_id {
+			if amf_pointer.0.load_be::<u8>() == pointer {
+				return None;
+			}
+			own_set = true;
+		}
+	}
+	if own_set {
+		warn!(
+			"AMF Pointer {:#x} of the own AMF Set {:#x} is not served, serving the Ue here",
+			pointer, set_id
+		);
+		return None;
+	}
+	Some(five_g_s_tmsi.amf_set_id.clone())
+}
+
+/// Builds the Reroute NAS Request carrying the original Initial UE Message,
+/// which the gNB forwards to an AMF of `amf_set_id`, TS 38.413 8.6.5.
+pub fn build_reroute_nas_request(
+	request: &InitialUeMessage,
+	amf_set_id: NgapAmfSetId,
+) -> Result<RerouteNasRequest, ThreeGppAsn1PerError> {
+	Ok(RerouteNasRequest {
+		ran_ue_ngap_id: request.ran_ue_ngap_id,
+		ngap_message: codec_to_bytes(request)?,
+		amf_set_id,
+		..Default::default()
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use ngap_models::{AmfPointer, FiveGTmsi, NasPdu, RanUeNgapId};
+	use nonempty::nonempty;
+
+	use super::*;
+
+	fn five_g_s_tmsi(
+		set_id: u16,
+		pointer: u8,
+	) -> NgapFiveGSTmsi {
+		let mut amf_set_id = bitvec![u8, Msb0; 0; 10];
+		let mut amf_pointer = bitvec![u8, Msb0; 0; 6];
+		amf_set_id.store_be(set_id);
+		amf_pointer.store_be(pointer);
+		NgapFiveGSTmsi {
+			amf_set_id: NgapAmfSetId(amf_set_id.into()),
+			amf_pointer: AmfPointer(amf_pointer.into()),
+			five_g_tmsi: FiveGTmsi([0x12, 0x34, 0x56, 0x78]),
+		}
+	}
+
+	fn served_guami_list() -> NonEmpty<SbiGuami> {
+		// AMF Id 0xcafe01: region 0xca, set 0x3f8, pointer 1.
+		nonempty![
+			serde_json::from_value(serde_json::json!({
+				"plmnId": { "mcc": "208", "mnc": "93" },
+				"amfId": "cafe01",
+			}))
+			.unwrap()
+		]
+	}
+
+	#[test]
+	fn test_served_amf_is_not_rerouted() {
+		let tmsi = five_g_s_tmsi(0x3f8, 1);
+		assert!(reroute_amf_set_id(&tmsi, &served_guami_list()).is_none());
+	}
+
+	#[test]
+	fn test_other_amf_of_the_own_set_is_served_here() {
+		let tmsi = five_g_s_tmsi(0x3f8, 2);
+		assert!(reroute_amf_set_id(&tmsi, &served_guami_list()).is_none());
+	}
+
+	#[test]
+	fn test_other_amf_is_rerouted_with_original_message() {
+		let tmsi = five_g_s_tmsi(0x1a0, 1);
+		let request = InitialUeMessage {
+			ran_ue_ngap_id: RanUeNgapId(7),
+			nas_pdu: NasPdu(vec![0x7e, 0x00, 0x41]),
+			five_g_s_tmsi: Some(tmsi.clone()),
+			..Default::default()
+		};
+
+		let amf_set_id = reroute_amf_set_id(&tmsi, &served_guami_list()).unwrap();
+		assert_eq!(amf_set_id.0.load_be::<u16>(), 0x1a0);
+
+		let reroute = build_reroute_nas_request(&request, amf_set_id).unwrap();
+		assert_eq!(reroute.ran_ue_ngap_id.0, 7);
+		assert_eq!(reroute.amf_set_id.0.load_be::<u16>(), 0x1a0);
+		assert_eq!(reroute.ngap_message, codec_to_bytes(&request).unwrap());
+	}
+}
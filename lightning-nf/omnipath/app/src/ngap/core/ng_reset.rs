@@ -0,0 +1,302 @@
+use std::{collections::HashMap, hash::BuildHasher, sync::Arc};
+
+use ngap_models::{
+	AmfUeNgapId,
+	Cause,
+	CauseMisc,
+	GlobalRanNodeId,
+	NgReset,
+	NgResetAcknowledge,
+	RanUeNgapId,
+	ResetAll,
+	ResetType,
+	ToNgapPdu,
+	UeAssociatedLogicalNgConnectionItem,
+	UeAssociatedLogicalNgConnectionList,
+};
+use nonempty::NonEmpty;
+use thiserror::Error;
+use tokio::{sync::oneshot, time::timeout};
+use tracing::{info, warn};
+
+use crate::{
+	context::{GnbContext, NgapContext},
+	ngap::{
+		constants::app::NG_RESET_ACKNOWLEDGE_TIMEOUT,
+		engine::{
+			EmptyResponse,
+			NgapResponseError,
+			controller::{NgapWriteError, encode_and_write_ngap_pdu},
+		},
+	},
+};
+
+/// What an AMF initiated NG Reset resets, TS 38.413 8.7.4.2.1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResetScope {
+	/// Every UE of the gNB.
+	Full,
+	/// The listed UEs, those the gNB does not serve are left out.
+	Partial(NonEmpty<AmfUeNgapId>),
+}
+
+impl NgapContext {
+	/// Resets the UE associated connections of a gNB in `scope`, releasing
+	/// their UE contexts. The gNB itself stays connected.
+	pub async fn reset_gnb(
+		&self,
+		global_ran_node_id: &GlobalRanNodeId,
+		scope: ResetScope,
+	) -> Result<(), NgResetError> {
+		let gnb_context = self
+			.gnb_contexts
+			.read_async(global_ran_node_id, |_, gnb| gnb.clone())
+			.await
+			.ok_or_else(|| NgResetError::UnknownGnb(global_ran_node_id.clone()))?;
+		let ues = reset_ues(&*self.ue_ids.read().await, global_ran_node_id, &scope);
+		let Some(reset_type) = reset_type(&scope, &ues) else {
+			// None of the listed UEs is served by the gNB, nothing to reset.
+			return Ok(());
+		};
+
+		let (tx, rx) = oneshot::channel();
+		{
+			let mut pending_reset = gnb_context.pending_reset.lock().await;
+			if pending_reset.is_some() {
+				return Err(NgResetError::ResetInProgress);
+			}
+			*pending_reset = Some(tx);
+		}
+		let ng_reset = NgReset {
+			cause: Cause::Misc(CauseMisc::OmIntervention),
+			reset_type,
+			..Default::default()
+		};
+		info!(
+			"Resetting {} Ues of gNB {:?}",
+			ues.len(),
+			global_ran_node_id
+		);
+		let sent =
+			encode_and_write_ngap_pdu(gnb_context.tnla_association.as_ref(), ng_reset.to_pdu())
+				.await;
+		if let Err(e) = sent {
+			gnb_context.pending_reset.lock().await.take();
+			return Err(NgResetError::WriteError(e));
+		}
+		let acknowledged = timeout(NG_RESET_ACKNOWLEDGE_TIMEOUT, rx).await;
+		gnb_context.pending_reset.lock().await.take();
+
+		// Having received the reset, the gNB drops the UEs whether its
+		// acknowledgement makes it back or not.
+		for (amf_ue_ngap_id, _) in &ues {
+			self.release_ue(amf_ue_ngap_id).await;
+		}
+		match acknowledged {
+			Ok(Ok(_)) => Ok(()),
+			_ => Err(NgResetError::NotAcknowledged),
+		}
+	}
+
+	/// Completes the AMF initiated NG Reset awaiting the acknowledgement.
+	pub async fn handle_ng_reset_acknowledge(
+		&self,
+		gnb_context: Arc<GnbContext>,
+		acknowledge: NgResetAcknowledge,
+	) -> Result<EmptyResponse, NgapResponseError<EmptyResponse, NgResetError>> {
+		let pending_reset = gnb_context.pending_reset.lock().await.take();
+		match pending_reset {
+			// A late acknowledgement, the reset timed out already.
+			Some(tx) if tx.send(acknowledge).is_err() => {
+				warn!("NG Reset Acknowledge after its reset timed out");
+				Ok(EmptyResponse::new())
+			}
+			Some(_) => Ok(EmptyResponse::new()),
+			None => Err(NgapResponseError::new_empty_failure_error(
+				NgResetError::UnexpectedAcknowledge,
+			)),
+		}
+	}
+}
+
+/// The UEs of the gNB in `scope`, with the `RanUeNgapId` the gNB knows them
+/// by.
+fn reset_ues<S: BuildHasher>(
+	ue_ids: &HashMap<AmfUeNgapId, (GlobalRanNodeId, RanUeNgapId), S>,
+	global_ran_node_id: &GlobalRanNodeId,
+	scope: &ResetScope,
+) -> Vec<(AmfUeNgapId, RanUeNgapId)> {
+	let served = |amf_ue_ngap_id: &AmfUeNgapId| {
+		ue_ids
+			.get(amf_ue_ngap_id)
+			.filter(|(gnb, _)| gnb == global_ran_node_id)
+			.map(|(_, ran_ue_ngap_id)| (amf_ue_ngap_id.clone(), *ran_ue_ngap_id))
+	};
+	match scope {
+		ResetScope::Full => ue_ids.keys().filter_map(served).collect(),
+		ResetScope::Partial(amf_ue_ngap_ids) => amf_ue_ngap_ids.iter().filter_map(served).collect(),
+	}
+}
+
+/// The Reset Type IE of the reset, `None` for a partial reset left with no
+/// UE.
+fn reset_type(
+	scope: &ResetScope,
+	ues: &[(AmfUeNgapId, RanUeNgapId)],
+) -> Option<ResetType> {
+	match scope {
+		ResetScope::Full => Some(ResetType::NgInterface(ResetAll::ResetAll)),
+		ResetScope::Partial(_) => {
+			let items = ues
+				.iter()
+				.map(
+					|(amf_ue_ngap_id, ran_ue_ngap_id)| UeAssociatedLogicalNgConnectionItem {
+						amf_ue_ngap_id: Some(amf_ue_ngap_id.clone()),
+						ran_ue_ngap_id: Some(*ran_ue_ngap_id),
+						..Default::default()
+					},
+				)
+				.collect::<Vec<_>>();
+			NonEmpty::from_vec(items).map(|items| {
+				ResetType::PartOfNgInterface(UeAssociatedLogicalNgConnectionList(items))
+			})
+		}
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum NgResetError {
+	#[error("UnknownGnb: No gNB {0:?} is connected")]
+	UnknownGnb(GlobalRanNodeId),
+
+	#[error("ResetInProgress: The gNB has an NG Reset awaiting its acknowledgement")]
+	ResetInProgress,
+
+	#[error("WriteError: Failed to send the NG Reset")]
+	WriteError(#[source] NgapWriteError),
+
+	#[error("NotAcknowledged: The gNB did not acknowledge the NG Reset in time")]
+	NotAcknowledged,
+
+	#[error("UnexpectedAcknowledge: NG Reset Acknowledge with no NG Reset pending")]
+	UnexpectedAcknowledge,
+}
+
+#[cfg(test)]
+mod tests {
+	use bitvec::prelude::*;
+	use ngap_models::{GlobalGnbId, GnbId, InitiatingMessage, NgapPdu};
+	use rustc_hash::FxBuildHasher;
+
+	use super::*;
+	use crate::{
+		context::UeContext,
+		ngap::{
+			engine::decode_ngap_pdu,
+			network::{SctpEvent, TnlaAssociation},
+		},
+	};
+
+	fn ue_ids() -> HashMap<AmfUeNgapId, (GlobalRanNodeId, RanUeNgapId), FxBuildHasher> {
+		(1..=3)
+			.map(|id| {
+				let ue = (GlobalRanNodeId::default(), RanUeNgapId(id as u32 + 10));
+				(AmfUeNgapId(id), ue)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_full_reset_covers_every_ue_of_the_gnb() {
+		let gnb = GlobalRanNodeId::default();
+		let mut ues = reset_ues(&ue_ids(), &gnb, &ResetScope::Full);
+		ues.sort_by_key(|(amf_ue_ngap_id, _)| amf_ue_ngap_id.0);
+		assert_eq!(
+			ues,
+			[
+				(AmfUeNgapId(1), RanUeNgapId(11)),
+				(AmfUeNgapId(2), RanUeNgapId(12)),
+				(AmfUeNgapId(3), RanUeNgapId(13)),
+			]
+		);
+		assert!(matches!(
+			reset_type(&ResetScope::Full, &ues),
+			Some(ResetType::NgInterface(ResetAll::ResetAll))
+		));
+	}
+
+	#[test]
+	fn test_partial_reset_covers_the_listed_ues() {
+		let gnb = GlobalRanNodeId::default();
+		// The gNB serves no UE 7.
+		let scope = ResetScope::Partial(NonEmpty::from((AmfUeNgapId(2), vec![AmfUeNgapId(7)])));
+		let ues = reset_ues(&ue_ids(), &gnb, &scope);
+		assert_eq!(ues, [(AmfUeNgapId(2), RanUeNgapId(12))]);
+
+		let Some(ResetType::PartOfNgInterface(list)) = reset_type(&scope, &ues) else {
+			panic!("Partial reset without its UE list");
+		};
+		assert_eq!(list.0.len(), 1);
+		assert_eq!(list.0.head.amf_ue_ngap_id, Some(AmfUeNgapId(2)));
+		assert_eq!(list.0.head.ran_ue_ngap_id, Some(RanUeNgapId(12)));
+
+		let nobody = ResetScope::Partial(NonEmpty::new(AmfUeNgapId(7)));
+		assert!(reset_ues(&ue_ids(), &gnb, &nobody).is_empty());
+		assert!(reset_type(&nobody, &[]).is_none());
+	}
+
+	#[tokio::test]
+	async fn test_acknowledged_reset_releases_the_ues_of_the_gnb() {
+		let ngap_context = NgapContext::test_fixture();
+		let mut gnb_id = bitvec![u8, Msb0; 0; 22];
+		gnb_id.store_be(1u32);
+		let global_ran_node_id = GlobalRanNodeId::GlobalGnbId(GlobalGnbId {
+			gnb_id: GnbId::GnbId(gnb_id.into()),
+			..Default::default()
+		});
+		let (gnb_context, gnb) = ngap_context.gnb_test_fixture(global_ran_node_id).await;
+		let gnb = TnlaAssociation::new(gnb).unwrap();
+		ngap_context
+			.gnb_contexts
+			.insert_async(gnb_context.global_ran_node_id.clone(), gnb_context.clone())
+			.await
+			.unwrap();
+		for id in 1..=2 {
+			let ran_ue_ngap_id = RanUeNgapId(id as u32 + 10);
+			let ue_context =
+				UeContext::test_fixture(ran_ue_ngap_id, AmfUeNgapId(id), gnb_context.clone());
+			ngap_context.add_test_ue(ue_context).await;
+		}
+
+		// The gNB acknowledges the NG Reset sent over its association.
+		let gnb_side = async {
+			let Ok(Some(SctpEvent::Data(message))) = gnb.read_data().await else {
+				panic!("expected an NGAP message");
+			};
+			let NgapPdu::InitiatingMessage(InitiatingMessage::NgReset(ng_reset)) =
+				decode_ngap_pdu(&message).unwrap()
+			else {
+				panic!("expected the NG Reset");
+			};
+			assert!(matches!(
+				ng_reset.reset_type,
+				ResetType::NgInterface(ResetAll::ResetAll)
+			));
+			ngap_context
+				.handle_ng_reset_acknowledge(gnb_context.clone(), NgResetAcknowledge::default())
+				.await
+				.unwrap();
+		};
+		let global_ran_node_id = ngap_context.find_gnb_by_gnb_id(1).await.unwrap();
+		let (reset, ()) = tokio::join!(
+			ngap_context.reset_gnb(&global_ran_node_id, ResetScope::Full),
+			gnb_side
+		);
+		reset.unwrap();
+		assert!(gnb_context.ue_context_manager.is_empty());
+		for id in 1..=2 {
+			assert_eq!(ngap_context.find_ue(&AmfUeNgapId(id)).await, None);
+		}
+		assert!(ngap_context.find_gnb_by_gnb_id(2).await.is_none());
+	}
+}
@@ -10,18 +10,24 @@ use ngap_models::{
 	RelativeAmfCapacity,
 	SupportedTaItem,
 };
-use oasbi::common::{PlmnId, Tac as SbiTac, Tai, error::ConversionError};
+use nonempty::NonEmpty;
+use oasbi::common::{PlmnId, Snssai, Tac as SbiTac, Tai, error::ConversionError};
 use thiserror::Error;
-use tracing::trace;
+use tracing::{trace, warn};
 
 use crate::{
+	config::PlmnSupportItem,
 	context::{GnbContext, NgapContext, SupportedTai},
 	get_global_app_context,
 	ngap::{
 		core::utils::{new_semantic_error_cause, resolve_ran_name},
 		engine::{NgapRequestHandler, NgapResponseError},
 	},
-	utils::{convert as ngap_convert, try_convert as ngap_try_convert},
+	utils::{
+		convert as ngap_convert,
+		models::{NssaiAvailability, TaiSet},
+		try_convert as ngap_try_convert,
+	},
 };
 
 impl NgapRequestHandler<NgSetupRequest, &mut GnbContext> for NgapContext {
@@ -96,15 +102,33 @@ impl NgapRequestHandler<NgSetupRequest, &mut GnbContext> for NgapContext {
 		trace!(supported_tais = ?supported_tais);
 		let app_context = get_global_app_context().await;
 		// Check if at least one TA is supported by AMF
-		let amf_supported_tai_list = &app_context.get_config().support_tai_list;
-
-		let ran_tai_set: std::collections::HashSet<_> =
-			supported_tais.iter().map(|t| &t.tai).collect();
-
-		// Find first matching TAI
-		let found = amf_supported_tai_list
-			.iter()
-			.any(|supported_tai| ran_tai_set.contains(&supported_tai));
+		let config = app_context.get_config();
+		if config.reject_unsupported_plmns {
+			ensure_supported_plmns(&config.plmn_support_list, &supported_tais)?;
+		}
+		let (served_tais, unsupported_tais) =
+			partition_tais(&config.support_tai_set, &supported_tais);
+		if !served_tais.is_empty() && !unsupported_tais.is_empty() {
+			// The setup still succeeds, but the gNB covers areas the AMF does not
+			// serve, which is worth an operator's look.
+			self.counters.record_partial_tai_match();
+			warn!(
+				ran_id = ?state.global_ran_node_id,
+				unsupported_tais = ?unsupported_tais,
+				"gNB advertised TAIs the AMF does not serve"
+			);
+		}
+		let unavailable_slices = unavailable_slices(&config.nssai_availability, &supported_tais);
+		if !unavailable_slices.is_empty() {
+			warn!(
+				ran_id = ?state.global_ran_node_id,
+				unavailable_slices = ?unavailable_slices,
+				"gNB advertised slices not available in their TAIs"
+			);
+		}
+		let found = !served_tais.is_empty();
+		state.unsupported_tais = unsupported_tais.into_iter().cloned().collect();
+		state.unavailable_slices = unavailable_slices;
 
 		if !found {
 			Err(NgapResponseError::new_failure_error(
@@ -113,14 +137,26 @@ impl NgapRequestHandler<NgSetupRequest, &mut GnbContext> for NgapContext {
 			))
 		} else {
 			// Success case
-			let amf_name = &app_context.get_config().name;
-			let served_guami_list = &app_context.get_config().served_guami_list;
-			let plmn_support_list = &app_context.get_config().plmn_support_list;
+			let params = app_context.ng_setup_response_params();
+			// Only PLMN IDs slipping past the config validation fail here.
+			let amf_failure =
+				|e: ConversionError| -> NgapResponseError<NgSetupFailure, NgSetupError> {
+					NgapResponseError::new_failure_error(
+						build_failure(Cause::Misc(CauseMisc::Unspecified)),
+						e,
+					)
+				};
+			let plmn_support_list = available_plmn_support(
+				params.plmn_support_list(),
+				params.nssai_availability(),
+				&served_tais,
+			);
 			let response = Self::Success {
-				plmn_support_list: ngap_convert(plmn_support_list),
-				served_guami_list: ngap_convert(served_guami_list),
-				relative_amf_capacity: RelativeAmfCapacity(u8::MAX),
-				amf_name: AmfName(amf_name.to_string()),
+				plmn_support_list: ngap_try_convert(&plmn_support_list).map_err(amf_failure)?,
+				served_guami_list: ngap_try_convert(params.served_guami_list())
+					.map_err(amf_failure)?,
+				relative_amf_capacity: RelativeAmfCapacity(params.relative_amf_capacity()),
+				amf_name: AmfName(params.amf_name().to_owned()),
 				..Default::default()
 			};
 			Ok(response)
@@ -136,13 +172,357 @@ pub enum NgSetupError {
 	#[error("UnsupportedTais: {0:?}")]
 	UnsupportedTais(Vec<SupportedTai>),
 
+	#[error("UnsupportedPlmns: The gNB broadcasts PLMNs the AMF does not support {0:?}")]
+	UnsupportedPlmns(Vec<PlmnId>),
+
 	#[error("ConflictingRanId: {0:?}")]
 	ConflictingRanId(GlobalRanNodeId),
 }
 
+/// Fails the setup of a gNB broadcasting any PLMN the AMF does not support,
+/// whether or not it also advertises TAIs the AMF serves.
+fn ensure_supported_plmns(
+	plmn_support_list: &NonEmpty<PlmnSupportItem>,
+	supported_tais: &[SupportedTai],
+) -> Result<(), NgapResponseError<NgSetupFailure, NgSetupError>> {
+	let mut unsupported_plmns = vec![];
+	for supported_tai in supported_tais {
+		let plmn_id = &supported_tai.tai.plmn_id;
+		if !plmn_support_list
+			.iter()
+			.any(|item| &item.plmn_id == plmn_id)
+			&& !unsupported_plmns.contains(plmn_id)
+		{
+			unsupported_plmns.push(plmn_id.clone());
+		}
+	}
+	if !unsupported_plmns.is_empty() {
+		return Err(NgapResponseError::new_failure_error(
+			build_failure(Cause::Misc(CauseMisc::UnknownPlmnOrSnpn)),
+			NgSetupError::UnsupportedPlmns(unsupported_plmns),
+		));
+	}
+	Ok(())
+}
+
+/// Splits the TAIs advertised by a gNB into those the AMF serves and those it
+/// does not.
+fn partition_tais<'a>(
+	amf_tais: &TaiSet,
+	ran_tais: &'a [SupportedTai],
+) -> (Vec<&'a Tai>, Vec<&'a Tai>) {
+	ran_tais
+		.iter()
+		.map(|supported_tai| &supported_tai.tai)
+		.partition(|tai| amf_tais.contains(tai))
+}
+
+/// The slices advertised by a gNB for TAIs they are not available in.
+fn unavailable_slices(
+	availability: &NssaiAvailability,
+	ran_tais: &[SupportedTai],
+) -> Vec<(Tai, Snssai)> {
+	ran_tais
+		.iter()
+		.flat_map(|supported_tai| {
+			supported_tai
+				.snssais
+				.iter()
+				.filter(|snssai| !availability.is_available(&supported_tai.tai, snssai))
+				.map(|snssai| (supported_tai.tai.clone(), snssai.clone()))
+		})
+		.collect()
+}
+
+/// The PLMN support list narrowed, for the PLMNs of the TAIs served to the
+/// gNB, to the slices available in at least one of those TAIs. The full list
+/// is kept when nothing would be left of it.
+fn available_plmn_support(
+	plmn_support_list: &NonEmpty<PlmnSupportItem>,
+	availability: &NssaiAvailability,
+	served_tais: &[&Tai],
+) -> NonEmpty<PlmnSupportItem> {
+	let available = plmn_support_list.iter().filter_map(|item| {
+		let plmn_tais = served_tais
+			.iter()
+			.filter(|tai| tai.plmn_id == item.plmn_id)
+			.collect::<Vec<_>>();
+		if plmn_tais.is_empty() {
+			return Some(item.clone());
+		}
+		let snssais = item
+			.snssai_list
+			.iter()
+			.filter(|snssai| {
+				plmn_tais
+					.iter()
+					.any(|tai| availability.is_available(tai, snssai))
+			})
+			.cloned()
+			.collect();
+		Some(PlmnSupportItem {
+			plmn_id: item.plmn_id.clone(),
+			snssai_list: NonEmpty::from_vec(snssais)?,
+		})
+	});
+	NonEmpty::collect(available).unwrap_or_else(|| plmn_support_list.clone())
+}
+
 fn build_failure(cause: Cause) -> NgSetupFailure {
 	NgSetupFailure {
 		cause,
 		..Default::default()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use ngap_models::{
+		BroadcastPlmnList,
+		InitiatingMessage,
+		NgapPdu,
+		SupportedTaList,
+		Tac,
+		ToNgapPdu,
+	};
+	use tokio_util::sync::CancellationToken;
+
+	use super::*;
+	use crate::ngap::{
+		constants::app::MAX_EARLY_NGAP_MESSAGES,
+		engine::{
+			EarlyMessages,
+			NgapFailure,
+			codec_to_bytes,
+			controller::NgapSetupError,
+			decode_ngap_pdu,
+		},
+		network::{SctpEvent, TnlaAssociation},
+	};
+
+	/// id-SupportedTAList, TS 38.413 9.4.7.
+	const ID_SUPPORTED_TA_LIST: [u8; 2] = [0x00, 0x66];
+
+	fn tai(tac: u8) -> Tai {
+		Tai {
+			tac: ngap_convert(&Tac([0, 0, tac])),
+			..Default::default()
+		}
+	}
+
+	fn advertised(tac: u8) -> SupportedTai {
+		SupportedTai {
+			tai: tai(tac),
+			snssais: NonEmpty::new(Snssai::default()),
+		}
+	}
+
+	#[test]
+	fn test_partial_tai_match_reports_unsupported_tais() {
+		let amf_tais = [tai(1), tai(4)].iter().collect::<TaiSet>();
+		let ran_tais = [advertised(1), advertised(2), advertised(3)];
+		let (served, unsupported) = partition_tais(&amf_tais, &ran_tais);
+		assert_eq!(served, [&tai(1)]);
+		assert_eq!(unsupported, [&tai(2), &tai(3)]);
+
+		let (served, unsupported) = partition_tais(&amf_tais, &ran_tais[1..]);
+		assert!(served.is_empty());
+		assert_eq!(unsupported.len(), 2);
+	}
+
+	/// A TA of the PLMN of the default config supporting its slice.
+	fn supported_ta_item(tac: Tac) -> SupportedTaItem {
+		SupportedTaItem {
+			tac,
+			broadcast_plmn_list: BroadcastPlmnList(NonEmpty::new(BroadcastPlmnItem {
+				plmn_identity: ngap_try_convert(&Tai::default().plmn_id).unwrap(),
+				tai_slice_support_list: ngap_convert(&NonEmpty::new(Snssai::default())),
+				..Default::default()
+			})),
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn test_partial_tai_match_is_counted() {
+		let ngap_context = NgapContext::test_fixture();
+		let (fixture, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let mut gnb_context = GnbContext::new(
+			fixture.tnla_association.clone(),
+			ngap_context.ue_identities.clone(),
+			CancellationToken::new(),
+		);
+		// The TAI of the default config along with two the AMF does not serve.
+		let request = NgSetupRequest {
+			global_ran_node_id: GlobalRanNodeId::default(),
+			supported_ta_list: SupportedTaList(NonEmpty::from((
+				supported_ta_item(ngap_convert(&Tai::default().tac)),
+				vec![
+					supported_ta_item(Tac([0xff, 0xff, 0x02])),
+					supported_ta_item(Tac([0xff, 0xff, 0x03])),
+				],
+			))),
+			..Default::default()
+		};
+
+		assert!(
+			ngap_context
+				.handle_request(&mut gnb_context, request)
+				.await
+				.is_ok()
+		);
+		assert_eq!(gnb_context.unsupported_tais.len(), 2);
+		assert_eq!(ngap_context.stats().await.partial_tai_matches, 1);
+	}
+
+	/// Cuts the value of the Supported TA List IE out of an encoded NG Setup
+	/// Request, leaving the IE with a zero length value.
+	fn without_supported_ta_list(encoded: &[u8]) -> Vec<u8> {
+		// Procedure code and criticality, the length of the value, then the
+		// extension bit and IE count ahead of the IEs, each an id, criticality
+		// and length prefixed value. Lengths below 128 take one octet.
+		assert!(encoded[3] < 0x80);
+		let (mut value, mut ies) = (encoded[4..7].to_vec(), &encoded[7..]);
+		let mut cut = false;
+		while let [id_high, id_low, criticality, length, rest @ ..] = ies {
+			assert!(*length < 0x80);
+			let length = usize::from(*length);
+			if [*id_high, *id_low] == ID_SUPPORTED_TA_LIST {
+				value.extend_from_slice(&[*id_high, *id_low, *criticality, 0]);
+				cut = true;
+			} else {
+				value.extend_from_slice(&ies[..4 + length]);
+			}
+			ies = &rest[length..];
+		}
+		assert!(cut);
+		let mut message = encoded[..3].to_vec();
+		message.push(u8::try_from(value.len()).unwrap());
+		message.extend(value);
+		message
+	}
+
+	#[tokio::test]
+	async fn test_empty_supported_ta_list_is_rejected_at_decode() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let gnb = TnlaAssociation::new(gnb).unwrap();
+		let request = NgSetupRequest {
+			global_ran_node_id: GlobalRanNodeId::default(),
+			supported_ta_list: SupportedTaList(NonEmpty::new(supported_ta_item(ngap_convert(
+				&Tai::default().tac,
+			)))),
+			..Default::default()
+		};
+		let encoded = codec_to_bytes(&request.to_pdu()).unwrap();
+		gnb.write_data(without_supported_ta_list(&encoded).into(), None)
+			.await
+			.unwrap();
+
+		let mut early_messages = EarlyMessages::new(MAX_EARLY_NGAP_MESSAGES);
+		let setup = ngap_context
+			.try_ng_setup(
+				gnb_context.tnla_association.clone(),
+				CancellationToken::new(),
+				&mut early_messages,
+			)
+			.await;
+		assert!(matches!(
+			setup,
+			Err(NgapSetupError::PerCodecEncodingError(_))
+		));
+		let Ok(Some(SctpEvent::Data(message))) = gnb.read_data().await else {
+			panic!("expected an NGAP message");
+		};
+		assert!(matches!(
+			decode_ngap_pdu(&message),
+			Ok(NgapPdu::InitiatingMessage(
+				InitiatingMessage::ErrorIndication(_)
+			))
+		));
+		assert!(ngap_context.gnb_contexts.is_empty());
+	}
+
+	#[test]
+	fn test_unsupported_plmn_is_rejected() {
+		let plmn_support_list = NonEmpty::new(PlmnSupportItem {
+			plmn_id: PlmnId::default(),
+			snssai_list: NonEmpty::new(Snssai::default()),
+		});
+		let foreign_plmn = PlmnId {
+			mcc: "310".parse().unwrap(),
+			mnc: "410".parse().unwrap(),
+		};
+		// The first TAI is served, the second broadcasts another PLMN.
+		let ran_tais = [
+			advertised(1),
+			SupportedTai {
+				tai: Tai {
+					plmn_id: foreign_plmn.clone(),
+					..tai(1)
+				},
+				snssais: NonEmpty::new(Snssai::default()),
+			},
+		];
+
+		let error = ensure_supported_plmns(&plmn_support_list, &ran_tais).unwrap_err();
+		assert!(matches!(
+			error.error,
+			NgSetupError::UnsupportedPlmns(plmns) if plmns == [foreign_plmn]
+		));
+		assert!(matches!(
+			error.failure,
+			NgapFailure::Failure(NgSetupFailure {
+				cause: Cause::Misc(CauseMisc::UnknownPlmnOrSnpn),
+				..
+			})
+		));
+		assert!(ensure_supported_plmns(&plmn_support_list, &ran_tais[..1]).is_ok());
+	}
+
+	fn slice(sst: u8) -> Snssai {
+		Snssai {
+			sst,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_slice_unavailable_in_its_tai_is_flagged() {
+		let embb = NonEmpty::new(slice(1));
+		let availability = [(&tai(1), &embb)]
+			.into_iter()
+			.collect::<NssaiAvailability>();
+		let ran_tais = [
+			SupportedTai {
+				tai: tai(1),
+				snssais: NonEmpty::from((slice(1), vec![slice(2)])),
+			},
+			SupportedTai {
+				tai: tai(2),
+				snssais: NonEmpty::new(slice(2)),
+			},
+		];
+
+		let unavailable = unavailable_slices(&availability, &ran_tais);
+		assert_eq!(unavailable.len(), 1);
+		assert_eq!(unavailable[0].0, tai(1));
+		assert_eq!(unavailable[0].1.sst, 2);
+
+		let plmn_support_list = NonEmpty::new(PlmnSupportItem {
+			plmn_id: PlmnId::default(),
+			snssai_list: NonEmpty::from((slice(1), vec![slice(2)])),
+		});
+		let advertised = available_plmn_support(&plmn_support_list, &availability, &[&tai(1)]);
+		assert_eq!(advertised.head.snssai_list.len(), 1);
+		assert_eq!(advertised.head.snssai_list.head.sst, 1);
+		// A TAI without restrictions has every slice.
+		let advertised =
+			available_plmn_support(&plmn_support_list, &availability, &[&tai(1), &tai(2)]);
+		assert_eq!(advertised.head.snssai_list.len(), 2);
+	}
+}
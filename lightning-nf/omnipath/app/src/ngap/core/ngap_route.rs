@@ -1,7 +1,22 @@
-use std::{error::Error, fmt::Debug, sync::Arc};
+use std::{
+	collections::HashMap,
+	error::Error,
+	fmt::Debug,
+	future::Future,
+	pin::Pin,
+	sync::{Arc, LazyLock},
+};
 
-use ngap_models::{InitiatingMessage, NgapPdu};
-use tracing::error;
+use ngap_models::{
+	Cause,
+	CauseProtocol,
+	ErrorIndication,
+	InitiatingMessage,
+	NgapPdu,
+	SuccessfulOutcome,
+	UnsuccessfulOutcome,
+};
+use tracing::{error, warn};
 
 use super::utils::new_semantic_error;
 use crate::context::{GnbContext, NgapContext};
@@ -11,82 +26,222 @@ use crate::ngap::engine::{
 	NgapResponseError,
 	// NgapResponseHandler,
 	ToPdu,
+	decode_ngap_pdu,
+	log_error_indication,
+	message_diagnostics,
 };
+use crate::ngap::procedure_code_enum::{ProcedureAllowList, ProcedureCodeEnum};
 
-/// Routes an incoming NGAP PDU to the appropriate handler based on its type.
-///
-/// This function serves as the primary entry point for processing NGAP messages
-/// received from a gNB. It uses macros to dispatch the request to specific
-/// handlers (`handle_request`, `handle_success_response`,
-/// `handle_failure_response`) based on whether the PDU is an
-/// `InitiatingMessage`, `SuccessfulOutcome`, or `UnsuccessfulOutcome`.
-///
-/// Currently, it only explicitly handles `InitiatingMessage::InitialUeMessage`.
-/// Other PDU types will result in a generic semantic error response.
-///
-/// # Arguments
-///
-/// * `gnb_context` - An `Arc`-wrapped `GnbContext` representing the state of
-///   the gNB associated with this message.
-/// * `request` - The incoming `NgapPdu` to be processed.
-///
-/// # Returns
-///
-/// An `Option<NgapPdu>` containing the response PDU if one is generated, or
-/// `None` if the handler does not produce a direct response or an error occurs
-/// during conversion.
 impl NgapContext {
+	/// Decodes an incoming NGAP PDU and routes it to the appropriate handler
+	/// based on its type.
+	///
+	/// This function serves as the primary entry point for processing NGAP
+	/// messages received from a gNB. Messages of procedures left out of the
+	/// configured allow list are answered with an `ErrorIndication` before
+	/// being decoded. The handler is looked up in the [`DispatchTable`] by the
+	/// message class of the PDU and its procedure code.
+	///
+	/// Currently, it only explicitly handles
+	/// `InitiatingMessage::InitialUeMessage`,
+	/// `SuccessfulOutcome::NgResetAcknowledge`,
+	/// `SuccessfulOutcome::UeContextReleaseComplete` and the messages of the
+	/// intra-AMF N2 handover. Other PDU types will result in a generic semantic
+	/// error response.
+	///
+	/// # Arguments
+	///
+	/// * `gnb_context` - An `Arc`-wrapped `GnbContext` representing the state
+	///   of the gNB associated with this message.
+	/// * `message` - The encoded NGAP PDU to be processed.
+	///
+	/// # Returns
+	///
+	/// An `Option<NgapPdu>` containing the response PDU if one is generated, or
+	/// `None` if the handler does not produce a direct response or an error
+	/// occurs during conversion.
 	pub async fn ngap_route(
 		&self,
 		gnb_context: Arc<GnbContext>,
-		request: NgapPdu,
+		message: &[u8],
 	) -> Option<NgapPdu> {
-		macro_rules! match_ue_pdu {
-            ($msg:expr, InitiatingMessage, $($variant:ident),*) => {
-                match $msg {
-                    $(
-                        InitiatingMessage::$variant(pdu) => {
-                            let resp = self.handle_request(gnb_context, pdu).await;
-                            log_and_convert_to_pdu(resp)
-                        },
-                    )*
-                    _ => new_semantic_error(None, None).to_pdu(),
-                }
-            };
-            ($msg:expr, SuccessfulOutcome, $($variant:ident),*) => {
-                match $msg {
-                    $(
-                        SuccessfulOutcome::$variant(pdu) => {
-                            let resp = self.handle_success_response(gnb_context, pdu).await;
-                            log_and_convert_to_pdu(resp)
-                        },
-                    )*
-                    _ => new_semantic_error(None, None).to_pdu(),
-                }
-            };
-            ($msg:expr, UnsuccessfulOutcome, $($variant:ident),*) => {
-                match $msg {
-                    $(
-                        UnsuccessfulOutcome::$variant(pdu) => {
-                            let resp = self.handle_failure_response(gnb_context, pdu).await;
-                            log_and_convert_to_pdu(resp)
-                        },
-                    )*
-                    _ => new_semantic_error(None, None).to_pdu(),
-                }
-            };
-        }
-
-		match request {
-			NgapPdu::InitiatingMessage(initiating_message) => {
-				match_ue_pdu!(initiating_message, InitiatingMessage, InitialUeMessage)
+		self.counters.record_message(message);
+		if let Some(error) = disabled_procedure_error(&self.enabled_procedures, message) {
+			log_error_indication(&error);
+			return error.to_pdu();
+		}
+		let request = match decode_ngap_pdu(message) {
+			Ok(request) => request,
+			Err((pdu, error)) => {
+				error!(diagnostic = "Error decoding NGAP PDU", error = ?error);
+				return Some(pdu);
 			}
-			_ => new_semantic_error(None, None).to_pdu(),
+		};
+
+		let (_, procedure_code, _) = NgapPdu::get_message_info(message);
+		let handler = procedure_code
+			.and_then(|code| ProcedureCodeEnum::try_from(code.0).ok())
+			.and_then(|procedure| DISPATCH_TABLE.get(MessageClass::of(&request), procedure));
+		match handler {
+			Some(handler) => handler(self, gnb_context, request).await,
+			None => {
+				let error = ErrorIndication {
+					criticality_diagnostics: Some(message_diagnostics(message, None)),
+					..new_semantic_error(None, None)
+				};
+				log_error_indication(&error);
+				error.to_pdu()
+			}
+		}
+	}
+}
+
+/// The choice of an `NGAP-PDU`, TS 38.413 9.4.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+	InitiatingMessage,
+	SuccessfulOutcome,
+	UnsuccessfulOutcome,
+}
+
+impl MessageClass {
+	pub fn of(pdu: &NgapPdu) -> Self {
+		match pdu {
+			NgapPdu::InitiatingMessage(_) => Self::InitiatingMessage,
+			NgapPdu::SuccessfulOutcome(_) => Self::SuccessfulOutcome,
+			NgapPdu::UnsuccessfulOutcome(_) => Self::UnsuccessfulOutcome,
 		}
 	}
 }
 
-fn log_and_convert_to_pdu<T, F, E>(result: Result<T, NgapResponseError<F, E>>) -> Option<NgapPdu>
+type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Option<NgapPdu>> + Send + 'a>>;
+
+/// Handles a decoded PDU of the message class and procedure it is registered
+/// for, returning the PDU to answer with.
+pub type Handler = for<'a> fn(&'a NgapContext, Arc<GnbContext>, NgapPdu) -> HandlerFuture<'a>;
+
+/// Builds the [`Handler`] of a message, handing it to an `NgapRequestHandler`
+/// or `NgapResponseHandler` method of `NgapContext`.
+macro_rules! handler {
+	($class:ident :: $variant:ident => $handle:ident) => {{
+		fn handler<'a>(
+			context: &'a NgapContext,
+			gnb_context: Arc<GnbContext>,
+			pdu: NgapPdu,
+		) -> HandlerFuture<'a> {
+			Box::pin(async move {
+				match pdu {
+					NgapPdu::$class($class::$variant(pdu)) => {
+						log_and_convert_to_pdu(
+							stringify!($variant),
+							context.$handle(gnb_context, pdu).await,
+						)
+					}
+					// Only registered for its own message.
+					_ => new_semantic_error(None, None).to_pdu(),
+				}
+			})
+		}
+		handler as Handler
+	}};
+}
+
+static DISPATCH_TABLE: LazyLock<DispatchTable> = LazyLock::new(DispatchTable::new);
+
+/// The handlers of the NGAP messages by message class and procedure. Handling
+/// another message is registering its handler in [`DispatchTable::new`].
+pub struct DispatchTable(HashMap<(MessageClass, ProcedureCodeEnum), Handler>);
+
+impl DispatchTable {
+	fn new() -> Self {
+		let mut table = Self(HashMap::new());
+		table.register(
+			MessageClass::InitiatingMessage,
+			ProcedureCodeEnum::InitialUEMessage,
+			handler!(InitiatingMessage::InitialUeMessage => handle_request),
+		);
+		table.register(
+			MessageClass::SuccessfulOutcome,
+			ProcedureCodeEnum::NGReset,
+			handler!(SuccessfulOutcome::NgResetAcknowledge => handle_ng_reset_acknowledge),
+		);
+		table.register(
+			MessageClass::InitiatingMessage,
+			ProcedureCodeEnum::HandoverPreparation,
+			handler!(InitiatingMessage::HandoverRequired => handle_handover_required),
+		);
+		table.register(
+			MessageClass::SuccessfulOutcome,
+			ProcedureCodeEnum::HandoverResourceAllocation,
+			handler!(
+				SuccessfulOutcome::HandoverRequestAcknowledge => handle_handover_request_acknowledge
+			),
+		);
+		table.register(
+			MessageClass::UnsuccessfulOutcome,
+			ProcedureCodeEnum::HandoverResourceAllocation,
+			handler!(UnsuccessfulOutcome::HandoverFailure => handle_handover_failure),
+		);
+		table.register(
+			MessageClass::InitiatingMessage,
+			ProcedureCodeEnum::HandoverNotification,
+			handler!(InitiatingMessage::HandoverNotify => handle_handover_notify),
+		);
+		table.register(
+			MessageClass::SuccessfulOutcome,
+			ProcedureCodeEnum::UEContextRelease,
+			handler!(
+				SuccessfulOutcome::UeContextReleaseComplete => handle_ue_context_release_complete
+			),
+		);
+		table
+	}
+
+	fn register(
+		&mut self,
+		class: MessageClass,
+		procedure: ProcedureCodeEnum,
+		handler: Handler,
+	) {
+		self.0.insert((class, procedure), handler);
+	}
+
+	pub fn get(
+		&self,
+		class: MessageClass,
+		procedure: ProcedureCodeEnum,
+	) -> Option<Handler> {
+		self.0.get(&(class, procedure)).copied()
+	}
+}
+
+/// Builds the `ErrorIndication` answering a message whose procedure is not in
+/// `enabled`, `None` when the procedure is handled.
+fn disabled_procedure_error(
+	enabled: &ProcedureAllowList,
+	message: &[u8],
+) -> Option<ErrorIndication> {
+	let (_, procedure_code, _) = NgapPdu::get_message_info(message);
+	let procedure = ProcedureCodeEnum::try_from(procedure_code?.0).ok()?;
+	if enabled.is_enabled(procedure) {
+		return None;
+	}
+	warn!("Rejecting {:?}, the procedure is disabled", procedure);
+	Some(ErrorIndication {
+		cause: Some(Cause::Protocol(
+			CauseProtocol::MessageNotCompatibleWithReceiverState,
+		)),
+		criticality_diagnostics: Some(message_diagnostics(message, None)),
+		..Default::default()
+	})
+}
+
+/// Converts the outcome of handling a `message` to the PDU to answer with,
+/// logging the error of a failed one.
+fn log_and_convert_to_pdu<T, F, E>(
+	message: &str,
+	result: Result<T, NgapResponseError<F, E>>,
+) -> Option<NgapPdu>
 where
 	T: ToPdu,
 	F: ToPdu + Debug,
@@ -95,11 +250,88 @@ where
 	match result {
 		Ok(resp) => resp.to_pdu(),
 		Err(NgapResponseError { failure, error }) => {
-			error!("Error handling InitialUeMessage: {:?}", error);
+			error!("Error handling {}: {:?}", message, error);
 			match failure {
 				NgapFailure::Failure(failure) => failure.to_pdu(),
-				NgapFailure::GenericError(error) => error.to_pdu(),
+				NgapFailure::GenericError(error) => {
+					log_error_indication(&error);
+					error.to_pdu()
+				}
 			}
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A Paging message, procedure code 24, with an empty IE container, only
+	/// its header is read.
+	const PAGING: &[u8] = &[0x00, 0x18, 0x40, 0x03, 0x00, 0x00, 0x00];
+
+	fn handle_nothing<'a>(
+		_: &'a NgapContext,
+		_: Arc<GnbContext>,
+		_: NgapPdu,
+	) -> HandlerFuture<'a> {
+		Box::pin(async { None })
+	}
+
+	fn handle_anything<'a>(
+		_: &'a NgapContext,
+		_: Arc<GnbContext>,
+		_: NgapPdu,
+	) -> HandlerFuture<'a> {
+		Box::pin(async { new_semantic_error(None, None).to_pdu() })
+	}
+
+	#[test]
+	fn test_dispatch_picks_the_registered_handler() {
+		use ProcedureCodeEnum::{InitialUEMessage, Paging};
+
+		let mut table = DispatchTable::new();
+		table.register(MessageClass::SuccessfulOutcome, Paging, handle_nothing);
+		table.register(MessageClass::UnsuccessfulOutcome, Paging, handle_anything);
+
+		let handler = |class, procedure| table.get(class, procedure);
+		assert!(handler(MessageClass::InitiatingMessage, InitialUEMessage).is_some());
+		assert!(std::ptr::fn_addr_eq(
+			handler(MessageClass::SuccessfulOutcome, Paging).unwrap(),
+			handle_nothing as Handler
+		));
+		assert!(std::ptr::fn_addr_eq(
+			handler(MessageClass::UnsuccessfulOutcome, Paging).unwrap(),
+			handle_anything as Handler
+		));
+		// Left to the semantic error of `ngap_route`.
+		assert!(handler(MessageClass::InitiatingMessage, Paging).is_none());
+		assert!(handler(MessageClass::SuccessfulOutcome, InitialUEMessage).is_none());
+	}
+
+	#[test]
+	fn test_enabled_procedure_is_routed() {
+		assert!(disabled_procedure_error(&ProcedureAllowList::default(), PAGING).is_none());
+	}
+
+	#[test]
+	fn test_disabled_procedure_is_rejected() {
+		let enabled = ProcedureAllowList::new([
+			ProcedureCodeEnum::NGSetup,
+			ProcedureCodeEnum::InitialUEMessage,
+		]);
+		let error = disabled_procedure_error(&enabled, PAGING).unwrap();
+
+		assert!(matches!(
+			error.cause,
+			Some(Cause::Protocol(
+				CauseProtocol::MessageNotCompatibleWithReceiverState
+			))
+		));
+		let diagnostics = error.criticality_diagnostics.unwrap();
+		assert_eq!(
+			diagnostics.procedure_code.map(|code| code.0),
+			Some(ProcedureCodeEnum::Paging as u8)
+		);
+	}
+}
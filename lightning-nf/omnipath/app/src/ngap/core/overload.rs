@@ -0,0 +1,117 @@
+use ngap_models::{OverloadAction, OverloadResponse, OverloadStart, OverloadStop, ToNgapPdu};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+	context::{AmfEvent, NgapContext, OverloadTransition},
+	ngap::{
+		constants::app::OVERLOAD_CHECK_INTERVAL,
+		engine::controller::encode_and_write_ngap_pdu,
+	},
+};
+
+impl NgapContext {
+	/// Checks the backlog against the overload threshold every
+	/// [`OVERLOAD_CHECK_INTERVAL`], until `shutdown`. Returns at once without
+	/// overload control.
+	pub async fn monitor_overload(
+		&self,
+		shutdown: CancellationToken,
+	) {
+		if self.overload.is_none() {
+			return;
+		}
+		let mut interval = tokio::time::interval(OVERLOAD_CHECK_INTERVAL);
+		loop {
+			tokio::select! {
+				_ = shutdown.cancelled() => break,
+				_ = interval.tick() => {
+					self.check_overload().await;
+				}
+			}
+		}
+	}
+
+	/// Starts or stops the overload as the current backlog calls for, sending
+	/// the Overload Start or Stop to every connected gNB, TS 38.413 8.7.6 and
+	/// 8.7.7.
+	pub async fn check_overload(&self) -> Option<OverloadTransition> {
+		let overload = self.overload.as_ref()?;
+		let backlog = self.backlog().await;
+		let transition = overload.update(backlog)?;
+		info!("Overload {:?} with a backlog of {}", transition, backlog);
+		let pdu = match transition {
+			OverloadTransition::Start => OverloadStart {
+				// Keeps the gNBs admitting emergency and mobile terminated
+				// traffic, the UEs already served are left alone.
+				amf_overload_response: Some(OverloadResponse::OverloadAction(
+					OverloadAction::RejectNonEmergencyMoDt,
+				)),
+				..Default::default()
+			}
+			.to_pdu(),
+			OverloadTransition::Stop => OverloadStop::default().to_pdu(),
+		};
+		let mut gnb_contexts = Vec::with_capacity(self.gnb_contexts.len());
+		self.gnb_contexts
+			.scan_async(|_, gnb_context| gnb_contexts.push(gnb_context.clone()))
+			.await;
+		for gnb_context in gnb_contexts {
+			let sent =
+				encode_and_write_ngap_pdu(gnb_context.tnla_association.as_ref(), pdu.clone()).await;
+			if let Err(e) = sent {
+				warn!(
+					"Unable to send the Overload {:?} to gNB {:?}: {:?}",
+					transition, gnb_context.global_ran_node_id, e
+				);
+			}
+		}
+		self.events.emit(match transition {
+			OverloadTransition::Start => AmfEvent::OverloadStarted,
+			OverloadTransition::Stop => AmfEvent::OverloadStopped,
+		});
+		Some(transition)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::context::OverloadControl;
+
+	#[tokio::test]
+	async fn test_overload_toggles_with_hysteresis() {
+		// Overload past 4 pending, stopping back down at 2.
+		let ngap_context =
+			NgapContext::test_fixture().with_overload_control(OverloadControl::new(4, 50));
+		let mut subscriber = ngap_context.events.subscribe();
+
+		let mut tasks = (0..5)
+			.map(|_| ngap_context.counters.track_task())
+			.collect::<Vec<_>>();
+		assert_eq!(
+			ngap_context.check_overload().await,
+			Some(OverloadTransition::Start)
+		);
+		assert_eq!(subscriber.try_recv().unwrap(), AmfEvent::OverloadStarted);
+		assert!(ngap_context.stats().await.overloaded);
+
+		// Below the threshold, not yet recovered.
+		tasks.truncate(3);
+		assert_eq!(ngap_context.check_overload().await, None);
+		assert!(ngap_context.is_overloaded());
+
+		tasks.truncate(2);
+		assert_eq!(
+			ngap_context.check_overload().await,
+			Some(OverloadTransition::Stop)
+		);
+		assert_eq!(subscriber.try_recv().unwrap(), AmfEvent::OverloadStopped);
+		let stats = ngap_context.stats().await;
+		assert_eq!((stats.backlog, stats.overloaded), (2, false));
+
+		// Back at the threshold is not past it.
+		tasks.extend((0..2).map(|_| ngap_context.counters.track_task()));
+		assert_eq!(ngap_context.check_overload().await, None);
+	}
+}
@@ -0,0 +1,252 @@
+use std::str::FromStr;
+
+use ngap_models::{
+	AssistanceDataForPaging,
+	AssistanceDataForRecommendedCells,
+	FiveGSTmsi as NgapFiveGSTmsi,
+	FiveGTmsi as NgapFiveGTmsi,
+	NgranCgi,
+	Paging,
+	PagingPriority,
+	RecommendedCellItem,
+	RecommendedCellList,
+	RecommendedCellsForPaging,
+	ToNgapPdu,
+	UePagingIdentity,
+	UserLocationInformationNr,
+};
+use nonempty::NonEmpty;
+use oasbi::common::{AmfId as SbiAmfId, Tai, error::ConversionError};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+	context::{NgapContext, UeContext},
+	ngap::engine::controller::encode_and_write_ngap_pdu,
+	utils::{convert, models::FiveGGuti, try_convert},
+};
+
+impl UeContext {
+	/// The Paging of this UE across `tai_list`, recommending the cell it was
+	/// last seen in.
+	pub fn paging(
+		&self,
+		tai_list: &NonEmpty<Tai>,
+		paging_priority: Option<PagingPriority>,
+	) -> Result<Paging, PagingError> {
+		let guti = self.guti.as_ref().ok_or(PagingError::NoGuti)?;
+		build_paging(guti, tai_list, paging_priority, self.last_location.as_ref())
+	}
+}
+
+impl NgapContext {
+	/// Pages a CM-IDLE UE by its 5G-GUTI across `tai_list` at every connected
+	/// gNB. A gNB the Paging cannot be written to is skipped.
+	pub async fn send_paging(
+		&self,
+		guti: &FiveGGuti,
+		tai_list: &NonEmpty<Tai>,
+	) -> Result<(), PagingError> {
+		let paging = build_paging(guti, tai_list, None, None)?;
+		let mut gnb_contexts = Vec::with_capacity(self.gnb_contexts.len());
+		self.gnb_contexts
+			.scan_async(|_, gnb_context| gnb_contexts.push(gnb_context.clone()))
+			.await;
+		for gnb_context in gnb_contexts {
+			let pdu = paging.clone().to_pdu();
+			if let Err(e) = encode_and_write_ngap_pdu(&gnb_context.tnla_association, pdu).await {
+				warn!(
+					"Unable to send the Paging to {:?}: {:?}",
+					gnb_context.global_ran_node_id, e
+				);
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Builds the Paging of a UE, TS 38.413 8.5.1. The UE is paged by the
+/// 5G-S-TMSI of its 5G-GUTI, TS 23.003 2.11, and the gNBs are pointed at the
+/// cell of its last known location with the Assistance Data for Paging.
+pub fn build_paging(
+	guti: &FiveGGuti,
+	tai_list: &NonEmpty<Tai>,
+	paging_priority: Option<PagingPriority>,
+	last_location: Option<&UserLocationInformationNr>,
+) -> Result<Paging, PagingError> {
+	let assistance_data_for_paging = last_location.map(|location| AssistanceDataForPaging {
+		assistance_data_for_recommended_cells: Some(AssistanceDataForRecommendedCells {
+			recommended_cells_for_paging: RecommendedCellsForPaging {
+				recommended_cell_list: RecommendedCellList(NonEmpty::new(RecommendedCellItem {
+					ngran_cgi: NgranCgi::NrCgi(location.nr_cgi.clone()),
+					..Default::default()
+				})),
+				..Default::default()
+			},
+			..Default::default()
+		}),
+		..Default::default()
+	});
+	Ok(Paging {
+		ue_paging_identity: UePagingIdentity::FiveGSTmsi(paging_identity(guti)?),
+		tai_list_for_paging: try_convert(tai_list)?,
+		paging_priority,
+		assistance_data_for_paging,
+		..Default::default()
+	})
+}
+
+/// The 5G-S-TMSI of a 5G-GUTI, its AMF Set ID, AMF Pointer and 5G-TMSI.
+fn paging_identity(guti: &FiveGGuti) -> Result<NgapFiveGSTmsi, ConversionError> {
+	let amf_id = SbiAmfId::from_str(&guti.amf_id)
+		.map_err(|e| format!("Invalid AMF Identifier {}: {e:?}", guti.amf_id))?;
+	let (_, amf_set_id, amf_pointer) = convert(&amf_id);
+	Ok(NgapFiveGSTmsi {
+		amf_set_id,
+		amf_pointer,
+		five_g_tmsi: NgapFiveGTmsi(guti.tmsi.to_be_bytes()),
+	})
+}
+
+#[derive(Debug, Error)]
+pub enum PagingError {
+	#[error("NoGuti: The UE has no 5G-GUTI to be paged by")]
+	NoGuti,
+
+	#[error("ConversionError: {0}")]
+	ConversionError(#[from] ConversionError),
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bitvec::prelude::*;
+	use ngap_models::{
+		AmfUeNgapId,
+		GlobalRanNodeId,
+		InitiatingMessage,
+		NgapPdu,
+		NrCellIdentity,
+		NrCgi,
+		RanUeNgapId,
+		UserLocationInformation,
+	};
+	use nonempty::nonempty;
+
+	use super::*;
+	use crate::{
+		context::{GnbContext, UeIdentityIndex},
+		ngap::{
+			engine::decode_ngap_pdu,
+			network::{SctpEvent, TnlaAssociation},
+		},
+	};
+
+	fn nr_location(cell: u64) -> UserLocationInformation {
+		let mut cell_identity = bitvec![u8, Msb0; 0; 36];
+		cell_identity.store_be(cell);
+		UserLocationInformation::UserLocationInformationNr(UserLocationInformationNr {
+			nr_cgi: NrCgi {
+				nr_cell_identity: NrCellIdentity(cell_identity.into()),
+				..Default::default()
+			},
+			..Default::default()
+		})
+	}
+
+	fn recommended_cell(paging: Paging) -> u64 {
+		let recommended_cells = paging
+			.assistance_data_for_paging
+			.and_then(|assistance| assistance.assistance_data_for_recommended_cells)
+			.unwrap()
+			.recommended_cells_for_paging
+			.recommended_cell_list
+			.0;
+		assert_eq!(recommended_cells.len(), 1);
+		let NgranCgi::NrCgi(nr_cgi) = &recommended_cells.head.ngran_cgi else {
+			panic!(
+				"expected an NR CGI, got {:?}",
+				recommended_cells.head.ngran_cgi
+			);
+		};
+		nr_cgi.nr_cell_identity.0.load_be::<u64>()
+	}
+
+	#[tokio::test]
+	async fn test_paging_recommends_the_last_known_cell() {
+		let tai: Tai = serde_json::from_value(serde_json::json!({
+			"plmnId": { "mcc": "208", "mnc": "93" },
+			"tac": "000001",
+		}))
+		.unwrap();
+		let tai_list = nonempty![tai];
+		let (gnb_context, _gnb) = GnbContext::test_fixture(
+			Arc::new(UeIdentityIndex::default()),
+			GlobalRanNodeId::default(),
+		)
+		.await;
+		let mut ue_context = UeContext::test_fixture(RanUeNgapId(7), AmfUeNgapId(1), gnb_context);
+		assert!(matches!(
+			ue_context.paging(&tai_list, None),
+			Err(PagingError::NoGuti)
+		));
+
+		ue_context.set_guti("20893cafe0100000001".parse().unwrap());
+		let blanket = ue_context.paging(&tai_list, None).unwrap();
+		assert!(blanket.assistance_data_for_paging.is_none());
+
+		ue_context.update_location(nr_location(0x1234));
+		let paging = ue_context
+			.paging(&tai_list, Some(PagingPriority::default()))
+			.unwrap();
+		let UePagingIdentity::FiveGSTmsi(five_g_s_tmsi) = &paging.ue_paging_identity else {
+			panic!("expected a 5G-S-TMSI, got {:?}", paging.ue_paging_identity);
+		};
+		// AMF Id 0xcafe01: set 0x3f8, pointer 1.
+		assert_eq!(five_g_s_tmsi.amf_set_id.0.load_be::<u16>(), 0x3f8);
+		assert_eq!(five_g_s_tmsi.amf_pointer.0.load_be::<u8>(), 1);
+		assert_eq!(five_g_s_tmsi.five_g_tmsi.0, [0, 0, 0, 1]);
+		assert_eq!(paging.tai_list_for_paging.0.len(), 1);
+		assert!(paging.paging_priority.is_some());
+		assert_eq!(recommended_cell(paging), 0x1234);
+
+		// A later message moves the recommendation along with the UE.
+		ue_context.update_location(nr_location(0x5678));
+		let paging = ue_context.paging(&tai_list, None).unwrap();
+		assert_eq!(recommended_cell(paging), 0x5678);
+	}
+
+	#[tokio::test]
+	async fn test_idle_ue_is_paged_at_the_connected_gnbs() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let gnb = TnlaAssociation::new(gnb).unwrap();
+		ngap_context
+			.gnb_contexts
+			.insert_async(gnb_context.global_ran_node_id.clone(), gnb_context)
+			.await
+			.unwrap();
+
+		let guti = "20893cafe0100000001".parse().unwrap();
+		ngap_context
+			.send_paging(&guti, &nonempty![Tai::default()])
+			.await
+			.unwrap();
+		let Ok(Some(SctpEvent::Data(message))) = gnb.read_data().await else {
+			panic!("expected an NGAP message");
+		};
+		let Ok(NgapPdu::InitiatingMessage(InitiatingMessage::Paging(paging))) =
+			decode_ngap_pdu(&message)
+		else {
+			panic!("expected a Paging");
+		};
+		assert!(matches!(
+			paging.ue_paging_identity,
+			UePagingIdentity::FiveGSTmsi(_)
+		));
+		assert!(paging.assistance_data_for_paging.is_none());
+	}
+}
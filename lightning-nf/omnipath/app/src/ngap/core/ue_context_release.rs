@@ -0,0 +1,73 @@
+use std::{convert::Infallible, sync::Arc};
+
+use ngap_models::UeContextReleaseComplete;
+
+use crate::{
+	context::{GnbContext, NgapContext},
+	ngap::engine::{EmptyResponse, NgapResponseError},
+};
+
+impl NgapContext {
+	/// Takes the UE Context Release Complete of a gNB, TS 38.413 8.3.3. The
+	/// context is mostly gone already, released along the command, what is
+	/// left of the UE at this gNB is dropped. A UE since handed over to
+	/// another gNB stays there.
+	pub async fn handle_ue_context_release_complete(
+		&self,
+		gnb_context: Arc<GnbContext>,
+		complete: UeContextReleaseComplete,
+	) -> Result<EmptyResponse, NgapResponseError<EmptyResponse, Infallible>> {
+		let UeContextReleaseComplete {
+			amf_ue_ngap_id,
+			ran_ue_ngap_id,
+			..
+		} = complete;
+		let served_here = (gnb_context.global_ran_node_id.clone(), ran_ue_ngap_id);
+		if self.find_ue(&amf_ue_ngap_id).await.as_ref() == Some(&served_here) {
+			self.release_ue(&amf_ue_ngap_id).await;
+		} else {
+			gnb_context.release_ue_context(&ran_ue_ngap_id).await;
+		}
+		Ok(EmptyResponse::new())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ngap_models::{AmfUeNgapId, GlobalRanNodeId, RanUeNgapId};
+
+	use super::*;
+	use crate::context::UeContext;
+
+	#[tokio::test]
+	async fn test_release_complete_drops_the_ue() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, _gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let (ran_ue_ngap_id, amf_ue_ngap_id) = (RanUeNgapId(7), AmfUeNgapId(1));
+		let ue_context =
+			UeContext::test_fixture(ran_ue_ngap_id, amf_ue_ngap_id.clone(), gnb_context.clone());
+		ngap_context.add_test_ue(ue_context).await;
+
+		let complete = UeContextReleaseComplete {
+			amf_ue_ngap_id: amf_ue_ngap_id.clone(),
+			ran_ue_ngap_id,
+			..Default::default()
+		};
+		ngap_context
+			.handle_ue_context_release_complete(gnb_context.clone(), complete.clone())
+			.await
+			.unwrap();
+		assert_eq!(ngap_context.find_ue(&amf_ue_ngap_id).await, None);
+		assert!(gnb_context.ue_context_manager.is_empty());
+
+		// One for a context released along the command is taken all the same.
+		assert!(
+			ngap_context
+				.handle_ue_context_release_complete(gnb_context, complete)
+				.await
+				.is_ok()
+		);
+	}
+}
@@ -1,25 +1,21 @@
 use ngap_models::{AmfUeNgapId, Cause, CauseProtocol, ErrorIndication, ExtendedRanNodeName, RanNodeName, RanUeNgapId};
 
+/// The name a gNB gave itself, its extended name preferred as it may be longer
+/// and UTF-8 encoded. `None` when it gave neither.
 pub fn resolve_ran_name(
 	ran_node_name: Option<RanNodeName>,
 	extended_ran_node_name: Option<ExtendedRanNodeName>,
-) -> String {
-	match (ran_node_name, extended_ran_node_name) {
-		(Some(ran_node_name), None) => ran_node_name.0,
-		(Some(_), Some(extended_ran_node_name)) | (None, Some(extended_ran_node_name)) => {
-			let ExtendedRanNodeName {
-				ran_node_name_visible_string,
-				ran_node_name_utf8_string,
-			} = extended_ran_node_name;
-			match (ran_node_name_visible_string, ran_node_name_utf8_string) {
-				(Some(ran_node_name_visible_string), None) => ran_node_name_visible_string.0,
-				(Some(_), Some(ran_node_name_utf8_string))
-				| (None, Some(ran_node_name_utf8_string)) => ran_node_name_utf8_string.0,
-				(None, None) => String::new(),
-			}
-		}
-		(None, None) => String::new(),
-	}
+) -> Option<String> {
+	let extended_ran_node_name = extended_ran_node_name.and_then(|extended_ran_node_name| {
+		let ExtendedRanNodeName {
+			ran_node_name_visible_string,
+			ran_node_name_utf8_string,
+		} = extended_ran_node_name;
+		ran_node_name_utf8_string
+			.map(|name| name.0)
+			.or(ran_node_name_visible_string.map(|name| name.0))
+	});
+	extended_ran_node_name.or(ran_node_name.map(|name| name.0))
 }
 
 pub fn new_semantic_error(amf_ue_ngap_id: Option<AmfUeNgapId>, ran_ue_ngap_id: Option<RanUeNgapId>) -> ErrorIndication {
@@ -35,3 +31,59 @@ pub fn new_semantic_error(amf_ue_ngap_id: Option<AmfUeNgapId>, ran_ue_ngap_id: O
 pub fn new_semantic_error_cause() -> Cause {
 	Cause::Protocol(CauseProtocol::SemanticError)
 }
+
+#[cfg(test)]
+mod tests {
+	use ngap_models::{RanNodeNameUtf8String, RanNodeNameVisibleString};
+
+	use super::*;
+
+	fn basic(name: &str) -> Option<RanNodeName> {
+		Some(RanNodeName(name.to_owned()))
+	}
+
+	fn extended(
+		visible: Option<&str>,
+		utf8: Option<&str>,
+	) -> Option<ExtendedRanNodeName> {
+		Some(ExtendedRanNodeName {
+			ran_node_name_visible_string: visible
+				.map(|name| RanNodeNameVisibleString(name.to_owned())),
+			ran_node_name_utf8_string: utf8.map(|name| RanNodeNameUtf8String(name.to_owned())),
+		})
+	}
+
+	#[test]
+	fn test_only_basic_name() {
+		assert_eq!(
+			resolve_ran_name(basic("gnb-1"), None).as_deref(),
+			Some("gnb-1")
+		);
+	}
+
+	#[test]
+	fn test_only_extended_name() {
+		let name = resolve_ran_name(None, extended(None, Some("gNB Zürich Hauptbahnhof")));
+		assert_eq!(name.as_deref(), Some("gNB Zürich Hauptbahnhof"));
+		let name = resolve_ran_name(None, extended(Some("gnb-visible"), None));
+		assert_eq!(name.as_deref(), Some("gnb-visible"));
+	}
+
+	#[test]
+	fn test_extended_name_wins() {
+		let name = resolve_ran_name(
+			basic("gnb-1"),
+			extended(Some("gnb-visible"), Some("gNB ünë")),
+		);
+		assert_eq!(name.as_deref(), Some("gNB ünë"));
+		// An extended name carrying neither string leaves the basic one.
+		let name = resolve_ran_name(basic("gnb-1"), extended(None, None));
+		assert_eq!(name.as_deref(), Some("gnb-1"));
+	}
+
+	#[test]
+	fn test_no_name() {
+		assert_eq!(resolve_ran_name(None, None), None);
+		assert_eq!(resolve_ran_name(None, extended(None, None)), None);
+	}
+}
@@ -1,23 +1,36 @@
 use std::sync::Arc;
 
 use asn1_per::{PerCodecError, ThreeGppAsn1PerError};
+use backoff::Backoff;
+use bytes::Bytes;
 use ngap_models::{Cause, CauseProtocol, ErrorIndication, InitiatingMessage, NgapPdu, ToNgapPdu};
 use thiserror::Error;
+use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
-use tracing::{Instrument, error, info};
+use tracing::{Instrument, debug, error, info, warn};
 use valuable::Valuable;
 
 use super::{
+	EarlyMessages,
+	criticality::{log_error_indication, message_diagnostics},
 	decode_ngap_pdu,
+	drain_join_set,
 	interfaces::{NgapRequestHandler, NgapResponseError},
 	utils::codec_to_bytes,
 };
 use crate::{
-	context::{GnbContext, NgapContext},
+	context::{AmfEvent, GnbContext, NgapContext},
 	ngap::{
-		constants::app::INITIALIZATION_RETRIES,
+		constants::app::{
+			INITIALIZATION_RETRIES,
+			MAX_EARLY_NGAP_MESSAGES,
+			NG_SETUP_REQUEST_TIMEOUT,
+			NG_SETUP_RETRY_BASE_DELAY,
+			NG_SETUP_RETRY_MAX_DELAY,
+			NGAP_TASK_SHUTDOWN_GRACE,
+		},
 		core::ng_setup::NgSetupError,
-		network::{NetworkError, TnlaAssociation},
+		network::{NetworkError, SctpEvent, TnlaAssociation, TnlaError},
 	},
 };
 
@@ -61,7 +74,8 @@ impl NgapContext {
 	/// This function performs the following steps:
 	/// 1. Attempts to establish an NG setup connection with retries
 	/// 2. Creates and stores a new gNB context if setup is successful
-	/// 3. Spawns a new task to handle ongoing NGAP message processing
+	/// 3. Spawns a new task to handle ongoing NGAP message processing, starting
+	///    with the messages received ahead of the NG Setup
 	///
 	/// # Arguments
 	/// * `self` - Arc reference to NgapContext
@@ -71,18 +85,30 @@ impl NgapContext {
 		tnla: Arc<TnlaAssociation>,
 	) {
 		let sctp_loop_cancellation = CancellationToken::new();
+		let peer = tnla.remote_addr.ip();
+		let mut early_messages = EarlyMessages::new(MAX_EARLY_NGAP_MESSAGES);
 
 		// Try to establish NG setup request
 		let gnb_context = match self
-			.try_ng_setup_with_retries(tnla, sctp_loop_cancellation.clone())
+			.try_ng_setup_with_retries(tnla, sctp_loop_cancellation.clone(), &mut early_messages)
 			.await
 		{
-			Some(context) => Arc::new(context),
-			None => {
+			Ok(context) => {
+				self.setup_failures.record_success();
+				Arc::new(context)
+			}
+			Err(e) => {
 				error!(
 					"Failed to establish NG setup after {} retries",
 					INITIALIZATION_RETRIES
 				);
+				self.setup_failures.record_failure(peer, e);
+				if !early_messages.is_empty() {
+					warn!(
+						diagnostic = "Dropped the messages held for the NG Setup",
+						dropped = early_messages.len()
+					);
+				}
 				return;
 			}
 		};
@@ -95,6 +121,9 @@ impl NgapContext {
 			.insert_async(gnb_context.global_ran_node_id.clone(), gnb_context.clone())
 			.await
 			.unwrap();
+		self.events.emit(AmfEvent::GnbConnected(
+			gnb_context.global_ran_node_id.clone(),
+		));
 
 		info!(
 			global_ran_node_id = gnb_context.global_ran_node_id.as_value(),
@@ -103,11 +132,16 @@ impl NgapContext {
 		// Spawn NGAP processing loop
 		let gnb_context_clone = gnb_context.clone();
 		let self_clone = self.clone();
-		tokio::spawn(
+		let mut gnb_tasks = self.gnb_tasks.lock().await;
+		// Reap the loops of gone gNBs so the set only holds connected ones.
+		while gnb_tasks.try_join_next().is_some() {}
+		gnb_tasks.spawn(
 			async move {
-				let res = self_clone.run_ngap_loop(gnb_context.clone()).await;
+				let res = self_clone
+					.run_ngap_loop(gnb_context.clone(), early_messages)
+					.await;
 				let _ = res.map_err(|e| error!(diagnostic = "Error running NGAP loop", error = ?e));
-				// TODO: Implement cleanup logic for gNB context and UE contexts
+				self_clone.remove_gnb(&gnb_context).await;
 			}
 			.instrument(tracing::trace_span!(
 				"ngap_request",
@@ -121,44 +155,63 @@ impl NgapContext {
 	/// # Arguments
 	/// * `tnla` - Arc reference to the TNLA connection
 	/// * `cancellation` - Token for cancelling the setup process
+	/// * `early_messages` - Holds the messages received ahead of the NG Setup
 	///
 	/// # Returns
-	/// * `Option<GnbContext>` - Some(context) if setup succeeds, None if all
-	///   retries fail
+	/// * `Result<GnbContext, NgapSetupError>` - Ok(context) if setup succeeds,
+	///   the error of the last attempt if all retries fail
 	///
 	/// Retries the NG setup process up to INITIALIZATION_RETRIES times before
-	/// giving up.
+	/// giving up, backing off from [`NG_SETUP_RETRY_BASE_DELAY`] to
+	/// [`NG_SETUP_RETRY_MAX_DELAY`] in between.
 	async fn try_ng_setup_with_retries(
 		&self,
 		tnla: Arc<TnlaAssociation>,
 		cancellation: CancellationToken,
-	) -> Option<GnbContext> {
-		for attempt in 1..=INITIALIZATION_RETRIES {
-			match self.try_ng_setup(tnla.clone(), cancellation.clone()).await {
-				Ok(context) => return Some(context),
+		early_messages: &mut EarlyMessages,
+	) -> Result<GnbContext, NgapSetupError> {
+		let mut delays = Backoff::new(NG_SETUP_RETRY_BASE_DELAY, NG_SETUP_RETRY_MAX_DELAY)
+			.jitter(0.5)
+			.take(INITIALIZATION_RETRIES - 1);
+		let mut attempt = 1;
+		loop {
+			let setup = self.try_ng_setup(tnla.clone(), cancellation.clone(), early_messages);
+			match setup.await {
+				Ok(context) => return Ok(context),
 				Err(e) => {
 					error!(
 						diagnostic = "NG Setup attempt failed",
 						attempt = attempt,
 						error = ?e
 					);
+					let Some(delay) = delays.next() else {
+						return Err(e);
+					};
+					tokio::select! {
+						_ = cancellation.cancelled() => return Err(e),
+						_ = tokio::time::sleep(delay) => {}
+					}
 				}
 			}
+			attempt += 1;
 		}
-		None
 	}
 
 	/// Performs a single attempt at establishing an NG setup connection.
 	///
 	/// This function:
 	/// 1. Creates a new GnbContext
-	/// 2. Reads and decodes the initial NGAP PDU
-	/// 3. Handles the NG setup request if received
+	/// 2. Reads the initial NGAP PDU within [`NG_SETUP_REQUEST_TIMEOUT`],
+	///    holding the messages of other procedures in `early_messages` while
+	///    there is room
+	/// 3. Decodes and handles the NG setup request if received, taking a slot
+	///    of the `ng_setup_limiter` meanwhile
 	/// 4. Sends appropriate response back to the gNB
 	///
 	/// # Arguments
 	/// * `tnla` - Arc reference to the TNLA connection
 	/// * `cancellation` - Token for cancelling the setup process
+	/// * `early_messages` - Holds the messages received ahead of the NG Setup
 	///
 	/// # Returns
 	/// * `Result<GnbContext, NgapSetupError>` - Success with context or
@@ -167,6 +220,7 @@ impl NgapContext {
 	/// # Errors
 	/// Returns NgapSetupError for various failure scenarios:
 	/// - Network communication errors
+	/// - No NG Setup Request in time
 	/// - PDU encoding/decoding errors
 	/// - Invalid or unexpected messages
 	/// - NG setup protocol errors
@@ -174,51 +228,68 @@ impl NgapContext {
 		&self,
 		tnla: Arc<TnlaAssociation>,
 		cancellation: CancellationToken,
+		early_messages: &mut EarlyMessages,
 	) -> Result<GnbContext, NgapSetupError> {
-		let mut gnb_context = GnbContext::new(tnla.clone(), cancellation);
-		let request = gnb_context
-			.tnla_association
-			.read_data()
-			.await
-			.map_err(|e| NetworkError::TnlaReadError(tnla.id, e))?;
-		let request = match request {
-			Some(request) => request,
-			None => return Err(NgapSetupError::SocketClosed),
-		};
-		let request = decode_ngap_pdu(&request);
-		let result;
-		let response = match request {
-			Ok(NgapPdu::InitiatingMessage(InitiatingMessage::NgSetupRequest(ng_setup_request))) => {
-				let ngap_resp = self
-					.handle_request(&mut gnb_context, ng_setup_request)
-					.await;
-				match ngap_resp {
-					Ok(success) => {
-						result = Ok(gnb_context);
-						success.to_pdu()
-					}
-					Err(error) => {
-						let NgapResponseError { failure, error } = error;
-						result = Err(NgapSetupError::NgSetupError(error));
-						failure.to_pdu()
+		let mut gnb_context =
+			GnbContext::new(tnla.clone(), self.ue_identities.clone(), cancellation);
+		let read_request = async {
+			loop {
+				let event = gnb_context.tnla_association.read_data().await;
+				match next_step(event) {
+					LoopStep::Handle(request) => match early_messages.hold(request) {
+						Some(request) => return Ok(request),
+						None => {
+							info!(diagnostic = "Holding a message until the NG Setup completes")
+						}
+					},
+					LoopStep::Skip => continue,
+					LoopStep::Close(Some(e)) => {
+						return Err(NetworkError::TnlaReadError(tnla.id, e).into());
 					}
+					LoopStep::Close(None) => return Err(NgapSetupError::SocketClosed),
 				}
 			}
-			Err((e, err)) => {
-				result = Err(NgapSetupError::PerCodecEncodingError(err));
-				e
-			}
-			Ok(request) => {
-				result = Err(NgapSetupError::DidNotReceiveNgSetup(request));
-				ErrorIndication {
-					cause: Some(Cause::Protocol(
-						CauseProtocol::MessageNotCompatibleWithReceiverState,
-					)),
-					..Default::default()
+		};
+		let request = tokio::time::timeout(NG_SETUP_REQUEST_TIMEOUT, read_request)
+			.await
+			.map_err(|_| NgapSetupError::NgSetupTimeout)??;
+		self.counters.record_message(&request);
+		// Only decoding and handling count against the limit, a gNB slow to send
+		// its NG Setup Request does not hold up the others.
+		let setup = async move {
+			match decode_ngap_pdu(&request) {
+				Ok(NgapPdu::InitiatingMessage(InitiatingMessage::NgSetupRequest(
+					ng_setup_request,
+				))) => {
+					let ngap_resp = self
+						.handle_request(&mut gnb_context, ng_setup_request)
+						.await;
+					match ngap_resp {
+						Ok(success) => (Ok(gnb_context), success.to_pdu()),
+						Err(error) => {
+							let NgapResponseError { failure, error } = error;
+							(Err(NgapSetupError::NgSetupError(error)), failure.to_pdu())
+						}
+					}
+				}
+				Err((e, err)) => (Err(NgapSetupError::PerCodecEncodingError(err)), e),
+				Ok(pdu) => {
+					let error = ErrorIndication {
+						cause: Some(Cause::Protocol(
+							CauseProtocol::MessageNotCompatibleWithReceiverState,
+						)),
+						criticality_diagnostics: Some(message_diagnostics(&request, None)),
+						..Default::default()
+					};
+					log_error_indication(&error);
+					(
+						Err(NgapSetupError::DidNotReceiveNgSetup(pdu)),
+						error.to_pdu(),
+					)
 				}
-				.to_pdu()
 			}
 		};
+		let (result, response) = self.ng_setup_limiter.run(setup).await;
 		encode_and_write_ngap_pdu(tnla.as_ref(), response).await?;
 		result
 	}
@@ -240,6 +311,8 @@ impl NgapContext {
 	/// * `self` - Arc reference to NgapContext for shared access
 	/// * `gnb_context` - Arc reference to the GnbContext containing the gNB
 	///   connection state
+	/// * `early_messages` - Messages received ahead of the NG Setup, handled
+	///   first and one after the other, in the order received
 	///
 	/// # Returns
 	/// * `Result<(), NetworkError>` - Ok(()) if loop terminates normally, or
@@ -251,45 +324,122 @@ impl NgapContext {
 	pub async fn run_ngap_loop(
 		self: Arc<Self>,
 		gnb_context: Arc<GnbContext>,
+		early_messages: EarlyMessages,
 	) -> Result<(), NetworkError> {
-		while let Ok(Some(message)) = gnb_context.tnla_association.read_data().await {
+		for message in early_messages {
+			self.route_and_respond(&gnb_context, &message).await;
+		}
+		let mut tasks = JoinSet::new();
+		loop {
+			// A message being read when the loop is cancelled is dropped, the
+			// association is going away with it.
+			let event = tokio::select! {
+				biased;
+				_ = gnb_context.sctp_loop_cancellation.cancelled() => break,
+				event = gnb_context.tnla_association.read_data() => event,
+			};
+			let message = match next_step(event) {
+				LoopStep::Handle(message) => message,
+				LoopStep::Skip => continue,
+				LoopStep::Close(_) => break,
+			};
+			// Reap finished tasks so the set only holds in-flight ones.
+			while tasks.try_join_next().is_some() {}
+
 			let gnb_context_clone = gnb_context.clone();
 			let self_clone = self.clone();
-			tokio::spawn(async move {
-				let pdu = decode_ngap_pdu(&message);
-				let response = match pdu {
-					Ok(pdu) => self_clone.ngap_route(gnb_context_clone.clone(), pdu).await,
-					Err((pdu, error)) => {
-						error!(diagnostic = "Error decoding NGAP PDU", error = ?error);
-						Some(pdu)
-					}
-				};
-				if let Some(response) = response {
-					let resp = encode_and_write_ngap_pdu(
-						&gnb_context_clone.as_ref().tnla_association,
-						response,
-					)
-					.await;
-					match resp {
-						Ok(_) => (),
-						Err(e) => {
-							// TODO: Add valuable trait implementation for having structured records
-							// of struct for tracing. https://docs.rs/tracing/latest/tracing/field/index.html#using-valuable
-							error!(diagnostic = "Ngap write error", error = ?e)
-						}
-					}
-				}
+			tasks.spawn(async move {
+				let _task = self_clone.counters.track_task();
+				self_clone
+					.route_and_respond(&gnb_context_clone, &message)
+					.await
 			});
 		}
+		let (_, aborted) = drain_join_set(tasks, NGAP_TASK_SHUTDOWN_GRACE).await;
+		if aborted > 0 {
+			warn!(
+				diagnostic = "Aborted in-flight NGAP tasks",
+				aborted = aborted
+			);
+		}
 		Ok(())
 	}
 
-	// TODO: Implement graceful shutdown for the network
+	/// Routes a message of the gNB and writes the response back, if any.
+	async fn route_and_respond(
+		&self,
+		gnb_context: &Arc<GnbContext>,
+		message: &[u8],
+	) {
+		let response = self.ngap_route(gnb_context.clone(), message).await;
+		if let Some(response) = response {
+			// Error Indications only inform, a slow gNB is spared them.
+			let stats = &gnb_context.tnla_association.stats;
+			if stats.is_slow()
+				&& matches!(
+					response,
+					NgapPdu::InitiatingMessage(InitiatingMessage::ErrorIndication(_))
+				) {
+				stats.record_shed();
+				debug!(
+					global_ran_node_id = gnb_context.global_ran_node_id.as_value(),
+					diagnostic = "Shed an Error Indication to a slow gNB"
+				);
+				return;
+			}
+			let resp =
+				encode_and_write_ngap_pdu(&gnb_context.as_ref().tnla_association, response).await;
+			match resp {
+				Ok(_) => (),
+				Err(e) => {
+					// TODO: Add valuable trait implementation for having structured records
+					// of struct for tracing. https://docs.rs/tracing/latest/tracing/field/index.html#using-valuable
+					error!(diagnostic = "Ngap write error", error = ?e)
+				}
+			}
+		}
+	}
+
+	/// Stops the NGAP loop of every gNB and waits for them to wind down. Each
+	/// loop gives its in-flight message tasks `NGAP_TASK_SHUTDOWN_GRACE` to
+	/// finish before aborting them.
 	pub async fn graceful_shutdown(&self) -> Result<(), NetworkError> {
+		self.gnb_contexts
+			.scan_async(|_, gnb_context| gnb_context.sctp_loop_cancellation.cancel())
+			.await;
+		let gnb_tasks = std::mem::take(&mut *self.gnb_tasks.lock().await);
+		drain_join_set(gnb_tasks, 2 * NGAP_TASK_SHUTDOWN_GRACE).await;
 		Ok(())
 	}
 }
 
+/// What an NGAP loop does with the outcome of a read of its association.
+#[derive(Debug)]
+enum LoopStep {
+	Handle(Bytes),
+	/// A transport event that leaves the association usable.
+	Skip,
+	/// The association is gone, from a read error when there is one. The gNB
+	/// has to be cleaned up.
+	Close(Option<TnlaError>),
+}
+
+fn next_step(event: Result<Option<SctpEvent>, TnlaError>) -> LoopStep {
+	match event {
+		Ok(Some(SctpEvent::Data(message))) => LoopStep::Handle(message),
+		Ok(Some(SctpEvent::Notification(notification))) if notification.ends_association() => {
+			info!(diagnostic = "SCTP association ended", notification = ?notification);
+			LoopStep::Close(None)
+		}
+		Ok(Some(SctpEvent::Notification(notification))) => {
+			info!(diagnostic = "SCTP notification", notification = ?notification);
+			LoopStep::Skip
+		}
+		Ok(None) => LoopStep::Close(None),
+		Err(e) => LoopStep::Close(Some(e)),
+	}
+}
+
 /// Encodes and writes an NGAP PDU to the specified TNLA connection.
 ///
 /// # Arguments
@@ -346,4 +496,269 @@ pub enum NgapSetupError {
 
 	#[error("SocketClosed")]
 	SocketClosed,
+
+	#[error("NgSetupTimeout")]
+	NgSetupTimeout,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use ngap_models::{
+		BroadcastPlmnItem,
+		BroadcastPlmnList,
+		CriticalityDiagnostics,
+		GlobalRanNodeId,
+		NgSetupRequest,
+		ProcedureCode,
+		SuccessfulOutcome,
+		SupportedTaItem,
+		SupportedTaList,
+	};
+	use nonempty::NonEmpty;
+	use oasbi::common::{Snssai, Tai};
+	use tokio::sync::oneshot;
+
+	use super::*;
+	use crate::{
+		ngap::{
+			constants::app::{
+				SLOW_PEER_BLOCKED_WRITES,
+				SLOW_PEER_RECOVERY_WRITES,
+				SLOW_PEER_WRITE_THRESHOLD,
+			},
+			network::{AssocChangeState, PeerAddrState, SctpNotification},
+		},
+		utils::{convert, try_convert},
+	};
+
+	#[test]
+	fn test_shutdown_notification_closes_loop() {
+		let event = SctpEvent::Notification(SctpNotification::Shutdown);
+		assert!(matches!(next_step(Ok(Some(event))), LoopStep::Close(None)));
+
+		let event =
+			SctpEvent::Notification(SctpNotification::AssocChange(AssocChangeState::CommLost));
+		assert!(matches!(next_step(Ok(Some(event))), LoopStep::Close(None)));
+	}
+
+	#[test]
+	fn test_transient_notification_keeps_loop() {
+		let event = SctpEvent::Notification(SctpNotification::PeerAddrChange {
+			addr: None,
+			state: PeerAddrState::Unreachable,
+		});
+		assert!(matches!(next_step(Ok(Some(event))), LoopStep::Skip));
+
+		let event = SctpEvent::Data(Bytes::from_static(&[0x00, 0x15]));
+		assert!(matches!(next_step(Ok(Some(event))), LoopStep::Handle(_)));
+		assert!(matches!(next_step(Ok(None)), LoopStep::Close(None)));
+	}
+
+	#[tokio::test]
+	async fn test_ng_setups_past_the_limit_wait() {
+		let ngap_context = Arc::new(NgapContext::test_fixture());
+		let association = async || {
+			let (gnb_context, gnb) = ngap_context
+				.gnb_test_fixture(GlobalRanNodeId::default())
+				.await;
+			(
+				gnb_context.tnla_association.clone(),
+				TnlaAssociation::new(gnb).unwrap(),
+			)
+		};
+		let setup = async |tnla| {
+			let mut early_messages = EarlyMessages::new(MAX_EARLY_NGAP_MESSAGES);
+			ngap_context
+				.try_ng_setup(tnla, CancellationToken::new(), &mut early_messages)
+				.await
+		};
+		// The header of an NG Setup Request too short to decode.
+		let ng_setup_request = Bytes::from_static(&[0x00, 0x15]);
+
+		// A gNB yet to send its NG Setup Request takes no slot.
+		let (silent, _silent_gnb) = association().await;
+		let silent_setup = tokio::spawn({
+			let ngap_context = ngap_context.clone();
+			async move {
+				let mut early_messages = EarlyMessages::new(MAX_EARLY_NGAP_MESSAGES);
+				ngap_context
+					.try_ng_setup(silent, CancellationToken::new(), &mut early_messages)
+					.await
+			}
+		});
+		let (tnla, gnb) = association().await;
+		gnb.write_data(ng_setup_request.clone(), None)
+			.await
+			.unwrap();
+		let handled = tokio::time::timeout(Duration::from_secs(1), setup(tnla.clone())).await;
+		assert!(matches!(
+			handled,
+			Ok(Err(NgapSetupError::PerCodecEncodingError(_)))
+		));
+
+		// With the only slot taken the setup waits for it.
+		let (release, released) = oneshot::channel::<()>();
+		let holder = tokio::spawn({
+			let ngap_context = ngap_context.clone();
+			async move { ngap_context.ng_setup_limiter.run(released).await }
+		});
+		while ngap_context.ng_setup_limiter.available() > 0 {
+			tokio::task::yield_now().await;
+		}
+		gnb.write_data(ng_setup_request, None).await.unwrap();
+		let waiting = setup(tnla);
+		tokio::pin!(waiting);
+		assert!(
+			tokio::time::timeout(Duration::from_millis(100), &mut waiting)
+				.await
+				.is_err()
+		);
+		release.send(()).unwrap();
+		assert!(matches!(
+			waiting.await,
+			Err(NgapSetupError::PerCodecEncodingError(_))
+		));
+		holder.await.unwrap().unwrap();
+		silent_setup.abort();
+	}
+
+	/// An NG Setup Request advertising the TAI and slice of the default config.
+	fn ng_setup_request() -> NgSetupRequest {
+		let tai = Tai::default();
+		NgSetupRequest {
+			global_ran_node_id: GlobalRanNodeId::default(),
+			supported_ta_list: SupportedTaList(NonEmpty::new(SupportedTaItem {
+				tac: convert(&tai.tac),
+				broadcast_plmn_list: BroadcastPlmnList(NonEmpty::new(BroadcastPlmnItem {
+					plmn_identity: try_convert(&tai.plmn_id).unwrap(),
+					tai_slice_support_list: convert(&NonEmpty::new(Snssai::default())),
+					..Default::default()
+				})),
+				..Default::default()
+			})),
+			..Default::default()
+		}
+	}
+
+	async fn read_pdu(gnb: &TnlaAssociation) -> NgapPdu {
+		let Ok(Some(SctpEvent::Data(message))) = gnb.read_data().await else {
+			panic!("expected an NGAP message");
+		};
+		decode_ngap_pdu(&message).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_ng_setup_announces_the_connected_gnb() {
+		let ngap_context = Arc::new(NgapContext::test_fixture());
+		let mut subscriber = ngap_context.events.subscribe();
+		let (gnb_context, gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let gnb = TnlaAssociation::new(gnb).unwrap();
+		let processing = tokio::spawn(
+			ngap_context
+				.clone()
+				.start_ngap_processing(gnb_context.tnla_association.clone()),
+		);
+
+		encode_and_write_ngap_pdu(&gnb, ng_setup_request().to_pdu())
+			.await
+			.unwrap();
+		assert!(matches!(
+			read_pdu(&gnb).await,
+			NgapPdu::SuccessfulOutcome(SuccessfulOutcome::NgSetupResponse(_))
+		));
+		processing.await.unwrap();
+		assert_eq!(
+			subscriber.recv().await.unwrap(),
+			AmfEvent::GnbConnected(GlobalRanNodeId::default())
+		);
+		assert_eq!(ngap_context.list_gnbs().await.len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_messages_ahead_of_the_ng_setup_are_replayed_in_order() {
+		let ngap_context = Arc::new(NgapContext::test_fixture());
+		let (gnb_context, gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let gnb = TnlaAssociation::new(gnb).unwrap();
+		let processing = tokio::spawn(
+			ngap_context
+				.clone()
+				.start_ngap_processing(gnb_context.tnla_association.clone()),
+		);
+
+		// The headers of an Initial UE Message and a Paging with an empty IE
+		// container, procedure codes 15 and 24, ahead of the NG Setup Request.
+		for early in [
+			&[0x00, 0x0f, 0x40, 0x03, 0x00, 0x00, 0x00],
+			&[0x00, 0x18, 0x40, 0x03, 0x00, 0x00, 0x00],
+		] {
+			gnb.write_data(Bytes::from_static(early), None)
+				.await
+				.unwrap();
+		}
+		encode_and_write_ngap_pdu(&gnb, ng_setup_request().to_pdu())
+			.await
+			.unwrap();
+		assert!(matches!(
+			read_pdu(&gnb).await,
+			NgapPdu::SuccessfulOutcome(SuccessfulOutcome::NgSetupResponse(_))
+		));
+		processing.await.unwrap();
+
+		// Each held message is answered once the setup is done, in the order
+		// received.
+		for procedure_code in [15, 24] {
+			let NgapPdu::InitiatingMessage(InitiatingMessage::ErrorIndication(error)) =
+				read_pdu(&gnb).await
+			else {
+				panic!("expected an Error Indication");
+			};
+			assert!(matches!(
+				error.criticality_diagnostics,
+				Some(CriticalityDiagnostics {
+					procedure_code: Some(ProcedureCode(code)),
+					..
+				}) if code == procedure_code
+			));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_slow_gnb_is_spared_error_indications() {
+		let ngap_context = NgapContext::test_fixture();
+		let (gnb_context, gnb) = ngap_context
+			.gnb_test_fixture(GlobalRanNodeId::default())
+			.await;
+		let gnb = TnlaAssociation::new(gnb).unwrap();
+		let stats = &gnb_context.tnla_association.stats;
+		for _ in 0..SLOW_PEER_BLOCKED_WRITES {
+			stats.record_write(0, SLOW_PEER_WRITE_THRESHOLD);
+		}
+		// The header of an Initial UE Message with an empty IE container,
+		// answered with an Error Indication.
+		let message = [0x00, 0x0f, 0x40, 0x03, 0x00, 0x00, 0x00];
+
+		ngap_context.route_and_respond(&gnb_context, &message).await;
+		assert!(
+			tokio::time::timeout(Duration::from_millis(100), gnb.read_data())
+				.await
+				.is_err()
+		);
+		assert_eq!(stats.snapshot().shed_messages, 1);
+
+		for _ in 0..SLOW_PEER_RECOVERY_WRITES {
+			stats.record_write(0, Duration::ZERO);
+		}
+		ngap_context.route_and_respond(&gnb_context, &message).await;
+		assert!(matches!(
+			read_pdu(&gnb).await,
+			NgapPdu::InitiatingMessage(InitiatingMessage::ErrorIndication(_))
+		));
+		assert_eq!(stats.snapshot().shed_messages, 1);
+	}
 }
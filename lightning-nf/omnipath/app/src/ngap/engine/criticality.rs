@@ -0,0 +1,371 @@
+use std::fmt;
+
+use ngap_models::{
+	Criticality,
+	CriticalityDiagnostics,
+	CriticalityDiagnosticsIeItem,
+	CriticalityDiagnosticsIeList,
+	ErrorIndication,
+	NgapPdu,
+	TypeOfError,
+};
+use tracing::warn;
+use valuable::Valuable;
+
+use crate::ngap::procedure_code_enum::ProcedureCodeEnum;
+
+/// What to do with a message whose IEs were not comprehended, TS 38.413
+/// 10.3.4.
+#[derive(Debug)]
+pub enum IeErrorAction {
+	/// Every offending IE may be ignored, the message is decoded again from
+	/// these bytes, stripped of them.
+	Proceed(Vec<u8>),
+	/// The message has to be rejected, reporting these IEs.
+	Reject(Option<CriticalityDiagnosticsIeList>),
+}
+
+/// Decides on the IE errors reported by the decoder. Not understood IEs marked
+/// `ignore` or `notify` are dropped, a `reject` marked or missing IE rejects
+/// the message and only the `reject` marked ones are reported.
+pub fn resolve_ie_errors(
+	request: &[u8],
+	ie_diagnostics: Option<CriticalityDiagnosticsIeList>,
+) -> IeErrorAction {
+	let Some(CriticalityDiagnosticsIeList(items)) = ie_diagnostics else {
+		return IeErrorAction::Reject(None);
+	};
+	let ignorable = |item: &CriticalityDiagnosticsIeItem| {
+		matches!(item.type_of_error, TypeOfError::NotUnderstood)
+			&& !matches!(item.i_e_criticality, Criticality::Reject)
+	};
+	if items.is_empty() || !items.iter().all(ignorable) {
+		let rejected = items
+			.into_iter()
+			.filter(|item| matches!(item.i_e_criticality, Criticality::Reject))
+			.collect::<Vec<_>>();
+		return IeErrorAction::Reject(
+			(!rejected.is_empty()).then(|| CriticalityDiagnosticsIeList(rejected)),
+		);
+	}
+
+	let Some(mut container) = ProtocolIeContainer::parse(request) else {
+		return IeErrorAction::Reject(Some(CriticalityDiagnosticsIeList(items)));
+	};
+	let ie_count = container.ies.len();
+	container
+		.ies
+		.retain(|ie| !items.iter().any(|item| item.i_e_id.0 == ie.id));
+	// Nothing stripped means the decoder blamed an IE that is not there.
+	if container.ies.len() == ie_count {
+		return IeErrorAction::Reject(Some(CriticalityDiagnosticsIeList(items)));
+	}
+	for item in items
+		.iter()
+		.filter(|item| matches!(item.i_e_criticality, Criticality::Notify))
+	{
+		warn!("Ignoring not understood IE {} marked notify", item.i_e_id.0);
+	}
+	match container.to_bytes() {
+		Some(stripped) => IeErrorAction::Proceed(stripped),
+		None => IeErrorAction::Reject(Some(CriticalityDiagnosticsIeList(items))),
+	}
+}
+
+/// The criticality diagnostics of a message, its procedure, triggering
+/// message and criticality read from its header, TS 38.413 9.3.1.3.
+pub fn message_diagnostics(
+	message: &[u8],
+	i_es_criticality_diagnostics: Option<CriticalityDiagnosticsIeList>,
+) -> CriticalityDiagnostics {
+	let (triggering_message, procedure_code, procedure_criticality) =
+		NgapPdu::get_message_info(message);
+	CriticalityDiagnostics {
+		procedure_code,
+		triggering_message,
+		procedure_criticality,
+		i_es_criticality_diagnostics,
+	}
+}
+
+/// [`CriticalityDiagnostics`] in a readable form, for logging as a structured
+/// field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Valuable)]
+pub struct DiagnosticsRecord {
+	/// The procedure name, or its code when not a known procedure.
+	pub procedure: Option<String>,
+	pub triggering_message: Option<String>,
+	pub procedure_criticality: Option<String>,
+	pub ies: Vec<IeDiagnosticRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Valuable)]
+pub struct IeDiagnosticRecord {
+	pub id: u16,
+	pub criticality: String,
+	pub type_of_error: String,
+}
+
+impl From<&CriticalityDiagnostics> for DiagnosticsRecord {
+	fn from(diagnostics: &CriticalityDiagnostics) -> Self {
+		let procedure = diagnostics.procedure_code.as_ref().map(|code| {
+			match ProcedureCodeEnum::try_from(code.0) {
+				Ok(procedure) => format!("{procedure:?}"),
+				Err(_) => code.0.to_string(),
+			}
+		});
+		let ies = diagnostics
+			.i_es_criticality_diagnostics
+			.iter()
+			.flat_map(|CriticalityDiagnosticsIeList(items)| items)
+			.map(|item| IeDiagnosticRecord {
+				id: item.i_e_id.0,
+				criticality: format!("{:?}", item.i_e_criticality),
+				type_of_error: format!("{:?}", item.type_of_error),
+			})
+			.collect();
+		Self {
+			procedure,
+			triggering_message: diagnostics
+				.triggering_message
+				.as_ref()
+				.map(|message| format!("{message:?}")),
+			procedure_criticality: diagnostics
+				.procedure_criticality
+				.as_ref()
+				.map(|criticality| format!("{criticality:?}")),
+			ies,
+		}
+	}
+}
+
+impl fmt::Display for DiagnosticsRecord {
+	/// As in `InitialUEMessage InitiatingMessage Reject, IE 26 Reject
+	/// Missing`, the parts not known left out.
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		let header = [
+			&self.procedure,
+			&self.triggering_message,
+			&self.procedure_criticality,
+		];
+		let header = header.into_iter().flatten().cloned().collect::<Vec<_>>();
+		if header.is_empty() {
+			write!(f, "unknown procedure")?;
+		} else {
+			write!(f, "{}", header.join(" "))?;
+		}
+		for ie in &self.ies {
+			write!(f, ", IE {} {} {}", ie.id, ie.criticality, ie.type_of_error)?;
+		}
+		Ok(())
+	}
+}
+
+/// Logs an Error Indication about to be sent, its diagnostics as a
+/// structured field.
+pub fn log_error_indication(error_indication: &ErrorIndication) {
+	let diagnostics = error_indication
+		.criticality_diagnostics
+		.as_ref()
+		.map(DiagnosticsRecord::from);
+	warn!(
+		cause = ?error_indication.cause,
+		diagnostics = diagnostics.as_value(),
+		"Sending an Error Indication"
+	);
+}
+
+/// An IE of a protocol IE container, kept encoded.
+#[derive(Debug, Clone)]
+pub struct RawProtocolIe {
+	pub id: u16,
+	/// The criticality octet, the value sits in its two most significant bits.
+	pub criticality: u8,
+	pub value: Vec<u8>,
+}
+
+/// The aligned PER layout of an NGAP PDU whose message is a plain
+/// `SEQUENCE { protocolIEs ProtocolIE-Container, ... }`, which holds for every
+/// message of TS 38.413 9.4.
+///
+/// The header is the PDU choice, the procedure code and the criticality
+/// octets, the container is preceded by the extension bit octet of the
+/// message sequence and a two octet IE count.
+#[derive(Debug, Clone)]
+pub struct ProtocolIeContainer {
+	header: [u8; 3],
+	pub ies: Vec<RawProtocolIe>,
+}
+
+impl ProtocolIeContainer {
+	/// Returns `None` for messages with extension additions or lengths
+	/// needing fragmentation, neither is used by NGAP messages seen in
+	/// practice.
+	pub fn parse(pdu: &[u8]) -> Option<Self> {
+		let (header, rest) = pdu.split_first_chunk::<3>()?;
+		let (message, rest) = read_open_type(rest)?;
+		if !rest.is_empty() {
+			return None;
+		}
+		let (&extension, message) = message.split_first()?;
+		if extension & 0x80 != 0 {
+			return None;
+		}
+		let (count, mut message) = message.split_first_chunk::<2>()?;
+		let mut ies = Vec::with_capacity(u16::from_be_bytes(*count) as usize);
+		for _ in 0..u16::from_be_bytes(*count) {
+			let (id, rest) = message.split_first_chunk::<2>()?;
+			let (&criticality, rest) = rest.split_first()?;
+			let (value, rest) = read_open_type(rest)?;
+			ies.push(RawProtocolIe {
+				id: u16::from_be_bytes(*id),
+				criticality,
+				value: value.to_vec(),
+			});
+			message = rest;
+		}
+		if !message.is_empty() {
+			return None;
+		}
+		Some(Self {
+			header: *header,
+			ies,
+		})
+	}
+
+	pub fn to_bytes(&self) -> Option<Vec<u8>> {
+		let mut message = vec![0];
+		message.extend_from_slice(&u16::try_from(self.ies.len()).ok()?.to_be_bytes());
+		for ie in &self.ies {
+			message.extend_from_slice(&ie.id.to_be_bytes());
+			message.push(ie.criticality);
+			write_open_type(&mut message, &ie.value)?;
+		}
+		let mut pdu = self.header.to_vec();
+		write_open_type(&mut pdu, &message)?;
+		Some(pdu)
+	}
+}
+
+/// Reads an octet aligned length determinant and the value it prefixes.
+fn read_open_type(data: &[u8]) -> Option<(&[u8], &[u8])> {
+	let (&first, rest) = data.split_first()?;
+	let (len, rest) = match first {
+		len if len & 0x80 == 0 => (len as usize, rest),
+		len if len & 0xc0 == 0x80 => {
+			let (&second, rest) = rest.split_first()?;
+			((((len & 0x3f) as usize) << 8) | second as usize, rest)
+		}
+		_ => return None,
+	};
+	(rest.len() >= len).then(|| rest.split_at(len))
+}
+
+fn write_open_type(
+	out: &mut Vec<u8>,
+	value: &[u8],
+) -> Option<()> {
+	match value.len() {
+		len if len < 0x80 => out.push(len as u8),
+		len if len < 0x4000 => out.extend_from_slice(&(0x8000 | len as u16).to_be_bytes()),
+		_ => return None,
+	}
+	out.extend_from_slice(value);
+	Some(())
+}
+
+#[cfg(test)]
+mod tests {
+	use ngap_models::{
+		InitialUeMessage,
+		InitiatingMessage,
+		NasPdu,
+		NgapPdu,
+		ProtocolIeId,
+		RanUeNgapId,
+		ToNgapPdu,
+	};
+
+	use super::*;
+	use crate::ngap::engine::{codec_to_bytes, decode_ngap_pdu};
+
+	const UNKNOWN_IE_ID: u16 = 9999;
+
+	/// An Initial UE Message carrying an IE this AMF does not know about.
+	fn message_with_unknown_ie(criticality: u8) -> Vec<u8> {
+		let request = InitialUeMessage {
+			ran_ue_ngap_id: RanUeNgapId(7),
+			nas_pdu: NasPdu(vec![0x7e, 0x00, 0x41]),
+			..Default::default()
+		};
+		let pdu = codec_to_bytes(&<_ as ToNgapPdu>::to_pdu(request)).unwrap();
+		let mut container = ProtocolIeContainer::parse(&pdu).unwrap();
+		container.ies.push(RawProtocolIe {
+			id: UNKNOWN_IE_ID,
+			criticality,
+			value: vec![0x00],
+		});
+		container.to_bytes().unwrap()
+	}
+
+	#[test]
+	fn test_container_round_trip() {
+		let pdu = message_with_unknown_ie(0x40);
+		let container = ProtocolIeContainer::parse(&pdu).unwrap();
+		assert_eq!(container.ies.last().unwrap().id, UNKNOWN_IE_ID);
+		assert_eq!(container.to_bytes().unwrap(), pdu);
+	}
+
+	#[test]
+	fn test_unknown_ie_marked_ignore_is_skipped() {
+		let pdu = decode_ngap_pdu(&message_with_unknown_ie(0x40)).unwrap();
+		let NgapPdu::InitiatingMessage(InitiatingMessage::InitialUeMessage(request)) = pdu else {
+			panic!("expected an Initial UE Message, got {pdu:?}");
+		};
+		assert_eq!(request.ran_ue_ngap_id.0, 7);
+	}
+
+	#[test]
+	fn test_unknown_ie_marked_reject_is_reported() {
+		let Err((pdu, _)) = decode_ngap_pdu(&message_with_unknown_ie(0x00)) else {
+			panic!("a reject marked unknown IE must fail the message");
+		};
+		let NgapPdu::InitiatingMessage(InitiatingMessage::ErrorIndication(error)) = pdu else {
+			panic!("expected an Error Indication, got {pdu:?}");
+		};
+		let CriticalityDiagnosticsIeList(items) = error
+			.criticality_diagnostics
+			.unwrap()
+			.i_es_criticality_diagnostics
+			.unwrap();
+		assert_eq!(items.len(), 1);
+		assert_eq!(items[0].i_e_id, ProtocolIeId(UNKNOWN_IE_ID));
+		assert!(matches!(items[0].i_e_criticality, Criticality::Reject));
+	}
+
+	#[test]
+	fn test_rendered_diagnostics_name_the_offending_ie() {
+		let Err((pdu, _)) = decode_ngap_pdu(&message_with_unknown_ie(0x00)) else {
+			panic!("a reject marked unknown IE must fail the message");
+		};
+		let NgapPdu::InitiatingMessage(InitiatingMessage::ErrorIndication(error)) = pdu else {
+			panic!("expected an Error Indication, got {pdu:?}");
+		};
+		let record = DiagnosticsRecord::from(&error.criticality_diagnostics.unwrap());
+		assert_eq!(record.procedure.as_deref(), Some("InitialUEMessage"));
+		assert_eq!(
+			record.ies,
+			[IeDiagnosticRecord {
+				id: UNKNOWN_IE_ID,
+				criticality: "Reject".to_string(),
+				type_of_error: "NotUnderstood".to_string(),
+			}]
+		);
+		let rendered = record.to_string();
+		assert!(rendered.starts_with("InitialUEMessage"));
+		assert!(rendered.ends_with(", IE 9999 Reject NotUnderstood"));
+	}
+}
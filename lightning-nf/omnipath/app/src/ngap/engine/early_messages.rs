@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+use ngap_models::NgapPdu;
+
+use crate::ngap::procedure_code_enum::ProcedureCodeEnum;
+
+/// Messages a gNB sends ahead of its NG Setup completing, like the Initial UE
+/// Messages of a gNB reconnecting, held to be handled once the setup is done.
+#[derive(Debug)]
+pub struct EarlyMessages {
+	messages: VecDeque<Bytes>,
+	capacity: usize,
+}
+
+impl EarlyMessages {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			messages: VecDeque::with_capacity(capacity),
+			capacity,
+		}
+	}
+
+	/// Holds a message of another procedure than NG Setup. The message is
+	/// handed back for the NG Setup to handle, or reject, when it is of the NG
+	/// Setup, of no known procedure, or past the capacity.
+	pub fn hold(
+		&mut self,
+		message: Bytes,
+	) -> Option<Bytes> {
+		let (_, procedure_code, _) = NgapPdu::get_message_info(&message);
+		let procedure = procedure_code.and_then(|code| ProcedureCodeEnum::try_from(code.0).ok());
+		match procedure {
+			Some(procedure)
+				if procedure != ProcedureCodeEnum::NGSetup
+					&& self.messages.len() < self.capacity =>
+			{
+				self.messages.push_back(message);
+				None
+			}
+			_ => Some(message),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.messages.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.messages.is_empty()
+	}
+}
+
+impl IntoIterator for EarlyMessages {
+	type Item = Bytes;
+	type IntoIter = std::collections::vec_deque::IntoIter<Bytes>;
+
+	/// The messages in the order they were received.
+	fn into_iter(self) -> Self::IntoIter {
+		self.messages.into_iter()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The headers of messages with an empty IE container, procedure codes 21,
+	/// 15 and 24.
+	const NG_SETUP_REQUEST: &[u8] = &[0x00, 0x15, 0x00, 0x03, 0x00, 0x00, 0x00];
+	const INITIAL_UE_MESSAGE: &[u8] = &[0x00, 0x0f, 0x40, 0x03, 0x00, 0x00, 0x00];
+	const PAGING: &[u8] = &[0x00, 0x18, 0x40, 0x03, 0x00, 0x00, 0x00];
+
+	#[test]
+	fn test_early_initial_ue_message_is_held_until_setup() {
+		let mut early = EarlyMessages::new(1);
+		let initial_ue_message = Bytes::from_static(INITIAL_UE_MESSAGE);
+		assert_eq!(early.hold(initial_ue_message.clone()), None);
+		// Past the capacity, left to the NG Setup to reject.
+		let paging = Bytes::from_static(PAGING);
+		assert_eq!(early.hold(paging.clone()), Some(paging));
+
+		let ng_setup_request = Bytes::from_static(NG_SETUP_REQUEST);
+		assert_eq!(early.hold(ng_setup_request.clone()), Some(ng_setup_request));
+		assert_eq!(early.len(), 1);
+		assert_eq!(early.into_iter().collect::<Vec<_>>(), [initial_ue_message]);
+	}
+}
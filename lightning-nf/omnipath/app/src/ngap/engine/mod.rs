@@ -1,7 +1,16 @@
 pub mod controller;
+mod criticality;
+mod early_messages;
 mod interfaces;
 mod ue_actions;
 mod utils;
 
+pub use criticality::{
+	DiagnosticsRecord,
+	IeDiagnosticRecord,
+	log_error_indication,
+	message_diagnostics,
+};
+pub use early_messages::EarlyMessages;
 pub use interfaces::*;
-pub use utils::decode_ngap_pdu;
+pub use utils::{codec_to_bytes, decode_ngap_pdu, drain_join_set};
@@ -1,4 +1,12 @@
-use ngap_models::{DownlinkNasTransport, NasPdu, ToNgapPdu};
+use ngap_models::{
+	Cause,
+	DownlinkNasTransport,
+	NasPdu,
+	ToNgapPdu,
+	UeContextReleaseCommand,
+	UeNgapIdPair,
+	UeNgapIds,
+};
 
 use crate::{
 	context::UeContext,
@@ -21,4 +29,23 @@ impl UeContext {
 		let pdu = downlink_nas_transport.to_pdu();
 		encode_and_write_ngap_pdu(self.gnb_context.tnla_association.as_ref(), pdu).await
 	}
+
+	/// Asks the gNB to release the UE to CM-IDLE, TS 38.413 8.3.3.
+	pub async fn send_ue_context_release_command(
+		&self,
+		cause: Cause,
+	) -> Result<(), NgapWriteError> {
+		let ue_context_release_command = UeContextReleaseCommand {
+			ue_ngap_ids: UeNgapIds::UeNgapIdPair(UeNgapIdPair {
+				amf_ue_ngap_id: self.amf_ue_ngap_id,
+				ran_ue_ngap_id: self.ran_ue_ngap_id,
+				..Default::default()
+			}),
+			cause,
+			..Default::default()
+		};
+
+		let pdu = ue_context_release_command.to_pdu();
+		encode_and_write_ngap_pdu(self.gnb_context.tnla_association.as_ref(), pdu).await
+	}
 }
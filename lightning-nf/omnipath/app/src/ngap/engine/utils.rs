@@ -1,14 +1,24 @@
 // use asn1_codecs::PerCodecError;
+use std::{borrow::Cow, time::Duration};
+
 use asn1_per::{CodecDataAllocator, PerCodec, PerCodecError, SerDes, ThreeGppAsn1PerError};
 use ngap_models::{
 	Cause,
 	CauseProtocol,
-	CriticalityDiagnostics,
+	CriticalityDiagnosticsIeList,
 	ErrorIndication,
 	NgapPdu,
 	ToNgapPdu,
 	convert_diagnostics_to_ie,
 };
+use tokio::task::JoinSet;
+
+use super::criticality::{
+	IeErrorAction,
+	log_error_indication,
+	message_diagnostics,
+	resolve_ie_errors,
+};
 
 /// Attempts to encode an NGAP PDU payload and returns the encoded bytes.
 ///
@@ -28,6 +38,9 @@ pub fn codec_to_bytes<T: PerCodec>(data: &T) -> Result<Vec<u8>, ThreeGppAsn1PerE
 /// decoded PDU or an error indication PDU that should be sent back to the
 /// sender along with decoding error information.
 ///
+/// Not understood IEs are handled according to their criticality, those that
+/// may be ignored are dropped and the rest of the message is decoded.
+///
 /// # Arguments
 /// * `request` - The raw NGAP PDU bytes to decode
 ///
@@ -36,52 +49,90 @@ pub fn codec_to_bytes<T: PerCodec>(data: &T) -> Result<Vec<u8>, ThreeGppAsn1PerE
 /// * `Err((NgapPdu, PerCodecError))` - Error indication PDU to be sent back
 ///   along with the decoding error information.
 pub fn decode_ngap_pdu(request: &[u8]) -> Result<NgapPdu, (NgapPdu, PerCodecError)> {
-	match NgapPdu::from_bytes(request) {
-		Ok(pdu) => Ok(pdu),
-		Err(e) => {
-			let (err, codec_error) = build_criticality_diagnostics(&request, e);
-			Err((err.to_pdu(), codec_error))
+	let mut request = Cow::Borrowed(request);
+	// Each round strips at least one IE, so this ends once the container is
+	// exhausted.
+	loop {
+		let ThreeGppAsn1PerError {
+			diagnostics,
+			codec_error,
+		} = match NgapPdu::from_bytes(&request) {
+			Ok(pdu) => return Ok(pdu),
+			Err(e) => e,
+		};
+		match resolve_ie_errors(&request, convert_diagnostics_to_ie(diagnostics)) {
+			IeErrorAction::Proceed(stripped) => request = Cow::Owned(stripped),
+			IeErrorAction::Reject(i_es_criticality_diagnostics) => {
+				let err = build_criticality_diagnostics(&request, i_es_criticality_diagnostics);
+				log_error_indication(&err);
+				return Err((err.to_pdu(), codec_error));
+			}
 		}
 	}
 }
 
-/// Builds an error indication PDU with criticality diagnostics from a decoding
-/// error.
+/// Builds an error indication PDU with criticality diagnostics for a message
+/// that could not be decoded.
 ///
 /// # Arguments
-/// * `request` - The raw NGAP PDU bytes to decode
-/// * `error` - The decoding error
+/// * `request` - The raw NGAP PDU bytes that failed decoding
+/// * `i_es_criticality_diagnostics` - The IEs to report, rejected IEs are
+///   reported with an `AbstractSyntaxErrorReject` cause.
 ///
 /// # Returns
-/// * `(ErrorIndication, PerCodecError)` - Error indication PDU and decoding
-///   error information.
+/// * `ErrorIndication` - Error indication to be sent back.
 pub fn build_criticality_diagnostics(
 	request: &[u8],
-	error: ThreeGppAsn1PerError,
-) -> (ErrorIndication, PerCodecError) {
-	let ThreeGppAsn1PerError {
-		diagnostics,
-		codec_error,
-	} = error;
-	let i_es_criticality_diagnostics = convert_diagnostics_to_ie(diagnostics);
-
-	let (triggering_message, procedure_code, procedure_criticality) =
-		NgapPdu::get_message_info(&request);
-
-	let criticality_diagnostics = CriticalityDiagnostics {
-		procedure_code,
-		triggering_message,
-		procedure_criticality,
-		i_es_criticality_diagnostics,
+	i_es_criticality_diagnostics: Option<CriticalityDiagnosticsIeList>,
+) -> ErrorIndication {
+	let cause = match i_es_criticality_diagnostics {
+		Some(_) => CauseProtocol::AbstractSyntaxErrorReject,
+		None => CauseProtocol::AbstractSyntaxErrorFalselyConstructedMessage,
 	};
+	let criticality_diagnostics = message_diagnostics(request, i_es_criticality_diagnostics);
 
-	let err = ErrorIndication {
-		cause: Some(Cause::Protocol(
-			CauseProtocol::AbstractSyntaxErrorFalselyConstructedMessage,
-		)),
+	ErrorIndication {
+		cause: Some(Cause::Protocol(cause)),
 		criticality_diagnostics: Some(criticality_diagnostics),
 		..Default::default()
-	};
+	}
+}
 
-	(err, codec_error)
+/// Waits up to `grace` for the tasks of `tasks` to finish, then aborts the
+/// ones still running.
+///
+/// # Returns
+/// * `(usize, usize)` - The number of tasks that completed and that were
+///   aborted.
+pub async fn drain_join_set<T: 'static>(
+	mut tasks: JoinSet<T>,
+	grace: Duration,
+) -> (usize, usize) {
+	let mut completed = 0;
+	let _ = tokio::time::timeout(grace, async {
+		while tasks.join_next().await.is_some() {
+			completed += 1;
+		}
+	})
+	.await;
+	let aborted = tasks.len();
+	tasks.shutdown().await;
+	(completed, aborted)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_drain_waits_for_grace_then_aborts() {
+		let mut tasks = JoinSet::new();
+		tasks.spawn(tokio::time::sleep(Duration::from_millis(10)));
+		tasks.spawn(tokio::time::sleep(Duration::from_secs(60)));
+
+		let started = tokio::time::Instant::now();
+		let (completed, aborted) = drain_join_set(tasks, Duration::from_millis(100)).await;
+		assert_eq!((completed, aborted), (1, 1));
+		assert!(started.elapsed() < Duration::from_secs(60));
+	}
 }
@@ -166,6 +166,37 @@ impl<T: Identifiable + Send + Sync + 'static> ContextManager<T> {
 		self.queues.contains_async(id).await
 	}
 
+	/// Removes the context element with the given ID, returning whether it was
+	/// present.
+	pub async fn remove_context(
+		&self,
+		id: &T::ID,
+	) -> bool {
+		self.queues.remove_async(id).await.is_some()
+	}
+
+	/// Removes every context element.
+	pub async fn clear(&self) {
+		self.queues.clear_async().await;
+	}
+
+	pub fn len(&self) -> usize {
+		self.queues.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.queues.is_empty()
+	}
+
+	/// Operations queued or running across every context element.
+	pub async fn queue_depth(&self) -> usize {
+		let mut depth = 0;
+		self.queues
+			.scan_async(|_, queue| depth += queue.depth())
+			.await;
+		depth
+	}
+
 	/// Executes a closure with exclusive access to a context element and
 	/// returns its result.
 	///
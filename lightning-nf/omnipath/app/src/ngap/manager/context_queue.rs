@@ -4,11 +4,13 @@ use std::{
 	pin::Pin,
 	sync::{
 		Arc,
-		atomic::{AtomicBool, Ordering},
+		atomic::{AtomicBool, AtomicUsize, Ordering},
 	},
 };
 
+use thiserror::Error;
 use tokio::sync::{Mutex, OwnedRwLockWriteGuard, RwLock, oneshot};
+use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
 
 use super::context_manager::Identifiable;
@@ -36,6 +38,8 @@ pub(crate) struct ContextQueue<T> {
 	inner: Arc<RwLock<T>>,
 	queue: Arc<Mutex<VecDeque<Pin<Box<dyn Future<Output = ()> + Send + 'static>>>>>,
 	processor_active: AtomicBool,
+	/// Operations pushed and not yet done, the running one included.
+	depth: AtomicUsize,
 }
 
 impl<T> ContextQueue<T> {
@@ -44,9 +48,15 @@ impl<T> ContextQueue<T> {
 			inner: Arc::new(RwLock::new(context)),
 			queue: Arc::new(Mutex::new(VecDeque::new())),
 			processor_active: AtomicBool::new(false),
+			depth: AtomicUsize::new(0),
 		}
 	}
 
+	/// Operations queued or running on the context.
+	pub fn depth(&self) -> usize {
+		self.depth.load(Ordering::Relaxed)
+	}
+
 	/// Extracts the inner context value, consuming the queue.
 	/// This should only be used when you are certain there are no pending
 	/// operations.
@@ -75,6 +85,8 @@ where
 		self: Arc<Self>,
 		future: Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>,
 	) {
+		// Counted ahead of the lock, which is held while an operation runs.
+		self.depth.fetch_add(1, Ordering::Relaxed);
 		let mut queue = self.queue.lock().await;
 		queue.push_back(future);
 
@@ -104,6 +116,7 @@ where
 			// Safety: The check for `queue.is_empty()` is performed above.
 			let fut = queue.pop_front().unwrap();
 			fut.await;
+			self.depth.fetch_sub(1, Ordering::Relaxed);
 		}
 	}
 
@@ -208,4 +221,100 @@ where
 		// task would be aborted anyway.
 		rx.await.unwrap()
 	}
+
+	/// Like [`schedule_and_wait`](Self::schedule_and_wait), but stops waiting
+	/// once `cancel` fires. The operation is queued regardless and still runs,
+	/// its result is dropped.
+	pub async fn schedule_and_wait_cancellable<F, O>(
+		self: Arc<Self>,
+		closure: F,
+		cancel: CancellationToken,
+	) -> Result<O, Cancelled>
+	where
+		F: FnOnce(
+				OwnedRwLockWriteGuard<T>,
+			) -> Pin<Box<dyn Future<Output = O> + Send + Sync + 'static>>
+			+ Send
+			+ Sync
+			+ 'static,
+		O: Send + Sync + 'static,
+	{
+		let (tx, rx) = oneshot::channel::<O>();
+		let future = self.enqueue_and_get_result(closure, tx).await;
+		// The queue stays locked while an operation runs, pushing from a task of
+		// its own keeps a cancelled caller from dropping the push.
+		tokio::spawn(self.push_future(future));
+
+		tokio::select! {
+			// Safety: as in `schedule_and_wait`, the queued future sends the output
+			// unless it panics.
+			output = rx => Ok(output.unwrap()),
+			_ = cancel.cancelled() => Err(Cancelled),
+		}
+	}
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Cancelled: Stopped waiting for the queued operation")]
+pub(crate) struct Cancelled;
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	struct Counter {
+		id: u32,
+		value: u32,
+	}
+
+	impl Identifiable for Counter {
+		type ID = u32;
+
+		fn id(&self) -> &Self::ID {
+			&self.id
+		}
+	}
+
+	#[tokio::test]
+	async fn test_cancelled_wait_returns_while_a_long_op_runs() {
+		let queue = Arc::new(ContextQueue::new(Counter { id: 1, value: 0 }));
+		let (started, has_started) = oneshot::channel::<()>();
+		let (release, released) = oneshot::channel::<()>();
+		let long_op = tokio::spawn(queue.clone().schedule_and_wait(|mut counter| {
+			Box::pin(async move {
+				started.send(()).unwrap();
+				released.await.unwrap();
+				counter.value += 1;
+			})
+		}));
+		has_started.await.unwrap();
+
+		let cancel = CancellationToken::new();
+		let waiting = tokio::spawn(queue.clone().schedule_and_wait_cancellable(
+			|mut counter| {
+				Box::pin(async move {
+					counter.value += 10;
+				})
+			},
+			cancel.clone(),
+		));
+		tokio::time::sleep(Duration::from_millis(10)).await;
+		assert!(!waiting.is_finished());
+		cancel.cancel();
+		let cancelled = tokio::time::timeout(Duration::from_secs(1), waiting)
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(cancelled, Err(Cancelled));
+
+		// The cancelled operation still runs after the long one.
+		release.send(()).unwrap();
+		long_op.await.unwrap();
+		let value = queue
+			.schedule_and_wait(|counter| Box::pin(async move { counter.value }))
+			.await;
+		assert_eq!(value, 11);
+	}
 }
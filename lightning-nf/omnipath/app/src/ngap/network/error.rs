@@ -1,13 +1,20 @@
-use std::{io::Error as IoError, net::SocketAddr};
+use std::{
+	fmt,
+	io::{Error as IoError, ErrorKind},
+	net::SocketAddr,
+};
 
+use sctp_config::SocketStyle;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum NetworkError {
 	#[error("SocketCreationError: Failed to create SCTP socket")]
 	SocketCreationError(#[source] IoError),
-	#[error("ListenerBindingError: Failed to bind SCTP listener")]
-	ListenerBindingError(#[source] IoError),
+	#[error("UnsupportedSocketStyle: {0:?} SCTP sockets are not supported")]
+	UnsupportedSocketStyle(SocketStyle),
+	#[error("ListenerBindingError: Failed to bind SCTP listener on {0}, {1}")]
+	ListenerBindingError(SocketAddr, BindErrorCause, #[source] IoError),
 	#[error("SctpSocketConfigurationError: Failed to set SCTP socket parameters")]
 	SctpSocketConfigurationError(#[source] IoError),
 	#[error("ConnectionAcceptError: Failed to accept sctp connection")]
@@ -22,6 +29,42 @@ pub enum NetworkError {
 	TnlaSendError(usize, #[source] TnlaError),
 	#[error("TnlaReadError: Failed to read from TNLA association with id {0}")]
 	TnlaReadError(usize, #[source] TnlaError),
+	#[error("TapCreationError: Failed to open the NGAP tap file")]
+	TapCreationError(#[source] IoError),
+}
+
+/// Why the NGAP listener could not bind its address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindErrorCause {
+	AddressInUse,
+	PermissionDenied,
+	AddressNotAvailable,
+	Other,
+}
+
+impl BindErrorCause {
+	pub fn of(error: &IoError) -> Self {
+		match error.kind() {
+			ErrorKind::AddrInUse => Self::AddressInUse,
+			ErrorKind::PermissionDenied => Self::PermissionDenied,
+			ErrorKind::AddrNotAvailable => Self::AddressNotAvailable,
+			_ => Self::Other,
+		}
+	}
+}
+
+impl fmt::Display for BindErrorCause {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		f.write_str(match self {
+			Self::AddressInUse => "the address is in use, by another AMF instance maybe",
+			Self::PermissionDenied => "permission denied, the AMF lacks the privileges to bind it",
+			Self::AddressNotAvailable => "the address is not assigned to any local interface",
+			Self::Other => "see the cause below",
+		})
+	}
 }
 
 #[derive(Error, Debug)]
@@ -34,4 +77,8 @@ pub enum TnlaError {
 	LocalAddressError(#[source] IoError),
 	#[error("RemoteAddressError: Failed to get remote address for SCTP association")]
 	RemoteAddressError(#[source] IoError),
+	#[error("SubscriptionError: Failed to subscribe to SCTP notifications")]
+	SubscriptionError(#[source] IoError),
+	#[error("InvalidNotification: Failed to parse SCTP notification of {0} bytes")]
+	InvalidNotification(usize),
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+use std::{
+	sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+	time::Duration,
+};
+
+use crate::ngap::constants::app::{
+	SLOW_PEER_BLOCKED_WRITES,
+	SLOW_PEER_RECOVERY_WRITES,
+	SLOW_PEER_WRITE_THRESHOLD,
+};
+
+/// Traffic counters of a TNLA association, along with slow peer detection.
+///
+/// A write blocks when the peer does not drain its receive window and the
+/// socket's send buffer fills up. After `SLOW_PEER_BLOCKED_WRITES` blocked
+/// writes in a row the peer is flagged slow, it is cleared again once
+/// `SLOW_PEER_RECOVERY_WRITES` writes in a row went through promptly. A slow
+/// peer is spared the messages that only inform, those shed are counted.
+#[derive(Debug, Default)]
+pub struct FlowStats {
+	messages_received: AtomicU64,
+	bytes_received: AtomicU64,
+	messages_sent: AtomicU64,
+	bytes_sent: AtomicU64,
+	blocked_writes: AtomicU64,
+	shed_messages: AtomicU64,
+	streak: WriteStreak,
+	slow: AtomicBool,
+}
+
+impl FlowStats {
+	pub fn record_read(
+		&self,
+		bytes: usize,
+	) {
+		self.messages_received.fetch_add(1, Ordering::Relaxed);
+		self.bytes_received
+			.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	/// Records a write of `bytes` that took `elapsed` to be accepted by the
+	/// socket.
+	pub fn record_write(
+		&self,
+		bytes: usize,
+		elapsed: Duration,
+	) {
+		self.messages_sent.fetch_add(1, Ordering::Relaxed);
+		self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+		if elapsed >= SLOW_PEER_WRITE_THRESHOLD {
+			self.blocked_writes.fetch_add(1, Ordering::Relaxed);
+			if self.streak.blocked() >= SLOW_PEER_BLOCKED_WRITES {
+				self.slow.store(true, Ordering::Relaxed);
+			}
+		} else if self.streak.prompt() >= SLOW_PEER_RECOVERY_WRITES {
+			self.slow.store(false, Ordering::Relaxed);
+		}
+	}
+
+	pub fn is_slow(&self) -> bool {
+		self.slow.load(Ordering::Relaxed)
+	}
+
+	/// Records a message not sent, the peer being slow.
+	pub fn record_shed(&self) {
+		self.shed_messages.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn snapshot(&self) -> FlowStatsSnapshot {
+		FlowStatsSnapshot {
+			messages_received: self.messages_received.load(Ordering::Relaxed),
+			bytes_received: self.bytes_received.load(Ordering::Relaxed),
+			messages_sent: self.messages_sent.load(Ordering::Relaxed),
+			bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+			blocked_writes: self.blocked_writes.load(Ordering::Relaxed),
+			shed_messages: self.shed_messages.load(Ordering::Relaxed),
+			slow_peer: self.is_slow(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlowStatsSnapshot {
+	pub messages_received: u64,
+	pub bytes_received: u64,
+	pub messages_sent: u64,
+	pub bytes_sent: u64,
+	pub blocked_writes: u64,
+	pub shed_messages: u64,
+	pub slow_peer: bool,
+}
+
+/// Length of the current run of blocked or prompt writes, a run of one kind
+/// ends the other.
+#[derive(Debug, Default)]
+struct WriteStreak {
+	blocked: AtomicU32,
+	prompt: AtomicU32,
+}
+
+impl WriteStreak {
+	/// Returns the length of the blocked run including this write.
+	fn blocked(&self) -> u32 {
+		self.prompt.store(0, Ordering::Relaxed);
+		self.blocked
+			.fetch_add(1, Ordering::Relaxed)
+			.saturating_add(1)
+	}
+
+	/// Returns the length of the prompt run including this write.
+	fn prompt(&self) -> u32 {
+		self.blocked.store(0, Ordering::Relaxed);
+		self.prompt
+			.fetch_add(1, Ordering::Relaxed)
+			.saturating_add(1)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PROMPT: Duration = Duration::from_micros(10);
+
+	#[test]
+	fn test_peer_not_draining_is_flagged_slow() {
+		let stats = FlowStats::default();
+		// A peer that never drains its receive buffer, once the send buffer is
+		// full every write waits for the retransmission timer.
+		for _ in 0..4 {
+			stats.record_write(100, PROMPT);
+		}
+		for _ in 1..SLOW_PEER_BLOCKED_WRITES {
+			stats.record_write(100, SLOW_PEER_WRITE_THRESHOLD * 2);
+			assert!(!stats.is_slow());
+		}
+		stats.record_write(100, SLOW_PEER_WRITE_THRESHOLD * 2);
+		assert!(stats.is_slow());
+
+		let snapshot = stats.snapshot();
+		assert_eq!(
+			snapshot.messages_sent,
+			4 + u64::from(SLOW_PEER_BLOCKED_WRITES)
+		);
+		assert_eq!(snapshot.blocked_writes, u64::from(SLOW_PEER_BLOCKED_WRITES));
+		assert!(snapshot.slow_peer);
+	}
+
+	#[test]
+	fn test_slow_peer_recovers_after_prompt_writes() {
+		let stats = FlowStats::default();
+		for _ in 0..SLOW_PEER_BLOCKED_WRITES {
+			stats.record_write(100, SLOW_PEER_WRITE_THRESHOLD);
+		}
+		assert!(stats.is_slow());
+
+		// An occasional blocked write restarts the recovery.
+		for _ in 1..SLOW_PEER_RECOVERY_WRITES {
+			stats.record_write(100, PROMPT);
+		}
+		stats.record_write(100, SLOW_PEER_WRITE_THRESHOLD);
+		for _ in 1..SLOW_PEER_RECOVERY_WRITES {
+			stats.record_write(100, PROMPT);
+		}
+		assert!(stats.is_slow());
+		stats.record_write(100, PROMPT);
+		assert!(!stats.is_slow());
+	}
+}
@@ -1,7 +1,13 @@
+mod flow_stats;
 mod network;
+mod sctp_event;
 mod tnla_assoc;
 mod error;
+#[cfg(feature = "ngap-tap")]
+pub mod tap;
 
+pub use flow_stats::{FlowStats, FlowStatsSnapshot};
 pub use network::Network;
-pub use error::{NetworkError, TnlaError};
+pub use sctp_event::{AssocChangeState, PeerAddrState, SctpEvent, SctpNotification};
+pub use error::{BindErrorCause, NetworkError, TnlaError};
 pub use tnla_assoc::TnlaAssociation;
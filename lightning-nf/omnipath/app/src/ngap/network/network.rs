@@ -1,24 +1,39 @@
 use std::{
 	collections::{HashMap, HashSet},
 	hash::BuildHasherDefault,
+	io,
+	mem,
 	net::{IpAddr, SocketAddr},
+	os::fd::AsRawFd,
 	sync::Arc,
 };
 
 use bytes::Bytes;
 use rustc_hash::FxBuildHasher;
-use socket2::Domain;
+use sctp_config::{SctpConfig, SocketStyle};
+use socket2::{Domain, SockRef};
 use solana_nohash_hasher::NoHashHasher;
 use tokio::sync::RwLock;
 use tokio_sctp::{SctpListener, SctpSocket, SctpStream};
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 
-use super::{NetworkError, TnlaAssociation};
-use crate::config;
+use super::{BindErrorCause, NetworkError, TnlaAssociation};
 
 type UnitHasher<T> = BuildHasherDefault<NoHashHasher<T>>;
 
+const SOL_SCTP: libc::c_int = 132;
+/// `SCTP_PEER_ADDR_PARAMS` socket option, RFC 6458 8.1.12.
+const SCTP_PEER_ADDR_PARAMS: libc::c_int = 9;
+/// Size of the packed `struct sctp_paddrparams` up to `spp_flags`, the
+/// layout predating the flow label and DSCP, which every kernel takes.
+const PADDRPARAMS_LEN: usize = 152;
+/// Offsets of `spp_hbinterval` and `spp_flags`, past the association id and
+/// the `sockaddr_storage` of the address.
+const SPP_HBINTERVAL_OFFSET: usize = 132;
+const SPP_FLAGS_OFFSET: usize = 146;
+const SPP_HB_ENABLE: u32 = 1;
+
 pub struct Associations {
 	associations: HashMap<usize, Arc<TnlaAssociation>, UnitHasher<usize>>,
 	associations_set: HashSet<(SocketAddr, SocketAddr), FxBuildHasher>,
@@ -117,7 +132,7 @@ impl Network {
 	pub fn new(
 		ip_addr: IpAddr,
 		port: u16,
-		sctp_config: &config::SCTP,
+		sctp_config: &SctpConfig,
 	) -> Result<Self, NetworkError> {
 		info!("Connecting to SCTP port {} on IP address {}", port, ip_addr);
 		let domain = match ip_addr {
@@ -125,22 +140,29 @@ impl Network {
 			IpAddr::V6(_) => Domain::IPV6,
 		};
 		let init_msg = sctp_config.into();
-		let socket = SctpSocket::new(domain).map_err(NetworkError::SocketCreationError)?;
+		let socket = new_socket(domain, sctp_config.socket_style)?;
 
-		// TODO: Setting initialization parameters is effective only on an unconnected
-		// socket (for one-to-many style sockets, only future associations are
-		// affected by the change).
+		// Initialization parameters are effective only on an unconnected socket,
+		// the listener here, and are inherited by the associations it accepts.
 		socket
 			.set_sctp_initmsg(&init_msg)
 			.map_err(NetworkError::SctpSocketConfigurationError)?;
 
 		socket
-			.set_nodelay(true)
+			.set_nodelay(sctp_config.nodelay)
 			.map_err(NetworkError::SctpSocketConfigurationError)?;
 
+		// Accepted associations inherit the buffer sizes of the listener.
+		set_buffer_sizes(&socket, sctp_config)
+			.map_err(NetworkError::SctpSocketConfigurationError)?;
+		if let Some(interval) = sctp_config.heartbeat_interval {
+			set_heartbeat_interval(&socket, interval)
+				.map_err(NetworkError::SctpSocketConfigurationError)?;
+		}
+
 		let addr = SocketAddr::new(ip_addr, port);
-		let listener =
-			SctpListener::bind_from(socket, addr).map_err(NetworkError::ListenerBindingError)?;
+		let listener = SctpListener::bind_from(socket, addr)
+			.map_err(|e| NetworkError::ListenerBindingError(addr, BindErrorCause::of(&e), e))?;
 
 		Ok(Self {
 			listener,
@@ -204,3 +226,158 @@ impl Network {
 		}
 	}
 }
+
+/// Creates the listening socket in the configured style.
+///
+/// Each gNB is served on the association `accept` returns, which only
+/// one-to-one sockets do; `tokio_sctp` creates no one-to-many sockets.
+fn new_socket(
+	domain: Domain,
+	style: SocketStyle,
+) -> Result<SctpSocket, NetworkError> {
+	match style {
+		SocketStyle::OneToOne => SctpSocket::new(domain).map_err(NetworkError::SocketCreationError),
+		SocketStyle::OneToMany => Err(NetworkError::UnsupportedSocketStyle(style)),
+	}
+}
+
+/// Sizes `SO_SNDBUF` and `SO_RCVBUF` of `socket` as configured, leaving the
+/// kernel's defaults for those left out.
+fn set_buffer_sizes(
+	socket: &SctpSocket,
+	sctp_config: &SctpConfig,
+) -> io::Result<()> {
+	let socket = SockRef::from(socket);
+	if let Some(size) = sctp_config.send_buffer_size {
+		socket.set_send_buffer_size(size as usize)?;
+	}
+	if let Some(size) = sctp_config.recv_buffer_size {
+		socket.set_recv_buffer_size(size as usize)?;
+	}
+	Ok(())
+}
+
+/// Enables the heartbeats of the associations `socket` accepts, every
+/// `interval` milliseconds. Set on the listener, with no association or
+/// address given, the parameters are the defaults of its associations.
+fn set_heartbeat_interval(
+	socket: &SctpSocket,
+	interval: u32,
+) -> io::Result<()> {
+	let mut params = [0u8; PADDRPARAMS_LEN];
+	params[SPP_HBINTERVAL_OFFSET..SPP_HBINTERVAL_OFFSET + 4]
+		.copy_from_slice(&interval.to_ne_bytes());
+	params[SPP_FLAGS_OFFSET..SPP_FLAGS_OFFSET + 4].copy_from_slice(&SPP_HB_ENABLE.to_ne_bytes());
+	// SAFETY: `params` outlives the call and its length is passed along.
+	let res = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			SOL_SCTP,
+			SCTP_PEER_ADDR_PARAMS,
+			params.as_ptr() as *const libc::c_void,
+			mem::size_of_val(&params) as libc::socklen_t,
+		)
+	};
+	if res == 0 {
+		Ok(())
+	} else {
+		Err(io::Error::last_os_error())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use socket2::Type;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_one_to_one_socket_accepts_each_association() {
+		let socket = new_socket(Domain::IPV4, SocketStyle::OneToOne).unwrap();
+		assert_eq!(SockRef::from(&socket).r#type().unwrap(), Type::STREAM);
+		assert!(matches!(
+			new_socket(Domain::IPV4, SocketStyle::OneToMany),
+			Err(NetworkError::UnsupportedSocketStyle(SocketStyle::OneToMany))
+		));
+
+		let listener = SctpListener::bind_from(socket, ([127, 0, 0, 1], 0).into()).unwrap();
+		let addr = listener.local_addr().unwrap();
+		let mut remotes = Vec::new();
+		for _ in 0..2 {
+			let client = SctpSocket::new(Domain::IPV4).unwrap();
+			let (client, accepted) = tokio::join!(client.connect(addr), listener.accept());
+			let (stream, remote) = accepted.unwrap();
+			assert_eq!(remote, client.unwrap().local_addr().unwrap());
+			assert_eq!(stream.local_addr().unwrap(), addr);
+			remotes.push(remote);
+		}
+		// An association of its own for each peer.
+		assert_ne!(remotes[0], remotes[1]);
+	}
+
+	fn bind_error_cause(network: Result<Network, NetworkError>) -> BindErrorCause {
+		match network {
+			Err(NetworkError::ListenerBindingError(_, cause, _)) => cause,
+			Err(e) => panic!("Not a binding error: {e:?}"),
+			Ok(_) => panic!("Bound the listener"),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_bind_failures_are_classified() {
+		let localhost = IpAddr::from([127, 0, 0, 1]);
+		let sctp_config = SctpConfig::default();
+		let network = Network::new(localhost, 0, &sctp_config).unwrap();
+		let port = network.listener.local_addr().unwrap().port();
+
+		let in_use = Network::new(localhost, port, &sctp_config);
+		assert_eq!(bind_error_cause(in_use), BindErrorCause::AddressInUse);
+		// TEST-NET-1, assigned to no interface.
+		let not_local = Network::new(IpAddr::from([192, 0, 2, 1]), port, &sctp_config);
+		let error = not_local.err().unwrap();
+		assert!(error.to_string().contains("192.0.2.1"));
+		assert_eq!(
+			bind_error_cause(Err(error)),
+			BindErrorCause::AddressNotAvailable
+		);
+	}
+
+	#[test]
+	fn test_configured_buffer_sizes_are_applied() {
+		let socket = SctpSocket::new(Domain::IPV4).unwrap();
+		let sctp_config = SctpConfig {
+			send_buffer_size: Some(96 * 1024),
+			recv_buffer_size: Some(128 * 1024),
+			..Default::default()
+		};
+		set_buffer_sizes(&socket, &sctp_config).unwrap();
+
+		// Linux doubles the requested size to account for its bookkeeping.
+		let socket = SockRef::from(&socket);
+		assert!(socket.send_buffer_size().unwrap() >= 96 * 1024);
+		assert!(socket.recv_buffer_size().unwrap() >= 128 * 1024);
+	}
+
+	#[test]
+	fn test_configured_heartbeat_interval_is_applied() {
+		let socket = SctpSocket::new(Domain::IPV4).unwrap();
+		set_heartbeat_interval(&socket, 5000).unwrap();
+
+		let mut params = [0u8; PADDRPARAMS_LEN];
+		let mut len = PADDRPARAMS_LEN as libc::socklen_t;
+		// SAFETY: `params` and `len` outlive the call, `len` holds the length
+		// of `params`.
+		let res = unsafe {
+			libc::getsockopt(
+				socket.as_raw_fd(),
+				SOL_SCTP,
+				SCTP_PEER_ADDR_PARAMS,
+				params.as_mut_ptr() as *mut libc::c_void,
+				&mut len,
+			)
+		};
+		assert_eq!(res, 0, "{}", io::Error::last_os_error());
+		let interval = &params[SPP_HBINTERVAL_OFFSET..SPP_HBINTERVAL_OFFSET + 4];
+		assert_eq!(u32::from_ne_bytes(interval.try_into().unwrap()), 5000);
+	}
+}
@@ -0,0 +1,251 @@
+use std::{
+	io,
+	mem,
+	net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+	os::fd::AsRawFd,
+};
+
+use bytes::Bytes;
+use tokio_sctp::SctpStream;
+
+const SOL_SCTP: libc::c_int = 132;
+/// `SCTP_EVENTS` socket option, RFC 6458 6.2.1.
+const SCTP_EVENTS: libc::c_int = 11;
+
+const SCTP_ASSOC_CHANGE: u16 = 0x8001;
+const SCTP_PEER_ADDR_CHANGE: u16 = 0x8002;
+const SCTP_SHUTDOWN_EVENT: u16 = 0x8005;
+
+/// Size of the `sn_type`, `sn_flags` and `sn_length` header of every
+/// notification.
+const NOTIFICATION_HEADER_LEN: usize = 8;
+const SOCKADDR_STORAGE_LEN: usize = 128;
+
+/// What a read of a TNLA association yields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SctpEvent {
+	/// An NGAP message.
+	Data(Bytes),
+	/// A transport event of the association.
+	Notification(SctpNotification),
+}
+
+/// The SCTP notifications a TNLA association subscribes to, RFC 6458 6.1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SctpNotification {
+	AssocChange(AssocChangeState),
+	PeerAddrChange {
+		addr: Option<SocketAddr>,
+		state: PeerAddrState,
+	},
+	/// The peer sent a SHUTDOWN, no more data will be received.
+	Shutdown,
+	/// Any other notification, by its `sn_type`.
+	Other(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssocChangeState {
+	CommUp,
+	CommLost,
+	Restart,
+	ShutdownComplete,
+	CantStartAssoc,
+	Other(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerAddrState {
+	Available,
+	Unreachable,
+	Removed,
+	Added,
+	MadePrimary,
+	Confirmed,
+	Other(i32),
+}
+
+impl SctpNotification {
+	/// Parses a notification as delivered by `recvmsg` with `MSG_NOTIFICATION`
+	/// set, the fields are in host byte order.
+	pub fn parse(data: &[u8]) -> Option<Self> {
+		if data.len() < NOTIFICATION_HEADER_LEN {
+			return None;
+		}
+		let notification = match read_u16(data, 0)? {
+			SCTP_ASSOC_CHANGE => {
+				SctpNotification::AssocChange(match read_u16(data, NOTIFICATION_HEADER_LEN)? {
+					0 => AssocChangeState::CommUp,
+					1 => AssocChangeState::CommLost,
+					2 => AssocChangeState::Restart,
+					3 => AssocChangeState::ShutdownComplete,
+					4 => AssocChangeState::CantStartAssoc,
+					state => AssocChangeState::Other(state),
+				})
+			}
+			SCTP_PEER_ADDR_CHANGE => {
+				let addr = data.get(NOTIFICATION_HEADER_LEN..)?;
+				let state = i32::from_ne_bytes(
+					addr.get(SOCKADDR_STORAGE_LEN..SOCKADDR_STORAGE_LEN + 4)?
+						.try_into()
+						.ok()?,
+				);
+				SctpNotification::PeerAddrChange {
+					addr: parse_sockaddr(addr),
+					state: match state {
+						0 => PeerAddrState::Available,
+						1 => PeerAddrState::Unreachable,
+						2 => PeerAddrState::Removed,
+						3 => PeerAddrState::Added,
+						4 => PeerAddrState::MadePrimary,
+						5 => PeerAddrState::Confirmed,
+						state => PeerAddrState::Other(state),
+					},
+				}
+			}
+			SCTP_SHUTDOWN_EVENT => SctpNotification::Shutdown,
+			sn_type => SctpNotification::Other(sn_type),
+		};
+		Some(notification)
+	}
+
+	/// Whether the association is gone or going away, its gNB has to be
+	/// cleaned up.
+	pub fn ends_association(&self) -> bool {
+		matches!(
+			self,
+			SctpNotification::Shutdown
+				| SctpNotification::AssocChange(
+					AssocChangeState::CommLost
+						| AssocChangeState::ShutdownComplete
+						| AssocChangeState::CantStartAssoc
+				)
+		)
+	}
+}
+
+/// Subscribes the stream to the association change, peer address change and
+/// shutdown notifications, on top of the `sctp_sndrcvinfo` of every message.
+pub fn subscribe_notifications(stream: &SctpStream) -> io::Result<()> {
+	// The leading fields of `struct sctp_event_subscribe`: data_io,
+	// association, address, send_failure, peer_error and shutdown. Kernels
+	// take the subscription prefix given.
+	let events: [u8; 6] = [1, 1, 1, 0, 0, 1];
+	// SAFETY: `events` outlives the call and its length is passed along.
+	let res = unsafe {
+		libc::setsockopt(
+			stream.as_raw_fd(),
+			SOL_SCTP,
+			SCTP_EVENTS,
+			events.as_ptr() as *const libc::c_void,
+			mem::size_of_val(&events) as libc::socklen_t,
+		)
+	};
+	if res == 0 {
+		Ok(())
+	} else {
+		Err(io::Error::last_os_error())
+	}
+}
+
+fn read_u16(
+	data: &[u8],
+	offset: usize,
+) -> Option<u16> {
+	Some(u16::from_ne_bytes(
+		data.get(offset..offset + 2)?.try_into().ok()?,
+	))
+}
+
+/// Reads a `sockaddr_in` or `sockaddr_in6` out of a `sockaddr_storage`.
+fn parse_sockaddr(data: &[u8]) -> Option<SocketAddr> {
+	let family = read_u16(data, 0)?;
+	let port = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?);
+	match family as libc::c_int {
+		libc::AF_INET => {
+			let ip: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+			Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port))
+		}
+		libc::AF_INET6 => {
+			let ip: [u8; 16] = data.get(8..24)?.try_into().ok()?;
+			Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port))
+		}
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn notification(
+		sn_type: u16,
+		body: &[u8],
+	) -> Vec<u8> {
+		let mut data = sn_type.to_ne_bytes().to_vec();
+		data.extend_from_slice(&0u16.to_ne_bytes());
+		data.extend_from_slice(&((NOTIFICATION_HEADER_LEN + body.len()) as u32).to_ne_bytes());
+		data.extend_from_slice(body);
+		data
+	}
+
+	#[test]
+	fn test_shutdown_ends_association() {
+		// sse_assoc_id
+		let data = notification(SCTP_SHUTDOWN_EVENT, &7u32.to_ne_bytes());
+		let parsed = SctpNotification::parse(&data).unwrap();
+		assert_eq!(parsed, SctpNotification::Shutdown);
+		assert!(parsed.ends_association());
+	}
+
+	#[test]
+	fn test_assoc_change() {
+		let body = |state: u16| {
+			let mut body = state.to_ne_bytes().to_vec();
+			// sac_error, sac_outbound_streams, sac_inbound_streams and
+			// sac_assoc_id.
+			body.extend_from_slice(&[0; 10]);
+			body
+		};
+		let comm_lost =
+			SctpNotification::parse(&notification(SCTP_ASSOC_CHANGE, &body(1))).unwrap();
+		assert_eq!(
+			comm_lost,
+			SctpNotification::AssocChange(AssocChangeState::CommLost)
+		);
+		assert!(comm_lost.ends_association());
+
+		let comm_up = SctpNotification::parse(&notification(SCTP_ASSOC_CHANGE, &body(0))).unwrap();
+		assert!(!comm_up.ends_association());
+	}
+
+	#[test]
+	fn test_peer_addr_change() {
+		let mut body = vec![0; SOCKADDR_STORAGE_LEN];
+		body[..2].copy_from_slice(&(libc::AF_INET as u16).to_ne_bytes());
+		body[2..4].copy_from_slice(&38412u16.to_be_bytes());
+		body[4..8].copy_from_slice(&[10, 0, 0, 1]);
+		// spc_state, spc_error and spc_assoc_id.
+		body.extend_from_slice(&1i32.to_ne_bytes());
+		body.extend_from_slice(&[0; 8]);
+
+		let parsed = SctpNotification::parse(&notification(SCTP_PEER_ADDR_CHANGE, &body)).unwrap();
+		assert_eq!(
+			parsed,
+			SctpNotification::PeerAddrChange {
+				addr: Some("10.0.0.1:38412".parse().unwrap()),
+				state: PeerAddrState::Unreachable,
+			}
+		);
+		assert!(!parsed.ends_association());
+	}
+
+	#[test]
+	fn test_truncated_notification() {
+		assert_eq!(SctpNotification::parse(&[0x01, 0x80]), None);
+		assert_eq!(
+			SctpNotification::parse(&notification(SCTP_ASSOC_CHANGE, &[])),
+			None
+		);
+	}
+}
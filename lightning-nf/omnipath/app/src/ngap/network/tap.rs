@@ -0,0 +1,131 @@
+//! Debug tap recording the raw NGAP PDUs exchanged with gNBs.
+//!
+//! Every PDU is appended to the configured file as a single line:
+//!
+//! ```text
+//! <unix timestamp in µs> <UL|DL> <tnla id> <hex encoded pdu>
+//! ```
+//!
+//! `UL` marks PDUs received from a gNB and `DL` PDUs sent towards it. The tap
+//! is compiled in with the `ngap-tap` feature and stays inactive unless it is
+//! enabled through `configuration.ngapTap`.
+
+use std::{
+	fs::{File, OpenOptions},
+	io::{self, BufWriter, Write},
+	path::Path,
+	sync::{Mutex, OnceLock, PoisonError},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing::{info, warn};
+
+use crate::config::NgapTap;
+
+static NGAP_TAP: OnceLock<TapWriter> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	Uplink,
+	Downlink,
+}
+
+impl Direction {
+	pub fn marker(self) -> &'static str {
+		match self {
+			Direction::Uplink => "UL",
+			Direction::Downlink => "DL",
+		}
+	}
+}
+
+pub struct TapWriter {
+	file: Mutex<BufWriter<File>>,
+}
+
+impl TapWriter {
+	pub fn create(path: &Path) -> io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		Ok(Self {
+			file: Mutex::new(BufWriter::new(file)),
+		})
+	}
+
+	pub fn record(
+		&self,
+		direction: Direction,
+		tnla_id: usize,
+		data: &[u8],
+	) -> io::Result<()> {
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_micros();
+		let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+		writeln!(
+			file,
+			"{timestamp} {} {tnla_id} {}",
+			direction.marker(),
+			faster_hex::hex_string(data)
+		)?;
+		file.flush()
+	}
+}
+
+/// Installs the process wide tap if it is enabled in the configuration.
+pub fn install(config: &NgapTap) -> io::Result<()> {
+	if !config.enable {
+		return Ok(());
+	}
+	let writer = TapWriter::create(&config.path)?;
+	if NGAP_TAP.set(writer).is_err() {
+		warn!(
+			"NGAP tap already installed, ignoring {}",
+			config.path.display()
+		);
+	} else {
+		info!("NGAP tap writing to {}", config.path.display());
+	}
+	Ok(())
+}
+
+/// Records a PDU on the installed tap. Tap failures are logged and never
+/// interfere with the NGAP traffic itself.
+pub(crate) fn record(
+	direction: Direction,
+	tnla_id: usize,
+	data: &[u8],
+) {
+	if let Some(tap) = NGAP_TAP.get() {
+		if let Err(e) = tap.record(direction, tnla_id, data) {
+			warn!(diagnostic = "Unable to write NGAP tap record", error = ?e);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_tap_records_direction_markers() {
+		let path = std::env::temp_dir().join(format!("ngap-tap-{}.log", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		let tap = TapWriter::create(&path).unwrap();
+
+		tap.record(Direction::Uplink, 7, &[0x00, 0x15, 0x00, 0x2f])
+			.unwrap();
+		tap.record(Direction::Downlink, 7, &[0x20, 0x15]).unwrap();
+
+		let contents = std::fs::read_to_string(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+		let records: Vec<Vec<&str>> = contents
+			.lines()
+			.map(|line| line.split(' ').collect())
+			.collect();
+		assert_eq!(records.len(), 2);
+		assert!(records[0][0].parse::<u128>().is_ok());
+		assert_eq!(records[0][1..], ["UL", "7", "0015002f"]);
+		assert_eq!(records[1][1..], ["DL", "7", "2015"]);
+	}
+}
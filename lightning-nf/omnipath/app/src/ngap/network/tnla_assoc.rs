@@ -1,13 +1,14 @@
 use std::{
 	hash::{Hash, Hasher},
 	net::SocketAddr,
+	time::Instant,
 };
 
 use bytes::{Bytes, BytesMut};
 use counter::CounterUsize;
-use tokio_sctp::{SctpStream, SendOptions};
+use tokio_sctp::{RecvFlags, SctpStream, SendOptions};
 
-use super::TnlaError;
+use super::{FlowStats, SctpEvent, SctpNotification, TnlaError, sctp_event};
 
 const READ_BUFFER_SIZE: usize = 1024;
 const NGAP_PPID: u32 = 60;
@@ -22,6 +23,7 @@ pub struct TnlaAssociation {
 	pub local_addr: SocketAddr,
 	pub remote_addr: SocketAddr,
 	pub stream: SctpStream,
+	pub stats: FlowStats,
 }
 
 impl Hash for TnlaAssociation {
@@ -52,6 +54,8 @@ impl TnlaAssociation {
 		// Get remote address
 		let remote_addr = stream.peer_addr().map_err(TnlaError::RemoteAddressError)?;
 
+		sctp_event::subscribe_notifications(&stream).map_err(TnlaError::SubscriptionError)?;
+
 		// Generate a unique ID using the atomic counter
 		let id = TNLA_ASSOCIATION_COUNTER.increment();
 
@@ -60,30 +64,42 @@ impl TnlaAssociation {
 			local_addr,
 			remote_addr,
 			stream,
+			stats: FlowStats::default(),
 		})
 	}
 
-	/// Reads data from the SCTP stream asynchronously.
+	/// Reads the next message or notification from the SCTP stream
+	/// asynchronously.
 	/// 
 	/// # Returns
-	/// - `Ok(Some(Bytes))` - Successfully read data from the stream
+	/// - `Ok(Some(SctpEvent::Data(_)))` - Successfully read data from the stream
+	/// - `Ok(Some(SctpEvent::Notification(_)))` - The association reported a
+	///   transport event, like the peer shutting down or becoming unreachable
 	/// - `Ok(None)` - Stream has been closed by the peer (received EOF)
 	/// - `Err(TnlaError)` - An error occurred while reading from the stream
 	/// 
-	/// When this function returns `Ok(None)`, it indicates that the peer has closed their
-	/// end of the socket gracefully. The caller should handle this case by closing the
-	/// local socket and cleaning up any associated resources.
-	pub async fn read_data(&self) -> Result<Option<Bytes>, TnlaError> {
+	/// When this function returns `Ok(None)`, or a notification for which
+	/// `ends_association` holds, the peer has gone away. The caller should
+	/// handle this case by closing the local socket and cleaning up any
+	/// associated resources.
+	pub async fn read_data(&self) -> Result<Option<SctpEvent>, TnlaError> {
 		let mut buf = BytesMut::with_capacity(READ_BUFFER_SIZE);
-		let (n, _, _) = self.stream
+		let (n, _, flags) = self.stream
 			.recvmsg_eor_buf(&mut buf)
 			.await
 			.map_err(TnlaError::ReadError)?;
 		if n == 0 {
 			Ok(None)
+		} else if flags.contains(RecvFlags::NOTIFICATION) {
+			let notification = SctpNotification::parse(&buf)
+				.ok_or(TnlaError::InvalidNotification(n))?;
+			Ok(Some(SctpEvent::Notification(notification)))
 		} else {
 			let data = buf.freeze();
-			Ok(Some(data))
+			self.stats.record_read(data.len());
+			#[cfg(feature = "ngap-tap")]
+			super::tap::record(super::tap::Direction::Uplink, self.id, &data);
+			Ok(Some(SctpEvent::Data(data)))
 		}
 	}
 
@@ -93,13 +109,17 @@ impl TnlaAssociation {
 		send_options: Option<SendOptions>,
 	) -> Result<(), TnlaError> {
 		// TODO: Add handling for the stream no. here for load balancing
+		#[cfg(feature = "ngap-tap")]
+		super::tap::record(super::tap::Direction::Downlink, self.id, &data);
 		let mut send_options = send_options.unwrap_or_default();
 		send_options.ppid = NGAP_PPID;
+		let started = Instant::now();
 		let _n = self
 			.stream
 			.sendmsg(data.as_ref(), None, &send_options)
 			.await
 			.map_err(TnlaError::WriteError)?;
+		self.stats.record_write(data.len(), started.elapsed());
 		// TODO: Handle the case where the number of bytes written is not equal to the
 		// message size
 		Ok(())
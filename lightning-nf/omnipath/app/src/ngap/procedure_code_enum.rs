@@ -1,11 +1,12 @@
-use std::convert::TryFrom;
+use std::{collections::BTreeSet, convert::TryFrom};
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use ngap_models::ProcedureCode;
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ProcedureCodeEnum {
 	AMFConfigurationUpdate = 0,
 	AMFStatusIndication = 1,
@@ -139,6 +140,27 @@ impl From<ProcedureCodeEnum> for ProcedureCode {
 	}
 }
 
+/// The NGAP procedures handled for connected gNBs, every procedure when no
+/// list is configured.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ProcedureAllowList(Option<BTreeSet<ProcedureCodeEnum>>);
+
+impl ProcedureAllowList {
+	pub fn new(enabled: impl IntoIterator<Item = ProcedureCodeEnum>) -> Self {
+		Self(Some(enabled.into_iter().collect()))
+	}
+
+	pub fn is_enabled(
+		&self,
+		procedure: ProcedureCodeEnum,
+	) -> bool {
+		self.0
+			.as_ref()
+			.is_none_or(|enabled| enabled.contains(&procedure))
+	}
+}
+
 #[derive(Error, Debug)]
 pub enum ProcedureCodeEnumError {
 	#[error("Unable to perform try from on procedure code")]
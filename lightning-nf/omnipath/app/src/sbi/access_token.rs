@@ -0,0 +1,316 @@
+use std::sync::Arc;
+
+use axum::{
+	extract::{Request, State},
+	http::{
+		HeaderValue,
+		StatusCode,
+		header::{AUTHORIZATION, WWW_AUTHENTICATE},
+	},
+	middleware::Next,
+	response::Response,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, errors::ErrorKind};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::problem::problem_response;
+use crate::config::{AccessTokenConfig, NrfPublicKey};
+
+/// The claims of an NRF issued access token checked here, TS 29.510
+/// 6.3.5.2.4. `exp` and `aud` are checked on decoding.
+#[derive(Deserialize, Debug)]
+struct AccessTokenClaims {
+	/// Space separated names of the services the consumer may call.
+	scope: String,
+}
+
+struct NrfKey {
+	kid: Option<String>,
+	key: DecodingKey,
+	validation: Validation,
+}
+
+/// Verifies the bearer tokens of requests to the Namf services against the
+/// NRF public keys, decoded once and kept for the life of the server.
+#[derive(Clone)]
+pub struct AccessTokenVerifier(Arc<[NrfKey]>);
+
+impl AccessTokenVerifier {
+	/// Builds a verifier accepting tokens whose audience is one of
+	/// `audiences`, the NF type or the NF instance id of this AMF.
+	pub fn new<'a>(
+		keys: impl IntoIterator<Item = (Option<String>, &'a [u8])>,
+		audiences: &[String],
+	) -> Result<Self, AccessTokenError> {
+		keys.into_iter()
+			.map(|(kid, pem)| {
+				let (key, algorithm) = decoding_key(pem)
+					.map_err(|e| AccessTokenError::InvalidKeyError(e, kid.clone()))?;
+				let mut validation = Validation::new(algorithm);
+				validation.set_audience(audiences);
+				validation.set_required_spec_claims(&["exp", "aud"]);
+				Ok(NrfKey {
+					kid,
+					key,
+					validation,
+				})
+			})
+			.collect::<Result<Arc<[_]>, _>>()
+			.map(Self)
+	}
+
+	pub fn from_config(
+		config: &AccessTokenConfig,
+		audiences: &[String],
+	) -> Result<Self, AccessTokenError> {
+		let pems = config
+			.nrf_public_keys
+			.iter()
+			.map(|NrfPublicKey { kid, pem }| {
+				std::fs::read(pem)
+					.map(|contents| (kid.clone(), contents))
+					.map_err(|e| AccessTokenError::KeyReadError(e, pem.clone()))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		Self::new(
+			pems.iter()
+				.map(|(kid, contents)| (kid.clone(), contents.as_slice())),
+			audiences,
+		)
+	}
+
+	/// Checks the signature, expiry and audience of `token` and that its scope
+	/// includes `service_name`.
+	fn verify(
+		&self,
+		token: &str,
+		service_name: &str,
+	) -> Result<(), TokenRejection> {
+		let header = jsonwebtoken::decode_header(token).map_err(|_| TokenRejection::Malformed)?;
+		let key = match header.kid {
+			Some(kid) => self.0.iter().find(|key| key.kid.as_ref() == Some(&kid)),
+			// Without a key id the token can only be told apart with a single key.
+			None if self.0.len() == 1 => self.0.first(),
+			None => None,
+		}
+		.ok_or(TokenRejection::UnknownKey)?;
+		let claims = jsonwebtoken::decode::<AccessTokenClaims>(token, &key.key, &key.validation)
+			.map_err(|e| match e.kind() {
+				ErrorKind::ExpiredSignature => TokenRejection::Expired,
+				ErrorKind::InvalidAudience => TokenRejection::WrongAudience,
+				_ => TokenRejection::Malformed,
+			})?
+			.claims;
+		if claims.scope.split(' ').any(|scope| scope == service_name) {
+			Ok(())
+		} else {
+			Err(TokenRejection::InsufficientScope)
+		}
+	}
+}
+
+/// The PEM encoded public key of the NRF, the key type picking the
+/// algorithm.
+fn decoding_key(pem: &[u8]) -> Result<(DecodingKey, Algorithm), jsonwebtoken::errors::Error> {
+	DecodingKey::from_ec_pem(pem)
+		.map(|key| (key, Algorithm::ES256))
+		.or_else(|_| DecodingKey::from_rsa_pem(pem).map(|key| (key, Algorithm::RS256)))
+		.or_else(|_| DecodingKey::from_ed_pem(pem).map(|key| (key, Algorithm::EdDSA)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenRejection {
+	Missing,
+	Malformed,
+	UnknownKey,
+	Expired,
+	WrongAudience,
+	InsufficientScope,
+}
+
+/// Rejects requests to the Namf services without a valid access token, TS
+/// 33.501 13.4.1.2. Failures answer 401, a scope not covering the called
+/// service 403, as of RFC 6750 3.1.
+pub async fn access_token_guard(
+	State(verifier): State<AccessTokenVerifier>,
+	request: Request,
+	next: Next,
+) -> Response {
+	let path = request.uri().path();
+	let service_name = path
+		.trim_start_matches('/')
+		.split('/')
+		.next()
+		.unwrap_or_default();
+	let token = request
+		.headers()
+		.get(AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+		.ok_or(TokenRejection::Missing);
+	let verified = token.and_then(|token| verifier.verify(token, service_name));
+	match verified {
+		Ok(()) => next.run(request).await,
+		Err(rejection) => rejected(rejection),
+	}
+}
+
+fn rejected(rejection: TokenRejection) -> Response {
+	let (status, error, detail) = match rejection {
+		TokenRejection::Missing => (
+			StatusCode::UNAUTHORIZED,
+			None,
+			"The request carries no bearer access token",
+		),
+		TokenRejection::Malformed => (
+			StatusCode::UNAUTHORIZED,
+			Some("invalid_token"),
+			"The access token is malformed or its signature is invalid",
+		),
+		TokenRejection::UnknownKey => (
+			StatusCode::UNAUTHORIZED,
+			Some("invalid_token"),
+			"The access token is signed by an unknown key",
+		),
+		TokenRejection::Expired => (
+			StatusCode::UNAUTHORIZED,
+			Some("invalid_token"),
+			"The access token is expired",
+		),
+		TokenRejection::WrongAudience => (
+			StatusCode::UNAUTHORIZED,
+			Some("invalid_token"),
+			"The access token is not meant for this NF",
+		),
+		TokenRejection::InsufficientScope => (
+			StatusCode::FORBIDDEN,
+			Some("insufficient_scope"),
+			"The access token scope does not include the called service",
+		),
+	};
+	let mut response = problem_response(status, None, detail.to_owned());
+	let challenge = match error {
+		Some(error) => format!("Bearer error=\"{error}\""),
+		None => "Bearer".to_owned(),
+	};
+	// Safety: the challenges are plain ASCII.
+	response
+		.headers_mut()
+		.insert(WWW_AUTHENTICATE, HeaderValue::try_from(challenge).unwrap());
+	response
+}
+
+#[derive(Error, Debug)]
+pub enum AccessTokenError {
+	#[error("KeyReadError: Unable to read the Nrf public key {1}")]
+	KeyReadError(#[source] std::io::Error, String),
+
+	#[error("InvalidKeyError: Nrf public key {1:?} is no PEM encoded EC, RSA or Ed25519 key")]
+	InvalidKeyError(#[source] jsonwebtoken::errors::Error, Option<String>),
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{SystemTime, UNIX_EPOCH};
+
+	use axum::{Router, body::Body, middleware::from_fn_with_state, routing::get};
+	use jsonwebtoken::{EncodingKey, Header};
+	use serde_json::json;
+	use tower::ServiceExt;
+
+	use super::*;
+
+	struct Nrf(rcgen::KeyPair);
+
+	impl Nrf {
+		fn new() -> Self {
+			Self(rcgen::KeyPair::generate().unwrap())
+		}
+
+		/// A token for the `namf-comm` service to `aud`, expiring `expires_in`
+		/// seconds from now.
+		fn token(
+			&self,
+			aud: &str,
+			expires_in: i64,
+		) -> String {
+			let now = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap()
+				.as_secs() as i64;
+			let claims = json!({
+				"iss": "nrf",
+				"sub": "smf",
+				"aud": aud,
+				"scope": "namf-comm namf-evts",
+				"exp": now + expires_in,
+			});
+			let key = EncodingKey::from_ec_pem(self.0.serialize_pem().as_bytes()).unwrap();
+			jsonwebtoken::encode(&Header::new(Algorithm::ES256), &claims, &key).unwrap()
+		}
+
+		fn router(&self) -> Router {
+			let verifier = AccessTokenVerifier::new(
+				[(None, self.0.public_key_pem().as_bytes())],
+				&["AMF".to_owned()],
+			)
+			.unwrap();
+			Router::new()
+				.route("/namf-comm/v1/ping", get(|| async { "pong" }))
+				.layer(from_fn_with_state(verifier, access_token_guard))
+		}
+	}
+
+	async fn status_with(
+		router: Router,
+		token: &str,
+	) -> StatusCode {
+		let request = Request::builder()
+			.uri("/namf-comm/v1/ping")
+			.header(AUTHORIZATION, format!("Bearer {token}"))
+			.body(Body::empty())
+			.unwrap();
+		router.oneshot(request).await.unwrap().status()
+	}
+
+	#[tokio::test]
+	async fn test_valid_token_is_accepted() {
+		let nrf = Nrf::new();
+		assert_eq!(
+			status_with(nrf.router(), &nrf.token("AMF", 3600)).await,
+			StatusCode::OK
+		);
+	}
+
+	#[tokio::test]
+	async fn test_expired_token_is_rejected() {
+		let nrf = Nrf::new();
+		// Past the default leeway of a minute.
+		assert_eq!(
+			status_with(nrf.router(), &nrf.token("AMF", -120)).await,
+			StatusCode::UNAUTHORIZED
+		);
+	}
+
+	#[tokio::test]
+	async fn test_wrong_audience_token_is_rejected() {
+		let nrf = Nrf::new();
+		let response = nrf
+			.router()
+			.oneshot(
+				Request::builder()
+					.uri("/namf-comm/v1/ping")
+					.header(AUTHORIZATION, format!("Bearer {}", nrf.token("SMF", 3600)))
+					.body(Body::empty())
+					.unwrap(),
+			)
+			.await
+			.unwrap();
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+		assert_eq!(
+			response.headers()[WWW_AUTHENTICATE],
+			"Bearer error=\"invalid_token\""
+		);
+	}
+}
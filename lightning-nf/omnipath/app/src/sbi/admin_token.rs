@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use axum::{
+	extract::{Request, State},
+	http::{
+		HeaderValue,
+		StatusCode,
+		header::{AUTHORIZATION, WWW_AUTHENTICATE},
+	},
+	middleware::Next,
+	response::Response,
+};
+use thiserror::Error;
+
+use super::problem::problem_response;
+use crate::config::AdminConfig;
+
+/// The bearer token the operator requests under `/admin` carry.
+#[derive(Clone)]
+pub struct AdminToken(Arc<[u8]>);
+
+impl AdminToken {
+	pub fn new(token: &str) -> Self {
+		Self(token.as_bytes().into())
+	}
+
+	pub fn from_config(config: &AdminConfig) -> Result<Self, AdminTokenError> {
+		let contents = std::fs::read_to_string(&config.token_file)
+			.map_err(|e| AdminTokenError::ReadError(e, config.token_file.clone()))?;
+		let token = contents.trim();
+		if token.is_empty() {
+			return Err(AdminTokenError::EmptyToken(config.token_file.clone()));
+		}
+		Ok(Self::new(token))
+	}
+
+	/// Compares in constant time, not to leak how much of a guess matched.
+	fn matches(
+		&self,
+		token: &[u8],
+	) -> bool {
+		self.0.len() == token.len()
+			&& self
+				.0
+				.iter()
+				.zip(token)
+				.fold(0, |diff, (a, b)| diff | (a ^ b))
+				== 0
+	}
+}
+
+/// Rejects operator requests without the admin token, 401 as of RFC 6750 3.1.
+/// Without a token configured the admin API is disabled and every request
+/// answers 403.
+pub async fn admin_token_guard(
+	State(admin_token): State<Option<AdminToken>>,
+	request: Request,
+	next: Next,
+) -> Response {
+	let Some(admin_token) = admin_token else {
+		return problem_response(
+			StatusCode::FORBIDDEN,
+			None,
+			"The admin API is disabled, no admin token is configured".to_owned(),
+		);
+	};
+	let token = request
+		.headers()
+		.get(AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "));
+	if token.is_some_and(|token| admin_token.matches(token.as_bytes())) {
+		return next.run(request).await;
+	}
+	let mut response = problem_response(
+		StatusCode::UNAUTHORIZED,
+		None,
+		"The request carries no valid admin token".to_owned(),
+	);
+	response
+		.headers_mut()
+		.insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+	response
+}
+
+#[derive(Error, Debug)]
+pub enum AdminTokenError {
+	#[error("ReadError: Unable to read the admin token file {1}")]
+	ReadError(#[source] std::io::Error, String),
+
+	#[error("EmptyToken: The admin token file {0} is empty")]
+	EmptyToken(String),
+}
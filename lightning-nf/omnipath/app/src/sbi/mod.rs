@@ -0,0 +1,14 @@
+mod access_token;
+mod admin_token;
+pub mod namf_comm;
+mod problem;
+mod server;
+mod validated;
+mod version;
+
+pub use access_token::{AccessTokenError, AccessTokenVerifier, access_token_guard};
+pub use admin_token::{AdminToken, AdminTokenError, admin_token_guard};
+pub use problem::InvalidParam;
+pub use server::{admin_router, router};
+pub use validated::{ValidatedJson, invalid_params};
+pub use version::{ApiVersions, api_version_guard};
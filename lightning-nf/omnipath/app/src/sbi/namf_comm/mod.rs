@@ -0,0 +1,6 @@
+mod models;
+mod n1_n2_message;
+mod ngap;
+
+pub use models::*;
+pub use n1_n2_message::{N1Delivery, N1DeliveryError, n1_n2_message_transfer};
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Subset of `N1N2MessageTransferReqData`, TS 29.518 6.1.6.2.25.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct N1N2MessageTransferReqData {
+	pub n1_message_container: Option<N1MessageContainer>,
+	pub n2_info_container: Option<N2InfoContainer>,
+	pub pdu_session_id: Option<u8>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct N1MessageContainer {
+	pub n1_message_class: N1MessageClass,
+	pub n1_message_content: RefToBinaryData,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum N1MessageClass {
+	#[serde(rename = "5GMM")]
+	FiveGmm,
+	#[serde(rename = "SM")]
+	Sm,
+	#[serde(rename = "LPP")]
+	Lpp,
+	#[serde(rename = "SMS")]
+	Sms,
+	#[serde(rename = "UPDP")]
+	Updp,
+	#[serde(rename = "LCS")]
+	Lcs,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct N2InfoContainer {
+	pub n2_information_class: String,
+}
+
+/// Points at the multipart body part holding the binary content, by its
+/// `Content-ID`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RefToBinaryData {
+	pub content_id: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct N1N2MessageTransferRspData {
+	pub cause: N1N2MessageTransferCause,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum N1N2MessageTransferCause {
+	N1N2TransferInitiated,
+	AttemptingToReachUe,
+}
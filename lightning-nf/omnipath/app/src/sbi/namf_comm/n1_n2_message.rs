@@ -0,0 +1,381 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use axum::{
+	Json,
+	body::{Bytes, to_bytes},
+	extract::{Path, Request, State},
+	http::{StatusCode, header::CONTENT_TYPE},
+	response::{IntoResponse, Response},
+};
+use mime::Mime;
+use multer::{Constraints, SizeLimit};
+use thiserror::Error;
+use tracing::error;
+
+use super::models::{
+	N1MessageClass,
+	N1N2MessageTransferCause,
+	N1N2MessageTransferReqData,
+	N1N2MessageTransferRspData,
+};
+use crate::{
+	nas::{PayloadContainerType, build_dl_nas_transport},
+	ngap::{core::paging::PagingError, engine::controller::NgapWriteError},
+	sbi::problem::problem_response,
+	utils::models::Supi,
+};
+
+const MAX_TRANSFER_SIZE: usize = 1 << 20;
+
+/// Delivers a NAS PDU to a CM-CONNECTED UE over its NGAP association.
+pub trait N1Delivery: Send + Sync + 'static {
+	fn deliver_n1(
+		&self,
+		supi: &Supi,
+		nas_pdu: Vec<u8>,
+	) -> impl Future<Output = Result<(), N1DeliveryError>> + Send;
+
+	/// Pages a CM-IDLE UE, for it to come back to CM-CONNECTED.
+	fn page_ue(
+		&self,
+		supi: &Supi,
+	) -> impl Future<Output = Result<(), N1DeliveryError>> + Send;
+}
+
+#[derive(Debug, Error)]
+pub enum N1DeliveryError {
+	#[error("UeNotFound: No UE context for {0}")]
+	UeNotFound(String),
+
+	#[error("UeInCmIdleState: Ue {0} has no NGAP association")]
+	UeInCmIdleState(String),
+
+	#[error("NoServedTais: No TAI is served to page Ue {0} in")]
+	NoServedTais(String),
+
+	#[error("PagingError: {0}")]
+	PagingError(#[from] PagingError),
+
+	#[error("WriteError: Unable to send the Downlink Nas Transport")]
+	WriteError(#[from] NgapWriteError),
+}
+
+/// Namf_Communication N1N2MessageTransfer, TS 29.518 5.2.2.3.1.
+///
+/// The JSON part references the binary N1 content by `Content-ID` in a
+/// `multipart/related` body. The N1 content is sent in a DL NAS Transport to
+/// CM-CONNECTED UEs, and N2 information is rejected.
+///
+/// CM-IDLE UEs are paged instead, TS 29.518 5.2.2.3.1.2. The N1 content is not
+/// kept for a later Service Request, so the consumer transfers it again once
+/// the UE is reachable.
+pub async fn n1_n2_message_transfer<T: N1Delivery>(
+	State(delivery): State<Arc<T>>,
+	Path((_api_version, ue_context_id)): Path<(String, String)>,
+	request: Request,
+) -> Response {
+	let (transfer, mut parts) = match parse_transfer(request).await {
+		Ok(parsed) => parsed,
+		Err(detail) => {
+			return problem_response(StatusCode::BAD_REQUEST, Some("INVALID_MSG_FORMAT"), detail);
+		}
+	};
+
+	if transfer.n2_info_container.is_some() {
+		return problem_response(
+			StatusCode::NOT_IMPLEMENTED,
+			None,
+			"N2 information transfer is not supported yet".to_owned(),
+		);
+	}
+	let Some(n1_container) = transfer.n1_message_container else {
+		return problem_response(
+			StatusCode::BAD_REQUEST,
+			Some("MANDATORY_IE_MISSING"),
+			"n1MessageContainer is missing".to_owned(),
+		);
+	};
+	let Some(payload_container_type) = payload_container_type(n1_container.n1_message_class) else {
+		return problem_response(
+			StatusCode::NOT_IMPLEMENTED,
+			None,
+			"5GMM content is not relayed to the UE".to_owned(),
+		);
+	};
+	let pdu_session_id = transfer.pdu_session_id;
+	if payload_container_type == PayloadContainerType::N1SmInformation && pdu_session_id.is_none() {
+		return problem_response(
+			StatusCode::BAD_REQUEST,
+			Some("MANDATORY_IE_MISSING"),
+			"pduSessionId is missing for SM content".to_owned(),
+		);
+	}
+	let content_id = &n1_container.n1_message_content.content_id;
+	let Some(n1_message) = parts.remove(content_id) else {
+		return problem_response(
+			StatusCode::BAD_REQUEST,
+			Some("MANDATORY_IE_MISSING"),
+			format!("No body part with Content-ID {content_id}"),
+		);
+	};
+	let supi = match ue_context_id.parse::<Supi>() {
+		Ok(supi) => supi,
+		Err(err) => {
+			return problem_response(
+				StatusCode::NOT_FOUND,
+				Some("CONTEXT_NOT_FOUND"),
+				err.to_string(),
+			);
+		}
+	};
+
+	let nas_pdu = match build_dl_nas_transport(
+		payload_container_type,
+		&n1_message,
+		pdu_session_id.filter(|_| payload_container_type == PayloadContainerType::N1SmInformation),
+	) {
+		Ok(nas_pdu) => nas_pdu,
+		Err(err) => {
+			return problem_response(
+				StatusCode::BAD_REQUEST,
+				Some("INVALID_MSG_FORMAT"),
+				err.to_string(),
+			);
+		}
+	};
+
+	match delivery.deliver_n1(&supi, nas_pdu).await {
+		Ok(()) => (
+			StatusCode::OK,
+			Json(N1N2MessageTransferRspData {
+				cause: N1N2MessageTransferCause::N1N2TransferInitiated,
+			}),
+		)
+			.into_response(),
+		Err(err @ N1DeliveryError::UeNotFound(_)) => problem_response(
+			StatusCode::NOT_FOUND,
+			Some("CONTEXT_NOT_FOUND"),
+			err.to_string(),
+		),
+		Err(N1DeliveryError::UeInCmIdleState(_)) => page_ue(delivery.as_ref(), &supi).await,
+		Err(err) => {
+			error!("N1N2MessageTransfer to {} failed: {:?}", supi, err);
+			problem_response(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				Some("SYSTEM_FAILURE"),
+				err.to_string(),
+			)
+		}
+	}
+}
+
+/// Pages the CM-IDLE UE, answering that the AMF attempts to reach it.
+async fn page_ue<T: N1Delivery>(
+	delivery: &T,
+	supi: &Supi,
+) -> Response {
+	match delivery.page_ue(supi).await {
+		Ok(()) => (
+			StatusCode::ACCEPTED,
+			Json(N1N2MessageTransferRspData {
+				cause: N1N2MessageTransferCause::AttemptingToReachUe,
+			}),
+		)
+			.into_response(),
+		Err(err) => {
+			error!("Paging {} failed: {:?}", supi, err);
+			problem_response(
+				StatusCode::INTERNAL_SERVER_ERROR,
+				Some("SYSTEM_FAILURE"),
+				err.to_string(),
+			)
+		}
+	}
+}
+
+/// The DL NAS Transport payload container of the N1 content, 5GMM content is
+/// not carried in one.
+fn payload_container_type(n1_message_class: N1MessageClass) -> Option<PayloadContainerType> {
+	match n1_message_class {
+		N1MessageClass::FiveGmm => None,
+		N1MessageClass::Sm => Some(PayloadContainerType::N1SmInformation),
+		N1MessageClass::Lpp => Some(PayloadContainerType::Lpp),
+		N1MessageClass::Sms => Some(PayloadContainerType::Sms),
+		N1MessageClass::Updp => Some(PayloadContainerType::UePolicyContainer),
+		N1MessageClass::Lcs => Some(PayloadContainerType::LocationServices),
+	}
+}
+
+/// Splits the request into its JSON data and the binary parts keyed by
+/// `Content-ID`. A plain `application/json` body carries no binary parts.
+async fn parse_transfer(
+	request: Request
+) -> Result<(N1N2MessageTransferReqData, HashMap<String, Bytes>), String> {
+	let boundary = request
+		.headers()
+		.get(CONTENT_TYPE)
+		.and_then(|content_type| content_type.to_str().ok())
+		.and_then(|content_type| content_type.parse::<Mime>().ok())
+		.filter(|mime| mime.type_() == mime::MULTIPART)
+		.and_then(|mime| mime.get_param(mime::BOUNDARY).map(|b| b.to_string()));
+
+	let Some(boundary) = boundary else {
+		let body = to_bytes(request.into_body(), MAX_TRANSFER_SIZE)
+			.await
+			.map_err(|e| e.to_string())?;
+		let transfer = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+		return Ok((transfer, HashMap::new()));
+	};
+
+	let constraints =
+		Constraints::new().size_limit(SizeLimit::new().whole_stream(MAX_TRANSFER_SIZE as u64));
+	let mut multipart = multer::Multipart::with_constraints(
+		request.into_body().into_data_stream(),
+		boundary,
+		constraints,
+	);
+	let mut transfer = None;
+	let mut parts = HashMap::new();
+	while let Some(field) = multipart.next_field().await.map_err(|e| e.to_string())? {
+		let is_json = field
+			.content_type()
+			.is_some_and(|mime| mime.essence_str() == mime::APPLICATION_JSON.essence_str());
+		let content_id = field
+			.headers()
+			.get("content-id")
+			.and_then(|content_id| content_id.to_str().ok())
+			.map(|content_id| content_id.trim_matches(['<', '>']).to_owned());
+		let data = field.bytes().await.map_err(|e| e.to_string())?;
+		match content_id {
+			_ if is_json && transfer.is_none() => {
+				transfer = Some(serde_json::from_slice(&data).map_err(|e| e.to_string())?);
+			}
+			Some(content_id) => {
+				parts.insert(content_id, data);
+			}
+			None => (),
+		}
+	}
+	let transfer = transfer.ok_or_else(|| "No JSON part in the multipart body".to_owned())?;
+	Ok((transfer, parts))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use axum::{Router, body::Body, routing::post};
+	use tower::ServiceExt;
+
+	use super::*;
+
+	const BOUNDARY: &str = "n1n2-boundary";
+
+	/// Records the delivered NAS PDUs and the paged UEs, the UE being either
+	/// connected or idle.
+	struct ScriptedDelivery {
+		idle: bool,
+		delivered: Mutex<Vec<(String, Vec<u8>)>>,
+		paged: Mutex<Vec<String>>,
+	}
+
+	impl N1Delivery for ScriptedDelivery {
+		async fn deliver_n1(
+			&self,
+			supi: &Supi,
+			nas_pdu: Vec<u8>,
+		) -> Result<(), N1DeliveryError> {
+			if self.idle {
+				return Err(N1DeliveryError::UeInCmIdleState(supi.to_string()));
+			}
+			self.delivered
+				.lock()
+				.unwrap()
+				.push((supi.to_string(), nas_pdu));
+			Ok(())
+		}
+
+		async fn page_ue(
+			&self,
+			supi: &Supi,
+		) -> Result<(), N1DeliveryError> {
+			self.paged.lock().unwrap().push(supi.to_string());
+			Ok(())
+		}
+	}
+
+	fn transfer_request(n1_message: &[u8]) -> Request {
+		let json = r#"{"n1MessageContainer":{"n1MessageClass":"SM","n1MessageContent":{"contentId":"n1msg"}},"pduSessionId":1}"#;
+		let mut body = format!(
+			"--{BOUNDARY}\r\nContent-Type: \
+			 application/json\r\n\r\n{json}\r\n--{BOUNDARY}\r\nContent-Type: \
+			 application/vnd.3gpp.5gnas\r\nContent-Id: n1msg\r\n\r\n"
+		)
+		.into_bytes();
+		body.extend_from_slice(n1_message);
+		body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+		Request::builder()
+			.method("POST")
+			.uri("/namf-comm/v1/ue-contexts/imsi-208930000000001/n1-n2-messages")
+			.header(
+				CONTENT_TYPE,
+				format!("multipart/related; boundary={BOUNDARY}"),
+			)
+			.body(Body::from(body))
+			.unwrap()
+	}
+
+	async fn transfer(idle: bool) -> (Response, Arc<ScriptedDelivery>) {
+		let delivery = Arc::new(ScriptedDelivery {
+			idle,
+			delivered: Mutex::new(Vec::new()),
+			paged: Mutex::new(Vec::new()),
+		});
+		let router = Router::new()
+			.route(
+				"/namf-comm/:api_version/ue-contexts/:ue_context_id/n1-n2-messages",
+				post(n1_n2_message_transfer::<ScriptedDelivery>),
+			)
+			.with_state(delivery.clone());
+		let response = router
+			.oneshot(transfer_request(&[0x2e, 0x01, 0x01, 0xc1]))
+			.await
+			.unwrap();
+		(response, delivery)
+	}
+
+	async fn cause_of(response: Response) -> String {
+		let body = to_bytes(response.into_body(), MAX_TRANSFER_SIZE)
+			.await
+			.unwrap();
+		let rsp: serde_json::Value = serde_json::from_slice(&body).unwrap();
+		rsp["cause"].as_str().unwrap().to_owned()
+	}
+
+	#[tokio::test]
+	async fn test_transfer_to_connected_ue() {
+		let (response, delivery) = transfer(false).await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(cause_of(response).await, "N1_N2_TRANSFER_INITIATED");
+		let delivered = delivery.delivered.lock().unwrap();
+		assert_eq!(delivered[0].0, "imsi-208930000000001");
+		// The SM content goes in a DL NAS Transport along its PDU session ID.
+		assert_eq!(
+			delivered[0].1,
+			[
+				0x7e, 0x00, 0x68, 0x01, 0x00, 0x04, 0x2e, 0x01, 0x01, 0xc1, 0x12, 0x01
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_transfer_to_idle_ue_pages_it() {
+		let (response, delivery) = transfer(true).await;
+
+		assert_eq!(response.status(), StatusCode::ACCEPTED);
+		assert_eq!(cause_of(response).await, "ATTEMPTING_TO_REACH_UE");
+		assert!(delivery.delivered.lock().unwrap().is_empty());
+		assert_eq!(*delivery.paged.lock().unwrap(), ["imsi-208930000000001"]);
+	}
+}
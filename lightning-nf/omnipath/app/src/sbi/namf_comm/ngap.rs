@@ -0,0 +1,65 @@
+use nonempty::NonEmpty;
+use tokio::sync::OwnedRwLockWriteGuard;
+
+use super::{N1Delivery, N1DeliveryError};
+use crate::{
+	context::{NgapContext, UeContext, app_context::get_global_app_context},
+	ngap::{core::paging::PagingError, manager::PinnedSendSyncFuture},
+	utils::models::Supi,
+};
+
+impl N1Delivery for NgapContext {
+	async fn deliver_n1(
+		&self,
+		supi: &Supi,
+		nas_pdu: Vec<u8>,
+	) -> Result<(), N1DeliveryError> {
+		let not_found = || N1DeliveryError::UeNotFound(supi.to_string());
+		let amf_ue_ngap_id = self
+			.ue_identities
+			.find_by_supi(supi)
+			.ok_or_else(not_found)?;
+		// Released UEs stay indexed by their identities, without an NGAP
+		// association.
+		let idle = || N1DeliveryError::UeInCmIdleState(supi.to_string());
+		let (global_ran_node_id, ran_ue_ngap_id) =
+			self.find_ue(&amf_ue_ngap_id).await.ok_or_else(idle)?;
+		let gnb_context = self
+			.gnb_contexts
+			.read_async(&global_ran_node_id, |_, gnb| gnb.clone())
+			.await
+			.ok_or_else(idle)?;
+
+		let future_closure = move |ue_context: OwnedRwLockWriteGuard<UeContext>| {
+			Box::pin(async move { ue_context.send_downlink_nas_transport(nas_pdu).await })
+				as PinnedSendSyncFuture<_>
+		};
+		gnb_context
+			.ue_context_manager
+			.with_context(ran_ue_ngap_id, future_closure)
+			.await
+			.map_err(|_| idle())??;
+		self.ue_inactivity.seen(amf_ue_ngap_id);
+		Ok(())
+	}
+	/// Pages the UE by the 5G-GUTI it was last given across every TAI the AMF
+	/// serves, the registration area of a released UE not being kept.
+	async fn page_ue(
+		&self,
+		supi: &Supi,
+	) -> Result<(), N1DeliveryError> {
+		let amf_ue_ngap_id = self
+			.ue_identities
+			.find_by_supi(supi)
+			.ok_or_else(|| N1DeliveryError::UeNotFound(supi.to_string()))?;
+		let guti = self
+			.ue_identities
+			.guti_of(&amf_ue_ngap_id)
+			.ok_or(PagingError::NoGuti)?;
+		let app_context = get_global_app_context().await;
+		let tai_list = NonEmpty::from_vec(app_context.get_config().support_tai_list.clone())
+			.ok_or_else(|| N1DeliveryError::NoServedTais(supi.to_string()))?;
+		self.send_paging(&guti, &tai_list).await?;
+		Ok(())
+	}
+}
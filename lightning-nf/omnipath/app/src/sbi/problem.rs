@@ -0,0 +1,47 @@
+use axum::{
+	http::{StatusCode, header::CONTENT_TYPE},
+	response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+/// `InvalidParam` of a `ProblemDetails`, TS 29.571 5.2.4.2.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct InvalidParam {
+	/// JSON pointer to the offending attribute, e.g. `/eventList`.
+	pub param: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub reason: Option<String>,
+}
+
+/// Builds an `application/problem+json` response, TS 29.571 5.2.4.1.
+pub fn problem_response(
+	status: StatusCode,
+	cause: Option<&str>,
+	detail: String,
+) -> Response {
+	problem_with_params(status, cause, detail, &[])
+}
+
+pub fn problem_with_params(
+	status: StatusCode,
+	cause: Option<&str>,
+	detail: String,
+	invalid_params: &[InvalidParam],
+) -> Response {
+	let mut problem = serde_json::json!({
+		"status": status.as_u16(),
+		"detail": detail,
+	});
+	if let Some(cause) = cause {
+		problem["cause"] = cause.into();
+	}
+	if !invalid_params.is_empty() {
+		problem["invalidParams"] = serde_json::json!(invalid_params);
+	}
+	(
+		status,
+		[(CONTENT_TYPE, "application/problem+json")],
+		problem.to_string(),
+	)
+		.into_response()
+}
@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use axum::{
+	Router,
+	extract::Path,
+	http::StatusCode,
+	middleware::from_fn_with_state,
+	routing::{delete, get, post},
+};
+use client::{nrf_client::NrfClient, token_store::TokenStoreStats};
+
+use super::{
+	AccessTokenVerifier,
+	AdminToken,
+	ApiVersions,
+	access_token_guard,
+	admin_token_guard,
+	api_version_guard,
+	namf_comm::{self, N1Delivery},
+};
+use crate::{
+	context::{ForceReleaseError, NgapContext, Readiness},
+	ngap::core::ng_reset::{NgResetError, ResetScope},
+	utils::models::UeIdentity,
+};
+
+/// Routes of the Namf services, guarded by the advertised API versions and,
+/// with a verifier, by access tokens.
+pub fn router<T: N1Delivery>(
+	api_versions: ApiVersions,
+	n1_delivery: Arc<T>,
+	access_tokens: Option<AccessTokenVerifier>,
+) -> Router {
+	let router = Router::new()
+		.route(
+			"/namf-comm/:api_version/ue-contexts/:ue_context_id/n1-n2-messages",
+			post(namf_comm::n1_n2_message_transfer::<T>),
+		)
+		.layer(from_fn_with_state(api_versions, api_version_guard))
+		.with_state(n1_delivery);
+	match access_tokens {
+		Some(verifier) => router.layer(from_fn_with_state(verifier, access_token_guard)),
+		None => router,
+	}
+}
+
+/// `/ready` answers only once the AMF is registered and serving and `/metrics`
+/// exposes the cached NRF access tokens in the Prometheus text format.
+///
+/// The operator actions sit under `/admin`, refused without `admin_token`: a
+/// `DELETE` of `/admin/ue-contexts/{supi or 5g-guti}` evicts the UE and a
+/// `POST` to `/admin/gnbs/{gnb id}/reset` sends the gNB a full NG Reset.
+pub fn admin_router(
+	readiness: Readiness,
+	nrf_client: Arc<NrfClient>,
+	ngap_context: Arc<NgapContext>,
+	admin_token: Option<AdminToken>,
+) -> Router {
+	let release_context = ngap_context.clone();
+	let actions = Router::new()
+		.route(
+			"/ue-contexts/:ue_identity",
+			delete(move |Path(ue_identity): Path<String>| async move {
+				force_release_ue(&release_context, &ue_identity).await
+			}),
+		)
+		.route(
+			"/gnbs/:gnb_id/reset",
+			post(
+				move |Path(gnb_id): Path<u32>| async move { reset_gnb(&ngap_context, gnb_id).await },
+			),
+		)
+		.layer(from_fn_with_state(admin_token, admin_token_guard));
+	Router::new()
+		.route(
+			"/ready",
+			get(move || async move {
+				if readiness.is_ready() {
+					StatusCode::OK
+				} else {
+					StatusCode::SERVICE_UNAVAILABLE
+				}
+			}),
+		)
+		.route(
+			"/metrics",
+			get(move || async move { token_metrics(nrf_client.token_store_stats().await) }),
+		)
+		.nest("/admin", actions)
+}
+
+async fn force_release_ue(
+	ngap_context: &NgapContext,
+	ue_identity: &str,
+) -> StatusCode {
+	let Ok(ue_identity) = ue_identity.parse::<UeIdentity>() else {
+		return StatusCode::BAD_REQUEST;
+	};
+	match ngap_context.force_release_ue(&ue_identity).await {
+		Ok(()) => StatusCode::NO_CONTENT,
+		Err(ForceReleaseError::UeNotFound(_)) => StatusCode::NOT_FOUND,
+	}
+}
+
+async fn reset_gnb(
+	ngap_context: &NgapContext,
+	gnb_id: u32,
+) -> StatusCode {
+	let Some(global_ran_node_id) = ngap_context.find_gnb_by_gnb_id(gnb_id).await else {
+		return StatusCode::NOT_FOUND;
+	};
+	match ngap_context
+		.reset_gnb(&global_ran_node_id, ResetScope::Full)
+		.await
+	{
+		Ok(()) => StatusCode::NO_CONTENT,
+		Err(NgResetError::UnknownGnb(_)) => StatusCode::NOT_FOUND,
+		Err(NgResetError::ResetInProgress) => StatusCode::CONFLICT,
+		Err(NgResetError::NotAcknowledged) => StatusCode::GATEWAY_TIMEOUT,
+		Err(NgResetError::WriteError(_) | NgResetError::UnexpectedAcknowledge) => {
+			StatusCode::BAD_GATEWAY
+		}
+	}
+}
+
+fn token_metrics(stats: TokenStoreStats) -> String {
+	let TokenStoreStats {
+		ready,
+		updating,
+		failed,
+	} = stats;
+	let mut metrics = String::from("# TYPE nrf_access_tokens gauge\n");
+	for (state, count) in [("ready", ready), ("updating", updating), ("failed", failed)] {
+		metrics.push_str(&format!("nrf_access_tokens{{state=\"{state}\"}} {count}\n"));
+	}
+	metrics
+}
+
+#[cfg(test)]
+mod tests {
+	use nf_base::SbiServerRunner;
+	use oasbi::common::NfType;
+	use tokio_util::sync::CancellationToken;
+	use tower::ServiceExt;
+
+	use super::*;
+	use crate::{sbi::namf_comm::N1DeliveryError, utils::models::Supi};
+
+	struct NoUes;
+
+	impl N1Delivery for NoUes {
+		async fn deliver_n1(
+			&self,
+			supi: &Supi,
+			_nas_pdu: Vec<u8>,
+		) -> Result<(), N1DeliveryError> {
+			Err(N1DeliveryError::UeNotFound(supi.to_string()))
+		}
+
+		async fn page_ue(
+			&self,
+			supi: &Supi,
+		) -> Result<(), N1DeliveryError> {
+			Err(N1DeliveryError::UeNotFound(supi.to_string()))
+		}
+	}
+
+	#[tokio::test]
+	async fn test_server_binds_and_serves_health() {
+		let readiness = Readiness::new();
+		let server = SbiServerRunner::bind(
+			"127.0.0.1:0".parse().unwrap(),
+			router(ApiVersions::from_majors([1]), Arc::new(NoUes), None),
+			None,
+		)
+		.await
+		.unwrap()
+		.with_admin_routes(admin_router(
+			readiness.clone(),
+			Arc::new(NrfClient::new(
+				reqwest::Client::new(),
+				"http://127.0.0.1:1".parse().unwrap(),
+				NfType::Amf,
+			)),
+			Arc::new(NgapContext::test_fixture()),
+			Some(AdminToken::new("secret")),
+		));
+		let addr = server.local_addr().unwrap();
+		let shutdown = CancellationToken::new();
+		let handle = tokio::spawn(server.serve(shutdown.clone()));
+
+		let status_of = |path: &str| {
+			let url = format!("http://{addr}{path}");
+			async move { reqwest::get(url).await.unwrap().status() }
+		};
+		assert_eq!(status_of("/health").await, StatusCode::OK);
+		assert_eq!(status_of("/ready").await, StatusCode::SERVICE_UNAVAILABLE);
+
+		readiness.set_registered(true);
+		readiness.set_serving(true);
+		assert_eq!(status_of("/ready").await, StatusCode::OK);
+
+		let metrics = reqwest::get(format!("http://{addr}/metrics"))
+			.await
+			.unwrap()
+			.text()
+			.await
+			.unwrap();
+		assert!(metrics.contains("nrf_access_tokens{state=\"ready\"} 0\n"));
+
+		let release_status_of = |ue_identity: &str, token: Option<&str>| {
+			let url = format!("http://{addr}/admin/ue-contexts/{ue_identity}");
+			let request = reqwest::Client::new().delete(url);
+			let request = match token {
+				Some(token) => request.bearer_auth(token),
+				None => request,
+			};
+			async move { request.send().await.unwrap().status() }
+		};
+		let supi = "imsi-208930000000001";
+		assert_eq!(
+			release_status_of(supi, None).await,
+			StatusCode::UNAUTHORIZED
+		);
+		assert_eq!(
+			release_status_of(supi, Some("secret")).await,
+			StatusCode::NOT_FOUND
+		);
+		assert_eq!(
+			release_status_of("unknown", Some("secret")).await,
+			StatusCode::BAD_REQUEST
+		);
+
+		shutdown.cancel();
+		handle.await.unwrap().unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_unauthenticated_ue_release_is_refused() {
+		let admin_router_with = |admin_token| {
+			admin_router(
+				Readiness::new(),
+				Arc::new(NrfClient::new(
+					reqwest::Client::new(),
+					"http://127.0.0.1:1".parse().unwrap(),
+					NfType::Amf,
+				)),
+				Arc::new(NgapContext::test_fixture()),
+				admin_token,
+			)
+		};
+		let release = |router: Router, token: Option<&str>| {
+			let mut request =
+				axum::http::Request::delete("/admin/ue-contexts/imsi-208930000000001");
+			if let Some(token) = token {
+				request = request.header("authorization", format!("Bearer {token}"));
+			}
+			let request = request.body(axum::body::Body::empty()).unwrap();
+			async move { router.oneshot(request).await.unwrap().status() }
+		};
+
+		let router = admin_router_with(Some(AdminToken::new("secret")));
+		assert_eq!(
+			release(router.clone(), None).await,
+			StatusCode::UNAUTHORIZED
+		);
+		assert_eq!(
+			release(router.clone(), Some("guess")).await,
+			StatusCode::UNAUTHORIZED
+		);
+		assert_eq!(release(router, Some("secret")).await, StatusCode::NOT_FOUND);
+		// Without a token configured nobody gets in.
+		let router = admin_router_with(None);
+		assert_eq!(release(router, Some("secret")).await, StatusCode::FORBIDDEN);
+	}
+}
@@ -0,0 +1,171 @@
+use axum::{
+	Json,
+	async_trait,
+	extract::{FromRequest, Request},
+	http::StatusCode,
+	response::Response,
+};
+use serde::de::DeserializeOwned;
+use serde_valid::{Validate, validation::Errors};
+
+use super::problem::{InvalidParam, problem_response, problem_with_params};
+use crate::config::SerdeValidated;
+
+/// JSON request body checked against its `serde_valid` constraints.
+///
+/// Bodies that do not deserialize or violate a constraint are rejected with a
+/// 400 `ProblemDetails`, the violations listed as `invalidParams`.
+pub struct ValidatedJson<T>(SerdeValidated<T>);
+
+impl<T: Validate> ValidatedJson<T> {
+	pub fn into_inner(self) -> T {
+		self.0.into_inner()
+	}
+}
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+	T: DeserializeOwned + Validate,
+	S: Send + Sync,
+{
+	type Rejection = Response;
+
+	async fn from_request(
+		req: Request,
+		state: &S,
+	) -> Result<Self, Self::Rejection> {
+		let Json(value) = Json::<T>::from_request(req, state)
+			.await
+			.map_err(|rejection| {
+				problem_response(
+					rejection.status(),
+					Some("INVALID_MSG_FORMAT"),
+					rejection.body_text(),
+				)
+			})?;
+		SerdeValidated::new(value)
+			.map(ValidatedJson)
+			.map_err(|errors| {
+				problem_with_params(
+					StatusCode::BAD_REQUEST,
+					Some("INVALID_MSG_FORMAT"),
+					"Request body violates the API constraints".to_owned(),
+					&invalid_params(&errors),
+				)
+			})
+	}
+}
+
+/// Flattens the nested validation errors into one `InvalidParam` per
+/// violation, addressed by JSON pointer.
+pub fn invalid_params(errors: &Errors) -> Vec<InvalidParam> {
+	let mut params = Vec::new();
+	collect_invalid_params(errors, "", &mut params);
+	params
+}
+
+fn collect_invalid_params(
+	errors: &Errors,
+	pointer: &str,
+	params: &mut Vec<InvalidParam>,
+) {
+	let (own_errors, children): (_, Vec<(String, &Errors)>) = match errors {
+		Errors::Object(object) => (
+			&object.errors,
+			object
+				.properties
+				.iter()
+				.map(|(name, errors)| (lower_camel_case(name), errors))
+				.collect(),
+		),
+		Errors::Array(array) => (
+			&array.errors,
+			array
+				.items
+				.iter()
+				.map(|(index, errors)| (index.to_string(), errors))
+				.collect(),
+		),
+		Errors::NewType(errors) => (errors, Vec::new()),
+	};
+	let param = if pointer.is_empty() { "/" } else { pointer };
+	params.extend(own_errors.iter().map(|error| InvalidParam {
+		param: param.to_owned(),
+		reason: Some(error.to_string()),
+	}));
+	for (segment, errors) in children {
+		collect_invalid_params(errors, &format!("{pointer}/{segment}"), params);
+	}
+}
+
+/// `serde_valid` reports the Rust field names and ignores `rename_all`, while
+/// SBI attributes are lowerCamelCase, TS 29.501 5.3.1.
+fn lower_camel_case(name: &str) -> String {
+	let mut segments = name.split('_');
+	let mut camel = segments.next().unwrap_or_default().to_owned();
+	for segment in segments {
+		let mut chars = segment.chars();
+		if let Some(first) = chars.next() {
+			camel.extend(first.to_uppercase());
+			camel.push_str(chars.as_str());
+		}
+	}
+	camel
+}
+
+#[cfg(test)]
+mod tests {
+	use axum::{
+		Router,
+		body::{Body, to_bytes},
+		http::header::CONTENT_TYPE,
+		routing::post,
+	};
+	use serde::Deserialize;
+	use tower::ServiceExt;
+
+	use super::*;
+
+	#[derive(Deserialize, Validate)]
+	#[serde(rename_all = "camelCase")]
+	struct EventSubscription {
+		#[validate(min_items = 1)]
+		event_list: Vec<String>,
+	}
+
+	async fn post_subscription(body: &'static str) -> Response {
+		let router = Router::new().route(
+			"/subscriptions",
+			post(
+				|subscription: ValidatedJson<EventSubscription>| async move {
+					subscription.into_inner().event_list.len().to_string()
+				},
+			),
+		);
+		let request = Request::builder()
+			.method("POST")
+			.uri("/subscriptions")
+			.header(CONTENT_TYPE, "application/json")
+			.body(Body::from(body))
+			.unwrap();
+		router.oneshot(request).await.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_constraint_violation_is_rejected_with_field() {
+		let response = post_subscription(r#"{"eventList":[]}"#).await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+		let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+		let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+		assert_eq!(problem["status"], 400);
+		assert_eq!(problem["invalidParams"][0]["param"], "/eventList");
+	}
+
+	#[tokio::test]
+	async fn test_valid_body_reaches_handler() {
+		let response = post_subscription(r#"{"eventList":["LOCATION_REPORT"]}"#).await;
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use axum::{
+	extract::{Request, State},
+	http::StatusCode,
+	middleware::Next,
+	response::Response,
+};
+
+use super::problem::problem_response;
+
+/// The `apiVersion` path segments of the SBI resource URIs served by this NF,
+/// TS 29.501 4.4.1, e.g. `v1` in `/namf-comm/v1/ue-contexts`.
+#[derive(Debug, Clone)]
+pub struct ApiVersions(Arc<[String]>);
+
+impl ApiVersions {
+	pub fn from_majors(majors: impl IntoIterator<Item = u32>) -> Self {
+		Self(
+			majors
+				.into_iter()
+				.map(|major| format!("v{major}"))
+				.collect(),
+		)
+	}
+
+	pub fn accepts(
+		&self,
+		version: &str,
+	) -> bool {
+		self.0.iter().any(|v| v == version)
+	}
+}
+
+impl std::fmt::Display for ApiVersions {
+	fn fmt(
+		&self,
+		f: &mut std::fmt::Formatter<'_>,
+	) -> std::fmt::Result {
+		f.write_str(&self.0.join(", "))
+	}
+}
+
+/// Rejects requests whose `{apiName}/{apiVersion}` prefix names an API
+/// version not advertised in the NF profile. Paths without a version
+/// segment, such as `/health`, are passed through.
+pub async fn api_version_guard(
+	State(supported): State<ApiVersions>,
+	request: Request,
+	next: Next,
+) -> Response {
+	match requested_version(request.uri().path()) {
+		Some(version) if !supported.accepts(version) => unsupported_version(version, &supported),
+		_ => next.run(request).await,
+	}
+}
+
+fn requested_version(path: &str) -> Option<&str> {
+	let version = path.trim_start_matches('/').split('/').nth(1)?;
+	let is_version = version
+		.strip_prefix('v')
+		.is_some_and(|major| !major.is_empty() && major.bytes().all(|b| b.is_ascii_digit()));
+	is_version.then_some(version)
+}
+
+/// TS 29.500 5.2.7.2, an unsupported API version is an unknown resource.
+fn unsupported_version(
+	requested: &str,
+	supported: &ApiVersions,
+) -> Response {
+	problem_response(
+		StatusCode::NOT_FOUND,
+		Some("INVALID_API"),
+		format!("API version {requested} is not supported, expected one of {supported}"),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use axum::{Router, body::Body, middleware::from_fn_with_state, routing::get};
+	use tower::ServiceExt;
+
+	use super::*;
+
+	fn router() -> Router {
+		Router::new()
+			.route("/namf-comm/:version/ping", get(|| async { "pong" }))
+			.route("/health", get(|| async { "ok" }))
+			.layer(from_fn_with_state(
+				ApiVersions::from_majors([1]),
+				api_version_guard,
+			))
+	}
+
+	async fn status_of(path: &str) -> StatusCode {
+		let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+		router().oneshot(request).await.unwrap().status()
+	}
+
+	#[tokio::test]
+	async fn test_mismatched_api_version_is_rejected() {
+		assert_eq!(status_of("/namf-comm/v1/ping").await, StatusCode::OK);
+		assert_eq!(status_of("/namf-comm/v2/ping").await, StatusCode::NOT_FOUND);
+		assert_eq!(status_of("/health").await, StatusCode::OK);
+	}
+}
@@ -0,0 +1,197 @@
+use std::str::FromStr;
+
+use bitvec::prelude::*;
+use ngap_models::{
+	AmfPointer as NgapAmfPointer,
+	AmfRegionId as NgapAmfRegionId,
+	AmfSetId as NgapAmfSetId,
+	Guami as NgapGuami,
+};
+use oasbi::common::{AmfId as SbiAmfId, Guami as SbiGuami, error::ConversionError};
+
+use super::{Element, convert, try_convert};
+
+/// Bit widths of the AMF Identifier sub-fields, TS 23.003 2.10.1.
+pub const AMF_REGION_ID_BITS: usize = 8;
+pub const AMF_SET_ID_BITS: usize = 10;
+pub const AMF_POINTER_BITS: usize = 6;
+
+impl TryFrom<Element<&SbiGuami>> for Element<NgapGuami> {
+	type Error = ConversionError;
+
+	fn try_from(value: Element<&SbiGuami>) -> Result<Self, Self::Error> {
+		let (amf_region_id, amf_set_id, amf_pointer) = convert(&value.0.amf_id);
+
+		Ok(Element(NgapGuami {
+			plmn_identity: try_convert(&value.0.plmn_id)?,
+			amf_region_id,
+			amf_set_id,
+			amf_pointer,
+		}))
+	}
+}
+
+impl TryFrom<Element<&NgapGuami>> for Element<SbiGuami> {
+	type Error = ConversionError;
+
+	fn try_from(value: Element<&NgapGuami>) -> Result<Self, Self::Error> {
+		let NgapGuami {
+			plmn_identity,
+			amf_region_id,
+			amf_set_id,
+			amf_pointer,
+		} = value.0;
+
+		Ok(Element(SbiGuami {
+			plmn_id: try_convert(plmn_identity)?,
+			amf_id: try_convert((amf_region_id, amf_set_id, amf_pointer))?,
+		}))
+	}
+}
+
+impl From<Element<&SbiAmfId>> for Element<(NgapAmfRegionId, NgapAmfSetId, NgapAmfPointer)> {
+	fn from(value: Element<&SbiAmfId>) -> Self {
+		let SbiAmfId {
+			region_id,
+			set_id,
+			pointer_id,
+		} = value.0;
+		let mut ngap_amf_region_id = bitvec![u8,Msb0; 0; AMF_REGION_ID_BITS];
+		let mut ngap_amf_set_id = bitvec![u8,Msb0; 0; AMF_SET_ID_BITS];
+		let mut ngap_amf_pointer = bitvec![u8,Msb0; 0; AMF_POINTER_BITS];
+
+		ngap_amf_pointer.store_be::<u8>(*pointer_id);
+		ngap_amf_region_id.store_be::<u8>(region_id.inner());
+		ngap_amf_set_id.store_be::<u16>(set_id.inner());
+
+		Element((
+			NgapAmfRegionId(ngap_amf_region_id.into()),
+			NgapAmfSetId(ngap_amf_set_id.into()),
+			NgapAmfPointer(ngap_amf_pointer.into()),
+		))
+	}
+}
+
+impl TryFrom<Element<(&NgapAmfRegionId, &NgapAmfSetId, &NgapAmfPointer)>> for Element<SbiAmfId> {
+	type Error = ConversionError;
+
+	fn try_from(
+		value: Element<(&NgapAmfRegionId, &NgapAmfSetId, &NgapAmfPointer)>
+	) -> Result<Self, Self::Error> {
+		let (region_id, set_id, pointer) = value.0;
+		let region_id = region_id.0.clone().into_inner();
+		let set_id = set_id.0.clone().into_inner();
+		let pointer = pointer.0.clone().into_inner();
+		if region_id.len() != AMF_REGION_ID_BITS
+			|| set_id.len() != AMF_SET_ID_BITS
+			|| pointer.len() != AMF_POINTER_BITS
+		{
+			return Err(format!(
+				"Invalid AMF Identifier bit lengths: region {}, set {}, pointer {}",
+				region_id.len(),
+				set_id.len(),
+				pointer.len()
+			)
+			.into());
+		}
+		amf_id_from_parts(
+			region_id.load_be::<u8>(),
+			set_id.load_be::<u16>(),
+			pointer.load_be::<u8>(),
+		)
+		.map(Element)
+	}
+}
+
+/// Builds the SBI AMF Identifier, the 6 hex digit encoding of
+/// `<region (8 bits)><set (10 bits)><pointer (6 bits)>`.
+pub fn amf_id_from_parts(
+	region_id: u8,
+	set_id: u16,
+	pointer: u8,
+) -> Result<SbiAmfId, ConversionError> {
+	if set_id >= 1 << AMF_SET_ID_BITS || pointer >= 1 << AMF_POINTER_BITS {
+		return Err(
+			format!("AMF Set ID {set_id} or AMF Pointer {pointer} exceeds its bit width").into(),
+		);
+	}
+	let amf_id = (region_id as u32) << (AMF_SET_ID_BITS + AMF_POINTER_BITS)
+		| (set_id as u32) << AMF_POINTER_BITS
+		| pointer as u32;
+	SbiAmfId::from_str(&format!("{amf_id:06x}"))
+		.map_err(|e| format!("Invalid AMF Identifier {amf_id:06x}: {e:?}").into())
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn sbi_guami(amf_id: &str) -> SbiGuami {
+		serde_json::from_value(json!({
+			"plmnId": { "mcc": "208", "mnc": "93" },
+			"amfId": amf_id,
+		}))
+		.unwrap()
+	}
+
+	fn test_guami_round_trip(
+		amf_id: &str,
+		region_id: u8,
+		set_id: u16,
+		pointer: u8,
+	) {
+		let guami = sbi_guami(amf_id);
+		let ngap_guami: NgapGuami = try_convert(&guami).unwrap();
+		assert_eq!(ngap_guami.plmn_identity.0, [0x02, 0xF8, 0x39]);
+		assert_eq!(
+			ngap_guami
+				.amf_region_id
+				.0
+				.clone()
+				.into_inner()
+				.load_be::<u8>(),
+			region_id
+		);
+		assert_eq!(
+			ngap_guami
+				.amf_set_id
+				.0
+				.clone()
+				.into_inner()
+				.load_be::<u16>(),
+			set_id
+		);
+		assert_eq!(
+			ngap_guami
+				.amf_pointer
+				.0
+				.clone()
+				.into_inner()
+				.load_be::<u8>(),
+			pointer
+		);
+
+		let decoded: SbiGuami = try_convert(&ngap_guami).unwrap();
+		assert_eq!(
+			serde_json::to_value(&decoded).unwrap(),
+			serde_json::to_value(&guami).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_guami_interconversion() {
+		test_guami_round_trip("cafe00", 0xca, 0x3f8, 0x00);
+		test_guami_round_trip("123456", 0x12, 0xd1, 0x16);
+		test_guami_round_trip("ffffff", 0xff, 0x3ff, 0x3f);
+		test_guami_round_trip("000000", 0x00, 0x000, 0x00);
+	}
+
+	#[test]
+	fn test_amf_id_from_parts_rejects_out_of_range() {
+		assert!(amf_id_from_parts(0xca, 0x3f8, 0x00).is_ok());
+		assert!(amf_id_from_parts(0xca, 1 << AMF_SET_ID_BITS, 0x00).is_err());
+		assert!(amf_id_from_parts(0xca, 0x3f8, 1 << AMF_POINTER_BITS).is_err());
+	}
+}
@@ -32,7 +32,8 @@ use super::Element;
 /// * `mnc` - Mobile Network Code (2 or 3 digits)
 ///
 /// # Returns
-/// Returns a `NgapPlmnIdentity` containing the encoded 3-byte PLMN identifier
+/// Returns a `NgapPlmnIdentity` containing the encoded 3-byte PLMN identifier,
+/// or a `ConversionError` when a code has the wrong length or a non digit
 ///
 /// # Example encoding:
 /// For MCC = 234 and MNC = 15 (2 digits):
@@ -42,15 +43,19 @@ use super::Element;
 /// Octet 3: 0101 (5) | 0001 (1) = 0x51
 /// ```
 
-impl From<Element<&SbiPlmnId>> for Element<NgapPlmnIdentity> {
-	fn from(value: Element<&SbiPlmnId>) -> Self {
-		Element(convert_mcc_mnc_to_plmn_id(&value.0.mcc, &value.0.mnc))
+impl TryFrom<Element<&SbiPlmnId>> for Element<NgapPlmnIdentity> {
+	type Error = ConversionError;
+
+	fn try_from(value: Element<&SbiPlmnId>) -> Result<Self, Self::Error> {
+		convert_mcc_mnc_to_plmn_id(&value.0.mcc, &value.0.mnc).map(Element)
 	}
 }
 
-impl From<Element<&SbiPlmnIdNid>> for Element<NgapPlmnIdentity> {
-	fn from(value: Element<&SbiPlmnIdNid>) -> Self {
-		Element(convert_mcc_mnc_to_plmn_id(&value.0.mcc, &value.0.mnc))
+impl TryFrom<Element<&SbiPlmnIdNid>> for Element<NgapPlmnIdentity> {
+	type Error = ConversionError;
+
+	fn try_from(value: Element<&SbiPlmnIdNid>) -> Result<Self, Self::Error> {
+		convert_mcc_mnc_to_plmn_id(&value.0.mcc, &value.0.mnc).map(Element)
 	}
 }
 
@@ -62,14 +67,38 @@ impl TryFrom<Element<&NgapPlmnIdentity>> for Element<SbiPlmnId> {
 	}
 }
 
+impl TryFrom<Element<&NgapPlmnIdentity>> for Element<SbiPlmnIdNid> {
+	type Error = ConversionError;
+
+	fn try_from(value: Element<&NgapPlmnIdentity>) -> Result<Self, Self::Error> {
+		let SbiPlmnId { mcc, mnc } = convert_plmn_id_to_mcc_mnc(value.0)?;
+		Ok(Element(SbiPlmnIdNid {
+			mcc,
+			mnc,
+			nid: None,
+		}))
+	}
+}
+
 fn convert_mcc_mnc_to_plmn_id(
 	mcc: &SbiMcc,
 	mnc: &SbiMnc,
-) -> NgapPlmnIdentity {
+) -> Result<NgapPlmnIdentity, ConversionError> {
 	// Convert MCC and MNC to strings to get individual digits
 	let mcc_bytes = mcc.as_bytes();
 	let mnc_bytes = mnc.as_bytes();
 
+	// Codes built with `new_unchecked` skip the pattern check, so the lengths
+	// and digits indexed below are checked here.
+	let is_digits = |code: &[u8]| code.iter().all(u8::is_ascii_digit);
+	if mcc_bytes.len() != 3
+		|| !matches!(mnc_bytes.len(), 2 | 3)
+		|| !is_digits(mcc_bytes)
+		|| !is_digits(mnc_bytes)
+	{
+		return Err(format!("Invalid MCC {mcc} or MNC {mnc} for a PLMN Identity").into());
+	}
+
 	// Create a 3-byte array for PLMN Identity
 	let mut plmn_bytes = [0u8; 3];
 
@@ -94,7 +123,7 @@ fn convert_mcc_mnc_to_plmn_id(
 		(mnc_bytes[2] - b'0') << 4 | (mnc_bytes[1] - b'0')
 	};
 
-	NgapPlmnIdentity(plmn_bytes)
+	Ok(NgapPlmnIdentity(plmn_bytes))
 }
 
 /// Converts a PLMN (Public Land Mobile Network) Identity back to MCC (Mobile
@@ -150,7 +179,7 @@ mod tests {
 	) {
 		let encoded_mcc = SbiMcc::from_str(mcc).unwrap();
 		let encoded_mnc = SbiMnc::from_str(mnc).unwrap();
-		let plmn_identity = convert_mcc_mnc_to_plmn_id(&encoded_mcc, &encoded_mnc);
+		let plmn_identity = convert_mcc_mnc_to_plmn_id(&encoded_mcc, &encoded_mnc).unwrap();
 		assert_eq!(plmn_identity.0, plmn_id);
 
 		let SbiPlmnId {
@@ -167,4 +196,25 @@ mod tests {
 		test_mcc_mnc_interconversion("234", "15", [0x32, 0xF4, 0x51]);
 		test_mcc_mnc_interconversion("001", "001", [0x00, 0x01, 0x10]);
 	}
+
+	fn unchecked_plmn_id(
+		mcc: &str,
+		mnc: &str,
+	) -> Result<NgapPlmnIdentity, ConversionError> {
+		let mcc = unsafe { SbiMcc::new_unchecked(mcc.to_owned()) };
+		let mnc = unsafe { SbiMnc::new_unchecked(mnc.to_owned()) };
+		convert_mcc_mnc_to_plmn_id(&mcc, &mnc)
+	}
+
+	#[test]
+	fn test_short_mcc_is_rejected() {
+		assert!(unchecked_plmn_id("20", "93").is_err());
+		assert!(unchecked_plmn_id("", "93").is_err());
+	}
+
+	#[test]
+	fn test_four_digit_mnc_is_rejected() {
+		assert!(unchecked_plmn_id("208", "9301").is_err());
+		assert!(unchecked_plmn_id("208", "9a").is_err());
+	}
 }
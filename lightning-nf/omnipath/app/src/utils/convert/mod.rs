@@ -1,3 +1,4 @@
+pub mod guami;
 mod mcc_mnc_plmnid;
 mod tac;
 mod snssai;
@@ -34,3 +35,45 @@ pub fn transform_nonempty<T, U>(
 	let tail = value.tail().iter().map(f).collect::<Vec<_>>();
 	NonEmpty { head, tail }
 }
+
+/// Fallible counterpart of [`transform_nonempty`], stopping at the first
+/// element `f` fails on and returning its error.
+pub fn try_transform_nonempty<T, U, E>(
+	value: &NonEmpty<T>,
+	f: impl Fn(&T) -> Result<U, E>,
+) -> Result<NonEmpty<U>, E> {
+	let head = f(&value.head)?;
+	let tail = value.tail().iter().map(f).collect::<Result<Vec<_>, _>>()?;
+	Ok(NonEmpty { head, tail })
+}
+
+#[cfg(test)]
+mod tests {
+	use nonempty::nonempty;
+
+	use super::*;
+
+	fn parse(value: &&str) -> Result<u8, String> {
+		value
+			.parse::<u8>()
+			.map_err(|e| format!("invalid element {value}: {e}"))
+	}
+
+	#[test]
+	fn test_try_transform_nonempty_all_ok() {
+		let values = nonempty!["1", "2", "3"];
+		let transformed = try_transform_nonempty(&values, parse).unwrap();
+		assert_eq!(transformed, nonempty![1, 2, 3]);
+	}
+
+	#[test]
+	fn test_try_transform_nonempty_reports_failing_element() {
+		let values = nonempty!["1", "x", "300"];
+		let err = try_transform_nonempty(&values, parse).unwrap_err();
+		assert!(err.starts_with("invalid element x"), "{err}");
+
+		let values = nonempty!["y"];
+		let err = try_transform_nonempty(&values, parse).unwrap_err();
+		assert!(err.starts_with("invalid element y"), "{err}");
+	}
+}
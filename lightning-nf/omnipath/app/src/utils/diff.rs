@@ -0,0 +1,167 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+use treediff::{
+	tools::{ChangeType, Recorder},
+	value::Key,
+};
+
+/// A change between two JSON documents, at a JSON pointer like
+/// `/nfServices/0/versions`.
+///
+/// Serialized tagged by its `op`, `{"op":"modified","path":…,"old":…,"new":…}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum DiffEntry {
+	Added {
+		path: String,
+		value: Value,
+	},
+	Removed {
+		path: String,
+		value: Value,
+	},
+	Modified {
+		path: String,
+		old: Value,
+		new: Value,
+	},
+}
+
+impl DiffEntry {
+	pub fn path(&self) -> &str {
+		match self {
+			DiffEntry::Added { path, .. }
+			| DiffEntry::Removed { path, .. }
+			| DiffEntry::Modified { path, .. } => path,
+		}
+	}
+}
+
+impl fmt::Display for DiffEntry {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		match self {
+			DiffEntry::Added { path, value } => write!(f, "+ {path}: {value}"),
+			DiffEntry::Removed { path, value } => write!(f, "- {path}: {value}"),
+			DiffEntry::Modified { path, old, new } => write!(f, "~ {path}: {old} -> {new}"),
+		}
+	}
+}
+
+/// The changes turning `v1` into `v2`, ordered by path with array indices
+/// compared as numbers. Unchanged values are left out.
+pub fn json_diff<T: Serialize>(
+	v1: &T,
+	v2: &T,
+) -> Result<Vec<DiffEntry>, serde_json::Error> {
+	let v1 = serde_json::to_value(v1)?;
+	let v2 = serde_json::to_value(v2)?;
+	let mut recorder = Recorder::default();
+	treediff::diff(&v1, &v2, &mut recorder);
+
+	let mut changes = recorder.calls;
+	changes.sort_by(|a, b| change_keys(a).cmp(change_keys(b)));
+	Ok(changes
+		.into_iter()
+		.filter_map(|change| match change {
+			ChangeType::Added(keys, value) => Some(DiffEntry::Added {
+				path: json_pointer(&keys),
+				value: value.clone(),
+			}),
+			ChangeType::Removed(keys, value) => Some(DiffEntry::Removed {
+				path: json_pointer(&keys),
+				value: value.clone(),
+			}),
+			ChangeType::Modified(keys, old, new) => Some(DiffEntry::Modified {
+				path: json_pointer(&keys),
+				old: old.clone(),
+				new: new.clone(),
+			}),
+			ChangeType::Unchanged(..) => None,
+		})
+		.collect())
+}
+
+/// [`json_diff`] rendered one change per line, for logging.
+pub fn find_diff<T: Serialize>(
+	v1: &T,
+	v2: &T,
+) -> String {
+	match json_diff(v1, v2) {
+		Ok(changes) => changes
+			.iter()
+			.map(DiffEntry::to_string)
+			.collect::<Vec<_>>()
+			.join("\n"),
+		Err(err) => format!("Error While Serializing: {err}"),
+	}
+}
+
+fn change_keys<'a>(change: &'a ChangeType<'_, Key, Value>) -> &'a [Key] {
+	match change {
+		ChangeType::Added(keys, _)
+		| ChangeType::Removed(keys, _)
+		| ChangeType::Unchanged(keys, _)
+		| ChangeType::Modified(keys, ..) => keys,
+	}
+}
+
+/// RFC 6901 pointer of `keys`, `""` being the whole document.
+fn json_pointer(keys: &[Key]) -> String {
+	keys.iter()
+		.map(|key| match key {
+			Key::Index(index) => format!("/{index}"),
+			Key::String(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use serde_json::json;
+
+	use super::*;
+
+	/// Every map gets its own hasher seed, so serializing equal maps visits
+	/// their entries in differing orders.
+	fn profile(offset: u32) -> HashMap<String, Value> {
+		(0..32)
+			.map(|i| {
+				(
+					format!("field{i}"),
+					json!({ "value": i + offset, "list": [i, 2 * i] }),
+				)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_diff_is_byte_identical_across_runs() {
+		let first = find_diff(&profile(0), &profile(1));
+		for _ in 0..8 {
+			assert_eq!(find_diff(&profile(0), &profile(1)), first);
+		}
+		assert_eq!(first.lines().count(), 32);
+	}
+
+	#[test]
+	fn test_diff_is_sorted_by_path() {
+		let old = json!({ "b": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], "a/c": 1, "d": 4 });
+		let new = json!({ "b": [1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 12], "a/c": 2, "e": 5 });
+		let changes = json_diff(&old, &new).unwrap();
+		assert_eq!(
+			changes.iter().map(DiffEntry::path).collect::<Vec<_>>(),
+			["/a~1c", "/b/9", "/b/10", "/d", "/e"]
+		);
+		assert_eq!(changes[0].to_string(), "~ /a~1c: 1 -> 2");
+		assert_eq!(changes[3].to_string(), "- /d: 4");
+		assert_eq!(changes[4].to_string(), "+ /e: 5");
+		assert!(json_diff(&old, &old).unwrap().is_empty());
+	}
+}
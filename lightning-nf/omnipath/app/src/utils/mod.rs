@@ -1,4 +1,6 @@
 mod convert;
-pub use convert::{convert, try_convert};
+mod diff;
+pub use convert::{convert, guami, try_convert};
+pub use diff::{DiffEntry, find_diff, json_diff};
 
 pub mod models;
\ No newline at end of file
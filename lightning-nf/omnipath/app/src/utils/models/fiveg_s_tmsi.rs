@@ -6,7 +6,7 @@ use ngap_models::{
 	FiveGTmsi as NgapFiveGTmsi,
 };
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FiveGSTmsi(AsciiString);
 
 impl From<NgapFiveGSTmsi> for FiveGSTmsi {
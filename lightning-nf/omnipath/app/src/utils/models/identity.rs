@@ -0,0 +1,304 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+const SUPI_IMSI_PREFIX: &str = "imsi-";
+const SUPI_NAI_PREFIX: &str = "nai-";
+const SUCI_PREFIX: &str = "suci-";
+const GUTI_PREFIX: &str = "5g-guti-";
+
+/// Subscription Permanent Identifier in its SBI form, TS 29.571 5.3.2.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Supi {
+	/// `imsi-<MCC><MNC><MSIN>`, 5 to 15 digits.
+	Imsi(String),
+	/// `nai-<username@realm>`.
+	Nai(String),
+}
+
+impl FromStr for Supi {
+	type Err = IdentityError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match (
+			s.strip_prefix(SUPI_IMSI_PREFIX),
+			s.strip_prefix(SUPI_NAI_PREFIX),
+		) {
+			(Some(imsi), _) if (5..=15).contains(&imsi.len()) && is_digits(imsi) => {
+				Ok(Supi::Imsi(imsi.to_owned()))
+			}
+			(_, Some(nai)) if !nai.is_empty() => Ok(Supi::Nai(nai.to_owned())),
+			_ => Err(IdentityError::InvalidSupi(s.to_owned())),
+		}
+	}
+}
+
+impl fmt::Display for Supi {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		match self {
+			Supi::Imsi(imsi) => write!(f, "{SUPI_IMSI_PREFIX}{imsi}"),
+			Supi::Nai(nai) => write!(f, "{SUPI_NAI_PREFIX}{nai}"),
+		}
+	}
+}
+
+/// Subscription Concealed Identifier in its SBI form, TS 29.503 6.1.6.3.2,
+/// `suci-<type>-<MCC>-<MNC>-<routing indicator>-<scheme>-<key id>-<output>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Suci {
+	/// 0 for an IMSI based SUPI, 1 for a NAI.
+	pub supi_type: u8,
+	pub mcc: String,
+	pub mnc: String,
+	pub routing_indicator: String,
+	/// 0 is the null scheme, the scheme output then is the plain MSIN.
+	pub protection_scheme_id: u8,
+	pub home_network_public_key_id: u8,
+	pub scheme_output: String,
+}
+
+impl Suci {
+	pub const NULL_SCHEME: u8 = 0;
+
+	/// The SUPI of an IMSI SUCI left unconcealed by the null scheme, other
+	/// schemes have to be deconcealed by the UDM.
+	pub fn unconcealed_supi(&self) -> Option<Supi> {
+		(self.supi_type == 0 && self.protection_scheme_id == Self::NULL_SCHEME)
+			.then(|| Supi::Imsi(format!("{}{}{}", self.mcc, self.mnc, self.scheme_output)))
+	}
+}
+
+impl FromStr for Suci {
+	type Err = IdentityError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || IdentityError::InvalidSuci(s.to_owned());
+		let fields = s
+			.strip_prefix(SUCI_PREFIX)
+			.ok_or_else(invalid)?
+			.split('-')
+			.collect::<Vec<_>>();
+		let [
+			supi_type,
+			mcc,
+			mnc,
+			routing_indicator,
+			scheme,
+			key_id,
+			output,
+		] = fields[..]
+		else {
+			return Err(invalid());
+		};
+		let valid = matches!(supi_type, "0" | "1")
+			&& mcc.len() == 3
+			&& is_digits(mcc)
+			&& (2..=3).contains(&mnc.len())
+			&& is_digits(mnc)
+			&& (1..=4).contains(&routing_indicator.len())
+			&& is_digits(routing_indicator)
+			&& !output.is_empty()
+			&& output.bytes().all(|b| b.is_ascii_hexdigit());
+		if !valid {
+			return Err(invalid());
+		}
+		Ok(Suci {
+			supi_type: supi_type.parse().map_err(|_| invalid())?,
+			mcc: mcc.to_owned(),
+			mnc: mnc.to_owned(),
+			routing_indicator: routing_indicator.to_owned(),
+			protection_scheme_id: scheme.parse().map_err(|_| invalid())?,
+			home_network_public_key_id: key_id.parse().map_err(|_| invalid())?,
+			scheme_output: output.to_owned(),
+		})
+	}
+}
+
+impl fmt::Display for Suci {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		write!(
+			f,
+			"{SUCI_PREFIX}{}-{}-{}-{}-{}-{}-{}",
+			self.supi_type,
+			self.mcc,
+			self.mnc,
+			self.routing_indicator,
+			self.protection_scheme_id,
+			self.home_network_public_key_id,
+			self.scheme_output
+		)
+	}
+}
+
+/// 5G Globally Unique Temporary Identifier, TS 23.003 2.10.1, rendered as
+/// `5g-guti-<MCC><MNC><AMF ID><5G-TMSI>` like the `ueContextId` of TS 29.518.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FiveGGuti {
+	pub mcc: String,
+	pub mnc: String,
+	/// 6 hex digits, AMF Region ID, AMF Set ID and AMF Pointer.
+	pub amf_id: String,
+	pub tmsi: u32,
+}
+
+impl FromStr for FiveGGuti {
+	type Err = IdentityError;
+
+	/// The `5g-guti-` prefix is optional, NAS renders the GUTI without it.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || IdentityError::InvalidGuti(s.to_owned());
+		let guti = s.strip_prefix(GUTI_PREFIX).unwrap_or(s);
+		// MCC and the 2 or 3 digit MNC, followed by 6 + 8 hex digits.
+		let plmn_len = guti.len().checked_sub(14).ok_or_else(invalid)?;
+		if !(5..=6).contains(&plmn_len) || !guti.is_ascii() {
+			return Err(invalid());
+		}
+		let (plmn, ids) = guti.split_at(plmn_len);
+		let (amf_id, tmsi) = ids.split_at(6);
+		if !is_digits(plmn) || !amf_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+			return Err(invalid());
+		}
+		Ok(FiveGGuti {
+			mcc: plmn[..3].to_owned(),
+			mnc: plmn[3..].to_owned(),
+			amf_id: amf_id.to_ascii_lowercase(),
+			tmsi: u32::from_str_radix(tmsi, 16).map_err(|_| invalid())?,
+		})
+	}
+}
+
+impl fmt::Display for FiveGGuti {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		write!(
+			f,
+			"{GUTI_PREFIX}{}{}{}{:08x}",
+			self.mcc, self.mnc, self.amf_id, self.tmsi
+		)
+	}
+}
+
+/// A subscriber identity a UE is found by, its SUPI or its 5G-GUTI.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UeIdentity {
+	Supi(Supi),
+	Guti(FiveGGuti),
+}
+
+impl FromStr for UeIdentity {
+	type Err = IdentityError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.parse()
+			.map(UeIdentity::Supi)
+			.or_else(|_| s.parse().map(UeIdentity::Guti))
+			.map_err(|_| IdentityError::InvalidUeIdentity(s.to_owned()))
+	}
+}
+
+impl fmt::Display for UeIdentity {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		match self {
+			UeIdentity::Supi(supi) => supi.fmt(f),
+			UeIdentity::Guti(guti) => guti.fmt(f),
+		}
+	}
+}
+
+fn is_digits(s: &str) -> bool {
+	!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum IdentityError {
+	#[error("InvalidSupi: {0} is not a valid SUPI")]
+	InvalidSupi(String),
+
+	#[error("InvalidSuci: {0} is not a valid SUCI")]
+	InvalidSuci(String),
+
+	#[error("InvalidGuti: {0} is not a valid 5G-GUTI")]
+	InvalidGuti(String),
+
+	#[error("InvalidUeIdentity: {0} is neither a SUPI nor a 5G-GUTI")]
+	InvalidUeIdentity(String),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn round_trip<T>(identity: &str) -> T
+	where
+		T: FromStr<Err = IdentityError> + fmt::Display,
+	{
+		let parsed = identity.parse::<T>().unwrap();
+		assert_eq!(parsed.to_string(), identity);
+		parsed
+	}
+
+	#[test]
+	fn test_supi_round_trip() {
+		let supi = round_trip::<Supi>("imsi-208930000000001");
+		assert_eq!(supi, Supi::Imsi("208930000000001".to_owned()));
+		round_trip::<Supi>("nai-ue1@example.org");
+
+		assert!("imsi-2089300000000012".parse::<Supi>().is_err());
+		assert!("imsi-20893a".parse::<Supi>().is_err());
+		assert!("208930000000001".parse::<Supi>().is_err());
+	}
+
+	#[test]
+	fn test_suci_round_trip() {
+		let suci = round_trip::<Suci>("suci-0-208-93-0000-0-0-0000000001");
+		assert_eq!(suci.mnc, "93");
+		assert_eq!(
+			suci.unconcealed_supi(),
+			Some(Supi::Imsi("208930000000001".to_owned()))
+		);
+
+		let concealed = round_trip::<Suci>("suci-0-208-93-0000-1-27-a1b2c3d4");
+		assert_eq!(concealed.unconcealed_supi(), None);
+
+		assert!("suci-0-208-93-0000-0-0".parse::<Suci>().is_err());
+		assert!("suci-2-208-93-0000-0-0-0000000001".parse::<Suci>().is_err());
+	}
+
+	#[test]
+	fn test_guti_round_trip() {
+		let guti = round_trip::<FiveGGuti>("5g-guti-20893cafe0000000001");
+		assert_eq!(guti.mnc, "93");
+		assert_eq!(guti.amf_id, "cafe00");
+		assert_eq!(guti.tmsi, 1);
+		round_trip::<FiveGGuti>("5g-guti-208093cafe00deadbeef");
+
+		let without_prefix = "20893cafe0000000001".parse::<FiveGGuti>().unwrap();
+		assert_eq!(without_prefix, guti);
+		assert!("5g-guti-20893cafe00000001".parse::<FiveGGuti>().is_err());
+	}
+	#[test]
+	fn test_ue_identity_is_a_supi_or_a_guti() {
+		let supi = round_trip::<UeIdentity>("imsi-208930000000001");
+		assert!(matches!(supi, UeIdentity::Supi(Supi::Imsi(_))));
+		let guti = round_trip::<UeIdentity>("5g-guti-20893cafe0000000001");
+		assert!(matches!(guti, UeIdentity::Guti(FiveGGuti { tmsi: 1, .. })));
+
+		assert_eq!(
+			"suci-0-208-93-0000-0-0-0000000001".parse::<UeIdentity>(),
+			Err(IdentityError::InvalidUeIdentity(
+				"suci-0-208-93-0000-0-0-0000000001".to_owned()
+			))
+		);
+	}
+}
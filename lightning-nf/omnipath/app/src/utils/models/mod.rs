@@ -1,3 +1,11 @@
 mod fiveg_s_tmsi;
+mod identity;
+mod nssai_availability;
+mod service_instance_id;
+mod tai_set;
 
-pub use fiveg_s_tmsi::FiveGSTmsi;
\ No newline at end of file
+pub use fiveg_s_tmsi::FiveGSTmsi;
+pub use identity::{FiveGGuti, IdentityError, Suci, Supi, UeIdentity};
+pub use nssai_availability::{NssaiAvailability, same_slice};
+pub use service_instance_id::ServiceInstanceId;
+pub use tai_set::TaiSet;
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use nonempty::NonEmpty;
+use oasbi::common::{Snssai, Tai};
+use rustc_hash::FxBuildHasher;
+
+use super::tai_set::normalize;
+
+/// The S-NSSAIs available in each TAI, for the TAIs restricting them. The
+/// slices of the PLMN support list are available in every other TAI.
+#[derive(Debug, Clone, Default)]
+pub struct NssaiAvailability(HashMap<Tai, NonEmpty<Snssai>, FxBuildHasher>);
+
+impl NssaiAvailability {
+	/// Restricts the TAI to `snssais`, replacing what it was restricted to.
+	pub fn insert(
+		&mut self,
+		tai: &Tai,
+		snssais: NonEmpty<Snssai>,
+	) {
+		self.0.insert(normalize(tai), snssais);
+	}
+
+	pub fn is_available(
+		&self,
+		tai: &Tai,
+		snssai: &Snssai,
+	) -> bool {
+		self.0.get(&normalize(tai)).is_none_or(|snssais| {
+			snssais
+				.iter()
+				.any(|available| same_slice(available, snssai))
+		})
+	}
+}
+
+impl<'a> FromIterator<(&'a Tai, &'a NonEmpty<Snssai>)> for NssaiAvailability {
+	fn from_iter<I: IntoIterator<Item = (&'a Tai, &'a NonEmpty<Snssai>)>>(iter: I) -> Self {
+		let mut availability = Self::default();
+		for (tai, snssais) in iter {
+			availability.insert(tai, snssais.clone());
+		}
+		availability
+	}
+}
+
+/// SDs compare regardless of how their hex digits are cased, as TACs do.
+pub fn same_slice(
+	a: &Snssai,
+	b: &Snssai,
+) -> bool {
+	let sd = |snssai: &Snssai| {
+		snssai
+			.sd
+			.as_ref()
+			.map(|sd| sd.to_string().to_ascii_lowercase())
+	};
+	a.sst == b.sst && sd(a) == sd(b)
+}
+
+#[cfg(test)]
+mod tests {
+	use oasbi::common::Tac;
+
+	use super::*;
+
+	fn tai(tac: &str) -> Tai {
+		Tai {
+			tac: unsafe { Tac::new_unchecked(tac.to_string()) },
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_unrestricted_tais_have_every_slice() {
+		let embb = Snssai {
+			sst: 1,
+			..Default::default()
+		};
+		let urllc = Snssai {
+			sst: 2,
+			..Default::default()
+		};
+		let restricted = NonEmpty::new(embb.clone());
+		let availability = [(&tai("00000A"), &restricted)]
+			.into_iter()
+			.collect::<NssaiAvailability>();
+
+		assert!(availability.is_available(&tai("00000a"), &embb));
+		assert!(!availability.is_available(&tai("00000a"), &urllc));
+		assert!(availability.is_available(&tai("000001"), &urllc));
+	}
+}
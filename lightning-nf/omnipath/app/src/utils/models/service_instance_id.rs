@@ -0,0 +1,67 @@
+use std::fmt;
+
+use oasbi::nrf::types::ServiceName;
+
+/// Identifier of an `NfService` within the NF profile, `{service_name}-{n}`
+/// where `n` counts earlier services of the same name. Unlike the service's
+/// position in the configured list it survives reordering of the list.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServiceInstanceId(String);
+
+impl ServiceInstanceId {
+	/// Assigns an id to every service name, unique across `service_names`.
+	pub fn assign(service_names: &[ServiceName]) -> Vec<Self> {
+		service_names
+			.iter()
+			.enumerate()
+			.map(|(i, service_name)| {
+				let occurrence = service_names[..i]
+					.iter()
+					.filter(|earlier| *earlier == service_name)
+					.count();
+				Self(format!("{service_name}-{occurrence}"))
+			})
+			.collect()
+	}
+
+	pub fn into_inner(self) -> String {
+		self.0
+	}
+}
+
+impl fmt::Display for ServiceInstanceId {
+	fn fmt(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+	) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use super::*;
+
+	#[test]
+	fn test_service_instance_ids_are_unique_and_stable() {
+		let services = [
+			ServiceName::NamfComm,
+			ServiceName::NamfEvts,
+			ServiceName::NamfComm,
+		];
+		let ids = ServiceInstanceId::assign(&services);
+		assert_eq!(ids, ServiceInstanceId::assign(&services));
+		assert_eq!(ids.iter().collect::<HashSet<_>>().len(), services.len());
+
+		let reordered = [
+			ServiceName::NamfEvts,
+			ServiceName::NamfComm,
+			ServiceName::NamfComm,
+		];
+		let reordered_ids = ServiceInstanceId::assign(&reordered);
+		assert_eq!(ids[1], reordered_ids[0]);
+		assert_eq!(ids[0], reordered_ids[1]);
+	}
+}
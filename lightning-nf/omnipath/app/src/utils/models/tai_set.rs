@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+
+use oasbi::common::{Tac, Tai};
+use rustc_hash::FxBuildHasher;
+
+/// Set of TAIs matched regardless of how their TAC hex digits are cased. TACs
+/// are kept lowercase, the form those converted from NGAP take.
+#[derive(Debug, Clone, Default)]
+pub struct TaiSet(HashSet<Tai, FxBuildHasher>);
+
+impl TaiSet {
+	/// Returns `false`, leaving the set as is, if the TAI is present already.
+	pub fn insert(
+		&mut self,
+		tai: &Tai,
+	) -> bool {
+		self.0.insert(normalize(tai))
+	}
+
+	pub fn contains(
+		&self,
+		tai: &Tai,
+	) -> bool {
+		self.0.contains(&normalize(tai))
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl<'a> FromIterator<&'a Tai> for TaiSet {
+	fn from_iter<I: IntoIterator<Item = &'a Tai>>(iter: I) -> Self {
+		let mut set = Self::default();
+		for tai in iter {
+			set.insert(tai);
+		}
+		set
+	}
+}
+
+pub(super) fn normalize(tai: &Tai) -> Tai {
+	let tac = tai.tac.as_str().to_ascii_lowercase();
+	Tai {
+		// SAFETY: Lowercasing keeps a valid hex TAC valid.
+		tac: unsafe { Tac::new_unchecked(tac) },
+		..tai.clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tai(tac: &str) -> Tai {
+		Tai {
+			tac: unsafe { Tac::new_unchecked(tac.to_string()) },
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_tac_case_is_normalized() {
+		let mut set = [tai("00000A"), tai("000001")].iter().collect::<TaiSet>();
+		assert_eq!(set.len(), 2);
+		assert!(set.contains(&tai("00000a")));
+		assert!(set.contains(&tai("00000A")));
+		assert!(!set.contains(&tai("000002")));
+		assert!(!set.insert(&tai("00000a")));
+	}
+}
@@ -0,0 +1,166 @@
+use std::{future::Future, time::Duration};
+
+/// Delays growing by `multiplier` from `base` up to `max`, for as long as it
+/// is iterated. Bound it with `take` to limit the retries.
+///
+/// With a jitter `j`, each delay is drawn uniformly from `[d * (1 - j), d]`,
+/// so peers retrying at once spread out.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+	current: Duration,
+	max: Duration,
+	multiplier: f64,
+	jitter: f64,
+}
+
+impl Backoff {
+	/// Doubles the delays, without jitter.
+	pub fn new(
+		base: Duration,
+		max: Duration,
+	) -> Self {
+		Self {
+			current: base.min(max),
+			max,
+			multiplier: 2.0,
+			jitter: 0.0,
+		}
+	}
+
+	/// Clamped to at least `1.0`, the delays never shrink.
+	pub fn multiplier(
+		mut self,
+		multiplier: f64,
+	) -> Self {
+		self.multiplier = multiplier.max(1.0);
+		self
+	}
+
+	/// Fraction of each delay that may be shaved off at random, clamped to
+	/// `0.0..=1.0`.
+	pub fn jitter(
+		mut self,
+		jitter: f64,
+	) -> Self {
+		self.jitter = jitter.clamp(0.0, 1.0);
+		self
+	}
+}
+
+impl Iterator for Backoff {
+	type Item = Duration;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let delay = self.current;
+		self.current = self.current.mul_f64(self.multiplier).min(self.max);
+		Some(delay.mul_f64(1.0 - self.jitter * fastrand::f64()))
+	}
+}
+
+/// Runs `operation` until it succeeds, fails with an error `should_retry`
+/// rejects, or `delays` run out, sleeping the next delay between attempts.
+/// The last error is returned on failure.
+pub async fn retry<T, E, Fut>(
+	delays: impl IntoIterator<Item = Duration>,
+	mut should_retry: impl FnMut(&E) -> bool,
+	mut operation: impl FnMut() -> Fut,
+) -> Result<T, E>
+where
+	Fut: Future<Output = Result<T, E>>,
+{
+	let mut delays = delays.into_iter();
+	loop {
+		let error = match operation().await {
+			Ok(value) => return Ok(value),
+			Err(error) => error,
+		};
+		match delays.next() {
+			Some(delay) if should_retry(&error) => tokio::time::sleep(delay).await,
+			_ => return Err(error),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+
+	use super::*;
+
+	const MS: Duration = Duration::from_millis(1);
+
+	#[test]
+	fn test_delays_cap_at_max() {
+		let delays = Backoff::new(100 * MS, 1000 * MS)
+			.take(6)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			delays,
+			[100 * MS, 200 * MS, 400 * MS, 800 * MS, 1000 * MS, 1000 * MS]
+		);
+
+		let delays = Backoff::new(10 * MS, 100 * MS)
+			.multiplier(3.0)
+			.take(4)
+			.collect::<Vec<_>>();
+		assert_eq!(delays, [10 * MS, 30 * MS, 90 * MS, 100 * MS]);
+	}
+
+	#[test]
+	fn test_jitter_stays_within_bounds() {
+		let jittered = Backoff::new(100 * MS, 1000 * MS).jitter(0.5);
+		let plain = Backoff::new(100 * MS, 1000 * MS);
+		for (delay, max) in jittered.zip(plain).take(1000) {
+			assert!(delay <= max);
+			assert!(delay >= max / 2);
+		}
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn test_retry_stops_on_success_or_permanent_error() {
+		let attempts = Cell::new(0);
+		let result: Result<u32, &str> = retry(
+			Backoff::new(10 * MS, 100 * MS).take(5),
+			|_| true,
+			|| {
+				attempts.set(attempts.get() + 1);
+				async {
+					if attempts.get() < 3 {
+						Err("transient")
+					} else {
+						Ok(7)
+					}
+				}
+			},
+		)
+		.await;
+		assert_eq!(result, Ok(7));
+		assert_eq!(attempts.get(), 3);
+
+		attempts.set(0);
+		let result: Result<u32, &str> = retry(
+			Backoff::new(10 * MS, 100 * MS).take(5),
+			|error| *error == "transient",
+			|| {
+				attempts.set(attempts.get() + 1);
+				async { Err("permanent") }
+			},
+		)
+		.await;
+		assert_eq!(result, Err("permanent"));
+		assert_eq!(attempts.get(), 1);
+
+		attempts.set(0);
+		let result: Result<u32, &str> = retry(
+			Backoff::new(10 * MS, 100 * MS).take(2),
+			|_| true,
+			|| {
+				attempts.set(attempts.get() + 1);
+				async { Err("transient") }
+			},
+		)
+		.await;
+		assert_eq!(result, Err("transient"));
+		assert_eq!(attempts.get(), 3);
+	}
+}
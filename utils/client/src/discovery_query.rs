@@ -0,0 +1,105 @@
+use oasbi::common::{Dnn, NfType, Snssai};
+use openapi_nrf::models::SearchNfInstancesQueryParams;
+
+/// Builds the query of an NF discovery, TS 29.510 6.2.3.2.3.1.
+///
+/// ```ignore
+/// let query = DiscoveryQuery::for_smf()
+/// 	.with_snssai(snssai)
+/// 	.with_dnn("internet")
+/// 	.build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiscoveryQuery {
+	target_nf_type: NfType,
+	requester_nf_type: NfType,
+	snssais: Vec<Snssai>,
+	dnn: Option<String>,
+}
+
+impl DiscoveryQuery {
+	/// Discovers NFs of `target_nf_type`, requested by the AMF unless
+	/// [`DiscoveryQuery::requested_by`] says otherwise.
+	pub fn new(target_nf_type: NfType) -> Self {
+		Self {
+			target_nf_type,
+			requester_nf_type: NfType::Amf,
+			snssais: Vec::new(),
+			dnn: None,
+		}
+	}
+
+	pub fn for_smf() -> Self {
+		Self::new(NfType::Smf)
+	}
+
+	pub fn for_ausf() -> Self {
+		Self::new(NfType::Ausf)
+	}
+
+	pub fn requested_by(
+		mut self,
+		requester_nf_type: NfType,
+	) -> Self {
+		self.requester_nf_type = requester_nf_type;
+		self
+	}
+
+	/// Restricts the NFs to those serving the slice, may be repeated for NFs
+	/// serving any of the slices.
+	pub fn with_snssai(
+		mut self,
+		snssai: Snssai,
+	) -> Self {
+		self.snssais.push(snssai);
+		self
+	}
+
+	pub fn with_dnn(
+		mut self,
+		dnn: impl Into<String>,
+	) -> Self {
+		self.dnn = Some(dnn.into());
+		self
+	}
+
+	pub fn build(self) -> SearchNfInstancesQueryParams {
+		SearchNfInstancesQueryParams {
+			target_nf_type: self.target_nf_type,
+			requester_nf_type: self.requester_nf_type,
+			snssais: (!self.snssais.is_empty()).then_some(self.snssais),
+			dnn: self.dnn.map(Dnn),
+			..Default::default()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use oasbi::common::SnssaiSd;
+
+	use super::*;
+
+	#[test]
+	fn test_smf_discovery_query_carries_its_filters() {
+		let snssai = Snssai {
+			sst: 1,
+			sd: Some(SnssaiSd::try_from("000001".to_owned()).unwrap()),
+		};
+		let query = DiscoveryQuery::for_smf()
+			.with_snssai(snssai.clone())
+			.with_dnn("internet")
+			.build();
+
+		assert_eq!(query.target_nf_type, NfType::Smf);
+		assert_eq!(query.requester_nf_type, NfType::Amf);
+		assert_eq!(query.snssais, Some(vec![snssai]));
+		assert_eq!(query.dnn, Some(Dnn("internet".to_owned())));
+
+		// Filters left out are absent from the query.
+		let query = DiscoveryQuery::for_ausf().build();
+		assert_eq!(query.target_nf_type, NfType::Ausf);
+		assert_eq!(query.snssais, None);
+		assert_eq!(query.dnn, None);
+	}
+}
@@ -0,0 +1,100 @@
+use reqwest::{Client, ClientBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::TlsVersion;
+
+/// Options of the HTTP clients towards the NRF and the peer NFs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpClientConfig {
+	/// Logs every read and write of the connections at trace level, under the
+	/// `reqwest::connect::verbose` target.
+	pub connection_verbose: bool,
+	/// Servers offering only older TLS versions are refused.
+	pub min_tls_version: TlsVersion,
+}
+
+impl HttpClientConfig {
+	pub fn builder(&self) -> ClientBuilder {
+		Client::builder()
+			.connection_verbose(self.connection_verbose)
+			.min_tls_version(self.min_tls_version.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Mutex, Once};
+
+	use log::{Level, LevelFilter, Log, Metadata, Record};
+	use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+	use super::*;
+
+	const VERBOSE_TARGET: &str = "reqwest::connect::verbose";
+
+	/// Keeps the messages of the verbose connection logs.
+	struct VerboseLogs(Mutex<Vec<String>>);
+
+	impl Log for VerboseLogs {
+		fn enabled(
+			&self,
+			metadata: &Metadata,
+		) -> bool {
+			metadata.level() <= Level::Trace
+		}
+
+		fn log(
+			&self,
+			record: &Record,
+		) {
+			if record.target() == VERBOSE_TARGET {
+				self.0.lock().unwrap().push(record.args().to_string());
+			}
+		}
+
+		fn flush(&self) {}
+	}
+
+	static LOGS: VerboseLogs = VerboseLogs(Mutex::new(Vec::new()));
+	static INIT: Once = Once::new();
+
+	/// Number of verbose log lines about a request to a fresh local server.
+	async fn verbose_lines(config: &HttpClientConfig) -> usize {
+		INIT.call_once(|| {
+			log::set_logger(&LOGS).unwrap();
+			log::set_max_level(LevelFilter::Trace);
+		});
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		tokio::spawn(async move {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			stream
+				.write_all(b"HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n")
+				.await
+				.unwrap();
+		});
+
+		let client = config.builder().build().unwrap();
+		client.get(format!("http://{addr}/")).send().await.unwrap();
+		// Requests of concurrently running tests are told apart by the port in
+		// their Host header.
+		let host = addr.to_string();
+		LOGS.0
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|line| line.contains(&host))
+			.count()
+	}
+
+	#[tokio::test]
+	async fn test_connection_verbose_is_opt_in() {
+		assert!(!HttpClientConfig::default().connection_verbose);
+		assert_eq!(verbose_lines(&HttpClientConfig::default()).await, 0);
+
+		let verbose =
+			serde_json::from_str::<HttpClientConfig>(r#"{"connectionVerbose": true}"#).unwrap();
+		assert!(verbose_lines(&verbose).await > 0);
+	}
+}
@@ -2,8 +2,10 @@
 #![feature(adt_const_params)]
 #![feature(async_closure)]
 
-use std::{backtrace::Backtrace, error::Error, fmt::Debug};
+use std::{backtrace::Backtrace, error::Error, fmt::Debug, iter};
 
+use bytes::Bytes;
+use futures_core::TryStream;
 use http::{
 	Request as HttpRequest,
 	Version,
@@ -17,13 +19,27 @@ use thiserror::Error;
 use tracing::trace;
 
 mod content_type;
+mod discovery_query;
 mod header_map_serializer;
+mod http_client_config;
+#[cfg(any(test, feature = "mock-nrf"))]
+pub mod mock_nrf;
 pub mod nf_clients;
 pub mod nrf_client;
+mod query_params;
+mod request_limiter;
+mod scp_routing;
 pub mod token_store;
+mod tls_version;
 
 pub use content_type::ContentType;
+pub use discovery_query::DiscoveryQuery;
 pub use header_map_serializer::{HeaderSerDeError, to_headers};
+pub use http_client_config::HttpClientConfig;
+pub use query_params::{QueryParams, QueryParamsError};
+pub use request_limiter::RequestLimiter;
+pub use scp_routing::{ScpRouting, TARGET_API_ROOT};
+pub use tls_version::TlsVersion;
 
 pub struct NFConfig {}
 
@@ -79,6 +95,9 @@ pub enum GenericClientError {
 	#[error("QuerySerDeError: Invalid Query {0}")]
 	QuerySerDeError(#[from] serde_urlencoded::ser::Error),
 
+	#[error("QueryParamsError: Invalid Query Parameters {0}")]
+	QueryParamsError(#[from] QueryParamsError),
+
 	#[error("QuerySerDeError: Invalid Form {0}")]
 	UrlFormEncodedError(#[from] serde_qs::Error),
 
@@ -98,6 +117,54 @@ pub enum GenericClientError {
 	TowerHttpError(#[from] tower_reqwest::Error),
 }
 
+impl GenericClientError {
+	/// HTTP status code of the response, if the peer answered at all.
+	pub fn status_code(&self) -> Option<u16> {
+		match self {
+			GenericClientError::InvalidResponse(status, ..) => Some(*status),
+			GenericClientError::ClientRequestError(e) => e.status().map(|s| s.as_u16()),
+			_ => None,
+		}
+	}
+
+	/// Application error cause carried in the `ProblemDetails` of the
+	/// response, if any.
+	pub fn problem_cause(&self) -> Option<&str> {
+		match self {
+			GenericClientError::InvalidResponse(_, Some(problem), _) => problem.cause.as_deref(),
+			_ => None,
+		}
+	}
+
+	/// The request timed out, either connecting or awaiting the response.
+	pub fn is_timeout(&self) -> bool {
+		self.reqwest_error().is_some_and(reqwest::Error::is_timeout)
+	}
+
+	/// No connection to the peer could be established.
+	pub fn is_connect(&self) -> bool {
+		self.reqwest_error().is_some_and(reqwest::Error::is_connect)
+	}
+
+	/// Sending the request body or reading the response body failed.
+	pub fn is_body(&self) -> bool {
+		self.reqwest_error().is_some_and(reqwest::Error::is_body)
+	}
+
+	/// Whether the request may be retried as is. Body errors are not, the peer
+	/// may have acted on the request already.
+	pub fn is_transient(&self) -> bool {
+		self.is_timeout() || self.is_connect()
+	}
+
+	/// The `reqwest::Error` behind this error, also when wrapped by the tower
+	/// client.
+	fn reqwest_error(&self) -> Option<&reqwest::Error> {
+		iter::successors(Some(self as &(dyn Error + 'static)), |&err| err.source())
+			.find_map(|err| err.downcast_ref::<reqwest::Error>())
+	}
+}
+
 pub fn remove_leading_slash(input: &str) -> &str {
 	if input.starts_with('/') {
 		&input[1..]
@@ -106,12 +173,43 @@ pub fn remove_leading_slash(input: &str) -> &str {
 	}
 }
 
+/// Layout of JSON bodies, compact unless pretty printing is asked for to
+/// debug the interop with strict peers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+	#[default]
+	Compact,
+	Pretty,
+}
+
+impl JsonFormat {
+	/// The format of outgoing JSON bodies, pretty with the `pretty-json`
+	/// feature.
+	pub const OUTGOING: JsonFormat = if cfg!(feature = "pretty-json") {
+		JsonFormat::Pretty
+	} else {
+		JsonFormat::Compact
+	};
+
+	pub fn encode<B: Serialize>(
+		self,
+		body: &B,
+	) -> Result<Vec<u8>, serde_json::Error> {
+		match self {
+			JsonFormat::Compact => serde_json::to_vec(body),
+			JsonFormat::Pretty => serde_json::to_vec_pretty(body),
+		}
+	}
+}
+
+/// Encodes `body` in full, so requests carry a `Content-Length` rather than
+/// being chunked, as NRFs may require of JSON and form bodies.
 pub fn serialize_body<B: Serialize>(
 	body: &B,
 	encoding_type: ContentType,
 ) -> Result<Body, GenericClientError> {
 	let encoded = match encoding_type {
-		ContentType::AppJson => serde_json::to_vec(body)?,
+		ContentType::AppJson | ContentType::AppPatchJson => JsonFormat::OUTGOING.encode(body)?,
 		ContentType::AppForm => {
 			let mut writer = vec![];
 			serde_qs::to_writer(body, &mut writer)?;
@@ -119,23 +217,55 @@ pub fn serialize_body<B: Serialize>(
 		}
 		_ => todo!(),
 	};
+	trace!("Serialized body: {}", String::from_utf8_lossy(&encoded));
 	Ok(encoded.into())
 }
 
+/// A body sent as the chunks of `stream` come, with chunked transfer encoding
+/// as its length is not known upfront. Length delimited bodies come from
+/// [`serialize_body`].
+pub fn streaming_body<S>(stream: S) -> Body
+where
+	S: TryStream + Send + 'static,
+	S::Error: Into<Box<dyn Error + Send + Sync>>,
+	Bytes: From<S::Ok>,
+{
+	Body::wrap_stream(stream)
+}
+
+/// What [`prepare_request`] builds a request of.
+pub struct RequestParams<'a, H, Q, B> {
+	pub url: Url,
+	pub path: &'a str,
+	pub method: Method,
+	pub header: Option<&'a H>,
+	pub query: Option<&'a Q>,
+	pub body: Option<&'a B>,
+	pub encoding_type: ContentType,
+	/// The SCP the request is sent through, directly to `url` without.
+	pub scp_routing: Option<&'a ScpRouting>,
+}
+
+/// Builds a request to `path` of `url`, sent through the SCP of
+/// `scp_routing` when given.
 pub fn prepare_request<H, Q, B>(
-	url: Url,
-	path: &str,
-	method: Method,
-	header: Option<&H>,
-	query: Option<&Q>,
-	body: Option<&B>,
-	encoding_type: ContentType,
+	params: RequestParams<'_, H, Q, B>
 ) -> Result<Request, GenericClientError>
 where
 	Q: Serialize,
 	H: Serialize,
 	B: Serialize,
 {
+	let RequestParams {
+		url,
+		path,
+		method,
+		header,
+		query,
+		body,
+		encoding_type,
+		scp_routing,
+	} = params;
 	let mut url = url;
 	url.set_path(remove_leading_slash(path));
 	trace!("Complete url: {path:?}");
@@ -156,6 +286,9 @@ where
 	*request.body_mut() = body
 		.map(|t| serialize_body(&t, encoding_type))
 		.transpose()?;
+	if let Some(scp_routing) = scp_routing {
+		scp_routing.route(&mut request)?;
+	}
 	Ok(request)
 }
 
@@ -197,3 +330,121 @@ where
 
 	Ok(req)
 }
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	use super::*;
+
+	async fn get(
+		url: String,
+		timeout: Duration,
+	) -> GenericClientError {
+		let client = Client::builder().timeout(timeout).build().unwrap();
+		client.get(url).send().await.unwrap_err().into()
+	}
+
+	#[tokio::test]
+	async fn test_timeout_is_transient() {
+		// Accepts the connection but never answers.
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		tokio::spawn(async move {
+			let _stream = listener.accept().await.unwrap();
+			tokio::time::sleep(Duration::from_secs(60)).await;
+		});
+
+		let error = get(format!("http://{addr}"), Duration::from_millis(50)).await;
+		assert!(error.is_timeout());
+		assert!(!error.is_connect());
+		assert!(error.is_transient());
+	}
+
+	#[tokio::test]
+	async fn test_refused_connection_is_transient() {
+		let addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+
+		let error = get(format!("http://{addr}"), Duration::from_secs(5)).await;
+		assert!(error.is_connect());
+		assert!(!error.is_timeout());
+		assert!(!error.is_body());
+		assert!(error.is_transient());
+	}
+
+	/// Lower cased head of `body` as sent in a `POST` to a local server.
+	async fn request_head(body: Body) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		let server = tokio::spawn(async move {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			let mut received = Vec::new();
+			let mut buf = [0; 1024];
+			while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+				let n = stream.read(&mut buf).await.unwrap();
+				received.extend_from_slice(&buf[..n]);
+			}
+			stream
+				.write_all(b"HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n")
+				.await
+				.unwrap();
+			String::from_utf8_lossy(&received).to_lowercase()
+		});
+		Client::new()
+			.post(format!("http://{addr}/"))
+			.body(body)
+			.send()
+			.await
+			.unwrap();
+		let received = server.await.unwrap();
+		received.split("\r\n\r\n").next().unwrap().to_owned()
+	}
+
+	#[tokio::test]
+	async fn test_json_body_carries_content_length() {
+		let body = serde_json::json!({ "nfStatus": "REGISTERED" });
+		let encoded = serde_json::to_vec(&body).unwrap();
+		let head = request_head(serialize_body(&body, ContentType::AppJson).unwrap()).await;
+		assert!(
+			head.contains(&format!("content-length: {}", encoded.len())),
+			"{head}"
+		);
+		assert!(!head.contains("transfer-encoding"), "{head}");
+	}
+
+	#[test]
+	fn test_pretty_json_is_indented() {
+		let body = serde_json::json!({ "nfStatus": "REGISTERED", "priority": 1 });
+		let compact = String::from_utf8(JsonFormat::Compact.encode(&body).unwrap()).unwrap();
+		let pretty = String::from_utf8(JsonFormat::Pretty.encode(&body).unwrap()).unwrap();
+
+		assert!(!compact.contains('\n'), "{compact}");
+		assert!(
+			pretty.contains("\n  \"nfStatus\": \"REGISTERED\""),
+			"{pretty}"
+		);
+		let reparsed: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+		assert_eq!(reparsed, body);
+		assert_eq!(JsonFormat::default(), JsonFormat::Compact);
+	}
+
+	#[tokio::test]
+	async fn test_streaming_body_is_chunked() {
+		let chunks = futures::stream::iter([
+			Ok::<_, std::io::Error>(Bytes::from_static(b"{")),
+			Ok(Bytes::from_static(b"}")),
+		]);
+		let head = request_head(streaming_body(chunks)).await;
+		assert!(head.contains("transfer-encoding: chunked"), "{head}");
+		assert!(!head.contains("content-length"), "{head}");
+	}
+}
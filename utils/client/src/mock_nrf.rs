@@ -0,0 +1,357 @@
+//! In memory NRF serving the NF Management, NF Discovery and Access Token
+//! operations used by [`NrfClient`](crate::nrf_client::NrfClient), for tests
+//! of NRF dependent code. Enabled by the `mock-nrf` feature.
+
+use std::{
+	collections::HashMap,
+	net::SocketAddr,
+	sync::{
+		Arc,
+		Mutex,
+		atomic::{AtomicUsize, Ordering},
+	},
+	time::Duration,
+};
+
+use axum::{
+	Json,
+	Router,
+	body::Bytes,
+	extract::{Path, Request, State},
+	http::{
+		HeaderName,
+		HeaderValue,
+		Method,
+		StatusCode,
+		Uri,
+		header::{CONTENT_TYPE, LOCATION},
+	},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	routing::{get, post, put},
+};
+use reqwest::Url;
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// The NRF operations answered by [`MockNrf`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockNrfOperation {
+	RegisterNfInstance,
+	DeregisterNfInstance,
+	UpdateNfInstance,
+	SearchNfInstances,
+	RetrieveCompleteSearch,
+	AccessTokenRequest,
+}
+
+/// A scripted answer to an operation.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+	pub status: StatusCode,
+	pub headers: Vec<(HeaderName, HeaderValue)>,
+	pub body: Option<Value>,
+	/// Time waited before answering, e.g. to trigger client timeouts.
+	pub delay: Duration,
+}
+
+impl MockResponse {
+	pub fn new(status: StatusCode) -> Self {
+		Self {
+			status,
+			headers: Vec::new(),
+			body: None,
+			delay: Duration::ZERO,
+		}
+	}
+
+	pub fn json(
+		status: StatusCode,
+		body: Value,
+	) -> Self {
+		Self {
+			body: Some(body),
+			..Self::new(status)
+		}
+	}
+
+	/// A `ProblemDetails` answer carrying `cause`.
+	pub fn problem(
+		status: StatusCode,
+		cause: &str,
+	) -> Self {
+		Self::json(status, json!({ "status": status.as_u16(), "cause": cause })).with_header(
+			CONTENT_TYPE,
+			HeaderValue::from_static("application/problem+json"),
+		)
+	}
+
+	pub fn with_header(
+		mut self,
+		name: HeaderName,
+		value: HeaderValue,
+	) -> Self {
+		self.headers.push((name, value));
+		self
+	}
+
+	pub fn with_delay(
+		mut self,
+		delay: Duration,
+	) -> Self {
+		self.delay = delay;
+		self
+	}
+
+	async fn render(self) -> Response {
+		tokio::time::sleep(self.delay).await;
+		let mut response = match self.body {
+			Some(body) => (self.status, Json(body)).into_response(),
+			None => self.status.into_response(),
+		};
+		for (name, value) in self.headers {
+			response.headers_mut().insert(name, value);
+		}
+		response
+	}
+}
+
+/// A request received by the [`MockNrf`].
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+	pub operation: MockNrfOperation,
+	pub method: Method,
+	pub uri: Uri,
+	pub body: Bytes,
+}
+
+#[derive(Default)]
+struct MockNrfState {
+	responses: Mutex<HashMap<MockNrfOperation, MockResponse>>,
+	requests: Mutex<Vec<MockRequest>>,
+	inflight: AtomicUsize,
+	peak_inflight: AtomicUsize,
+}
+
+impl MockNrfState {
+	fn record(
+		&self,
+		operation: MockNrfOperation,
+		method: Method,
+		uri: Uri,
+		body: Bytes,
+	) -> Option<MockResponse> {
+		self.requests.lock().unwrap().push(MockRequest {
+			operation,
+			method,
+			uri,
+			body,
+		});
+		self.responses.lock().unwrap().get(&operation).cloned()
+	}
+}
+
+/// An NRF listening on a random local port until dropped.
+///
+/// Without a scripted response an operation succeeds: registration echoes
+/// the profile with `201 Created` and the instance `Location`, updates and
+/// deregistration answer `204 No Content`, discovery finds no instances and
+/// stores no searches, and token requests are granted a bearer token.
+pub struct MockNrf {
+	addr: SocketAddr,
+	state: Arc<MockNrfState>,
+	server: JoinHandle<()>,
+}
+
+impl MockNrf {
+	pub async fn start() -> Self {
+		let state = Arc::new(MockNrfState::default());
+		let router = Router::new()
+			.route(
+				"/nnrf-nfm/v1/nf-instances/:nf_instance_id",
+				put(register_nf_instance)
+					.delete(deregister_nf_instance)
+					.patch(update_nf_instance),
+			)
+			.route("/nnrf-disc/v1/nf-instances", get(search_nf_instances))
+			.route(
+				"/nnrf-disc/v1/searches/:search_id/complete",
+				get(retrieve_complete_search),
+			)
+			.route("/oauth2/token", post(access_token_request))
+			.layer(middleware::from_fn_with_state(
+				state.clone(),
+				track_inflight,
+			))
+			.with_state(state.clone());
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		let server = tokio::spawn(async move {
+			axum::serve(listener, router).await.unwrap();
+		});
+		Self {
+			addr,
+			state,
+			server,
+		}
+	}
+
+	pub fn url(&self) -> Url {
+		Url::parse(&format!("http://{}", self.addr)).unwrap()
+	}
+
+	/// Answers every later request for `operation` with `response`.
+	pub fn respond(
+		&self,
+		operation: MockNrfOperation,
+		response: MockResponse,
+	) {
+		self.state
+			.responses
+			.lock()
+			.unwrap()
+			.insert(operation, response);
+	}
+
+	/// The requests received so far, oldest first.
+	pub fn requests(&self) -> Vec<MockRequest> {
+		self.state.requests.lock().unwrap().clone()
+	}
+
+	/// The most requests answered at once so far.
+	pub fn peak_inflight(&self) -> usize {
+		self.state.peak_inflight.load(Ordering::SeqCst)
+	}
+}
+
+impl Drop for MockNrf {
+	fn drop(&mut self) {
+		self.server.abort();
+	}
+}
+
+async fn track_inflight(
+	State(state): State<Arc<MockNrfState>>,
+	request: Request,
+	next: Next,
+) -> Response {
+	let inflight = state.inflight.fetch_add(1, Ordering::SeqCst) + 1;
+	state.peak_inflight.fetch_max(inflight, Ordering::SeqCst);
+	let response = next.run(request).await;
+	state.inflight.fetch_sub(1, Ordering::SeqCst);
+	response
+}
+
+async fn register_nf_instance(
+	State(state): State<Arc<MockNrfState>>,
+	Path(nf_instance_id): Path<String>,
+	method: Method,
+	uri: Uri,
+	body: Bytes,
+) -> Response {
+	let operation = MockNrfOperation::RegisterNfInstance;
+	if let Some(response) = state.record(operation, method, uri, body.clone()) {
+		return response.render().await;
+	}
+	let Ok(profile) = serde_json::from_slice::<Value>(&body) else {
+		return MockResponse::problem(StatusCode::BAD_REQUEST, "INVALID_MSG_FORMAT")
+			.render()
+			.await;
+	};
+	let location = format!("http://mock-nrf/nnrf-nfm/v1/nf-instances/{nf_instance_id}");
+	MockResponse::json(StatusCode::CREATED, profile)
+		.with_header(LOCATION, HeaderValue::try_from(location).unwrap())
+		.render()
+		.await
+}
+
+async fn deregister_nf_instance(
+	State(state): State<Arc<MockNrfState>>,
+	method: Method,
+	uri: Uri,
+) -> Response {
+	state
+		.record(
+			MockNrfOperation::DeregisterNfInstance,
+			method,
+			uri,
+			Bytes::new(),
+		)
+		.unwrap_or_else(|| MockResponse::new(StatusCode::NO_CONTENT))
+		.render()
+		.await
+}
+
+async fn update_nf_instance(
+	State(state): State<Arc<MockNrfState>>,
+	method: Method,
+	uri: Uri,
+	body: Bytes,
+) -> Response {
+	state
+		.record(MockNrfOperation::UpdateNfInstance, method, uri, body)
+		.unwrap_or_else(|| MockResponse::new(StatusCode::NO_CONTENT))
+		.render()
+		.await
+}
+
+async fn search_nf_instances(
+	State(state): State<Arc<MockNrfState>>,
+	method: Method,
+	uri: Uri,
+) -> Response {
+	state
+		.record(
+			MockNrfOperation::SearchNfInstances,
+			method,
+			uri,
+			Bytes::new(),
+		)
+		.unwrap_or_else(|| {
+			MockResponse::json(
+				StatusCode::OK,
+				json!({ "validityPeriod": 3600, "nfInstances": [] }),
+			)
+		})
+		.render()
+		.await
+}
+
+async fn retrieve_complete_search(
+	State(state): State<Arc<MockNrfState>>,
+	method: Method,
+	uri: Uri,
+) -> Response {
+	state
+		.record(
+			MockNrfOperation::RetrieveCompleteSearch,
+			method,
+			uri,
+			Bytes::new(),
+		)
+		.unwrap_or_else(|| MockResponse::problem(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND"))
+		.render()
+		.await
+}
+
+async fn access_token_request(
+	State(state): State<Arc<MockNrfState>>,
+	method: Method,
+	uri: Uri,
+	body: Bytes,
+) -> Response {
+	state
+		.record(MockNrfOperation::AccessTokenRequest, method, uri, body)
+		.unwrap_or_else(|| {
+			MockResponse::json(
+				StatusCode::OK,
+				json!({
+					"access_token": "mock-token",
+					"token_type": "Bearer",
+					"expires_in": 3600,
+				}),
+			)
+		})
+		.render()
+		.await
+}
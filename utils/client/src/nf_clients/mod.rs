@@ -17,7 +17,7 @@ use openapi_nrf::models::{
 	SearchNfInstancesQueryParams,
 	SearchResult,
 };
-use reqwest::{Body, Client, ClientBuilder, Request, Response};
+use reqwest::{Body, Client, Request, Response};
 use serde::Serialize;
 use thiserror::Error;
 use tower::{
@@ -44,7 +44,9 @@ use url::Url;
 pub mod amf;
 
 use crate::{
+	DiscoveryQuery,
 	GenericClientError,
+	HttpClientConfig,
 	nrf_client::{NrfClient, NrfDiscoveryError},
 	to_headers,
 };
@@ -63,11 +65,9 @@ pub trait NfClientController {
 		&self,
 		requester_nf_type: NfType,
 	) -> SearchNfInstancesQueryParams {
-		SearchNfInstancesQueryParams {
-			requester_nf_type,
-			target_nf_type: Self::CLIENT_TYPE,
-			..Default::default()
-		}
+		DiscoveryQuery::new(Self::CLIENT_TYPE)
+			.requested_by(requester_nf_type)
+			.build()
 	}
 }
 
@@ -89,6 +89,7 @@ where
 	pub async fn new(
 		nrf_client: Arc<NrfClient>,
 		controller: T,
+		client_config: &HttpClientConfig,
 	) -> Result<Self, NfClientError> {
 		// let url = controller.base_url();
 		let search_params = controller.get_search_params(APP_TYPE);
@@ -99,8 +100,7 @@ where
 			.search_nf_instance(search_params, header_params)
 			.await?;
 		let nf_profile = controller.profile_selection(search_result);
-		let builder = ClientBuilder::new();
-		let client = builder.build()?;
+		let client = client_config.builder().build()?;
 
 		let service = ServiceBuilder::new()
 			// Mark the `Authorization` request header as sensitive so it doesn't show in logs
@@ -1,4 +1,4 @@
-use std::{backtrace::Backtrace, str::FromStr, sync::Arc};
+use std::{backtrace::Backtrace, collections::HashSet, str::FromStr, sync::Arc, time::Duration};
 
 use arc_swap::ArcSwap;
 use formatx::formatx;
@@ -11,8 +11,10 @@ use oasbi::{
 		AccessTokenReqScope,
 		NfInstanceId,
 		NfType,
+		ProblemDetails,
 		error::ConversionError,
 	},
+	nrf::types::NfProfile,
 	service_properties::{
 		NrfAccessTokenOperation,
 		NrfNFDiscoveryOperation,
@@ -38,17 +40,21 @@ use openapi_nrf::{
 		ServiceName,
 	},
 };
-use reqwest::{Client, Request, Url};
+use reqwest::{Client, Method, Request, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
-use tracing::trace;
+use tracing::{Instrument, Span, field, info_span, trace};
 
 use crate::{
 	ContentType,
 	GenericClientError,
+	QueryParams,
+	RequestLimiter,
+	RequestParams,
+	ScpRouting,
 	prepare_request,
-	token_store::{StoreError, TokenEntry, TokenStore},
+	token_store::{StoreError, TokenEntry, TokenStore, TokenStoreStats},
 };
 
 /// `TraitSatisfier` is an empty enum that exists solely to satisfy trait
@@ -75,6 +81,60 @@ pub struct InitConfig {
 	pub source: NfType,
 }
 
+/// What the NRF did with a `RegisterNFInstance` request, TS 29.510 5.2.2.2.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationOutcome {
+	/// `201 Created`, carrying the instance id taken from the `Location`
+	/// header, which the NRF may have chosen itself.
+	Created(NfInstanceId),
+	/// `200 OK`, a profile was already registered under the id and has been
+	/// replaced.
+	Updated,
+}
+
+/// Operation of a JSON Patch, RFC 6902, as used by `UpdateNFInstance`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchOperation {
+	Add,
+	Remove,
+	Replace,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PatchItem {
+	pub op: PatchOperation,
+	/// JSON pointer into the NF profile, like `/load`.
+	pub path: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub value: Option<Value>,
+}
+
+impl PatchItem {
+	/// Sets the member at `path`, whether the profile has it yet or not.
+	pub fn add(
+		path: impl Into<String>,
+		value: impl Into<Value>,
+	) -> Self {
+		Self {
+			op: PatchOperation::Add,
+			path: path.into(),
+			value: Some(value.into()),
+		}
+	}
+
+	pub fn replace(
+		path: impl Into<String>,
+		value: impl Into<Value>,
+	) -> Self {
+		Self {
+			op: PatchOperation::Replace,
+			path: path.into(),
+			value: Some(value.into()),
+		}
+	}
+}
+
 /// Configuration that gets populated after successful NF Instance registration
 /// with NRF.
 ///
@@ -92,11 +152,29 @@ pub struct NfConfig {
 	pub oauth_enabled: bool,
 }
 
+/// Upper bound on concurrent access token requests sent to the NRF, so that a
+/// burst of new peer service names does not flood it.
+pub const DEFAULT_MAX_INFLIGHT_TOKEN_REQUESTS: usize = 8;
+
+/// Upper bound on concurrent discovery requests sent to the NRF, searches and
+/// retrievals of stored searches alike.
+pub const DEFAULT_MAX_INFLIGHT_DISCOVERY_REQUESTS: usize = 8;
+
+/// Retries of an NRF registration failing on a timeout or refused
+/// connection, waiting from the base to the max delay in between.
+pub const NRF_REGISTRATION_RETRIES: usize = 4;
+pub const NRF_REGISTRATION_BASE_DELAY: Duration = Duration::from_millis(500);
+pub const NRF_REGISTRATION_MAX_DELAY: Duration = Duration::from_secs(8);
+
 pub struct NrfClient {
 	client: Client,
 	init_config: InitConfig,
 	nf_config: ArcSwap<NfConfig>,
 	nf_token_store: TokenStore<Vec<ServiceName>, AccessTokenRsp>,
+	token_request_limiter: RequestLimiter,
+	discovery_request_limiter: RequestLimiter,
+	/// Routes the requests through an SCP rather than to the NRF directly.
+	scp_routing: Option<ScpRouting>,
 }
 
 impl NrfClient {
@@ -108,6 +186,20 @@ impl NrfClient {
 		self.nf_config.load().heartbeat_timer
 	}
 
+	/// Interval of the heartbeats the NRF asked for on registration, `None`
+	/// before registering or when it asked for none.
+	pub fn heartbeat_interval(&self) -> Option<Duration> {
+		match self.get_heartbeat_timer() {
+			0 => None,
+			secs => Some(Duration::from_secs(secs)),
+		}
+	}
+
+	/// States of the cached access tokens, for diagnostics.
+	pub async fn token_store_stats(&self) -> TokenStoreStats {
+		self.nf_token_store.stats().await
+	}
+
 	#[inline]
 	fn get_oauth_enabled(&self) -> bool {
 		self.nf_config.load().oauth_enabled
@@ -127,27 +219,91 @@ impl NrfClient {
 			init_config,
 			nf_config: ArcSwap::from_pointee(NfConfig::default()),
 			nf_token_store: TokenStore::new(),
+			token_request_limiter: RequestLimiter::new(DEFAULT_MAX_INFLIGHT_TOKEN_REQUESTS),
+			discovery_request_limiter: RequestLimiter::new(DEFAULT_MAX_INFLIGHT_DISCOVERY_REQUESTS),
+			scp_routing: None,
 		}
 	}
 
+	/// Sends the requests through the SCP of `scp_routing`.
+	pub fn with_scp_routing(
+		mut self,
+		scp_routing: ScpRouting,
+	) -> Self {
+		self.scp_routing = Some(scp_routing);
+		self
+	}
+
+	/// Overrides [`DEFAULT_MAX_INFLIGHT_TOKEN_REQUESTS`].
+	pub fn with_max_inflight_token_requests(
+		mut self,
+		max_inflight: usize,
+	) -> Self {
+		self.token_request_limiter = RequestLimiter::new(max_inflight);
+		self
+	}
+
+	/// Overrides [`DEFAULT_MAX_INFLIGHT_DISCOVERY_REQUESTS`].
+	pub fn with_max_inflight_discovery_requests(
+		mut self,
+		max_inflight: usize,
+	) -> Self {
+		self.discovery_request_limiter = RequestLimiter::new(max_inflight);
+		self
+	}
+
+	/// Creates the span every NRF operation runs in. The `status` and `cause`
+	/// fields stay empty unless the operation fails, in which case they are
+	/// filled from the NRF response.
+	fn operation_span(
+		&self,
+		operation: &'static str,
+		nf_instance_id: NfInstanceId,
+	) -> Span {
+		info_span!(
+			"nrf_request",
+			operation,
+			nf_instance_id = %nf_instance_id.0,
+			target = %self.init_config.url,
+			status = field::Empty,
+			cause = field::Empty,
+			error = field::Empty,
+		)
+	}
+
 	pub async fn search_nf_instance(
 		&self,
 		query: SearchNfInstancesQueryParams,
 		header: SearchNfInstancesHeaderParams,
+	) -> Result<SearchResult, NrfDiscoveryError> {
+		let span = self.operation_span("SearchNFInstances", self.get_nf_id());
+		self.discovery_request_limiter
+			.run(self.search_nf_instance_inner(query, header))
+			.instrument(span.clone())
+			.await
+			.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	async fn search_nf_instance_inner(
+		&self,
+		query: SearchNfInstancesQueryParams,
+		header: SearchNfInstancesHeaderParams,
 	) -> Result<SearchResult, NrfDiscoveryError> {
 		let nrf_service_properties =
 			NrfService::NFDiscovery(NrfNFDiscoveryOperation::SearchNFInstances);
 		let method = nrf_service_properties.get_http_method();
 		let path = nrf_service_properties.get_path();
-		let request = prepare_request(
-			self.init_config.url.clone(),
-			&path,
+		let query = QueryParams::discovery(&query).map_err(GenericClientError::from)?;
+		let request = prepare_request(RequestParams {
+			url: self.init_config.url.clone(),
+			path: &path,
 			method,
-			Some(&header),
-			Some(&query),
-			Option::<&TraitSatisfier>::None,
-			ContentType::AppJson,
-		)?;
+			header: Some(&header),
+			query: Some(&query),
+			body: Option::<&TraitSatisfier>::None,
+			encoding_type: ContentType::AppJson,
+			scp_routing: self.scp_routing.as_ref(),
+		})?;
 		let response = self
 			.client
 			.execute(request)
@@ -178,27 +334,120 @@ impl NrfClient {
 		}
 	}
 
+	/// Every profile matching `query`, across the pages of a search the NRF
+	/// limited, e.g. by the `limit` of `query`, TS 29.510 6.2.3.2.3.1.
+	///
+	/// The NRF keeps the complete result of a limited search under the
+	/// `searchId` of the first page, it is only fetched once that page is
+	/// consumed. Profiles listed on both pages are yielded once.
+	pub fn discover_all(
+		&self,
+		query: SearchNfInstancesQueryParams,
+		header: SearchNfInstancesHeaderParams,
+	) -> NfDiscovery<'_> {
+		NfDiscovery {
+			nrf_client: self,
+			search: Some((query, header)),
+			search_id: None,
+			profiles: Vec::new().into_iter(),
+			seen: HashSet::new(),
+		}
+	}
+
+	async fn retrieve_complete_search(
+		&self,
+		search_id: &str,
+	) -> Result<Vec<NfProfile>, NrfDiscoveryError> {
+		let span = self.operation_span("RetrieveCompleteSearch", self.get_nf_id());
+		self.discovery_request_limiter
+			.run(self.retrieve_complete_search_inner(search_id))
+			.instrument(span.clone())
+			.await
+			.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	async fn retrieve_complete_search_inner(
+		&self,
+		search_id: &str,
+	) -> Result<Vec<NfProfile>, NrfDiscoveryError> {
+		// The search id is the NRF's, it goes in as a single encoded segment.
+		let mut search_url = self.init_config.url.clone();
+		search_url
+			.path_segments_mut()
+			.map_err(|_| {
+				GenericClientError::from(url::ParseError::RelativeUrlWithCannotBeABaseBase)
+			})?
+			.clear()
+			.extend(["nnrf-disc", "v1", "searches", search_id, "complete"]);
+		let request = prepare_request(RequestParams {
+			url: search_url.clone(),
+			path: search_url.path(),
+			method: Method::GET,
+			header: Option::<&TraitSatisfier>::None,
+			query: Option::<&TraitSatisfier>::None,
+			body: Option::<&TraitSatisfier>::None,
+			encoding_type: ContentType::AppJson,
+			scp_routing: self.scp_routing.as_ref(),
+		})?;
+		let response = self
+			.client
+			.execute(request)
+			.await
+			.map_err(GenericClientError::from)?;
+		match response.status().as_u16() {
+			200 => {
+				let result = response
+					.json::<StoredSearchResult>()
+					.await
+					.map_err(GenericClientError::from)?;
+				Ok(result.nf_instances)
+			}
+			status => {
+				let problem = response.json::<ProblemDetails>().await.ok();
+				Err(GenericClientError::InvalidResponse(
+					status,
+					problem,
+					Backtrace::capture(),
+				))?
+			}
+		}
+	}
+
 	pub async fn register_nf_instance(
 		&self,
 		nf_instance_id: NfInstanceId,
 		header: &RegisterNfInstanceHeaderParams,
 		body: &NfProfile1,
-	) -> Result<(NfProfile1, Option<NfInstanceId>), NrfManagementError> {
+	) -> Result<(NfProfile1, RegistrationOutcome), NrfManagementError> {
+		let span = self.operation_span("RegisterNFInstance", nf_instance_id);
+		self.register_nf_instance_inner(nf_instance_id, header, body)
+			.instrument(span.clone())
+			.await
+			.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	async fn register_nf_instance_inner(
+		&self,
+		nf_instance_id: NfInstanceId,
+		header: &RegisterNfInstanceHeaderParams,
+		body: &NfProfile1,
+	) -> Result<(NfProfile1, RegistrationOutcome), NrfManagementError> {
 		let nrf_service_properties =
 			NrfService::NFManagement(NrfNFManagementOperation::RegisterNFInstance);
 		let method = nrf_service_properties.get_http_method();
 		let path = formatx!(&nrf_service_properties.get_path(), nf_instance_id.0)
 			.map_err(GenericClientError::from)?;
 		trace!("path: {path:?}");
-		let request = prepare_request(
-			self.init_config.url.clone(),
-			&path,
+		let request = prepare_request(RequestParams {
+			url: self.init_config.url.clone(),
+			path: &path,
 			method,
-			Some(header),
-			Option::<&TraitSatisfier>::None,
-			Some(body),
-			ContentType::AppJson,
-		)?;
+			header: Some(header),
+			query: Option::<&TraitSatisfier>::None,
+			body: Some(body),
+			encoding_type: ContentType::AppJson,
+			scp_routing: self.scp_routing.as_ref(),
+		})?;
 		let response = self
 			.client
 			.execute(request)
@@ -209,12 +458,14 @@ impl NrfClient {
 				.await
 				.map_err(GenericClientError::from)?;
 		let res = match (status_code.as_u16(), response) {
-			(_, RegisterNfInstanceResponse::Status200 { body, .. }) => Ok((body, None)),
+			(_, RegisterNfInstanceResponse::Status200 { body, .. }) => {
+				Ok((body, RegistrationOutcome::Updated))
+			}
 			(_, RegisterNfInstanceResponse::Status201 { body, location, .. }) => {
 				if let Some(index) = location.rfind('/') {
 					trace!("location string: {}", &location[index..]);
 					match NfInstanceId::from_str(&location[index + 1..]) {
-						Ok(instance) => Ok((body, Some(instance))),
+						Ok(instance) => Ok((body, RegistrationOutcome::Created(instance))),
 						Err(e) => {
 							return Err(NrfManagementError::InvalidLocationSent(
 								e,
@@ -248,7 +499,7 @@ impl NrfClient {
 				Backtrace::capture(),
 			))?,
 		};
-		res.map(|(nf, id)| {
+		res.map(|(nf, outcome)| {
 			let heartbeat_timer = nf
 				.get()
 				.heart_beat_timer
@@ -258,7 +509,10 @@ impl NrfClient {
 				Some(Value::Bool(true)) => true,
 				_ => false,
 			};
-			let nf_id = id.map_or(nf_instance_id, |id| id);
+			let nf_id = match outcome {
+				RegistrationOutcome::Created(id) => id,
+				RegistrationOutcome::Updated => nf_instance_id,
+			};
 			let nf_config = NfConfig {
 				oauth_enabled,
 				heartbeat_timer,
@@ -266,26 +520,51 @@ impl NrfClient {
 			};
 			self.nf_config.store(Arc::new(nf_config));
 			trace!("NfConfig Updated: {:#?}", self.nf_config.load());
-			(nf, id)
+			(nf, outcome)
 		})
 	}
 
 	pub async fn deregister_nf_instance(&self) -> Result<(), NrfManagementError> {
+		let nf_instance_id = self.get_nf_id();
+		let span = self.operation_span("DeregisterNFInstance", nf_instance_id);
+		self.deregister_nf_instance_inner(nf_instance_id)
+			.instrument(span.clone())
+			.await
+			.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	/// Deregisters `nf_instance_id` rather than the live instance, e.g. the
+	/// registration a crashed predecessor left behind.
+	pub async fn deregister_instance_by_id(
+		&self,
+		nf_instance_id: NfInstanceId,
+	) -> Result<(), NrfManagementError> {
+		let span = self.operation_span("DeregisterNFInstance", nf_instance_id);
+		self.deregister_nf_instance_inner(nf_instance_id)
+			.instrument(span.clone())
+			.await
+			.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	async fn deregister_nf_instance_inner(
+		&self,
+		nf_instance_id: NfInstanceId,
+	) -> Result<(), NrfManagementError> {
 		let nrf_service_properties =
 			NrfService::NFManagement(NrfNFManagementOperation::DeregisterNFInstance);
 		let method = nrf_service_properties.get_http_method();
-		let nf_instance_id = self.get_nf_id();
 		let path = formatx!(&nrf_service_properties.get_path(), nf_instance_id.0)
 			.map_err(GenericClientError::from)?;
-		let mut request = prepare_request(
-			self.init_config.url.clone(),
-			&path,
+		let mut request = prepare_request(RequestParams {
+			url: self.init_config.url.clone(),
+			path: &path,
 			method,
-			Option::<&TraitSatisfier>::None,
-			Option::<&TraitSatisfier>::None,
-			Option::<&TraitSatisfier>::None,
-			ContentType::AppJson,
-		)?;
+			header: Option::<&TraitSatisfier>::None,
+			query: Option::<&TraitSatisfier>::None,
+			body: Option::<&TraitSatisfier>::None,
+			encoding_type: ContentType::AppJson,
+			scp_routing: self.scp_routing.as_ref(),
+		})?;
 		self.set_auth_token::<{ NfType::Nrf }>(&mut request, vec![ServiceName::NnrfNfm])
 			.await?;
 		let response = self
@@ -300,10 +579,15 @@ impl NrfClient {
 				.map_err(GenericClientError::from)?;
 		match (status_code.as_u16(), response) {
 			(_, DeregisterNfInstanceResponse::Status204) => Ok(()),
+			// The instance is already gone, e.g. a retried deregistration during
+			// shutdown, which is what deregistering asked for.
+			(_, DeregisterNfInstanceResponse::Status404(problem)) => {
+				trace!("Nf Instance already deregistered: {:?}", problem);
+				Ok(())
+			}
 			(status, DeregisterNfInstanceResponse::Status400(problem))
 			| (status, DeregisterNfInstanceResponse::Status401(problem))
 			| (status, DeregisterNfInstanceResponse::Status403(problem))
-			| (status, DeregisterNfInstanceResponse::Status404(problem))
 			| (status, DeregisterNfInstanceResponse::Status411(problem))
 			| (status, DeregisterNfInstanceResponse::Status429(problem))
 			| (status, DeregisterNfInstanceResponse::Status500(problem))
@@ -319,12 +603,87 @@ impl NrfClient {
 		}
 	}
 
+	/// Applies `patch` to the registered profile, e.g. a heartbeat replacing
+	/// `/nfStatus`, TS 29.510 5.2.2.3.
+	pub async fn update_nf_instance(
+		&self,
+		patch: &[PatchItem],
+	) -> Result<(), NrfManagementError> {
+		let nf_instance_id = self.get_nf_id();
+		let span = self.operation_span("UpdateNFInstance", nf_instance_id);
+		self.update_nf_instance_inner(nf_instance_id, patch)
+			.instrument(span.clone())
+			.await
+			.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	async fn update_nf_instance_inner(
+		&self,
+		nf_instance_id: NfInstanceId,
+		patch: &[PatchItem],
+	) -> Result<(), NrfManagementError> {
+		let nrf_service_properties =
+			NrfService::NFManagement(NrfNFManagementOperation::UpdateNFInstance);
+		let method = nrf_service_properties.get_http_method();
+		let path = formatx!(&nrf_service_properties.get_path(), nf_instance_id.0)
+			.map_err(GenericClientError::from)?;
+		let mut request = prepare_request(RequestParams {
+			url: self.init_config.url.clone(),
+			path: &path,
+			method,
+			header: Option::<&TraitSatisfier>::None,
+			query: Option::<&TraitSatisfier>::None,
+			body: Some(&patch),
+			encoding_type: ContentType::AppPatchJson,
+			scp_routing: self.scp_routing.as_ref(),
+		})?;
+		self.set_auth_token::<{ NfType::Nrf }>(&mut request, vec![ServiceName::NnrfNfm])
+			.await?;
+		let response = self
+			.client
+			.execute(request)
+			.await
+			.map_err(GenericClientError::from)?;
+		// `200 OK` carries the updated profile, which is of no use to the
+		// heartbeats and load reports sent this way.
+		match response.status().as_u16() {
+			200 | 204 => Ok(()),
+			status => {
+				let problem = response.json::<ProblemDetails>().await.ok();
+				Err(GenericClientError::InvalidResponse(
+					status,
+					problem,
+					Backtrace::capture(),
+				))?
+			}
+		}
+	}
+
 	pub async fn authenticaion_request(
 		&self,
 		source_instance_id: NfInstanceId,
 		source_nf_type: NfType,
 		target_nf_type: NfType,
 		target_service_name: Vec<ServiceName>,
+	) -> Result<AccessTokenRsp, NrfAuthorizationError> {
+		let span = self.operation_span("AccessTokenRequest", source_instance_id);
+		self.authenticaion_request_inner(
+			source_instance_id,
+			source_nf_type,
+			target_nf_type,
+			target_service_name,
+		)
+		.instrument(span.clone())
+		.await
+		.inspect_err(|e| record_error(&span, e, e.client_error()))
+	}
+
+	async fn authenticaion_request_inner(
+		&self,
+		source_instance_id: NfInstanceId,
+		source_nf_type: NfType,
+		target_nf_type: NfType,
+		target_service_name: Vec<ServiceName>,
 	) -> Result<AccessTokenRsp, NrfAuthorizationError> {
 		let mut token_req = AccessTokenReq::default();
 		token_req.target_nf_type = Some(target_nf_type);
@@ -342,15 +701,16 @@ impl NrfClient {
 		let method = nrf_service_properties.get_http_method();
 		let path = nrf_service_properties.get_path();
 
-		let request = prepare_request(
-			self.init_config.url.clone(),
-			&path,
+		let request = prepare_request(RequestParams {
+			url: self.init_config.url.clone(),
+			path: &path,
 			method,
-			Option::<&TraitSatisfier>::None,
-			Option::<&TraitSatisfier>::None,
-			Some(&token_req),
-			ContentType::AppForm,
-		)?;
+			header: Option::<&TraitSatisfier>::None,
+			query: Option::<&TraitSatisfier>::None,
+			body: Some(&token_req),
+			encoding_type: ContentType::AppForm,
+			scp_routing: self.scp_routing.as_ref(),
+		})?;
 		let response = self
 			.client
 			.execute(request)
@@ -399,12 +759,12 @@ impl NrfClient {
 					.nf_token_store
 					.set(
 						target_service_name.clone(),
-						self.authenticaion_request(
+						self.token_request_limiter.run(self.authenticaion_request(
 							self.nf_config.load().nf_instance_id,
 							self.init_config.source,
 							T,
 							target_service_name,
-						),
+						)),
 					)
 					.await?;
 				Ok(resp)
@@ -435,6 +795,70 @@ pub enum NrfError {
 	),
 }
 
+/// Body of a `RetrieveCompleteSearch` answer, TS 29.510 6.2.6.2.6.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredSearchResult {
+	#[serde(default)]
+	nf_instances: Vec<NfProfile>,
+}
+
+/// Profiles of a discovery, fetched page by page as they are consumed, see
+/// [`NrfClient::discover_all`].
+pub struct NfDiscovery<'a> {
+	nrf_client: &'a NrfClient,
+	search: Option<(SearchNfInstancesQueryParams, SearchNfInstancesHeaderParams)>,
+	search_id: Option<String>,
+	profiles: std::vec::IntoIter<NfProfile>,
+	seen: HashSet<uuid::Uuid>,
+}
+
+impl NfDiscovery<'_> {
+	/// The next profile, `None` once every page has been yielded. A failed
+	/// page is yielded as an error and ends the discovery.
+	pub async fn next(&mut self) -> Option<Result<NfProfile, NrfDiscoveryError>> {
+		loop {
+			for profile in self.profiles.by_ref() {
+				if self.seen.insert(profile.nf_instance_id.0) {
+					return Some(Ok(profile));
+				}
+			}
+			let page = if let Some((query, header)) = self.search.take() {
+				self.nrf_client
+					.search_nf_instance(query, header)
+					.await
+					.map(|result| {
+						let complete = result
+							.num_nf_inst_complete
+							.as_ref()
+							.map_or(0u64, |v| u64::from(*v));
+						if complete > result.nf_instances.len() as u64 {
+							self.search_id = result.search_id;
+						}
+						result.nf_instances
+					})
+			} else if let Some(search_id) = self.search_id.take() {
+				self.nrf_client.retrieve_complete_search(&search_id).await
+			} else {
+				return None;
+			};
+			match page {
+				Ok(profiles) => self.profiles = profiles.into_iter(),
+				Err(e) => return Some(Err(e)),
+			}
+		}
+	}
+
+	/// Every remaining profile, or the first error.
+	pub async fn collect(mut self) -> Result<Vec<NfProfile>, NrfDiscoveryError> {
+		let mut profiles = Vec::new();
+		while let Some(profile) = self.next().await {
+			profiles.push(profile?);
+		}
+		Ok(profiles)
+	}
+}
+
 #[derive(Debug, Error)]
 pub enum NrfDiscoveryError {
 	#[error(transparent)]
@@ -445,6 +869,14 @@ pub enum NrfDiscoveryError {
 	),
 }
 
+impl NrfDiscoveryError {
+	pub fn client_error(&self) -> Option<&GenericClientError> {
+		match self {
+			NrfDiscoveryError::GenericClientError(e) => Some(e),
+		}
+	}
+}
+
 #[derive(Debug, Error)]
 pub enum NrfManagementError {
 	#[error("Invalid Location string: {1}")]
@@ -464,6 +896,16 @@ pub enum NrfManagementError {
 	NrfAuthorizationError(#[from] NrfAuthorizationError),
 }
 
+impl NrfManagementError {
+	pub fn client_error(&self) -> Option<&GenericClientError> {
+		match self {
+			NrfManagementError::GenericClientError(e) => Some(e),
+			NrfManagementError::NrfAuthorizationError(e) => e.client_error(),
+			_ => None,
+		}
+	}
+}
+
 #[derive(Error, Debug)]
 pub enum NrfAuthorizationError {
 	#[error("InvalidRequestScope: Converting Services to token scope error")]
@@ -486,6 +928,31 @@ pub enum NrfAuthorizationError {
 	TokenParsingError(#[from] header::InvalidHeaderValue),
 }
 
+impl NrfAuthorizationError {
+	pub fn client_error(&self) -> Option<&GenericClientError> {
+		match self {
+			NrfAuthorizationError::GenericClientError(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+/// Records a failed NRF operation on its span, including the HTTP status and
+/// the problem cause when the NRF answered with an error response.
+fn record_error(
+	span: &Span,
+	error: &dyn std::error::Error,
+	client_error: Option<&GenericClientError>,
+) {
+	span.record("error", field::display(error));
+	if let Some(status) = client_error.and_then(GenericClientError::status_code) {
+		span.record("status", status);
+	}
+	if let Some(cause) = client_error.and_then(GenericClientError::problem_cause) {
+		span.record("cause", cause);
+	}
+}
+
 pub(crate) fn set_auth_token(
 	req: &mut Request,
 	token_entry: TokenEntry<AccessTokenRsp>,
@@ -518,3 +985,364 @@ impl From<&[ServiceName]> for Scope {
 		)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::HashMap,
+		fmt::Debug,
+		sync::{Arc, Mutex},
+		time::Duration,
+	};
+
+	use http::{HeaderValue, StatusCode, header::LOCATION};
+	use tracing::{
+		Subscriber,
+		field::{Field, Visit},
+		span::{Attributes, Id, Record},
+	};
+	use tracing_subscriber::{Layer, layer::Context, prelude::*};
+
+	use super::*;
+	use crate::mock_nrf::{MockNrf, MockNrfOperation, MockResponse};
+
+	#[derive(Clone, Default)]
+	struct SpanFields(Arc<Mutex<HashMap<String, String>>>);
+
+	struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+	impl Visit for FieldVisitor<'_> {
+		fn record_str(
+			&mut self,
+			field: &Field,
+			value: &str,
+		) {
+			self.0.insert(field.name().to_owned(), value.to_owned());
+		}
+
+		fn record_debug(
+			&mut self,
+			field: &Field,
+			value: &dyn Debug,
+		) {
+			self.0.insert(field.name().to_owned(), format!("{value:?}"));
+		}
+	}
+
+	impl<S: Subscriber> Layer<S> for SpanFields {
+		fn on_new_span(
+			&self,
+			attrs: &Attributes<'_>,
+			_id: &Id,
+			_ctx: Context<'_, S>,
+		) {
+			attrs.record(&mut FieldVisitor(&mut self.0.lock().unwrap()));
+		}
+
+		fn on_record(
+			&self,
+			_id: &Id,
+			values: &Record<'_>,
+			_ctx: Context<'_, S>,
+		) {
+			values.record(&mut FieldVisitor(&mut self.0.lock().unwrap()));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_failed_operation_records_span_fields() {
+		let fields = SpanFields::default();
+		let _guard = tracing_subscriber::registry()
+			.with(fields.clone())
+			.set_default();
+
+		let nrf = MockNrf::start().await;
+		nrf.respond(
+			MockNrfOperation::DeregisterNfInstance,
+			MockResponse::problem(StatusCode::INTERNAL_SERVER_ERROR, "SYSTEM_FAILURE"),
+		);
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let result = nrf_client.deregister_nf_instance().await;
+		assert!(result.is_err());
+
+		let fields = fields.0.lock().unwrap();
+		assert_eq!(fields["operation"], "DeregisterNFInstance");
+		assert_eq!(
+			fields["nf_instance_id"],
+			NfInstanceId::default().0.to_string()
+		);
+		assert!(fields["target"].starts_with("http://127.0.0.1:"));
+		assert_eq!(fields["status"], "500");
+		assert_eq!(fields["cause"], "SYSTEM_FAILURE");
+		assert!(fields.contains_key("error"));
+	}
+
+	#[tokio::test]
+	async fn test_token_requests_respect_inflight_limit() {
+		let nrf = MockNrf::start().await;
+		nrf.respond(
+			MockNrfOperation::AccessTokenRequest,
+			MockResponse::json(
+				StatusCode::OK,
+				serde_json::json!({ "access_token": "token", "token_type": "Bearer" }),
+			)
+			.with_delay(Duration::from_millis(50)),
+		);
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf)
+			.with_max_inflight_token_requests(2);
+
+		// Every non-empty subset is a distinct scope, hence a distinct NRF request.
+		let names = [
+			ServiceName::NnrfNfm,
+			ServiceName::NnrfDisc,
+			ServiceName::NamfComm,
+			ServiceName::NamfEvts,
+		];
+		let scopes = (1..1usize << names.len()).map(|mask| {
+			names
+				.iter()
+				.enumerate()
+				.filter(|(i, _)| mask & (1 << i) != 0)
+				.map(|(_, name)| name.clone())
+				.collect::<Vec<_>>()
+		});
+		let results = futures::future::join_all(
+			scopes.map(|scope| nrf_client.get_token::<{ NfType::Nrf }>(scope)),
+		)
+		.await;
+
+		assert!(results.iter().all(Result::is_ok));
+		assert_eq!(nrf.peak_inflight(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_register_adopts_nrf_assigned_instance_id() {
+		let sent_id = NfInstanceId::from(uuid::Uuid::new_v4());
+		let assigned_id = NfInstanceId::from(uuid::Uuid::new_v4());
+		let profile = serde_json::json!({
+			"nfInstanceId": sent_id.0.to_string(),
+			"nfType": "AMF",
+			"nfStatus": "REGISTERED",
+		});
+		let nf_profile: NfProfile1 = serde_json::from_value(profile.clone()).unwrap();
+		let nrf = MockNrf::start().await;
+		let location = format!("http://nrf/nnrf-nfm/v1/nf-instances/{}", assigned_id.0);
+		nrf.respond(
+			MockNrfOperation::RegisterNfInstance,
+			MockResponse::json(StatusCode::CREATED, profile)
+				.with_header(LOCATION, HeaderValue::try_from(location).unwrap()),
+		);
+
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let (_, outcome) = nrf_client
+			.register_nf_instance(
+				sent_id,
+				&RegisterNfInstanceHeaderParams::default(),
+				&nf_profile,
+			)
+			.await
+			.unwrap();
+		assert_eq!(outcome, RegistrationOutcome::Created(assigned_id));
+		assert_eq!(nrf_client.get_nf_id(), assigned_id);
+
+		nrf_client.deregister_nf_instance().await.unwrap();
+		let requests = nrf.requests();
+		assert_eq!(requests[1].method, Method::DELETE);
+		assert_eq!(
+			requests[1].uri.path(),
+			format!("/nnrf-nfm/v1/nf-instances/{}", assigned_id.0)
+		);
+	}
+
+	#[tokio::test]
+	async fn test_register_existing_profile_is_updated() {
+		let nf_id = NfInstanceId::from(uuid::Uuid::new_v4());
+		let profile = serde_json::json!({
+			"nfInstanceId": nf_id.0.to_string(),
+			"nfType": "AMF",
+			"nfStatus": "REGISTERED",
+		});
+		let nf_profile: NfProfile1 = serde_json::from_value(profile.clone()).unwrap();
+		let nrf = MockNrf::start().await;
+		nrf.respond(
+			MockNrfOperation::RegisterNfInstance,
+			MockResponse::json(StatusCode::OK, profile),
+		);
+
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let (_, outcome) = nrf_client
+			.register_nf_instance(
+				nf_id,
+				&RegisterNfInstanceHeaderParams::default(),
+				&nf_profile,
+			)
+			.await
+			.unwrap();
+		assert_eq!(outcome, RegistrationOutcome::Updated);
+		assert_eq!(nrf_client.get_nf_id(), nf_id);
+	}
+
+	#[tokio::test]
+	async fn test_deregister_tolerates_not_found() {
+		let nrf = MockNrf::start().await;
+		nrf.respond(
+			MockNrfOperation::DeregisterNfInstance,
+			MockResponse::problem(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND"),
+		);
+
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		assert!(nrf_client.deregister_nf_instance().await.is_ok());
+	}
+
+	#[tokio::test]
+	async fn test_register_and_deregister_against_mock_nrf() {
+		let nrf = MockNrf::start().await;
+		let nf_id = NfInstanceId::from(uuid::Uuid::new_v4());
+		let nf_profile: NfProfile1 = serde_json::from_value(serde_json::json!({
+			"nfInstanceId": nf_id.0.to_string(),
+			"nfType": "AMF",
+			"nfStatus": "REGISTERED",
+			"heartBeatTimer": 30,
+			"customInfo": { "oauth2": true },
+		}))
+		.unwrap();
+
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let (_, outcome) = nrf_client
+			.register_nf_instance(
+				nf_id,
+				&RegisterNfInstanceHeaderParams::default(),
+				&nf_profile,
+			)
+			.await
+			.unwrap();
+		assert_eq!(outcome, RegistrationOutcome::Created(nf_id));
+		assert_eq!(nrf_client.get_nf_id(), nf_id);
+		assert_eq!(nrf_client.get_heartbeat_timer(), 30);
+		assert!(nrf_client.get_oauth_enabled());
+
+		// OAuth is enabled by the registered profile, deregistering fetches a
+		// token first.
+		nrf_client.deregister_nf_instance().await.unwrap();
+		let operations = nrf
+			.requests()
+			.into_iter()
+			.map(|request| request.operation)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			operations,
+			[
+				MockNrfOperation::RegisterNfInstance,
+				MockNrfOperation::AccessTokenRequest,
+				MockNrfOperation::DeregisterNfInstance,
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_deregister_instance_by_id_targets_that_instance() {
+		let nrf = MockNrf::start().await;
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let stale_id = NfInstanceId::from(uuid::Uuid::new_v4());
+		assert_ne!(stale_id, nrf_client.get_nf_id());
+
+		nrf_client
+			.deregister_instance_by_id(stale_id)
+			.await
+			.unwrap();
+		let requests = nrf.requests();
+		assert_eq!(requests.len(), 1);
+		assert_eq!(
+			requests[0].operation,
+			MockNrfOperation::DeregisterNfInstance
+		);
+		assert_eq!(requests[0].method, Method::DELETE);
+		assert_eq!(
+			requests[0].uri.path(),
+			format!("/nnrf-nfm/v1/nf-instances/{}", stale_id.0)
+		);
+	}
+
+	#[tokio::test]
+	async fn test_mock_nrf_scripted_failure() {
+		let nrf = MockNrf::start().await;
+		nrf.respond(
+			MockNrfOperation::DeregisterNfInstance,
+			MockResponse::problem(StatusCode::INTERNAL_SERVER_ERROR, "SYSTEM_FAILURE"),
+		);
+
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let error = nrf_client.deregister_nf_instance().await.unwrap_err();
+		let client_error = error.client_error().unwrap();
+		assert_eq!(client_error.status_code(), Some(500));
+		assert_eq!(client_error.problem_cause(), Some("SYSTEM_FAILURE"));
+	}
+
+	#[tokio::test]
+	async fn test_discover_all_yields_every_page() {
+		let nrf = MockNrf::start().await;
+		let profiles = (0..3)
+			.map(|_| {
+				serde_json::json!({
+					"nfInstanceId": uuid::Uuid::new_v4().to_string(),
+					"nfType": "SMF",
+					"nfStatus": "REGISTERED",
+				})
+			})
+			.collect::<Vec<_>>();
+		nrf.respond(
+			MockNrfOperation::SearchNfInstances,
+			MockResponse::json(
+				StatusCode::OK,
+				serde_json::json!({
+					"validityPeriod": 3600,
+					"nfInstances": [profiles[0]],
+					"searchId": "search/1",
+					"numNfInstComplete": 3,
+				}),
+			),
+		);
+		// The complete result lists the profile of the first page again.
+		nrf.respond(
+			MockNrfOperation::RetrieveCompleteSearch,
+			MockResponse::json(
+				StatusCode::OK,
+				serde_json::json!({ "nfInstances": profiles }),
+			),
+		);
+
+		let nrf_client = NrfClient::new(Client::new(), nrf.url(), NfType::Amf);
+		let query = SearchNfInstancesQueryParams {
+			requester_nf_type: NfType::Amf,
+			target_nf_type: NfType::Smf,
+			..Default::default()
+		};
+		let discovered = nrf_client
+			.discover_all(query, SearchNfInstancesHeaderParams::default())
+			.collect()
+			.await
+			.unwrap();
+		let discovered_ids = discovered
+			.iter()
+			.map(|profile| profile.nf_instance_id.0.to_string())
+			.collect::<Vec<_>>();
+		let expected_ids = profiles
+			.iter()
+			.map(|profile| profile["nfInstanceId"].as_str().unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(discovered_ids, expected_ids);
+
+		let requests = nrf.requests();
+		assert_eq!(
+			requests.iter().map(|r| r.operation).collect::<Vec<_>>(),
+			[
+				MockNrfOperation::SearchNfInstances,
+				MockNrfOperation::RetrieveCompleteSearch,
+			]
+		);
+		assert_eq!(
+			requests[1].uri.path(),
+			"/nnrf-disc/v1/searches/search%2F1/complete"
+		);
+	}
+}
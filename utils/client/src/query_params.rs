@@ -0,0 +1,210 @@
+use serde::{Serialize, Serializer, ser::SerializeSeq};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Query parameters of an SBI request, as ordered key value pairs.
+///
+/// Arrays are encoded as repeated keys, `snssais=a&snssais=b`. Scalars are
+/// taken as is, objects, like the elements of `snssais`, as their JSON text
+/// per the `application/json` encoding of TS 29.501 5.2.3.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParams {
+	pairs: Vec<(String, String)>,
+}
+
+impl QueryParams {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Flattens the fields of a query parameter struct, absent fields are
+	/// skipped.
+	pub fn from_serialize<Q: Serialize>(query: &Q) -> Result<Self, QueryParamsError> {
+		let Value::Object(fields) = serde_json::to_value(query)? else {
+			return Err(QueryParamsError::NotAStruct);
+		};
+		let mut params = Self::new();
+		for (key, value) in fields {
+			match value {
+				Value::Null => {}
+				Value::Array(items) => {
+					for item in items {
+						params.push(&key, encode_value(item)?);
+					}
+				}
+				value => {
+					params.push(&key, encode_value(value)?);
+				}
+			}
+		}
+		Ok(params)
+	}
+
+	/// The query of an NF Discovery request, TS 29.510 6.2.3.2.3.1.
+	pub fn discovery<Q: Serialize>(query: &Q) -> Result<Self, QueryParamsError> {
+		let params = Self::from_serialize(query)?;
+		params.require("target-nf-type")?;
+		params.require("requester-nf-type")?;
+		params.require_with("nsi-list", "snssais")?;
+		Ok(params)
+	}
+
+	pub fn push(
+		&mut self,
+		key: &str,
+		value: impl Into<String>,
+	) -> &mut Self {
+		self.pairs.push((key.to_owned(), value.into()));
+		self
+	}
+
+	pub fn contains(
+		&self,
+		key: &str,
+	) -> bool {
+		self.pairs.iter().any(|(k, _)| k == key)
+	}
+
+	/// Values of `key` in the order they were added.
+	pub fn get_all<'a>(
+		&'a self,
+		key: &'a str,
+	) -> impl Iterator<Item = &'a str> + 'a {
+		self.pairs
+			.iter()
+			.filter(move |(k, _)| k == key)
+			.map(|(_, v)| v.as_str())
+	}
+
+	pub fn require(
+		&self,
+		key: &'static str,
+	) -> Result<(), QueryParamsError> {
+		if self.contains(key) {
+			Ok(())
+		} else {
+			Err(QueryParamsError::MissingParam(key))
+		}
+	}
+
+	/// `dependency` has to be present whenever `key` is.
+	pub fn require_with(
+		&self,
+		key: &'static str,
+		dependency: &'static str,
+	) -> Result<(), QueryParamsError> {
+		if self.contains(key) && !self.contains(dependency) {
+			Err(QueryParamsError::MissingDependency(key, dependency))
+		} else {
+			Ok(())
+		}
+	}
+}
+
+impl Serialize for QueryParams {
+	fn serialize<S: Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		let mut seq = serializer.serialize_seq(Some(self.pairs.len()))?;
+		for pair in &self.pairs {
+			seq.serialize_element(pair)?;
+		}
+		seq.end()
+	}
+}
+
+fn encode_value(value: Value) -> Result<String, QueryParamsError> {
+	match value {
+		Value::String(value) => Ok(value),
+		Value::Number(value) => Ok(value.to_string()),
+		Value::Bool(value) => Ok(value.to_string()),
+		value => Ok(serde_json::to_string(&value)?),
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum QueryParamsError {
+	#[error("NotAStruct: Query parameters have to serialize to a map")]
+	NotAStruct,
+
+	#[error("MissingParam: Query parameter {0} is required")]
+	MissingParam(&'static str),
+
+	#[error("MissingDependency: Query parameter {0} requires {1}")]
+	MissingDependency(&'static str, &'static str),
+
+	#[error("EncodingError: Query parameter could not be encoded: {0}")]
+	EncodingError(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+	use oasbi::common::{NfType, Snssai, SnssaiSd};
+	use openapi_nrf::models::SearchNfInstancesQueryParams;
+
+	use super::*;
+
+	fn snssai(
+		sst: u8,
+		sd: Option<&str>,
+	) -> Snssai {
+		Snssai {
+			sst,
+			sd: sd.map(|sd| SnssaiSd::try_from(sd.to_owned()).unwrap()),
+		}
+	}
+
+	#[test]
+	fn test_discovery_snssais_are_repeated_keys() {
+		let query = SearchNfInstancesQueryParams {
+			requester_nf_type: NfType::Amf,
+			target_nf_type: NfType::Smf,
+			snssais: Some(vec![snssai(1, Some("000001")), snssai(2, None)]),
+			..Default::default()
+		};
+		let params = QueryParams::discovery(&query).unwrap();
+		let encoded = serde_urlencoded::to_string(&params).unwrap();
+
+		let snssais = url::form_urlencoded::parse(encoded.as_bytes())
+			.filter(|(key, _)| key == "snssais")
+			.map(|(_, value)| serde_json::from_str::<Snssai>(&value).unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(snssais, query.snssais.unwrap());
+		assert_eq!(encoded.matches("snssais=").count(), 2);
+		assert!(!encoded.contains("snssais=%5B"));
+		assert!(encoded.contains("target-nf-type=SMF"));
+		assert!(encoded.contains("requester-nf-type=AMF"));
+	}
+
+	#[test]
+	fn test_required_combinations() {
+		#[derive(Serialize)]
+		#[serde(rename_all = "kebab-case")]
+		struct Query {
+			target_nf_type: Option<&'static str>,
+			requester_nf_type: &'static str,
+			nsi_list: Vec<&'static str>,
+		}
+
+		let missing = Query {
+			target_nf_type: None,
+			requester_nf_type: "AMF",
+			nsi_list: vec![],
+		};
+		assert!(matches!(
+			QueryParams::discovery(&missing),
+			Err(QueryParamsError::MissingParam("target-nf-type"))
+		));
+
+		let dependent = Query {
+			target_nf_type: Some("SMF"),
+			requester_nf_type: "AMF",
+			nsi_list: vec!["1", "2"],
+		};
+		assert!(matches!(
+			QueryParams::discovery(&dependent),
+			Err(QueryParamsError::MissingDependency("nsi-list", "snssais"))
+		));
+	}
+}
@@ -0,0 +1,68 @@
+use std::future::Future;
+
+use tokio::sync::Semaphore;
+
+/// Bounds the number of futures run through it concurrently, the excess wait
+/// for a free slot in FIFO order.
+#[derive(Debug)]
+pub struct RequestLimiter(Semaphore);
+
+impl RequestLimiter {
+	pub fn new(max_inflight: usize) -> Self {
+		Self(Semaphore::new(max_inflight))
+	}
+
+	pub fn available(&self) -> usize {
+		self.0.available_permits()
+	}
+
+	/// Waits for a free slot and runs `future` while holding it.
+	pub async fn run<F: Future>(
+		&self,
+		future: F,
+	) -> F::Output {
+		// Safety: the semaphore is owned by the limiter and never closed.
+		let _permit = self.0.acquire().await.unwrap();
+		future.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		sync::{
+			Arc,
+			atomic::{AtomicUsize, Ordering},
+		},
+		time::Duration,
+	};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_limiter_bounds_concurrency() {
+		let limiter = Arc::new(RequestLimiter::new(2));
+		let inflight = Arc::new(AtomicUsize::new(0));
+		let peak = Arc::new(AtomicUsize::new(0));
+
+		let tasks = (0..8).map(|_| {
+			let (limiter, inflight, peak) = (limiter.clone(), inflight.clone(), peak.clone());
+			tokio::spawn(async move {
+				limiter
+					.run(async {
+						let now = inflight.fetch_add(1, Ordering::SeqCst) + 1;
+						peak.fetch_max(now, Ordering::SeqCst);
+						tokio::time::sleep(Duration::from_millis(10)).await;
+						inflight.fetch_sub(1, Ordering::SeqCst);
+					})
+					.await
+			})
+		});
+		for task in tasks.collect::<Vec<_>>() {
+			task.await.unwrap();
+		}
+
+		assert_eq!(peak.load(Ordering::SeqCst), 2);
+		assert_eq!(limiter.available(), 2);
+	}
+}
@@ -0,0 +1,83 @@
+use http::{HeaderName, HeaderValue};
+use reqwest::{Request, Url};
+
+use crate::GenericClientError;
+
+/// The apiRoot of the NF a request sent to an SCP is meant for, TS 29.500
+/// 5.2.3.2.4.
+pub const TARGET_API_ROOT: HeaderName = HeaderName::from_static("3gpp-sbi-target-apiroot");
+
+/// Indirect communication through an SCP, TS 29.500 6.10.
+///
+/// Requests go to the SCP, at the path they have on the target NF under the
+/// path of the SCP URI, the apiRoot of the target NF carried by the
+/// [`TARGET_API_ROOT`] header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScpRouting {
+	scp_uri: Url,
+}
+
+impl ScpRouting {
+	pub fn new(scp_uri: Url) -> Self {
+		Self { scp_uri }
+	}
+
+	pub fn scp_uri(&self) -> &Url {
+		&self.scp_uri
+	}
+
+	/// Redirects a request addressed to its target NF to the SCP.
+	pub fn route(
+		&self,
+		request: &mut Request,
+	) -> Result<(), GenericClientError> {
+		let target = request.url();
+		let api_root = target.origin().ascii_serialization();
+		let mut routed = self.scp_uri.clone();
+		routed.set_path(&format!(
+			"{}{}",
+			self.scp_uri.path().trim_end_matches('/'),
+			target.path()
+		));
+		routed.set_query(target.query());
+		let api_root = HeaderValue::try_from(api_root).map_err(http::Error::from)?;
+		request.headers_mut().insert(TARGET_API_ROOT, api_root);
+		*request.url_mut() = routed;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use reqwest::Method;
+
+	use super::*;
+	use crate::{ContentType, RequestParams, prepare_request};
+
+	#[test]
+	fn test_routed_request_targets_the_nf_through_the_scp() {
+		let scp = ScpRouting::new(Url::parse("http://scp.example:8080/proxy/").unwrap());
+		let nrf = Url::parse("https://nrf.example:8443").unwrap();
+		let query = [("target-nf-type", "SMF")];
+		let request = prepare_request(RequestParams {
+			url: nrf,
+			path: "/nnrf-disc/v1/nf-instances",
+			method: Method::GET,
+			header: Option::<&()>::None,
+			query: Some(&query),
+			body: Option::<&()>::None,
+			encoding_type: ContentType::AppJson,
+			scp_routing: Some(&scp),
+		})
+		.unwrap();
+
+		assert_eq!(
+			request.headers()[TARGET_API_ROOT],
+			"https://nrf.example:8443"
+		);
+		assert_eq!(
+			request.url().as_str(),
+			"http://scp.example:8080/proxy/nnrf-disc/v1/nf-instances?target-nf-type=SMF"
+		);
+	}
+}
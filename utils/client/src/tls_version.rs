@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// The oldest TLS version an SBI peer may connect with, TLS 1.3 only when
+/// compliance asks for it.
+#[derive(
+	Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum TlsVersion {
+	#[default]
+	#[serde(rename = "tls1.2")]
+	Tls12,
+	#[serde(rename = "tls1.3")]
+	Tls13,
+}
+
+impl From<TlsVersion> for reqwest::tls::Version {
+	fn from(version: TlsVersion) -> Self {
+		match version {
+			TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+			TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+		}
+	}
+}
@@ -97,6 +97,14 @@ where
 	                                                            * token states. */
 }
 
+/// How many entries of a `TokenStore` are in each state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenStoreStats {
+	pub ready: usize,
+	pub updating: usize,
+	pub failed: usize,
+}
+
 /// Represents an entry in the `TokenStore`.
 pub struct TokenEntry<V>(Arc<TokenState<V>>);
 
@@ -147,6 +155,34 @@ where
 		}
 	}
 
+	/// Number of keys with a token, whatever its state.
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// The keys with a token, in no particular order.
+	pub async fn keys(&self) -> Vec<K> {
+		let mut keys = Vec::with_capacity(self.len());
+		self.map.scan_async(|key, _| keys.push(key.clone())).await;
+		keys
+	}
+
+	pub async fn stats(&self) -> TokenStoreStats {
+		let mut stats = TokenStoreStats::default();
+		self.map
+			.scan_async(|_, token| match token.state {
+				TokenStateInner::Ready(_) => stats.ready += 1,
+				TokenStateInner::Updating(_) => stats.updating += 1,
+				TokenStateInner::Failed => stats.failed += 1,
+			})
+			.await;
+		stats
+	}
+
 	/// Asynchronously retrieves a token entry from the store.
 	/// Waits if the token is in the `Updating` state.
 	pub async fn get(
@@ -295,4 +331,40 @@ mod tests {
 		let result = store.get(&key).await;
 		assert!(matches!(result, Err(StoreError::ReadError(_))));
 	}
+
+	#[tokio::test]
+	async fn test_stats_count_every_state() {
+		let store = TokenStore::<&str, i32>::new();
+		store
+			.set("ready", async { Ok::<i32, StoreError>(1) })
+			.await
+			.unwrap();
+		store
+			.set("failed", async { Err(StoreError::ReadError(1)) })
+			.await
+			.unwrap_err();
+
+		let (release, released) = tokio::sync::oneshot::channel();
+		let updating = store.set("updating", async { released.await.map(|()| 3) });
+		let (_, stats) = tokio::join!(updating, async {
+			tokio::task::yield_now().await;
+			let stats = store.stats().await;
+			release.send(()).unwrap();
+			stats
+		});
+		assert_eq!(
+			stats,
+			TokenStoreStats {
+				ready: 1,
+				updating: 1,
+				failed: 1,
+			}
+		);
+
+		assert_eq!(store.len(), 3);
+		let mut keys = store.keys().await;
+		keys.sort();
+		assert_eq!(keys, ["failed", "ready", "updating"]);
+		assert_eq!(store.stats().await.ready, 2);
+	}
 }
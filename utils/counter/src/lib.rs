@@ -26,4 +26,65 @@ define_counter!(CounterU8, AtomicU8, u8);
 define_counter!(CounterU16, AtomicU16, u16);
 define_counter!(CounterU32, AtomicU32, u32);
 define_counter!(CounterU64, AtomicU64, u64);
-define_counter!(CounterUsize, AtomicUsize, usize);
\ No newline at end of file
+define_counter!(CounterUsize, AtomicUsize, usize);
+
+/// Unlike the counters, gauges start at zero and move both ways, tracking a
+/// current amount such as the number of live contexts.
+macro_rules! define_gauge {
+    ($gauge_name:ident, $atomic_type:ident, $base_type:ty) => {
+        #[derive(Debug)]
+        pub struct $gauge_name($atomic_type);
+
+        impl $gauge_name {
+            pub const fn new() -> Self {
+                $gauge_name($atomic_type::new(0))
+            }
+
+            /// Returns the value after the increment.
+            pub fn increment(&self) -> $base_type {
+                self.0.fetch_add(1, Ordering::Relaxed).wrapping_add(1)
+            }
+
+            /// Returns the value after the decrement, saturating at zero.
+            pub fn decrement(&self) -> $base_type {
+                self.0
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| v.checked_sub(1))
+                    .map_or(0, |prev| prev - 1)
+            }
+
+            pub fn get(&self) -> $base_type {
+                self.0.load(Ordering::Relaxed)
+            }
+
+            /// Sets the gauge back to zero, returning the value it held.
+            pub fn reset(&self) -> $base_type {
+                self.0.swap(0, Ordering::Relaxed)
+            }
+        }
+        impl Default for $gauge_name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+define_gauge!(GaugeU32, AtomicU32, u32);
+define_gauge!(GaugeU64, AtomicU64, u64);
+define_gauge!(GaugeUsize, AtomicUsize, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauge_saturates_at_zero() {
+        let gauge = GaugeUsize::new();
+        assert_eq!(gauge.increment(), 1);
+        assert_eq!(gauge.increment(), 2);
+        assert_eq!(gauge.decrement(), 1);
+        assert_eq!(gauge.reset(), 1);
+        assert_eq!(gauge.decrement(), 0);
+        assert_eq!(gauge.get(), 0);
+    }
+}
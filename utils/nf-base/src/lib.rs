@@ -1,5 +1,8 @@
+mod sbi_server;
+
 use std::{error, fmt};
 
+pub use sbi_server::{SbiServerError, SbiServerRunner, Tls};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use tokio_util::sync::CancellationToken;
 
@@ -26,6 +29,19 @@ pub struct LoggingConfig {
 	pub enable: bool,
 	pub level: String,
 	pub report_caller: bool,
+	#[serde(default)]
+	pub format: LogFormat,
+}
+
+/// Layout of the emitted log lines, `json` suits log aggregation systems.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+	#[default]
+	Full,
+	Pretty,
+	Compact,
+	Json,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -35,7 +51,7 @@ pub struct RuntimeConfig {
 	pub rt_type: RuntimeType,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum RuntimeType {
 	#[serde(rename = "single")]
 	Single,
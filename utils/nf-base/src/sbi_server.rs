@@ -0,0 +1,374 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{Router, routing::get};
+use client::TlsVersion;
+use hyper_util::{
+	rt::{TokioExecutor, TokioIo},
+	server::{conn::auto, graceful::GracefulShutdown},
+	service::TowerToHyperService,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio_rustls::{
+	TlsAcceptor,
+	rustls::{
+		self,
+		ServerConfig,
+		SupportedProtocolVersion,
+		pki_types::{
+			CertificateDer,
+			PrivateKeyDer,
+			pem::{self, PemObject},
+		},
+	},
+};
+use tokio_util::sync::CancellationToken;
+use tower_http::trace::TraceLayer;
+use tracing::{debug, warn};
+
+/// Peers not done with the TLS handshake by then are dropped, not to hold up
+/// a shutdown.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Local paths of the PEM encoded certificate chain and private key of an
+/// SBI server.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Tls {
+	pub pem: String,
+	pub key: String,
+	/// Handshakes of clients offering only older TLS versions fail.
+	#[serde(default)]
+	pub min_version: TlsVersion,
+}
+
+/// Serves the routes of an NF's SBI services, TLS terminated when configured.
+/// `/health` answers as long as the server runs.
+pub struct SbiServerRunner {
+	listener: TcpListener,
+	router: Router,
+	tls: Option<TlsAcceptor>,
+}
+
+impl SbiServerRunner {
+	pub async fn bind(
+		addr: SocketAddr,
+		router: Router,
+		tls: Option<&Tls>,
+	) -> Result<Self, SbiServerError> {
+		let tls = tls.map(tls_acceptor).transpose()?;
+		let listener = TcpListener::bind(addr)
+			.await
+			.map_err(|e| SbiServerError::BindError(e, addr))?;
+		Ok(Self {
+			listener,
+			router: router.route("/health", get(|| async {})),
+			tls,
+		})
+	}
+
+	/// Adds routes for the operator, like readiness or metrics, next to the
+	/// service ones.
+	pub fn with_admin_routes(
+		mut self,
+		admin: Router,
+	) -> Self {
+		self.router = self.router.merge(admin);
+		self
+	}
+
+	pub fn local_addr(&self) -> Result<SocketAddr, SbiServerError> {
+		self.listener
+			.local_addr()
+			.map_err(SbiServerError::ServeError)
+	}
+
+	/// Serves requests until `shutdown` is cancelled, letting in-flight
+	/// requests complete.
+	pub async fn serve(
+		self,
+		shutdown: CancellationToken,
+	) -> Result<(), SbiServerError> {
+		let router = self.router.layer(TraceLayer::new_for_http());
+		match self.tls {
+			Some(acceptor) => {
+				serve_tls(self.listener, router, acceptor, shutdown).await;
+				Ok(())
+			}
+			None => axum::serve(self.listener, router)
+				.with_graceful_shutdown(shutdown.cancelled_owned())
+				.await
+				.map_err(SbiServerError::ServeError),
+		}
+	}
+}
+
+fn tls_acceptor(tls: &Tls) -> Result<TlsAcceptor, SbiServerError> {
+	let certs = CertificateDer::pem_file_iter(&tls.pem)
+		.and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+		.and_then(|certs| {
+			if certs.is_empty() {
+				Err(pem::Error::NoItemsFound)
+			} else {
+				Ok(certs)
+			}
+		})
+		.map_err(|e| SbiServerError::CertificateError(e, tls.pem.clone()))?;
+	let key = PrivateKeyDer::from_pem_file(&tls.key)
+		.map_err(|e| SbiServerError::PrivateKeyError(e, tls.key.clone()))?;
+	let versions = protocol_versions(tls.min_version);
+	// The provider is given explicitly, no process wide default is installed.
+	let provider = Arc::new(rustls::crypto::ring::default_provider());
+	let mut config = ServerConfig::builder_with_provider(provider)
+		.with_protocol_versions(versions)
+		.map_err(SbiServerError::TlsConfigError)?
+		.with_no_client_auth()
+		.with_single_cert(certs, key)
+		.map_err(SbiServerError::TlsConfigError)?;
+	config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+	Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// The protocol versions offered from `min_version` on, with the cipher suites
+/// rustls enables by default.
+fn protocol_versions(min_version: TlsVersion) -> &'static [&'static SupportedProtocolVersion] {
+	match min_version {
+		TlsVersion::Tls12 => rustls::ALL_VERSIONS,
+		TlsVersion::Tls13 => &[&rustls::version::TLS13],
+	}
+}
+
+async fn serve_tls(
+	listener: TcpListener,
+	router: Router,
+	acceptor: TlsAcceptor,
+	shutdown: CancellationToken,
+) {
+	let graceful = GracefulShutdown::new();
+	loop {
+		let stream = tokio::select! {
+			_ = shutdown.cancelled() => break,
+			accepted = listener.accept() => match accepted {
+				Ok((stream, _)) => stream,
+				Err(e) => {
+					// Mostly running out of file descriptors, give some time for
+					// connections to close.
+					warn!("Unable to accept an Sbi connection: {e}");
+					tokio::time::sleep(Duration::from_secs(1)).await;
+					continue;
+				}
+			},
+		};
+		let acceptor = acceptor.clone();
+		let service = TowerToHyperService::new(router.clone());
+		let watcher = graceful.watcher();
+		// The handshake runs with the connection so slow peers do not hold up
+		// the accepting.
+		tokio::spawn(async move {
+			let stream =
+				match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+					Ok(Ok(stream)) => stream,
+					Ok(Err(e)) => {
+						debug!("Sbi TLS handshake failed: {e}");
+						return;
+					}
+					Err(_) => {
+						debug!("Sbi TLS handshake timed out");
+						return;
+					}
+				};
+			let builder = auto::Builder::new(TokioExecutor::new());
+			let connection = builder
+				.serve_connection_with_upgrades(TokioIo::new(stream), service)
+				.into_owned();
+			if let Err(e) = watcher.watch(connection).await {
+				debug!("Sbi connection closed: {e}");
+			}
+		});
+	}
+	graceful.shutdown().await;
+}
+
+#[derive(Error, Debug)]
+pub enum SbiServerError {
+	#[error("BindError: Unable to bind the Sbi server to {1}")]
+	BindError(#[source] std::io::Error, SocketAddr),
+
+	#[error("CertificateError: Unable to read the TLS certificate chain {1}")]
+	CertificateError(#[source] pem::Error, String),
+
+	#[error("PrivateKeyError: Unable to read the TLS private key {1}")]
+	PrivateKeyError(#[source] pem::Error, String),
+
+	#[error("TlsConfigError: TLS versions, certificate chain and key do not make a valid config")]
+	TlsConfigError(#[source] rustls::Error),
+
+	#[error("ServeError: Sbi server failed")]
+	ServeError(#[source] std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+
+	use reqwest::{Certificate, StatusCode};
+
+	use super::*;
+
+	/// Writes a self-signed certificate for `localhost` and its key into `dir`,
+	/// returning their config and the certificate to trust.
+	fn self_signed(
+		dir: &Path,
+		name: &str,
+	) -> (Tls, String) {
+		let rcgen::CertifiedKey { cert, key_pair } =
+			rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+		let (pem, key) = (
+			dir.join(format!("{name}.pem")),
+			dir.join(format!("{name}.key")),
+		);
+		std::fs::write(&pem, cert.pem()).unwrap();
+		std::fs::write(&key, key_pair.serialize_pem()).unwrap();
+		let tls = Tls {
+			pem: pem.display().to_string(),
+			key: key.display().to_string(),
+			..Default::default()
+		};
+		(tls, cert.pem())
+	}
+
+	fn temp_dir(test: &str) -> std::path::PathBuf {
+		let dir = std::env::temp_dir().join(format!("sbi-{test}-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	async fn bind(tls: &Tls) -> Result<SbiServerRunner, SbiServerError> {
+		SbiServerRunner::bind("127.0.0.1:0".parse().unwrap(), Router::new(), Some(tls)).await
+	}
+
+	#[tokio::test]
+	async fn test_runner_serves_health_over_tls() {
+		let dir = temp_dir("tls");
+		let (tls, cert) = self_signed(&dir, "sbi");
+
+		let runner = bind(&tls).await.unwrap();
+		let addr = runner.local_addr().unwrap();
+		let shutdown = CancellationToken::new();
+		let handle = tokio::spawn(runner.serve(shutdown.clone()));
+
+		let client = reqwest::Client::builder()
+			.add_root_certificate(Certificate::from_pem(cert.as_bytes()).unwrap())
+			.resolve("localhost", addr)
+			.build()
+			.unwrap();
+		let response = client
+			.get(format!("https://localhost:{}/health", addr.port()))
+			.send()
+			.await
+			.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		// Plain HTTP is not answered on a TLS listener.
+		assert!(
+			client
+				.get(format!("http://localhost:{}/health", addr.port()))
+				.send()
+				.await
+				.is_err()
+		);
+
+		shutdown.cancel();
+		handle.await.unwrap().unwrap();
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_tls12_client_is_refused_below_the_minimum_version() {
+		let dir = temp_dir("min-version");
+		let (tls, cert) = self_signed(&dir, "sbi");
+		let tls = Tls {
+			min_version: TlsVersion::Tls13,
+			..tls
+		};
+
+		let runner = bind(&tls).await.unwrap();
+		let addr = runner.local_addr().unwrap();
+		let shutdown = CancellationToken::new();
+		let handle = tokio::spawn(runner.serve(shutdown.clone()));
+
+		let client = |max_version| {
+			let builder = reqwest::Client::builder()
+				.use_rustls_tls()
+				.add_root_certificate(Certificate::from_pem(cert.as_bytes()).unwrap())
+				.resolve("localhost", addr);
+			match max_version {
+				Some(max_version) => builder.max_tls_version(max_version),
+				None => builder,
+			}
+			.build()
+			.unwrap()
+		};
+		let health = format!("https://localhost:{}/health", addr.port());
+		assert!(
+			client(Some(reqwest::tls::Version::TLS_1_2))
+				.get(&health)
+				.send()
+				.await
+				.is_err()
+		);
+		let response = client(None).get(&health).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+
+		shutdown.cancel();
+		handle.await.unwrap().unwrap();
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_bind_fails_on_missing_or_mismatched_tls_files() {
+		let dir = temp_dir("bad-tls");
+		let (tls, _) = self_signed(&dir, "sbi");
+		let (other, _) = self_signed(&dir, "other");
+
+		let missing = Tls {
+			pem: dir.join("missing.pem").display().to_string(),
+			..tls.clone()
+		};
+		assert!(matches!(
+			bind(&missing).await,
+			Err(SbiServerError::CertificateError(_, pem)) if pem == missing.pem
+		));
+
+		let no_certificate = Tls {
+			pem: tls.key.clone(),
+			..tls.clone()
+		};
+		assert!(matches!(
+			bind(&no_certificate).await,
+			Err(SbiServerError::CertificateError(
+				pem::Error::NoItemsFound,
+				_
+			))
+		));
+
+		let no_key = Tls {
+			key: tls.pem.clone(),
+			..tls.clone()
+		};
+		assert!(matches!(
+			bind(&no_key).await,
+			Err(SbiServerError::PrivateKeyError(_, key)) if key == tls.pem
+		));
+
+		let mismatched = Tls {
+			key: other.key,
+			..tls
+		};
+		assert!(matches!(
+			bind(&mismatched).await,
+			Err(SbiServerError::TlsConfigError(_))
+		));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
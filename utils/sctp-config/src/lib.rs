@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use tokio_sctp::InitMsg;
+
+/// SCTP parameters of a listening endpoint, the `sctp` section of an NF's
+/// configuration. A zero leaves the corresponding INIT parameter to the
+/// kernel's default, RFC 6458 5.3.1. Socket buffers take 4 KiB to 64 MiB.
+#[derive(
+	Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, smart_default::SmartDefault, Validate,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SctpConfig {
+	/// Outbound streams requested for each association.
+	pub num_ostreams: u16,
+	/// Inbound streams accepted on each association.
+	pub max_instreams: u16,
+	/// INIT retransmissions before an association attempt is abandoned.
+	pub max_attempts: u16,
+	/// Upper bound, in milliseconds, of the INIT retransmission timeout.
+	pub max_init_timeout: u16,
+	/// Disables Nagle-like bundling so PDUs are sent as soon as written.
+	#[serde(default = "default_nodelay")]
+	#[default(true)]
+	pub nodelay: bool,
+	/// `SO_SNDBUF` of the sockets, in bytes. The kernel's default when left
+	/// out, `net.core.wmem_max` caps it.
+	#[serde(default)]
+	#[validate(minimum = 4096)]
+	#[validate(maximum = 67108864)]
+	pub send_buffer_size: Option<u32>,
+	/// `SO_RCVBUF` of the sockets, in bytes. The kernel's default when left
+	/// out, `net.core.rmem_max` caps it.
+	#[serde(default)]
+	#[validate(minimum = 4096)]
+	#[validate(maximum = 67108864)]
+	pub recv_buffer_size: Option<u32>,
+	/// Interval, in milliseconds, of the heartbeats probing the idle peer
+	/// addresses of each association, RFC 6458 8.1.12. The kernel's default
+	/// when left out.
+	#[serde(default)]
+	#[validate(minimum = 1)]
+	pub heartbeat_interval: Option<u32>,
+	/// Style of the listening socket, one-to-one as the only one supported.
+	#[serde(default)]
+	#[validate(custom = supported_socket_style)]
+	pub socket_style: SocketStyle,
+}
+
+/// The SCTP socket styles of RFC 6458 3 and 4. One-to-one sockets accept
+/// each association as a socket of its own, the way NGAP endpoints are
+/// handled, while one-to-many sockets carry every association.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SocketStyle {
+	#[default]
+	OneToOne,
+	OneToMany,
+}
+
+fn default_nodelay() -> bool {
+	true
+}
+
+/// Rejects one-to-many sockets, which `tokio_sctp` does not create.
+fn supported_socket_style(style: &SocketStyle) -> Result<(), serde_valid::validation::Error> {
+	match style {
+		SocketStyle::OneToOne => Ok(()),
+		SocketStyle::OneToMany => Err(serde_valid::validation::Error::Custom(
+			"One-to-many SCTP sockets are not supported.".to_owned(),
+		)),
+	}
+}
+
+impl From<&SctpConfig> for InitMsg {
+	fn from(config: &SctpConfig) -> Self {
+		InitMsg {
+			num_ostreams: config.num_ostreams,
+			max_instreams: config.max_instreams,
+			max_attempts: config.max_attempts,
+			max_init_timeout: config.max_init_timeout,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_config_to_init_msg() {
+		let config: SctpConfig = serde_yaml::from_str(
+			"numOstreams: 3\nmaxInstreams: 5\nmaxAttempts: 2\nmaxInitTimeout: 2",
+		)
+		.unwrap();
+		assert!(config.nodelay);
+
+		let init_msg = InitMsg::from(&config);
+		assert_eq!(init_msg.num_ostreams, 3);
+		assert_eq!(init_msg.max_instreams, 5);
+		assert_eq!(init_msg.max_attempts, 2);
+		assert_eq!(init_msg.max_init_timeout, 2);
+		assert_eq!(config.send_buffer_size, None);
+		assert_eq!(config.recv_buffer_size, None);
+		assert_eq!(config.heartbeat_interval, None);
+		assert_eq!(config.socket_style, SocketStyle::OneToOne);
+
+		let config: SctpConfig =
+			serde_yaml::from_str("socketStyle: oneToMany\nheartbeatInterval: 5000").unwrap();
+		assert_eq!(config.socket_style, SocketStyle::OneToMany);
+		assert_eq!(config.heartbeat_interval, Some(5000));
+	}
+
+	#[test]
+	fn test_option_bounds() {
+		let config = |send: u32, recv: u32| SctpConfig {
+			send_buffer_size: Some(send),
+			recv_buffer_size: Some(recv),
+			..Default::default()
+		};
+		assert!(config(4096, 64 << 20).validate().is_ok());
+		assert!(config(4095, 1 << 20).validate().is_err());
+		assert!(config(1 << 20, (64 << 20) + 1).validate().is_err());
+		assert!(SctpConfig::default().validate().is_ok());
+
+		let heartbeat = |interval: u32| SctpConfig {
+			heartbeat_interval: Some(interval),
+			..Default::default()
+		};
+		assert!(heartbeat(1).validate().is_ok());
+		assert!(heartbeat(0).validate().is_err());
+
+		let one_to_many = SctpConfig {
+			socket_style: SocketStyle::OneToMany,
+			..Default::default()
+		};
+		assert!(one_to_many.validate().is_err());
+	}
+}